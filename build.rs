@@ -0,0 +1,72 @@
+// Generates the vault binary's table of the deposit program's error codes
+// from `VaultError` in programs/deposit, so the two can't drift.
+
+use std::{env, fs, path::Path};
+
+const PROGRAM: &str = "programs/deposit/src/lib.rs";
+
+fn unexpected(number: usize, line: &str) -> ! {
+    panic!("{PROGRAM}:{number}: can't read `{line}` in VaultError; teach build.rs about it")
+}
+
+fn main() {
+    println!("cargo::rerun-if-changed={PROGRAM}");
+    let source = fs::read_to_string(PROGRAM).expect("read the deposit program");
+    let (before, rest) = source
+        .split_once("pub enum VaultError {")
+        .expect("VaultError enum in programs/deposit");
+    let body = rest
+        .split_once("\n}")
+        .expect("end of VaultError in programs/deposit")
+        .0;
+    // the body's first line is the rest of the `pub enum` line
+    let first_line = before.lines().count() + 1;
+
+    // Variants in declaration order, which is how Anchor numbers them. Any
+    // line this doesn't understand stops the build rather than shifting the
+    // codes of the variants after it.
+    let mut entries = String::new();
+    let mut message = None;
+    for (number, line) in body.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let number = first_line + number;
+        if let Some(literal) = line
+            .strip_prefix("#[msg(")
+            .and_then(|rest| rest.strip_suffix(")]"))
+        {
+            let is_string =
+                literal.len() >= 2 && literal.starts_with('"') && literal.ends_with('"');
+            if !is_string || message.is_some() {
+                unexpected(number, line);
+            }
+            message = Some(literal.to_string());
+            continue;
+        }
+        let name = line
+            .strip_suffix(',')
+            .unwrap_or_else(|| unexpected(number, line));
+        let is_ident = name.starts_with(|c: char| c.is_ascii_uppercase())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_ident {
+            unexpected(number, line);
+        }
+        let message = message.take().unwrap_or_else(|| format!("{name:?}"));
+        entries.push_str(&format!("    ({name:?}, {message}),\n"));
+    }
+    if message.is_some() {
+        panic!("{PROGRAM}: VaultError ends with a #[msg] and no variant");
+    }
+    if entries.is_empty() {
+        panic!("{PROGRAM}: VaultError has no variants");
+    }
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("vault_errors.rs");
+    fs::write(
+        out,
+        format!("pub const VAULT_ERRORS: &[(&str, &str)] = &[\n{entries}];\n"),
+    )
+    .expect("write vault_errors.rs");
+}
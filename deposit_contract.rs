@@ -1,105 +0,0 @@
-#![allow(unexpected_cfgs)]
-
-use anchor_lang::prelude::*;
-
-declare_id!("HmbbLoNGiFK8w85KLFcaE3UK5kTyitugmByZfvb7x492");
-
-#[program]
-pub mod deposit {
-    use super::*;
-
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
-        let vault = &mut ctx.accounts.vault;
-        vault.owner = ctx.accounts.user.key();
-        Ok(())
-    }
-
-    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
-        let ix = anchor_lang::solana_program::system_instruction::transfer(
-            &ctx.accounts.user.key(),
-            &ctx.accounts.vault.key(),
-            amount,
-        );
-        anchor_lang::solana_program::program::invoke(
-            &ix,
-            &[
-                ctx.accounts.user.to_account_info(),
-                ctx.accounts.vault.to_account_info(),
-            ],
-        )?;
-        Ok(())
-    }
-
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
-        let vault_lamports = ctx.accounts.vault.to_account_info().lamports();
-        require!(vault_lamports >= amount, VaultError::InsufficientFunds);
-
-        **ctx
-            .accounts
-            .vault
-            .to_account_info()
-            .try_borrow_mut_lamports()? -= amount;
-        **ctx
-            .accounts
-            .user
-            .to_account_info()
-            .try_borrow_mut_lamports()? += amount;
-
-        Ok(())
-    }
-}
-
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = user,
-        space = 8 + 32,
-        seeds = [b"vault", user.key().as_ref()],
-        bump
-    )]
-    pub vault: Account<'info, Vault>,
-
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct Deposit<'info> {
-    #[account(
-        mut,
-        seeds = [b"vault", user.key().as_ref()],
-        bump,
-    )]
-    pub vault: Account<'info, Vault>,
-
-    #[account(mut)]
-    pub user: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-pub struct Withdraw<'info> {
-    #[account(
-        mut,
-        seeds = [b"vault", user.key().as_ref()],
-        bump,
-        constraint = vault.owner == user.key()
-    )]
-    pub vault: Account<'info, Vault>,
-
-    #[account(mut)]
-    pub user: Signer<'info>,
-}
-
-#[account]
-pub struct Vault {
-    pub owner: Pubkey,
-}
-
-#[error_code]
-pub enum VaultError {
-    #[msg("Insufficient funds in the vault.")]
-    InsufficientFunds,
-}
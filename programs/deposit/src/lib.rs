@@ -0,0 +1,2533 @@
+#![allow(unexpected_cfgs)]
+
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+declare_id!("HmbbLoNGiFK8w85KLFcaE3UK5kTyitugmByZfvb7x492");
+
+// how long a delayed-mode vault holds a requested withdrawal
+pub const WITHDRAW_DELAY_SECS: i64 = 24 * 60 * 60;
+
+// how long a new or cleared payout address or beneficiary waits before it
+// applies
+pub const PAYOUT_CHANGE_DELAY_SECS: i64 = 48 * 60 * 60;
+
+// vaults per batch_deposit, kept within the transaction's account and
+// compute limits
+pub const MAX_BATCH_DEPOSITS: usize = 10;
+
+// how long deposits stay paused before the admin can force withdrawals,
+// until changed with set_emergency_grace_period
+pub const DEFAULT_EMERGENCY_GRACE_SECS: i64 = 7 * 24 * 60 * 60;
+
+// With the `cpi-events` feature (which needs anchor-lang's `event-cpi`),
+// deposit and withdraw events are also emitted through a self-CPI, where
+// indexers read them from inner instructions instead of logs that can be
+// truncated. The emitting instructions then take the event authority PDA and
+// the program as two trailing accounts.
+#[program]
+pub mod deposit {
+    use super::*;
+
+    // `delayed` vaults only pay out through request_withdraw and
+    // execute_withdraw, so a stolen key can't drain them before the owner
+    // notices and cancels.
+    pub fn initialize(ctx: Context<Initialize>, delayed: bool) -> Result<()> {
+        set_up_vault(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.user,
+            ctx.bumps.vault,
+            delayed,
+        )
+    }
+
+    // `initialize` and `deposit` in one instruction, so a new user can
+    // create and fund a vault atomically.
+    pub fn initialize_with_deposit(
+        ctx: Context<InitializeWithDeposit>,
+        amount: u64,
+        delayed: bool,
+    ) -> Result<()> {
+        set_up_vault(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.user,
+            ctx.bumps.vault,
+            delayed,
+        )?;
+        pay_in(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.user,
+            &ctx.accounts.config,
+            amount,
+            [0; 16],
+        )?;
+
+        let event = DepositEvent {
+            user: ctx.accounts.user.key(),
+            vault: ctx.accounts.vault.key(),
+            amount,
+            new_balance: ctx.accounts.vault.to_account_info().lamports(),
+            reference: [0; 16],
+            deposit_count: ctx.accounts.vault.deposit_count,
+            last_deposit_ts: ctx.accounts.vault.last_deposit_ts,
+        };
+        emit!(event);
+        #[cfg(feature = "cpi-events")]
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    // `deposit` that first creates the signer's vault if it doesn't exist
+    // yet. An existing vault is never set up again, so its owner and
+    // settings stay as they are, and only its owner can pay in.
+    pub fn deposit_init_if_needed(ctx: Context<DepositInitIfNeeded>, amount: u64) -> Result<()> {
+        if ctx.accounts.vault.creator == Pubkey::default() {
+            set_up_vault(
+                &mut ctx.accounts.vault,
+                &ctx.accounts.user,
+                ctx.bumps.vault,
+                false,
+            )?;
+        }
+        pay_in(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.user,
+            &ctx.accounts.config,
+            amount,
+            [0; 16],
+        )?;
+
+        let event = DepositEvent {
+            user: ctx.accounts.user.key(),
+            vault: ctx.accounts.vault.key(),
+            amount,
+            new_balance: ctx.accounts.vault.to_account_info().lamports(),
+            reference: [0; 16],
+            deposit_count: ctx.accounts.vault.deposit_count,
+            last_deposit_ts: ctx.accounts.vault.last_deposit_ts,
+        };
+        emit!(event);
+        #[cfg(feature = "cpi-events")]
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        pay_in(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.user,
+            &ctx.accounts.config,
+            amount,
+            [0; 16],
+        )?;
+
+        let event = DepositEvent {
+            user: ctx.accounts.user.key(),
+            vault: ctx.accounts.vault.key(),
+            amount,
+            new_balance: ctx.accounts.vault.to_account_info().lamports(),
+            reference: [0; 16],
+            deposit_count: ctx.accounts.vault.deposit_count,
+            last_deposit_ts: ctx.accounts.vault.last_deposit_ts,
+        };
+        emit!(event);
+        #[cfg(feature = "cpi-events")]
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    // Like `deposit`, tagged with a caller-chosen id such as an invoice
+    // number. The vault keeps the last few in `recent_deposits`.
+    pub fn deposit_with_reference(
+        ctx: Context<Deposit>,
+        amount: u64,
+        reference: [u8; 16],
+    ) -> Result<()> {
+        pay_in(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.user,
+            &ctx.accounts.config,
+            amount,
+            reference,
+        )?;
+
+        let event = DepositEvent {
+            user: ctx.accounts.user.key(),
+            vault: ctx.accounts.vault.key(),
+            amount,
+            new_balance: ctx.accounts.vault.to_account_info().lamports(),
+            reference,
+            deposit_count: ctx.accounts.vault.deposit_count,
+            last_deposit_ts: ctx.accounts.vault.last_deposit_ts,
+        };
+        emit!(event);
+        #[cfg(feature = "cpi-events")]
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    // Like `deposit`, but also keeps the whole vault from being withdrawn
+    // for `lock_duration_secs`. A lock is only ever extended, never
+    // shortened.
+    pub fn deposit_locked(
+        ctx: Context<Deposit>,
+        amount: u64,
+        lock_duration_secs: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let until = i64::try_from(lock_duration_secs)
+            .ok()
+            .and_then(|duration| now.checked_add(duration))
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        pay_in(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.user,
+            &ctx.accounts.config,
+            amount,
+            [0; 16],
+        )?;
+        let vault = &mut ctx.accounts.vault;
+        vault.lock_until = vault.lock_until.max(until);
+
+        let event = DepositEvent {
+            user: ctx.accounts.user.key(),
+            vault: vault.key(),
+            amount,
+            new_balance: vault.to_account_info().lamports(),
+            reference: [0; 16],
+            deposit_count: vault.deposit_count,
+            last_deposit_ts: vault.last_deposit_ts,
+        };
+        emit!(event);
+        #[cfg(feature = "cpi-events")]
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    // A deposit that becomes withdrawable linearly over `duration_secs` from
+    // `start_ts`. A vault has one schedule at a time; a new one can start
+    // once the previous one has fully vested.
+    pub fn deposit_vested(
+        ctx: Context<Deposit>,
+        amount: u64,
+        start_ts: i64,
+        duration_secs: u64,
+    ) -> Result<()> {
+        require!(duration_secs > 0, VaultError::InvalidVestingSchedule);
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            ctx.accounts.vault.unvested(now) == 0,
+            VaultError::VestingInProgress
+        );
+        pay_in(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.user,
+            &ctx.accounts.config,
+            amount,
+            [0; 16],
+        )?;
+        let vault = &mut ctx.accounts.vault;
+        vault.vesting_amount = amount;
+        vault.vesting_start = start_ts;
+        vault.vesting_duration = duration_secs;
+
+        let event = DepositEvent {
+            user: ctx.accounts.user.key(),
+            vault: vault.key(),
+            amount,
+            new_balance: vault.to_account_info().lamports(),
+            reference: [0; 16],
+            deposit_count: vault.deposit_count,
+            last_deposit_ts: vault.last_deposit_ts,
+        };
+        emit!(event);
+        #[cfg(feature = "cpi-events")]
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    // A sponsor funding someone else's existing vault. The deposit counts
+    // toward the owner's withdrawable total like any other.
+    pub fn deposit_for(ctx: Context<DepositFor>, amount: u64) -> Result<()> {
+        pay_in(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.payer,
+            &ctx.accounts.config,
+            amount,
+            [0; 16],
+        )?;
+
+        let event = DepositForEvent {
+            payer: ctx.accounts.payer.key(),
+            beneficiary: ctx.accounts.beneficiary.key(),
+            vault: ctx.accounts.vault.key(),
+            amount,
+            new_balance: ctx.accounts.vault.to_account_info().lamports(),
+            deposit_count: ctx.accounts.vault.deposit_count,
+            last_deposit_ts: ctx.accounts.vault.last_deposit_ts,
+        };
+        emit!(event);
+        #[cfg(feature = "cpi-events")]
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    // `deposit_for` into several vaults at once. The remaining accounts are a
+    // (beneficiary, vault) pair per amount, in the same order; one bad vault
+    // fails the whole batch.
+    pub fn batch_deposit<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchDeposit<'info>>,
+        amounts: Vec<u64>,
+    ) -> Result<()> {
+        require!(
+            !amounts.is_empty() && amounts.len() <= MAX_BATCH_DEPOSITS,
+            VaultError::InvalidBatchSize
+        );
+        require!(
+            ctx.remaining_accounts.len() == amounts.len() * 2,
+            VaultError::BatchAccountsMismatch
+        );
+
+        for (pair, &amount) in ctx.remaining_accounts.chunks(2).zip(&amounts) {
+            let (beneficiary, vault_info) = (&pair[0], &pair[1]);
+            require!(vault_info.is_writable, VaultError::InvalidBatchVault);
+            let mut vault = Account::<Vault>::try_from(vault_info)?;
+            let expected = Pubkey::create_program_address(
+                &[b"vault", beneficiary.key.as_ref(), &[vault.bump]],
+                ctx.program_id,
+            )
+            .map_err(|_| VaultError::InvalidBatchVault)?;
+            require_keys_eq!(expected, vault.key(), VaultError::InvalidBatchVault);
+
+            pay_in(
+                &mut vault,
+                &ctx.accounts.payer,
+                &ctx.accounts.config,
+                amount,
+                [0; 16],
+            )?;
+            vault.exit(ctx.program_id)?;
+
+            let event = DepositForEvent {
+                payer: ctx.accounts.payer.key(),
+                beneficiary: beneficiary.key(),
+                vault: vault.key(),
+                amount,
+                new_balance: vault_info.lamports(),
+                deposit_count: vault.deposit_count,
+                last_deposit_ts: vault.last_deposit_ts,
+            };
+            emit!(event);
+            #[cfg(feature = "cpi-events")]
+            emit_cpi!(event);
+        }
+        Ok(())
+    }
+
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        require!(!ctx.accounts.vault.delayed, VaultError::DelayedWithdrawals);
+        check_cosigners(&ctx.accounts.vault, ctx.remaining_accounts)?;
+        check_withdrawal(&ctx.accounts.vault, amount)?;
+        let fee = withdrawal_fee(&ctx.accounts.config, ctx.accounts.treasury.as_ref(), amount)?;
+        let user = ctx.accounts.user.to_account_info();
+        let recipient = payout_recipient(&ctx.accounts.vault, &user, ctx.accounts.payout.as_ref())?;
+        let event = pay_out(&mut ctx.accounts.vault, &user, &recipient, amount, fee)?;
+        emit!(event);
+        #[cfg(feature = "cpi-events")]
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    // Pays a third party straight from the vault, with the same checks as
+    // `withdraw`.
+    pub fn withdraw_to(ctx: Context<WithdrawTo>, amount: u64) -> Result<()> {
+        require!(!ctx.accounts.vault.delayed, VaultError::DelayedWithdrawals);
+        check_cosigners(&ctx.accounts.vault, ctx.remaining_accounts)?;
+        check_withdrawal(&ctx.accounts.vault, amount)?;
+        if let Some(payout) = ctx
+            .accounts
+            .vault
+            .payout_address(Clock::get()?.unix_timestamp)
+        {
+            require_keys_eq!(
+                ctx.accounts.recipient.key(),
+                payout,
+                VaultError::WrongPayoutAddress
+            );
+        }
+        let fee = withdrawal_fee(&ctx.accounts.config, ctx.accounts.treasury.as_ref(), amount)?;
+        let event = pay_out(
+            &mut ctx.accounts.vault,
+            &ctx.accounts.user.to_account_info(),
+            &ctx.accounts.recipient.to_account_info(),
+            amount,
+            fee,
+        )?;
+        emit!(event);
+        #[cfg(feature = "cpi-events")]
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    // Every remaining deposit that fits above the rent-exempt minimum, so the
+    // vault stays alive, and within what's left of the epoch's limit.
+    pub fn withdraw_all(ctx: Context<Withdraw>) -> Result<()> {
+        require!(!ctx.accounts.vault.delayed, VaultError::DelayedWithdrawals);
+        check_cosigners(&ctx.accounts.vault, ctx.remaining_accounts)?;
+        check_version(&ctx.accounts.vault)?;
+        check_unlocked(&ctx.accounts.vault)?;
+        let vault_lamports = ctx.accounts.vault.to_account_info().lamports();
+        let minimum = Rent::get()?.minimum_balance(Vault::SPACE);
+        let allowance = ctx
+            .accounts
+            .vault
+            .epoch_allowance(Clock::get()?.epoch)
+            .unwrap_or(u64::MAX);
+        let amount = vault_lamports
+            .saturating_sub(minimum)
+            .min(
+                ctx.accounts
+                    .vault
+                    .available_deposits(Clock::get()?.unix_timestamp)?,
+            )
+            .min(allowance);
+        require!(amount > 0, VaultError::NothingToWithdraw);
+
+        let fee = withdrawal_fee(&ctx.accounts.config, ctx.accounts.treasury.as_ref(), amount)?;
+        let user = ctx.accounts.user.to_account_info();
+        let recipient = payout_recipient(&ctx.accounts.vault, &user, ctx.accounts.payout.as_ref())?;
+        let event = pay_out(&mut ctx.accounts.vault, &user, &recipient, amount, fee)?;
+        emit!(event);
+        #[cfg(feature = "cpi-events")]
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    // A new request replaces any earlier one and restarts the delay.
+    pub fn request_withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        require!(amount > 0, VaultError::ZeroAmount);
+        require!(
+            amount <= ctx.accounts.vault.remaining_deposits()?,
+            VaultError::ExceedsDeposits
+        );
+        let available_at = Clock::get()?
+            .unix_timestamp
+            .checked_add(WITHDRAW_DELAY_SECS)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        let vault = &mut ctx.accounts.vault;
+        vault.pending_amount = amount;
+        vault.available_at = available_at;
+        record_activity(vault, &ctx.accounts.user.key())?;
+
+        emit!(WithdrawRequestedEvent {
+            user: ctx.accounts.user.key(),
+            vault: vault.key(),
+            amount,
+            available_at,
+        });
+        Ok(())
+    }
+
+    // The balance may have changed since the request, so the usual
+    // withdrawal checks run again here.
+    pub fn execute_withdraw(ctx: Context<Withdraw>) -> Result<()> {
+        let amount = ctx.accounts.vault.pending_amount;
+        require!(amount > 0, VaultError::NoPendingWithdrawal);
+        check_cosigners(&ctx.accounts.vault, ctx.remaining_accounts)?;
+        if Clock::get()?.unix_timestamp < ctx.accounts.vault.available_at {
+            msg!(
+                "The withdrawal can be executed from {}.",
+                ctx.accounts.vault.available_at
+            );
+            return err!(VaultError::WithdrawalNotReady);
+        }
+        check_withdrawal(&ctx.accounts.vault, amount)?;
+        ctx.accounts.vault.pending_amount = 0;
+        ctx.accounts.vault.available_at = 0;
+
+        let fee = withdrawal_fee(&ctx.accounts.config, ctx.accounts.treasury.as_ref(), amount)?;
+        let user = ctx.accounts.user.to_account_info();
+        let recipient = payout_recipient(&ctx.accounts.vault, &user, ctx.accounts.payout.as_ref())?;
+        let event = pay_out(&mut ctx.accounts.vault, &user, &recipient, amount, fee)?;
+        emit!(event);
+        #[cfg(feature = "cpi-events")]
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn cancel_withdraw(ctx: Context<Withdraw>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(vault.pending_amount > 0, VaultError::NoPendingWithdrawal);
+        let amount = vault.pending_amount;
+        vault.pending_amount = 0;
+        vault.available_at = 0;
+        record_activity(vault, &ctx.accounts.user.key())?;
+
+        emit!(WithdrawCancelledEvent {
+            user: ctx.accounts.user.key(),
+            vault: vault.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    // Anyone can fund a vault through a receipt at ["receipt", vault,
+    // depositor] and take back up to what the receipt holds. Shared funds
+    // don't count toward the owner's deposits, so the owner can't withdraw
+    // them, and locks and delays don't apply to them.
+    pub fn deposit_shared(ctx: Context<DepositShared>, amount: u64) -> Result<()> {
+        require!(amount > 0, VaultError::ZeroAmount);
+        check_deposit(&ctx.accounts.config, &ctx.accounts.vault, amount)?;
+        let ix = anchor_lang::solana_program::system_instruction::transfer(
+            &ctx.accounts.depositor.key(),
+            &ctx.accounts.vault.key(),
+            amount,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.depositor.to_account_info(),
+                ctx.accounts.vault.to_account_info(),
+            ],
+        )?;
+
+        let receipt = &mut ctx.accounts.receipt;
+        receipt.depositor = ctx.accounts.depositor.key();
+        receipt.vault = ctx.accounts.vault.key();
+        receipt.bump = ctx.bumps.receipt;
+        receipt.amount = receipt
+            .amount
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        let vault = &mut ctx.accounts.vault;
+        vault.shared_balance = vault
+            .shared_balance
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        record_activity(vault, &ctx.accounts.depositor.key())?;
+
+        emit!(SharedDepositEvent {
+            depositor: receipt.depositor,
+            vault: receipt.vault,
+            amount,
+            receipt_amount: receipt.amount,
+        });
+        Ok(())
+    }
+
+    pub fn withdraw_shared(ctx: Context<WithdrawShared>, amount: u64) -> Result<()> {
+        require!(amount > 0, VaultError::ZeroAmount);
+        require!(
+            amount <= ctx.accounts.receipt.amount,
+            VaultError::ExceedsDeposits
+        );
+        let vault_lamports = ctx.accounts.vault.to_account_info().lamports();
+        require!(
+            vault_lamports.saturating_sub(amount) >= Rent::get()?.minimum_balance(Vault::SPACE),
+            VaultError::WouldBreakRentExemption
+        );
+
+        let receipt = &mut ctx.accounts.receipt;
+        receipt.amount -= amount;
+        let vault = &mut ctx.accounts.vault;
+        vault.shared_balance = vault
+            .shared_balance
+            .checked_sub(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        record_activity(vault, &ctx.accounts.depositor.key())?;
+        move_lamports(
+            &vault.to_account_info(),
+            &ctx.accounts.depositor.to_account_info(),
+            amount,
+        )?;
+
+        emit!(SharedWithdrawEvent {
+            depositor: receipt.depositor,
+            vault: receipt.vault,
+            amount,
+            receipt_amount: receipt.amount,
+        });
+        Ok(())
+    }
+
+    // `close = depositor` returns the receipt's rent once it's empty.
+    pub fn close_receipt(_ctx: Context<CloseReceipt>) -> Result<()> {
+        Ok(())
+    }
+
+    // Brings a vault from the original owner-only layout to the current one.
+    // The owner pays the extra rent, and the balance above rent becomes the
+    // vault's deposits.
+    pub fn migrate_vault(ctx: Context<MigrateVault>) -> Result<()> {
+        let info = ctx.accounts.vault.to_account_info();
+        let owner = ctx.accounts.user.key();
+        {
+            let data = info.try_borrow_data()?;
+            require!(
+                data.len() == Vault::LEGACY_SPACE,
+                VaultError::AlreadyMigrated
+            );
+            require!(
+                data[..8] == Vault::DISCRIMINATOR[..],
+                VaultError::AlreadyMigrated
+            );
+            require!(data[8..] == *owner.as_ref(), VaultError::NotOwner);
+        }
+
+        let rent = Rent::get()?;
+        let extra_rent = rent
+            .minimum_balance(Vault::SPACE)
+            .saturating_sub(rent.minimum_balance(Vault::LEGACY_SPACE));
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.user.to_account_info(),
+                    to: info.clone(),
+                },
+            ),
+            extra_rent,
+        )?;
+        info.realloc(Vault::SPACE, true)?;
+
+        let vault = Vault {
+            version: Vault::VERSION,
+            owner,
+            total_deposited: info
+                .lamports()
+                .saturating_sub(rent.minimum_balance(Vault::SPACE)),
+            total_withdrawn: 0,
+            bump: ctx.bumps.vault,
+            creator: owner,
+            pending_owner: None,
+            lock_until: 0,
+            delayed: false,
+            pending_amount: 0,
+            available_at: 0,
+            shared_balance: 0,
+            withdrawers: Vec::new(),
+            beneficiary: None,
+            inactivity_secs: 0,
+            last_activity: Clock::get()?.unix_timestamp,
+            max_withdraw_per_epoch: 0,
+            epoch_withdrawn: 0,
+            last_withdraw_epoch: 0,
+            recent_deposits: [DepositRecord::default(); Vault::RECENT_DEPOSITS],
+            next_deposit: 0,
+            deposit_count: 0,
+            withdraw_count: 0,
+            last_deposit_ts: 0,
+            last_withdraw_ts: 0,
+            cosigners: Vec::new(),
+            cosign_threshold: 0,
+            vesting_amount: 0,
+            vesting_start: 0,
+            vesting_duration: 0,
+            current_payout: None,
+            pending_payout: None,
+            payout_change_at: 0,
+            pending_beneficiary: None,
+            pending_inactivity_secs: 0,
+            beneficiary_change_at: 0,
+        };
+        vault.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+        Ok(())
+    }
+
+    // Withdrawers can take funds out like the owner, but can't manage the
+    // vault or its withdrawers.
+    pub fn add_withdrawer(ctx: Context<UpdateWithdrawers>, withdrawer: Pubkey) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        require!(
+            !vault.withdrawers.contains(&withdrawer),
+            VaultError::AlreadyWithdrawer
+        );
+        require!(
+            vault.withdrawers.len() < Vault::MAX_WITHDRAWERS,
+            VaultError::TooManyWithdrawers
+        );
+        vault.withdrawers.push(withdrawer);
+        record_activity(vault, &ctx.accounts.owner.key())?;
+
+        emit!(WithdrawersChangedEvent {
+            vault: vault.key(),
+            withdrawer,
+            added: true,
+        });
+        Ok(())
+    }
+
+    pub fn remove_withdrawer(ctx: Context<UpdateWithdrawers>, withdrawer: Pubkey) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        let index = vault
+            .withdrawers
+            .iter()
+            .position(|listed| *listed == withdrawer)
+            .ok_or(VaultError::NotWithdrawer)?;
+        vault.withdrawers.remove(index);
+        record_activity(vault, &ctx.accounts.owner.key())?;
+
+        emit!(WithdrawersChangedEvent {
+            vault: vault.key(),
+            withdrawer,
+            added: false,
+        });
+        Ok(())
+    }
+
+    // Ownership moves in two steps so a mistyped key can't lock the vault:
+    // the owner proposes, and the proposed wallet has to sign to accept.
+    // Proposing again replaces the pending owner.
+    pub fn propose_owner(ctx: Context<ProposeOwner>, new_owner: Pubkey) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.pending_owner = Some(new_owner);
+        record_activity(vault, &ctx.accounts.owner.key())?;
+
+        emit!(OwnershipProposedEvent {
+            owner: vault.owner,
+            vault: vault.key(),
+            pending_owner: new_owner,
+        });
+        Ok(())
+    }
+
+    // The current cosigners have to sign the handover too, since it drops
+    // them.
+    pub fn accept_ownership(ctx: Context<AcceptOwnership>) -> Result<()> {
+        check_cosigners(&ctx.accounts.vault, ctx.remaining_accounts)?;
+        let vault = &mut ctx.accounts.vault;
+        let previous_owner = vault.owner;
+        vault.owner = ctx.accounts.new_owner.key();
+        vault.pending_owner = None;
+        // the previous owner's delegates, cosigners and recovery address
+        // don't carry over
+        vault.withdrawers.clear();
+        vault.cosigners.clear();
+        vault.cosign_threshold = 0;
+        vault.beneficiary = None;
+        vault.inactivity_secs = 0;
+        vault.pending_beneficiary = None;
+        vault.pending_inactivity_secs = 0;
+        vault.beneficiary_change_at = 0;
+        record_activity(vault, &ctx.accounts.new_owner.key())?;
+
+        emit!(OwnershipTransferredEvent {
+            previous_owner,
+            new_owner: vault.owner,
+            vault: vault.key(),
+        });
+        Ok(())
+    }
+
+    // A dead man's switch: once the owner has signed nothing for
+    // `inactivity_secs`, the beneficiary can claim the vault. Like a payout
+    // address, the beneficiary only changes after PAYOUT_CHANGE_DELAY_SECS,
+    // so a stolen key can't name itself and claim the vault straight away.
+    pub fn set_beneficiary(
+        ctx: Context<UpdateBeneficiary>,
+        beneficiary: Pubkey,
+        inactivity_secs: i64,
+    ) -> Result<()> {
+        require!(inactivity_secs > 0, VaultError::InvalidInactivityPeriod);
+        let vault = &mut ctx.accounts.vault;
+        schedule_beneficiary(vault, Some(beneficiary), inactivity_secs)?;
+        record_activity(vault, &ctx.accounts.owner.key())
+    }
+
+    // Schedules removing the beneficiary, with the same delay.
+    pub fn clear_beneficiary(ctx: Context<UpdateBeneficiary>) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        schedule_beneficiary(vault, None, 0)?;
+        record_activity(vault, &ctx.accounts.owner.key())
+    }
+
+    // `close = beneficiary` hands the beneficiary everything in the vault,
+    // under the same conditions as close_vault. A vault bound to a payout
+    // address can only be claimed by that address.
+    pub fn claim_inactive(ctx: Context<ClaimInactive>) -> Result<()> {
+        let vault = &ctx.accounts.vault;
+        let now = Clock::get()?.unix_timestamp;
+        let (beneficiary, inactivity_secs) = vault.beneficiary_at(now);
+        require!(
+            beneficiary == Some(ctx.accounts.beneficiary.key()),
+            VaultError::NotBeneficiary
+        );
+        let idle = now.saturating_sub(vault.last_activity);
+        if idle <= inactivity_secs {
+            msg!(
+                "The vault can be claimed after {}.",
+                vault.last_activity.saturating_add(inactivity_secs)
+            );
+            return err!(VaultError::OwnerStillActive);
+        }
+        if let Some(payout) = vault.payout_address(now) {
+            require_keys_eq!(
+                ctx.accounts.beneficiary.key(),
+                payout,
+                VaultError::WrongPayoutAddress
+            );
+        }
+        check_cosigners(vault, ctx.remaining_accounts)?;
+        check_unlocked(vault)?;
+        check_vested(vault)?;
+        require!(
+            vault.shared_balance == 0,
+            VaultError::SharedFundsOutstanding
+        );
+        charge_closing_fee(vault, &ctx.accounts.config, ctx.accounts.treasury.as_ref())?;
+
+        emit!(InactiveClaimEvent {
+            beneficiary: ctx.accounts.beneficiary.key(),
+            vault: vault.key(),
+            amount: vault.to_account_info().lamports(),
+        });
+        Ok(())
+    }
+
+    // `close = user` sweeps deposits and rent back to the owner and deletes
+    // the account, so it honours the lock like a withdrawal. Delayed vaults
+    // have to be emptied through execute_withdraw first, and shared funds
+    // by their depositors.
+    pub fn close_vault(ctx: Context<CloseVault>) -> Result<()> {
+        check_closable(&ctx.accounts.vault, ctx.remaining_accounts)?;
+        charge_closing_fee(
+            &ctx.accounts.vault,
+            &ctx.accounts.config,
+            ctx.accounts.treasury.as_ref(),
+        )?;
+
+        // `close` sweeps what's left after the fee once this returns
+        let vault = ctx.accounts.vault.to_account_info();
+        emit!(VaultClosedEvent {
+            owner: ctx.accounts.user.key(),
+            vault: vault.key(),
+            amount: vault.lamports(),
+        });
+        Ok(())
+    }
+
+    // The same as close_vault, kept for clients built against it.
+    pub fn withdraw_and_close(ctx: Context<CloseVault>) -> Result<()> {
+        close_vault(ctx)
+    }
+
+    // Every payout and close then needs `threshold` of `cosigners` to sign
+    // too, passed as remaining accounts. A threshold of 0 goes back to the
+    // owner or a withdrawer alone. The change itself needs the current
+    // policy's cosigners, so the owner key alone can't drop them.
+    pub fn set_cosigners(
+        ctx: Context<UpdateCosigners>,
+        cosigners: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        require!(
+            cosigners.len() <= Vault::MAX_COSIGNERS,
+            VaultError::TooManyCosigners
+        );
+        require!(
+            usize::from(threshold) <= cosigners.len(),
+            VaultError::InvalidThreshold
+        );
+        for (i, cosigner) in cosigners.iter().enumerate() {
+            require!(
+                !cosigners[..i].contains(cosigner),
+                VaultError::DuplicateCosigner
+            );
+        }
+        check_cosigners(&ctx.accounts.vault, ctx.remaining_accounts)?;
+        let vault = &mut ctx.accounts.vault;
+        vault.cosigners = cosigners;
+        vault.cosign_threshold = threshold;
+        record_activity(vault, &ctx.accounts.owner.key())?;
+
+        emit!(CosignersChangedEvent {
+            vault: vault.key(),
+            cosigners: vault.cosigners.clone(),
+            threshold,
+        });
+        Ok(())
+    }
+
+    // Binds payouts to `address` once PAYOUT_CHANGE_DELAY_SECS have passed,
+    // so a stolen key can't redirect funds before the owner notices. `None`
+    // schedules removing the binding, with the same delay. A new request
+    // replaces a pending one.
+    pub fn set_payout_address(
+        ctx: Context<UpdatePayoutAddress>,
+        address: Option<Pubkey>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let effective_at = now
+            .checked_add(PAYOUT_CHANGE_DELAY_SECS)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        let vault = &mut ctx.accounts.vault;
+        // a change that has already taken effect stays
+        vault.current_payout = vault.payout_address(now);
+        vault.pending_payout = address;
+        vault.payout_change_at = effective_at;
+        record_activity(vault, &ctx.accounts.owner.key())?;
+
+        emit!(PayoutAddressRequestedEvent {
+            vault: vault.key(),
+            address,
+            effective_at,
+        });
+        Ok(())
+    }
+
+    // Caps what the owner and withdrawers can take out per epoch; 0 removes
+    // the cap.
+    pub fn set_withdraw_limit(
+        ctx: Context<UpdateWithdrawLimit>,
+        max_withdraw_per_epoch: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.vault;
+        vault.max_withdraw_per_epoch = max_withdraw_per_epoch;
+        record_activity(vault, &ctx.accounts.owner.key())
+    }
+
+    // Whoever creates the config becomes its admin, so it has to be created
+    // right after the program is deployed.
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        max_deposit_per_vault: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.paused = false;
+        config.max_deposit_per_vault = max_deposit_per_vault;
+        config.bump = ctx.bumps.config;
+        config.emergency_grace_secs = DEFAULT_EMERGENCY_GRACE_SECS;
+        Ok(())
+    }
+
+    // Pausing only stops deposits; owners can always take their funds out.
+    // Pausing again keeps the original `paused_at`.
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        if paused && !config.paused {
+            config.paused_at = Clock::get()?.unix_timestamp;
+        }
+        if !paused {
+            config.paused_at = 0;
+        }
+        config.paused = paused;
+        Ok(())
+    }
+
+    pub fn set_emergency_grace_period(
+        ctx: Context<UpdateConfig>,
+        emergency_grace_secs: i64,
+    ) -> Result<()> {
+        require!(emergency_grace_secs >= 0, VaultError::InvalidGracePeriod);
+        ctx.accounts.config.emergency_grace_secs = emergency_grace_secs;
+        Ok(())
+    }
+
+    // The admin's escape hatch once deposits have been paused for the grace
+    // period: sends a vault's withdrawable balance to its owner, ignoring
+    // locks, delays and the epoch limit. The recipient is the recorded owner,
+    // or the payout address the owner bound the vault to, and no fee is
+    // taken, so the admin can't redirect funds.
+    pub fn admin_emergency_withdraw(ctx: Context<EmergencyWithdraw>) -> Result<()> {
+        let config = &ctx.accounts.config;
+        require!(config.paused, VaultError::NotPaused);
+        let ready_at = config
+            .paused_at
+            .checked_add(config.emergency_grace_secs)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        if Clock::get()?.unix_timestamp < ready_at {
+            msg!("Emergency withdrawals open at {}.", ready_at);
+            return err!(VaultError::GracePeriodNotOver);
+        }
+
+        let vault = &mut ctx.accounts.vault;
+        check_version(vault)?;
+        let recipient = payout_recipient(
+            vault,
+            &ctx.accounts.owner.to_account_info(),
+            ctx.accounts.payout.as_ref(),
+        )?;
+        let minimum = Rent::get()?.minimum_balance(Vault::SPACE);
+        let amount = vault
+            .to_account_info()
+            .lamports()
+            .saturating_sub(minimum)
+            .min(vault.remaining_deposits()?);
+        require!(amount > 0, VaultError::NothingToWithdraw);
+
+        vault.total_withdrawn = vault
+            .total_withdrawn
+            .checked_add(amount)
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        // a requested withdrawal could no longer be covered
+        vault.pending_amount = 0;
+        vault.available_at = 0;
+        record_withdrawal(vault)?;
+        move_lamports(&vault.to_account_info(), &recipient, amount)?;
+
+        let event = EmergencyWithdrawEvent {
+            admin: ctx.accounts.admin.key(),
+            vault: vault.key(),
+            owner: ctx.accounts.owner.key(),
+            recipient: recipient.key(),
+            amount,
+            new_balance: vault.to_account_info().lamports(),
+            withdraw_count: vault.withdraw_count,
+            last_withdraw_ts: vault.last_withdraw_ts,
+        };
+        emit!(event);
+        #[cfg(feature = "cpi-events")]
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn set_deposit_cap(ctx: Context<UpdateConfig>, max_deposit_per_vault: u64) -> Result<()> {
+        ctx.accounts.config.max_deposit_per_vault = max_deposit_per_vault;
+        Ok(())
+    }
+
+    // `fee_bps` of every owner withdrawal goes to `treasury`; 0 turns the fee
+    // off.
+    pub fn set_fee(ctx: Context<UpdateConfig>, fee_bps: u16, treasury: Pubkey) -> Result<()> {
+        require!(fee_bps <= 10_000, VaultError::InvalidFee);
+        let config = &mut ctx.accounts.config;
+        config.fee_bps = fee_bps;
+        config.treasury = treasury;
+        Ok(())
+    }
+
+    // Token vaults mirror the SOL vault for one mint: the state PDA at
+    // ["token_vault", owner, mint] owns an associated token account holding
+    // the deposits. The mint's program, classic or token-2022, is recorded
+    // and required on later transfers.
+    pub fn initialize_token_vault(ctx: Context<InitializeTokenVault>) -> Result<()> {
+        let token_vault = &mut ctx.accounts.token_vault;
+        token_vault.owner = ctx.accounts.user.key();
+        token_vault.mint = ctx.accounts.mint.key();
+        token_vault.bump = ctx.bumps.token_vault;
+        token_vault.token_program = ctx.accounts.token_program.key();
+        Ok(())
+    }
+
+    // Token-2022 mints with a transfer fee deliver less than `amount`; the
+    // events report what arrived.
+    pub fn deposit_tokens(ctx: Context<DepositTokens>, amount: u64) -> Result<()> {
+        require!(amount > 0, VaultError::ZeroAmount);
+        let balance_before = ctx.accounts.vault_tokens.amount;
+        let accounts = TransferChecked {
+            from: ctx.accounts.user_tokens.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.vault_tokens.to_account_info(),
+            authority: ctx.accounts.user.to_account_info(),
+        };
+        token_interface::transfer_checked(
+            CpiContext::new(ctx.accounts.token_program.to_account_info(), accounts),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+        ctx.accounts.vault_tokens.reload()?;
+
+        emit!(TokenDepositEvent {
+            user: ctx.accounts.user.key(),
+            mint: ctx.accounts.mint.key(),
+            amount,
+            received: ctx
+                .accounts
+                .vault_tokens
+                .amount
+                .saturating_sub(balance_before),
+        });
+        Ok(())
+    }
+
+    pub fn withdraw_tokens(ctx: Context<WithdrawTokens>, amount: u64) -> Result<()> {
+        require!(amount > 0, VaultError::ZeroAmount);
+        require!(
+            ctx.accounts.vault_tokens.amount >= amount,
+            VaultError::InsufficientFunds
+        );
+        let balance_before = ctx.accounts.user_tokens.amount;
+        let token_vault = &ctx.accounts.token_vault;
+        let seeds: &[&[u8]] = &[
+            b"token_vault",
+            token_vault.owner.as_ref(),
+            token_vault.mint.as_ref(),
+            &[token_vault.bump],
+        ];
+        let accounts = TransferChecked {
+            from: ctx.accounts.vault_tokens.to_account_info(),
+            mint: ctx.accounts.mint.to_account_info(),
+            to: ctx.accounts.user_tokens.to_account_info(),
+            authority: token_vault.to_account_info(),
+        };
+        token_interface::transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                accounts,
+                &[seeds],
+            ),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
+        ctx.accounts.user_tokens.reload()?;
+
+        emit!(TokenWithdrawEvent {
+            user: ctx.accounts.user.key(),
+            mint: ctx.accounts.mint.key(),
+            amount,
+            received: ctx
+                .accounts
+                .user_tokens
+                .amount
+                .saturating_sub(balance_before),
+        });
+        Ok(())
+    }
+}
+
+fn set_up_vault(vault: &mut Account<Vault>, user: &Signer, bump: u8, delayed: bool) -> Result<()> {
+    vault.version = Vault::VERSION;
+    vault.owner = user.key();
+    vault.creator = vault.owner;
+    vault.bump = bump;
+    vault.delayed = delayed;
+    vault.last_activity = Clock::get()?.unix_timestamp;
+
+    emit!(InitializeEvent {
+        user: vault.owner,
+        vault: vault.key(),
+    });
+    Ok(())
+}
+
+// Until the config is created, deposits are unpaused and uncapped.
+fn check_deposit(config: &UncheckedAccount, vault: &Vault, amount: u64) -> Result<()> {
+    check_version(vault)?;
+    let Some(config) = load_config(config)? else {
+        return Ok(());
+    };
+    require!(!config.paused, VaultError::ProgramPaused);
+    if config.max_deposit_per_vault > 0 {
+        let balance = vault
+            .remaining_deposits()?
+            .checked_add(vault.shared_balance)
+            .and_then(|balance| balance.checked_add(amount))
+            .ok_or(VaultError::ArithmeticOverflow)?;
+        require!(
+            balance <= config.max_deposit_per_vault,
+            VaultError::DepositCapExceeded
+        );
+    }
+    Ok(())
+}
+
+fn pay_in<'info>(
+    vault: &mut Account<'info, Vault>,
+    payer: &Signer<'info>,
+    config: &UncheckedAccount<'info>,
+    amount: u64,
+    reference: [u8; 16],
+) -> Result<()> {
+    require!(amount > 0, VaultError::ZeroAmount);
+    check_deposit(config, vault, amount)?;
+    let ix = anchor_lang::solana_program::system_instruction::transfer(
+        &payer.key(),
+        &vault.key(),
+        amount,
+    );
+    anchor_lang::solana_program::program::invoke(
+        &ix,
+        &[payer.to_account_info(), vault.to_account_info()],
+    )?;
+
+    vault.total_deposited = vault
+        .total_deposited
+        .checked_add(amount)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    let now = Clock::get()?.unix_timestamp;
+    vault.record_deposit(DepositRecord {
+        reference,
+        amount,
+        timestamp: now,
+    });
+    vault.deposit_count = vault.deposit_count.saturating_add(1);
+    vault.last_deposit_ts = now;
+    record_activity(vault, &payer.key())
+}
+
+// Restarts the inactivity clock when the owner signs; other signers, such as
+// sponsors and withdrawers, don't count.
+fn record_activity(vault: &mut Vault, signer: &Pubkey) -> Result<()> {
+    if vault.owner == *signer {
+        vault.last_activity = Clock::get()?.unix_timestamp;
+    }
+    Ok(())
+}
+
+// Queues a beneficiary change for PAYOUT_CHANGE_DELAY_SECS from now. A
+// change that has already taken effect stays; a pending one is replaced.
+fn schedule_beneficiary(
+    vault: &mut Vault,
+    beneficiary: Option<Pubkey>,
+    inactivity_secs: i64,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let effective_at = now
+        .checked_add(PAYOUT_CHANGE_DELAY_SECS)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    (vault.beneficiary, vault.inactivity_secs) = vault.beneficiary_at(now);
+    vault.pending_beneficiary = beneficiary;
+    vault.pending_inactivity_secs = inactivity_secs;
+    vault.beneficiary_change_at = effective_at;
+    Ok(())
+}
+
+// Vaults still on the owner-only layout fail to deserialize with the same
+// error, so this only catches layouts from later versions that
+// `migrate_vault` hasn't caught up with yet.
+fn check_version(vault: &Vault) -> Result<()> {
+    require!(vault.version == Vault::VERSION, VaultError::NeedsMigration);
+    Ok(())
+}
+
+// For paths that take the whole vault.
+fn check_vested(vault: &Vault) -> Result<()> {
+    require!(
+        vault.unvested(Clock::get()?.unix_timestamp) == 0,
+        VaultError::StillVesting
+    );
+    Ok(())
+}
+
+fn check_unlocked(vault: &Vault) -> Result<()> {
+    if Clock::get()?.unix_timestamp < vault.lock_until {
+        msg!("The vault is locked until {}.", vault.lock_until);
+        return err!(VaultError::StillLocked);
+    }
+    Ok(())
+}
+
+// Closing pays the signer, so a vault bound to a payout address has to drop
+// the binding first.
+fn check_closable(vault: &Vault, signers: &[AccountInfo]) -> Result<()> {
+    require!(
+        vault.payout_address(Clock::get()?.unix_timestamp).is_none(),
+        VaultError::PayoutAddressBound
+    );
+    check_unlocked(vault)?;
+    check_vested(vault)?;
+    check_cosigners(vault, signers)?;
+    require!(
+        vault.shared_balance == 0,
+        VaultError::SharedFundsOutstanding
+    );
+    require!(vault.pending_amount == 0, VaultError::WithdrawalPending);
+    if vault.delayed {
+        require!(
+            vault.remaining_deposits()? == 0,
+            VaultError::DelayedWithdrawals
+        );
+    }
+    if let Some(allowance) = vault.epoch_allowance(Clock::get()?.epoch) {
+        require!(
+            vault.remaining_deposits()? <= allowance,
+            VaultError::WithdrawLimitExceeded
+        );
+    }
+    Ok(())
+}
+
+// The signer, unless the vault is bound to a payout address, which then has
+// to be passed as `payout`.
+fn payout_recipient<'info>(
+    vault: &Vault,
+    user: &AccountInfo<'info>,
+    payout: Option<&UncheckedAccount<'info>>,
+) -> Result<AccountInfo<'info>> {
+    let Some(address) = vault.payout_address(Clock::get()?.unix_timestamp) else {
+        return Ok(user.clone());
+    };
+    match payout {
+        Some(payout) if payout.key() == address => Ok(payout.to_account_info()),
+        _ => err!(VaultError::WrongPayoutAddress),
+    }
+}
+
+// Counts the listed cosigners among the signers passed as remaining
+// accounts; anyone else signing doesn't count.
+fn check_cosigners(vault: &Vault, signers: &[AccountInfo]) -> Result<()> {
+    if vault.cosign_threshold == 0 {
+        return Ok(());
+    }
+    let mut signed: Vec<Pubkey> = signers
+        .iter()
+        .filter(|account| account.is_signer && vault.cosigners.contains(account.key))
+        .map(|account| account.key())
+        .collect();
+    signed.sort();
+    signed.dedup();
+    if signed.len() < usize::from(vault.cosign_threshold) {
+        msg!(
+            "{} of {} required cosigners signed.",
+            signed.len(),
+            vault.cosign_threshold
+        );
+        return err!(VaultError::NotEnoughCosigners);
+    }
+    Ok(())
+}
+
+fn check_withdrawal(vault: &Account<Vault>, amount: u64) -> Result<()> {
+    require!(amount > 0, VaultError::ZeroAmount);
+    check_version(vault)?;
+    check_unlocked(vault)?;
+    let vault_lamports = vault.to_account_info().lamports();
+    require!(vault_lamports >= amount, VaultError::InsufficientFunds);
+    require!(
+        amount <= vault.remaining_deposits()?,
+        VaultError::ExceedsDeposits
+    );
+    let available = vault.available_deposits(Clock::get()?.unix_timestamp)?;
+    if amount > available {
+        msg!("At most {} lamports have vested.", available);
+        return err!(VaultError::StillVesting);
+    }
+    // below the minimum the runtime may purge the vault and its owner
+    let withdrawable = vault_lamports.saturating_sub(Rent::get()?.minimum_balance(Vault::SPACE));
+    if amount > withdrawable {
+        msg!("At most {} lamports can be withdrawn.", withdrawable);
+        return err!(VaultError::WouldBreakRentExemption);
+    }
+    Ok(())
+}
+
+// The vault is owned by this program, so lamports can leave it without a
+// system transfer.
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let from_lamports = from
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    let to_lamports = to
+        .lamports()
+        .checked_add(amount)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    **from.try_borrow_mut_lamports()? = from_lamports;
+    **to.try_borrow_mut_lamports()? = to_lamports;
+    Ok(())
+}
+
+// The config at the seeds-checked `config` address, or `None` before it has
+// been created.
+fn load_config(config: &UncheckedAccount) -> Result<Option<Config>> {
+    if config.data_is_empty() {
+        return Ok(None);
+    }
+    require_keys_eq!(*config.owner, ID, ErrorCode::AccountOwnedByWrongProgram);
+    Ok(Some(Config::try_deserialize(
+        &mut &config.try_borrow_data()?[..],
+    )?))
+}
+
+// The fee on an owner withdrawal and the treasury it goes to, or `None` when
+// there is no config or its fee is 0. The fee is floored so rounding favours
+// the user.
+fn withdrawal_fee<'info>(
+    config: &UncheckedAccount<'info>,
+    treasury: Option<&UncheckedAccount<'info>>,
+    amount: u64,
+) -> Result<Option<(u64, AccountInfo<'info>)>> {
+    let Some(config) = load_config(config)? else {
+        return Ok(None);
+    };
+    if config.fee_bps == 0 {
+        return Ok(None);
+    }
+    let treasury = treasury.ok_or(VaultError::WrongTreasury)?;
+    require_keys_eq!(treasury.key(), config.treasury, VaultError::WrongTreasury);
+    Ok(Some((
+        fee_amount(amount, config.fee_bps)?,
+        treasury.to_account_info(),
+    )))
+}
+
+// `amount * fee_bps / 10_000`, floored.
+fn fee_amount(amount: u64, fee_bps: u16) -> Result<u64> {
+    u128::from(amount)
+        .checked_mul(u128::from(fee_bps))
+        .map(|scaled| scaled / 10_000)
+        .and_then(|fee| u64::try_from(fee).ok())
+        .ok_or_else(|| error!(VaultError::ArithmeticOverflow))
+}
+
+// Closing hands over the deposits still in the vault, so they pay the
+// withdrawal fee like any other payout; the rent deposit doesn't. Runs
+// before `close` sweeps the rest, and returns the fee.
+fn charge_closing_fee<'info>(
+    vault: &Account<'info, Vault>,
+    config: &UncheckedAccount<'info>,
+    treasury: Option<&UncheckedAccount<'info>>,
+) -> Result<u64> {
+    let Some((fee, treasury)) = withdrawal_fee(config, treasury, vault.remaining_deposits()?)?
+    else {
+        return Ok(0);
+    };
+    if fee > 0 {
+        move_lamports(&vault.to_account_info(), &treasury, fee)?;
+    }
+    Ok(fee)
+}
+
+fn record_withdrawal(vault: &mut Vault) -> Result<()> {
+    vault.withdraw_count = vault.withdraw_count.saturating_add(1);
+    vault.last_withdraw_ts = Clock::get()?.unix_timestamp;
+    Ok(())
+}
+
+fn count_epoch_withdrawal(vault: &mut Vault, amount: u64) -> Result<()> {
+    let epoch = Clock::get()?.epoch;
+    if let Some(allowance) = vault.epoch_allowance(epoch) {
+        if amount > allowance {
+            msg!(
+                "At most {} more lamports can be withdrawn this epoch.",
+                allowance
+            );
+            return err!(VaultError::WithdrawLimitExceeded);
+        }
+    }
+    if vault.last_withdraw_epoch != epoch {
+        vault.last_withdraw_epoch = epoch;
+        vault.epoch_withdrawn = 0;
+    }
+    vault.epoch_withdrawn = vault
+        .epoch_withdrawn
+        .checked_add(amount)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+// The whole amount counts as withdrawn; the recipient gets it minus the fee.
+// Returns the event for the caller to emit, since emit_cpi! needs `ctx`.
+fn pay_out(
+    vault: &mut Account<Vault>,
+    user: &AccountInfo,
+    recipient: &AccountInfo,
+    amount: u64,
+    fee: Option<(u64, AccountInfo)>,
+) -> Result<WithdrawEvent> {
+    count_epoch_withdrawal(vault, amount)?;
+    vault.total_withdrawn = vault
+        .total_withdrawn
+        .checked_add(amount)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    record_withdrawal(vault)?;
+    record_activity(vault, &user.key())?;
+    let (fee, treasury) = match fee {
+        Some((fee, treasury)) => (fee, Some(treasury)),
+        None => (0, None),
+    };
+    let payout = amount
+        .checked_sub(fee)
+        .ok_or(VaultError::ArithmeticOverflow)?;
+    move_lamports(&vault.to_account_info(), recipient, payout)?;
+    if let Some(treasury) = treasury.filter(|_| fee > 0) {
+        move_lamports(&vault.to_account_info(), &treasury, fee)?;
+    }
+
+    Ok(WithdrawEvent {
+        user: user.key(),
+        vault: vault.key(),
+        recipient: recipient.key(),
+        amount,
+        fee,
+        new_balance: vault.to_account_info().lamports(),
+        withdraw_count: vault.withdraw_count,
+        last_withdraw_ts: vault.last_withdraw_ts,
+    })
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = Vault::SPACE,
+        seeds = [b"vault", user.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(feature = "cpi-events", event_cpi)]
+#[derive(Accounts)]
+pub struct InitializeWithDeposit<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = Vault::SPACE,
+        seeds = [b"vault", user.key().as_ref()],
+        bump
+    )]
+    pub vault: Account<'info, Vault>,
+    /// CHECK: address checked by the seeds, contents by `check_deposit`
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// The seeds use the signer, so nobody can reach another wallet's vault
+// through this. A vault the signer created but has since handed over is
+// refused like in `Deposit`; a new one still has no creator at this point.
+#[cfg_attr(feature = "cpi-events", event_cpi)]
+#[derive(Accounts)]
+pub struct DepositInitIfNeeded<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = Vault::SPACE,
+        seeds = [b"vault", user.key().as_ref()],
+        bump,
+        constraint = vault.creator == Pubkey::default() || vault.owner == user.key()
+            @ VaultError::NotOwner
+    )]
+    pub vault: Account<'info, Vault>,
+    /// CHECK: address checked by the seeds, contents by `check_deposit`
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateVault<'info> {
+    /// CHECK: a legacy vault doesn't deserialize as `Vault`; its layout and
+    /// owner are checked by `migrate_vault`
+    #[account(
+        mut,
+        seeds = [b"vault", user.key().as_ref()],
+        bump,
+        owner = crate::ID
+    )]
+    pub vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(feature = "cpi-events", event_cpi)]
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+        constraint = vault.owner == user.key() @ VaultError::NotOwner
+    )]
+    pub vault: Account<'info, Vault>,
+    /// CHECK: address checked by the seeds, contents by `check_deposit`
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(feature = "cpi-events", event_cpi)]
+#[derive(Accounts)]
+pub struct DepositFor<'info> {
+    // `Account` fails on a vault that was never initialized
+    #[account(
+        mut,
+        seeds = [b"vault", beneficiary.key().as_ref()],
+        bump = vault.bump,
+    )]
+    pub vault: Account<'info, Vault>,
+    /// CHECK: address checked by the seeds, contents by `check_deposit`
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+
+    /// CHECK: only used to derive the vault address
+    pub beneficiary: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// The vaults come in `remaining_accounts`; see `batch_deposit`.
+#[cfg_attr(feature = "cpi-events", event_cpi)]
+#[derive(Accounts)]
+pub struct BatchDeposit<'info> {
+    /// CHECK: address checked by the seeds, contents by `check_deposit`
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg_attr(feature = "cpi-events", event_cpi)]
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+        constraint = vault.can_withdraw(&user.key()) @ VaultError::NotAuthorized
+    )]
+    pub vault: Account<'info, Vault>,
+    /// CHECK: address checked by the seeds, contents by `withdrawal_fee`
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+    /// CHECK: must match the config's treasury, checked by `withdrawal_fee`
+    #[account(mut)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+    /// CHECK: must match the vault's payout address, checked by
+    /// `payout_recipient`; only needed while one is set
+    #[account(mut)]
+    pub payout: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[cfg_attr(feature = "cpi-events", event_cpi)]
+#[derive(Accounts)]
+pub struct WithdrawTo<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+        constraint = vault.can_withdraw(&user.key()) @ VaultError::NotAuthorized
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        constraint = recipient.key() != vault.key() @ VaultError::RecipientIsVault
+    )]
+    pub recipient: SystemAccount<'info>,
+    /// CHECK: address checked by the seeds, contents by `withdrawal_fee`
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+    /// CHECK: must match the config's treasury, checked by `withdrawal_fee`
+    #[account(mut)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct CloseVault<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+        constraint = vault.owner == user.key() @ VaultError::NotOwner,
+        close = user
+    )]
+    pub vault: Account<'info, Vault>,
+    /// CHECK: address checked by the seeds, contents by `withdrawal_fee`
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+    /// CHECK: must match the config's treasury, checked by `withdrawal_fee`
+    #[account(mut)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct DepositShared<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+    )]
+    pub vault: Account<'info, Vault>,
+    #[account(
+        init_if_needed,
+        payer = depositor,
+        space = DepositReceipt::SPACE,
+        seeds = [b"receipt", vault.key().as_ref(), depositor.key().as_ref()],
+        bump
+    )]
+    pub receipt: Account<'info, DepositReceipt>,
+    /// CHECK: address checked by the seeds, contents by `check_deposit`
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawShared<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+    )]
+    pub vault: Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"receipt", vault.key().as_ref(), depositor.key().as_ref()],
+        bump = receipt.bump,
+    )]
+    pub receipt: Account<'info, DepositReceipt>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseReceipt<'info> {
+    #[account(
+        mut,
+        seeds = [b"receipt", receipt.vault.as_ref(), depositor.key().as_ref()],
+        bump = receipt.bump,
+        constraint = receipt.amount == 0 @ VaultError::ReceiptNotEmpty,
+        close = depositor
+    )]
+    pub receipt: Account<'info, DepositReceipt>,
+
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeOwner<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+        constraint = vault.owner == owner.key() @ VaultError::NotOwner
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateWithdrawers<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+        constraint = vault.owner == owner.key() @ VaultError::NotOwner
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateBeneficiary<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+        constraint = vault.owner == owner.key() @ VaultError::NotOwner
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateWithdrawLimit<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+        constraint = vault.owner == owner.key() @ VaultError::NotOwner
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePayoutAddress<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+        constraint = vault.owner == owner.key() @ VaultError::NotOwner
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateCosigners<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+        constraint = vault.owner == owner.key() @ VaultError::NotOwner
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimInactive<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+        close = beneficiary
+    )]
+    pub vault: Account<'info, Vault>,
+    /// CHECK: address checked by the seeds, contents by `withdrawal_fee`
+    #[account(seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+    /// CHECK: must match the config's treasury, checked by `withdrawal_fee`
+    #[account(mut)]
+    pub treasury: Option<UncheckedAccount<'info>>,
+
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOwnership<'info> {
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+        constraint = vault.pending_owner == Some(new_owner.key()) @ VaultError::NotPendingOwner
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub new_owner: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = Config::SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ VaultError::NotAdmin
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+}
+
+#[cfg_attr(feature = "cpi-events", event_cpi)]
+#[derive(Accounts)]
+pub struct EmergencyWithdraw<'info> {
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin @ VaultError::NotAdmin
+    )]
+    pub config: Account<'info, Config>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"vault", vault.creator.as_ref()],
+        bump = vault.bump,
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut, address = vault.owner @ VaultError::NotOwner)]
+    pub owner: SystemAccount<'info>,
+    /// CHECK: must match the vault's payout address, checked by
+    /// `payout_recipient`; only needed while one is set
+    #[account(mut)]
+    pub payout: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeTokenVault<'info> {
+    #[account(
+        init,
+        payer = user,
+        space = TokenVault::SPACE,
+        seeds = [b"token_vault", user.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Account<'info, TokenVault>,
+    #[account(
+        init,
+        payer = user,
+        associated_token::mint = mint,
+        associated_token::authority = token_vault,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_tokens: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositTokens<'info> {
+    #[account(
+        seeds = [b"token_vault", token_vault.owner.as_ref(), token_vault.mint.as_ref()],
+        bump = token_vault.bump,
+        has_one = mint @ VaultError::WrongMint,
+        has_one = token_program @ VaultError::WrongTokenProgram,
+        constraint = token_vault.owner == user.key() @ VaultError::NotOwner
+    )]
+    pub token_vault: Account<'info, TokenVault>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = token_vault,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_tokens: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user,
+        token::token_program = token_program,
+    )]
+    pub user_tokens: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTokens<'info> {
+    #[account(
+        seeds = [b"token_vault", token_vault.owner.as_ref(), token_vault.mint.as_ref()],
+        bump = token_vault.bump,
+        has_one = mint @ VaultError::WrongMint,
+        has_one = token_program @ VaultError::WrongTokenProgram,
+        constraint = token_vault.owner == user.key() @ VaultError::NotOwner
+    )]
+    pub token_vault: Account<'info, TokenVault>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = token_vault,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_tokens: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program,
+    )]
+    pub user_tokens: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub user: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+// Vaults created before the deposit totals were added are 8 + 32 bytes and
+// don't deserialize as `Vault` until `migrate_vault` has moved them to this
+// layout. The account traits are written out instead of using `#[account]`
+// so those vaults fail with NeedsMigration rather than Anchor's
+// AccountDidNotDeserialize.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Vault {
+    // layout version, `Vault::VERSION` for every vault on this layout
+    pub version: u8,
+    pub owner: Pubkey,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    // canonical PDA bump, saved by `initialize`
+    pub bump: u8,
+    // the wallet the PDA was derived from; unlike `owner` it never changes
+    pub creator: Pubkey,
+    // set by `propose_owner` until the proposed wallet accepts
+    pub pending_owner: Option<Pubkey>,
+    // unix timestamp before which nothing can be withdrawn; 0 when unlocked
+    pub lock_until: i64,
+    // withdrawals go through request_withdraw/execute_withdraw
+    pub delayed: bool,
+    // the requested withdrawal, 0 when there is none
+    pub pending_amount: u64,
+    pub available_at: i64,
+    // lamports held for depositors through receipts
+    pub shared_balance: u64,
+    // wallets besides the owner allowed to withdraw, at most MAX_WITHDRAWERS
+    pub withdrawers: Vec<Pubkey>,
+    // can claim the vault after `inactivity_secs` without owner activity;
+    // read through `beneficiary_at`
+    pub beneficiary: Option<Pubkey>,
+    pub inactivity_secs: i64,
+    // unix timestamp of the last instruction the owner signed
+    pub last_activity: i64,
+    // 0 for no limit
+    pub max_withdraw_per_epoch: u64,
+    // withdrawn so far in `last_withdraw_epoch`
+    pub epoch_withdrawn: u64,
+    pub last_withdraw_epoch: u64,
+    // ring of the last `RECENT_DEPOSITS` deposits
+    pub recent_deposits: [DepositRecord; 4],
+    // index in `recent_deposits` the next deposit overwrites, so also the
+    // oldest entry
+    pub next_deposit: u8,
+    // owner deposits and payouts, shared funds not included
+    pub deposit_count: u32,
+    pub withdraw_count: u32,
+    // unix timestamps, 0 before the first one
+    pub last_deposit_ts: i64,
+    pub last_withdraw_ts: i64,
+    // at most MAX_COSIGNERS wallets, `cosign_threshold` of which must sign
+    // every payout; 0 for none
+    pub cosigners: Vec<Pubkey>,
+    pub cosign_threshold: u8,
+    // the `deposit_vested` schedule; `vesting_duration` is 0 without one
+    pub vesting_amount: u64,
+    pub vesting_start: i64,
+    pub vesting_duration: u64,
+    // where payouts must go; read through `payout_address`, which applies
+    // `pending_payout` once `payout_change_at` has passed
+    pub current_payout: Option<Pubkey>,
+    pub pending_payout: Option<Pubkey>,
+    // 0 without a pending change
+    pub payout_change_at: i64,
+    // the beneficiary and inactivity period `beneficiary_at` switches to
+    // once `beneficiary_change_at` has passed
+    pub pending_beneficiary: Option<Pubkey>,
+    pub pending_inactivity_secs: i64,
+    // 0 without a pending change
+    pub beneficiary_change_at: i64,
+}
+
+impl Discriminator for Vault {
+    // sha256("account:Vault")[..8], as `#[account]` derives it
+    const DISCRIMINATOR: &'static [u8] = &[211, 8, 232, 43, 2, 152, 117, 119];
+}
+
+impl Owner for Vault {
+    fn owner() -> Pubkey {
+        crate::ID
+    }
+}
+
+impl AccountSerialize for Vault {
+    fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+        if writer.write_all(Self::DISCRIMINATOR).is_err()
+            || AnchorSerialize::serialize(self, writer).is_err()
+        {
+            return err!(ErrorCode::AccountDidNotSerialize);
+        }
+        Ok(())
+    }
+}
+
+impl AccountDeserialize for Vault {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
+        if buf.len() < Self::DISCRIMINATOR.len() {
+            return err!(ErrorCode::AccountDiscriminatorNotFound);
+        }
+        if &buf[..Self::DISCRIMINATOR.len()] != Self::DISCRIMINATOR {
+            return Err(error!(ErrorCode::AccountDiscriminatorMismatch).with_account_name("Vault"));
+        }
+        require!(buf.len() != Self::LEGACY_SPACE, VaultError::NeedsMigration);
+        Self::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        let mut data: &[u8] = &buf[Self::DISCRIMINATOR.len()..];
+        AnchorDeserialize::deserialize(&mut data)
+            .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct DepositRecord {
+    // all zeroes for deposits made without one
+    pub reference: [u8; 16],
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+impl Vault {
+    pub const VERSION: u8 = 1;
+    pub const MAX_WITHDRAWERS: usize = 5;
+    pub const RECENT_DEPOSITS: usize = 4;
+    pub const MAX_COSIGNERS: usize = 5;
+
+    // discriminator + version + owner + totals + bump + creator + pending
+    // owner + lock + delayed flag + pending withdrawal + shared balance +
+    // withdrawers + beneficiary + inactivity period + last activity + epoch
+    // limit and counters + recent deposits and ring index + deposit and
+    // withdrawal counts and timestamps + cosigners and threshold + vesting
+    // schedule + payout address, pending change and its time + pending
+    // beneficiary change and its time
+    pub const SPACE: usize = 8
+        + 1
+        + 32
+        + 8
+        + 8
+        + 1
+        + 32
+        + (1 + 32)
+        + 8
+        + 1
+        + 8
+        + 8
+        + 8
+        + (4 + 32 * Self::MAX_WITHDRAWERS)
+        + (1 + 32)
+        + 8
+        + 8
+        + 8
+        + 8
+        + 8
+        + (16 + 8 + 8) * Self::RECENT_DEPOSITS
+        + 1
+        + 4
+        + 4
+        + 8
+        + 8
+        + (4 + 32 * Self::MAX_COSIGNERS)
+        + 1
+        + 8
+        + 8
+        + 8
+        + (1 + 32)
+        + (1 + 32)
+        + 8
+        + (1 + 32)
+        + 8
+        + 8;
+    // discriminator + owner
+    pub const LEGACY_SPACE: usize = 8 + 32;
+
+    // What can still be withdrawn in `epoch`, or `None` without a limit.
+    pub fn epoch_allowance(&self, epoch: u64) -> Option<u64> {
+        if self.max_withdraw_per_epoch == 0 {
+            return None;
+        }
+        let used = if self.last_withdraw_epoch == epoch {
+            self.epoch_withdrawn
+        } else {
+            0
+        };
+        Some(self.max_withdraw_per_epoch.saturating_sub(used))
+    }
+
+    pub fn record_deposit(&mut self, record: DepositRecord) {
+        let index = usize::from(self.next_deposit) % Self::RECENT_DEPOSITS;
+        self.recent_deposits[index] = record;
+        self.next_deposit = ((index + 1) % Self::RECENT_DEPOSITS) as u8;
+    }
+
+    pub fn can_withdraw(&self, key: &Pubkey) -> bool {
+        self.owner == *key || self.withdrawers.contains(key)
+    }
+
+    // The payout address in force at `now`. Ownership transfers keep it, so
+    // a stolen key can't shed it by handing the vault to itself.
+    pub fn payout_address(&self, now: i64) -> Option<Pubkey> {
+        if self.payout_change_at != 0 && now >= self.payout_change_at {
+            self.pending_payout
+        } else {
+            self.current_payout
+        }
+    }
+
+    // The beneficiary and inactivity period in force at `now`.
+    pub fn beneficiary_at(&self, now: i64) -> (Option<Pubkey>, i64) {
+        if self.beneficiary_change_at != 0 && now >= self.beneficiary_change_at {
+            (self.pending_beneficiary, self.pending_inactivity_secs)
+        } else {
+            (self.beneficiary, self.inactivity_secs)
+        }
+    }
+
+    // The part of the vesting deposit not yet released at `now`. Vested
+    // lamports round down, so nothing is released early.
+    pub fn unvested(&self, now: i64) -> u64 {
+        if self.vesting_duration == 0 {
+            return 0;
+        }
+        let elapsed = u64::try_from(now.saturating_sub(self.vesting_start))
+            .unwrap_or(0)
+            .min(self.vesting_duration);
+        let vested = u128::from(self.vesting_amount) * u128::from(elapsed)
+            / u128::from(self.vesting_duration);
+        // vested <= vesting_amount, so this fits
+        self.vesting_amount - vested as u64
+    }
+
+    // Deposits that can be withdrawn at `now`. Withdrawals draw on vested
+    // and liquid deposits alike, so only the unvested part is held back.
+    pub fn available_deposits(&self, now: i64) -> Result<u64> {
+        Ok(self
+            .remaining_deposits()?
+            .saturating_sub(self.unvested(now)))
+    }
+
+    // Deposits not yet withdrawn. Lamports sent to the vault directly are
+    // not the owner's to take out.
+    pub fn remaining_deposits(&self) -> Result<u64> {
+        self.total_deposited
+            .checked_sub(self.total_withdrawn)
+            .ok_or_else(|| error!(VaultError::ArithmeticOverflow))
+    }
+}
+
+// Instructions that deposit or pay out take this PDA as an unchecked
+// account, required even before it's initialized, so leaving it out can't
+// skip the pause, the cap or the fee.
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    // stops deposits, never withdrawals
+    pub paused: bool,
+    // owner and shared deposits a vault may hold; 0 for no cap
+    pub max_deposit_per_vault: u64,
+    pub bump: u8,
+    // withdrawal fee in basis points, paid to `treasury`
+    pub fee_bps: u16,
+    pub treasury: Pubkey,
+    // when deposits were paused; 0 while unpaused
+    pub paused_at: i64,
+    // how long after `paused_at` the admin can force withdrawals
+    pub emergency_grace_secs: i64,
+}
+
+impl Config {
+    // discriminator + admin + paused + cap + bump + fee + treasury + pause
+    // time + grace period
+    pub const SPACE: usize = 8 + 32 + 1 + 8 + 1 + 2 + 32 + 8 + 8;
+}
+
+#[account]
+pub struct DepositReceipt {
+    pub depositor: Pubkey,
+    pub vault: Pubkey,
+    // lamports the depositor can still withdraw
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl DepositReceipt {
+    // discriminator + depositor + vault + amount + bump
+    pub const SPACE: usize = 8 + 32 + 32 + 8 + 1;
+}
+
+#[account]
+pub struct TokenVault {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub bump: u8,
+    // the classic token program or token-2022, whichever owns `mint`
+    pub token_program: Pubkey,
+}
+
+impl TokenVault {
+    // discriminator + owner + mint + bump + token program
+    pub const SPACE: usize = 8 + 32 + 32 + 1 + 32;
+}
+
+#[event]
+pub struct InitializeEvent {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+}
+
+// `new_balance` is the vault's lamports after the instruction, rent included.
+#[event]
+pub struct DepositEvent {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64,
+    pub new_balance: u64,
+    // all zeroes unless made with `deposit_with_reference`
+    pub reference: [u8; 16],
+    pub deposit_count: u32,
+    pub last_deposit_ts: i64,
+}
+
+#[event]
+pub struct DepositForEvent {
+    pub payer: Pubkey,
+    pub beneficiary: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64,
+    pub new_balance: u64,
+    pub deposit_count: u32,
+    pub last_deposit_ts: i64,
+}
+
+// `user` is the owner or the listed withdrawer that signed; `recipient` is the
+// same wallet unless paid out with `withdraw_to`.
+#[event]
+pub struct WithdrawEvent {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    // part of `amount` paid to the treasury
+    pub fee: u64,
+    pub new_balance: u64,
+    pub withdraw_count: u32,
+    pub last_withdraw_ts: i64,
+}
+
+#[event]
+pub struct WithdrawRequestedEvent {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64,
+    pub available_at: i64,
+}
+
+#[event]
+pub struct WithdrawCancelledEvent {
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64,
+}
+
+// `receipt_amount` is what the depositor can still withdraw.
+#[event]
+pub struct SharedDepositEvent {
+    pub depositor: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64,
+    pub receipt_amount: u64,
+}
+
+#[event]
+pub struct SharedWithdrawEvent {
+    pub depositor: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64,
+    pub receipt_amount: u64,
+}
+
+// `amount` is everything the beneficiary got: the vault's rent and deposits,
+// less the withdrawal fee on the deposits.
+#[event]
+pub struct InactiveClaimEvent {
+    pub beneficiary: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct WithdrawersChangedEvent {
+    pub vault: Pubkey,
+    pub withdrawer: Pubkey,
+    pub added: bool,
+}
+
+#[event]
+pub struct CosignersChangedEvent {
+    pub vault: Pubkey,
+    pub cosigners: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+// `address` is `None` when the binding is being removed.
+#[event]
+pub struct PayoutAddressRequestedEvent {
+    pub vault: Pubkey,
+    pub address: Option<Pubkey>,
+    pub effective_at: i64,
+}
+
+#[event]
+pub struct OwnershipProposedEvent {
+    pub owner: Pubkey,
+    pub vault: Pubkey,
+    pub pending_owner: Pubkey,
+}
+
+#[event]
+pub struct OwnershipTransferredEvent {
+    pub previous_owner: Pubkey,
+    pub new_owner: Pubkey,
+    pub vault: Pubkey,
+}
+
+#[event]
+pub struct TokenDepositEvent {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    // `amount` minus any transfer fee
+    pub received: u64,
+}
+
+#[event]
+pub struct TokenWithdrawEvent {
+    pub user: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    // `amount` minus any transfer fee
+    pub received: u64,
+}
+
+// `admin` forced `amount` out of `owner`'s `vault` to `recipient`, the owner
+// unless the vault is bound to a payout address.
+#[event]
+pub struct EmergencyWithdrawEvent {
+    pub admin: Pubkey,
+    pub vault: Pubkey,
+    pub owner: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub new_balance: u64,
+    pub withdraw_count: u32,
+    pub last_withdraw_ts: i64,
+}
+
+// `amount` is everything returned to the owner, rent included.
+#[event]
+pub struct VaultClosedEvent {
+    pub owner: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64,
+}
+
+#[error_code]
+pub enum VaultError {
+    #[msg("Insufficient funds in the vault.")]
+    InsufficientFunds,
+    #[msg("Only the vault owner can do this.")]
+    NotOwner,
+    #[msg("Nothing above the rent-exempt minimum to withdraw.")]
+    NothingToWithdraw,
+    #[msg("Withdrawal would leave the vault below its rent-exempt minimum.")]
+    WouldBreakRentExemption,
+    #[msg("Withdrawal exceeds the deposits left in the vault.")]
+    ExceedsDeposits,
+    #[msg("Arithmetic overflow.")]
+    ArithmeticOverflow,
+    #[msg("Amount must be greater than zero.")]
+    ZeroAmount,
+    #[msg("Only the proposed owner can accept ownership.")]
+    NotPendingOwner,
+    #[msg("The vault can't pay out to itself.")]
+    RecipientIsVault,
+    #[msg("The mint doesn't match the token vault's.")]
+    WrongMint,
+    #[msg("The vault is still locked.")]
+    StillLocked,
+    #[msg("This vault only pays out through request_withdraw and execute_withdraw.")]
+    DelayedWithdrawals,
+    #[msg("No withdrawal has been requested.")]
+    NoPendingWithdrawal,
+    #[msg("The withdrawal delay hasn't passed yet.")]
+    WithdrawalNotReady,
+    #[msg("Depositors still have funds in the vault.")]
+    SharedFundsOutstanding,
+    #[msg("The receipt still holds a balance.")]
+    ReceiptNotEmpty,
+    #[msg("Only the config admin can do this.")]
+    NotAdmin,
+    #[msg("Deposits are paused.")]
+    ProgramPaused,
+    #[msg("Deposit would take the vault over its cap.")]
+    DepositCapExceeded,
+    #[msg("The fee can't be above 10000 basis points.")]
+    InvalidFee,
+    #[msg("The treasury doesn't match the config's.")]
+    WrongTreasury,
+    #[msg("Only the owner or a listed withdrawer can withdraw.")]
+    NotAuthorized,
+    #[msg("The vault already has the maximum number of withdrawers.")]
+    TooManyWithdrawers,
+    #[msg("The wallet is already a withdrawer.")]
+    AlreadyWithdrawer,
+    #[msg("The wallet isn't a withdrawer.")]
+    NotWithdrawer,
+    #[msg("The vault has to be migrated with migrate_vault first.")]
+    NeedsMigration,
+    #[msg("The vault is already on the current layout.")]
+    AlreadyMigrated,
+    #[msg("The inactivity period must be positive.")]
+    InvalidInactivityPeriod,
+    #[msg("Only the vault's beneficiary can claim it.")]
+    NotBeneficiary,
+    #[msg("The owner has been active too recently.")]
+    OwnerStillActive,
+    #[msg("Withdrawal exceeds the vault's limit for this epoch.")]
+    WithdrawLimitExceeded,
+    #[msg("A requested withdrawal hasn't been executed or cancelled.")]
+    WithdrawalPending,
+    #[msg("The grace period can't be negative.")]
+    InvalidGracePeriod,
+    #[msg("Deposits aren't paused.")]
+    NotPaused,
+    #[msg("Deposits haven't been paused for the grace period yet.")]
+    GracePeriodNotOver,
+    #[msg("A vault can have at most 5 cosigners.")]
+    TooManyCosigners,
+    #[msg("The threshold can't exceed the number of cosigners.")]
+    InvalidThreshold,
+    #[msg("Each cosigner can only be listed once.")]
+    DuplicateCosigner,
+    #[msg("Not enough cosigners signed.")]
+    NotEnoughCosigners,
+    #[msg("The vesting duration must be positive.")]
+    InvalidVestingSchedule,
+    #[msg("The vault's current vesting deposit hasn't fully vested.")]
+    VestingInProgress,
+    #[msg("Those funds haven't vested yet.")]
+    StillVesting,
+    #[msg("The token program doesn't match the token vault's.")]
+    WrongTokenProgram,
+    #[msg("Payouts from this vault must go to its payout address.")]
+    WrongPayoutAddress,
+    #[msg("Remove the vault's payout address before closing it.")]
+    PayoutAddressBound,
+    #[msg("A batch deposit takes between 1 and MAX_BATCH_DEPOSITS vaults.")]
+    InvalidBatchSize,
+    #[msg("A batch deposit needs a beneficiary and vault account per amount.")]
+    BatchAccountsMismatch,
+    #[msg("A batch deposit account isn't the beneficiary's writable vault.")]
+    InvalidBatchVault,
+}
+
+#[cfg(test)]
+mod tests;
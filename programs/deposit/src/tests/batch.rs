@@ -0,0 +1,145 @@
+use super::*;
+
+// `payer` funding each beneficiary's vault with the matching amount.
+fn batch_deposit(payer: &Pubkey, beneficiaries: &[Pubkey], amounts: Vec<u64>) -> Instruction {
+    let mut ix = instruction(
+        crate::accounts::BatchDeposit {
+            config: config_address(),
+            payer: *payer,
+            system_program: system_program::ID,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::BatchDeposit { amounts },
+    );
+    for beneficiary in beneficiaries {
+        ix.accounts
+            .push(AccountMeta::new_readonly(*beneficiary, false));
+        ix.accounts
+            .push(AccountMeta::new(vault_address(beneficiary), false));
+    }
+    ix
+}
+
+// `count` wallets with empty vaults.
+async fn beneficiaries(harness: &mut Harness, count: usize) -> Vec<Pubkey> {
+    let mut beneficiaries = vec![];
+    for _ in 0..count {
+        beneficiaries.push(harness.funded_vault(0).await.pubkey());
+    }
+    beneficiaries
+}
+
+#[tokio::test]
+async fn batch_deposit_funds_five_vaults() {
+    let mut harness = Harness::new().await;
+    let payer = harness.wallet(10 * SOL);
+    let beneficiaries = beneficiaries(&mut harness, 5).await;
+    let amounts: Vec<u64> = (1..=5).map(|i| i * SOL / 10).collect();
+    let rent = harness.rent(Vault::SPACE).await;
+
+    let logs = harness
+        .logs(
+            batch_deposit(&payer.pubkey(), &beneficiaries, amounts.clone()),
+            &[&payer],
+        )
+        .await;
+
+    assert_eq!(
+        harness.lamports(&payer.pubkey()).await,
+        10 * SOL - 15 * SOL / 10
+    );
+    let events = events::events::<DepositForEvent>(&logs);
+    assert_eq!(events.len(), 5);
+    for ((beneficiary, amount), event) in beneficiaries.iter().zip(&amounts).zip(&events) {
+        let vault = harness.vault(beneficiary).await.unwrap();
+        assert_eq!(vault.owner, *beneficiary);
+        assert_eq!(vault.total_deposited, *amount);
+        assert_eq!(vault.deposit_count, 1);
+        assert_eq!(
+            harness.lamports(&vault_address(beneficiary)).await,
+            rent + amount
+        );
+        assert_eq!(event.payer, payer.pubkey());
+        assert_eq!(event.beneficiary, *beneficiary);
+        assert_eq!(event.vault, vault_address(beneficiary));
+        assert_eq!(event.amount, *amount);
+    }
+}
+
+#[tokio::test]
+async fn one_wrong_vault_fails_the_whole_batch() {
+    let mut harness = Harness::new().await;
+    let payer = harness.wallet(10 * SOL);
+    let beneficiaries = beneficiaries(&mut harness, 3).await;
+
+    let mut ix = batch_deposit(&payer.pubkey(), &beneficiaries, vec![SOL; 3]);
+    // the last beneficiary with the first one's vault
+    let last = ix.accounts.len() - 1;
+    ix.accounts[last] = AccountMeta::new(vault_address(&beneficiaries[0]), false);
+    let result = harness.send(ix, &[&payer]).await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::InvalidBatchVault));
+    assert_eq!(harness.lamports(&payer.pubkey()).await, 10 * SOL);
+    for beneficiary in &beneficiaries {
+        let vault = harness.vault(beneficiary).await.unwrap();
+        assert_eq!(vault.total_deposited, 0);
+    }
+}
+
+#[tokio::test]
+async fn batch_amounts_must_match_the_vaults_passed() {
+    let mut harness = Harness::new().await;
+    let payer = harness.wallet(10 * SOL);
+    let beneficiaries = beneficiaries(&mut harness, 2).await;
+
+    let ix = batch_deposit(&payer.pubkey(), &beneficiaries, vec![SOL; 3]);
+    let result = harness.send(ix, &[&payer]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::BatchAccountsMismatch)
+    );
+
+    let mut ix = batch_deposit(&payer.pubkey(), &beneficiaries, vec![SOL; 2]);
+    // a beneficiary without its vault
+    ix.accounts.pop();
+    let result = harness.send(ix, &[&payer]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::BatchAccountsMismatch)
+    );
+    assert_eq!(harness.lamports(&payer.pubkey()).await, 10 * SOL);
+}
+
+#[tokio::test]
+async fn a_batch_holds_one_to_max_batch_deposits_vaults() {
+    let mut harness = Harness::new().await;
+    let payer = harness.wallet(10 * SOL);
+    let beneficiaries = beneficiaries(&mut harness, MAX_BATCH_DEPOSITS).await;
+
+    // the same vault over and over keeps the transaction small
+    let ix = batch_deposit(
+        &payer.pubkey(),
+        &[beneficiaries[0]; MAX_BATCH_DEPOSITS + 1],
+        vec![SOL / 10; MAX_BATCH_DEPOSITS + 1],
+    );
+    let result = harness.send(ix, &[&payer]).await;
+    assert_eq!(error_code(result), u32::from(VaultError::InvalidBatchSize));
+    let result = harness
+        .send(batch_deposit(&payer.pubkey(), &[], vec![]), &[&payer])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::InvalidBatchSize));
+
+    let ix = batch_deposit(
+        &payer.pubkey(),
+        &beneficiaries,
+        vec![SOL / 10; MAX_BATCH_DEPOSITS],
+    );
+    harness.send(ix, &[&payer]).await.unwrap();
+    for beneficiary in &beneficiaries {
+        let vault = harness.vault(beneficiary).await.unwrap();
+        assert_eq!(vault.total_deposited, SOL / 10);
+    }
+}
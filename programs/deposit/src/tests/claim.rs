@@ -0,0 +1,161 @@
+use super::*;
+
+const INACTIVITY_SECS: i64 = 30 * 24 * 60 * 60;
+
+// A funded vault with a beneficiary, and when the owner was last active.
+async fn vault_with_beneficiary(harness: &mut Harness) -> (Keypair, Keypair, i64) {
+    let owner = harness.funded_vault(SOL).await;
+    let beneficiary = harness.wallet(SOL);
+    harness
+        .send(
+            set_beneficiary(&owner.pubkey(), &beneficiary.pubkey(), INACTIVITY_SECS),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    let last_activity = harness.vault(&owner.pubkey()).await.unwrap().last_activity;
+    (owner, beneficiary, last_activity)
+}
+
+#[tokio::test]
+async fn claim_just_short_of_the_inactivity_period_fails() {
+    let mut harness = Harness::new().await;
+    let (owner, beneficiary, last_activity) = vault_with_beneficiary(&mut harness).await;
+
+    harness.warp_to(last_activity + INACTIVITY_SECS).await;
+    let result = harness
+        .send(
+            claim_inactive(&owner.pubkey(), &beneficiary.pubkey()),
+            &[&beneficiary],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::OwnerStillActive));
+    assert!(harness.vault(&owner.pubkey()).await.is_some());
+}
+
+#[tokio::test]
+async fn claim_past_the_inactivity_period_closes_the_vault() {
+    let mut harness = Harness::new().await;
+    let (owner, beneficiary, last_activity) = vault_with_beneficiary(&mut harness).await;
+    let rent = harness.rent(Vault::SPACE).await;
+    let before = harness.lamports(&beneficiary.pubkey()).await;
+
+    harness.warp_to(last_activity + INACTIVITY_SECS + 1).await;
+    harness
+        .send(
+            claim_inactive(&owner.pubkey(), &beneficiary.pubkey()),
+            &[&beneficiary],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        harness.lamports(&beneficiary.pubkey()).await,
+        before + SOL + rent
+    );
+    assert!(harness.vault(&owner.pubkey()).await.is_none());
+}
+
+#[tokio::test]
+async fn owner_activity_restarts_the_clock() {
+    let mut harness = Harness::new().await;
+    let (owner, beneficiary, last_activity) = vault_with_beneficiary(&mut harness).await;
+
+    harness.warp_to(last_activity + INACTIVITY_SECS).await;
+    harness
+        .send(deposit(&owner.pubkey(), 1), &[&owner])
+        .await
+        .unwrap();
+    harness.warp_to(last_activity + INACTIVITY_SECS + 1).await;
+    let result = harness
+        .send(
+            claim_inactive(&owner.pubkey(), &beneficiary.pubkey()),
+            &[&beneficiary],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::OwnerStillActive));
+}
+
+#[tokio::test]
+async fn only_the_beneficiary_can_claim() {
+    let mut harness = Harness::new().await;
+    let (owner, _, last_activity) = vault_with_beneficiary(&mut harness).await;
+    let stranger = harness.wallet(SOL);
+
+    harness.warp_to(last_activity + INACTIVITY_SECS + 1).await;
+    let result = harness
+        .send(
+            claim_inactive(&owner.pubkey(), &stranger.pubkey()),
+            &[&stranger],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotBeneficiary));
+    assert!(harness.vault(&owner.pubkey()).await.is_some());
+}
+
+#[tokio::test]
+async fn new_beneficiary_waits_out_the_change_delay() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(SOL).await;
+    let beneficiary = harness.wallet(SOL);
+    harness
+        .send(
+            set_beneficiary(&owner.pubkey(), &beneficiary.pubkey(), 60),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(
+        vault.beneficiary_change_at,
+        vault.last_activity + PAYOUT_CHANGE_DELAY_SECS
+    );
+
+    // idle well past the inactivity period, but the change isn't in force
+    harness.warp_to(vault.beneficiary_change_at - 1).await;
+    let claim = claim_inactive(&owner.pubkey(), &beneficiary.pubkey());
+    let result = harness.send(claim, &[&beneficiary]).await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotBeneficiary));
+
+    harness.warp_to(vault.beneficiary_change_at).await;
+    let claim = claim_inactive(&owner.pubkey(), &beneficiary.pubkey());
+    harness.send(claim, &[&beneficiary]).await.unwrap();
+    assert!(harness.vault(&owner.pubkey()).await.is_none());
+}
+
+#[tokio::test]
+async fn clearing_the_beneficiary_waits_out_the_change_delay() {
+    let mut harness = Harness::new().await;
+    let beneficiary = harness.wallet(SOL);
+    let mut owners = Vec::new();
+    for _ in 0..2 {
+        let owner = harness.funded_vault(SOL).await;
+        harness
+            .send(
+                set_beneficiary(&owner.pubkey(), &beneficiary.pubkey(), 60),
+                &[&owner],
+            )
+            .await
+            .unwrap();
+        owners.push(owner);
+    }
+    let vault = harness.vault(&owners[0].pubkey()).await.unwrap();
+    harness.warp_to(vault.beneficiary_change_at).await;
+    for owner in &owners {
+        harness
+            .send(clear_beneficiary(&owner.pubkey()), &[owner])
+            .await
+            .unwrap();
+    }
+    let cleared = harness.vault(&owners[0].pubkey()).await.unwrap();
+
+    // still the beneficiary while the removal is pending
+    harness.warp_to(cleared.last_activity + 61).await;
+    let claim = claim_inactive(&owners[0].pubkey(), &beneficiary.pubkey());
+    harness.send(claim, &[&beneficiary]).await.unwrap();
+
+    harness.warp_to(cleared.beneficiary_change_at).await;
+    let claim = claim_inactive(&owners[1].pubkey(), &beneficiary.pubkey());
+    let result = harness.send(claim, &[&beneficiary]).await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotBeneficiary));
+}
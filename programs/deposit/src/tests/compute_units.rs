@@ -0,0 +1,613 @@
+// Compute-unit budgets for every instruction, metered on the SBF build: a
+// builtin doesn't consume units the way the program does on chain, so
+// these need `cargo build-sbf` first and run with
+// `SBF_OUT_DIR=target/deploy cargo test -p deposit compute_units -- --ignored --nocapture`,
+// which also prints every measurement. CI runs them in the `compute-units`
+// job. Each budget covers the whole transaction, including the 150 units
+// of its compute budget instruction.
+
+use anchor_spl::{token::spl_token, token_2022::spl_token_2022};
+
+use super::*;
+
+const INITIALIZE: u64 = 30_000;
+const INITIALIZE_WITH_DEPOSIT: u64 = 40_000;
+const DEPOSIT_INIT_IF_NEEDED: u64 = 40_000;
+const DEPOSIT: u64 = 20_000;
+const DEPOSIT_WITH_REFERENCE: u64 = 20_000;
+const DEPOSIT_LOCKED: u64 = 20_000;
+const DEPOSIT_VESTED: u64 = 20_000;
+const DEPOSIT_FOR: u64 = 20_000;
+// three vaults
+const BATCH_DEPOSIT: u64 = 60_000;
+const WITHDRAW: u64 = 30_000;
+const WITHDRAW_WITH_FEE: u64 = 35_000;
+const WITHDRAW_TO: u64 = 30_000;
+const WITHDRAW_ALL: u64 = 30_000;
+const REQUEST_WITHDRAW: u64 = 15_000;
+const EXECUTE_WITHDRAW: u64 = 30_000;
+const CANCEL_WITHDRAW: u64 = 15_000;
+const DEPOSIT_SHARED: u64 = 35_000;
+const WITHDRAW_SHARED: u64 = 20_000;
+const CLOSE_RECEIPT: u64 = 10_000;
+const MIGRATE_VAULT: u64 = 30_000;
+const ADD_WITHDRAWER: u64 = 15_000;
+const REMOVE_WITHDRAWER: u64 = 15_000;
+const PROPOSE_OWNER: u64 = 15_000;
+const ACCEPT_OWNERSHIP: u64 = 15_000;
+const SET_BENEFICIARY: u64 = 15_000;
+const CLEAR_BENEFICIARY: u64 = 15_000;
+const CLAIM_INACTIVE: u64 = 25_000;
+const CLOSE_VAULT: u64 = 25_000;
+const WITHDRAW_AND_CLOSE: u64 = 25_000;
+const SET_COSIGNERS: u64 = 20_000;
+const SET_PAYOUT_ADDRESS: u64 = 15_000;
+const SET_WITHDRAW_LIMIT: u64 = 15_000;
+const INITIALIZE_CONFIG: u64 = 20_000;
+const SET_PAUSED: u64 = 10_000;
+const SET_EMERGENCY_GRACE_PERIOD: u64 = 10_000;
+const ADMIN_EMERGENCY_WITHDRAW: u64 = 30_000;
+const SET_DEPOSIT_CAP: u64 = 10_000;
+const SET_FEE: u64 = 10_000;
+const INITIALIZE_TOKEN_VAULT: u64 = 80_000;
+const DEPOSIT_TOKENS: u64 = 40_000;
+const WITHDRAW_TOKENS: u64 = 45_000;
+
+async fn sbf_harness() -> Harness {
+    // `None` loads deposit.so from SBF_OUT_DIR or tests/fixtures
+    let mut program = ProgramTest::new("deposit", ID, None);
+    program.prefer_bpf(true);
+    Harness::start(program).await
+}
+
+// Checks every measurement before failing, so one run reports all
+// the instructions over budget.
+#[derive(Default)]
+struct Budgets {
+    over: Vec<String>,
+}
+
+impl Budgets {
+    fn check(&mut self, name: &str, consumed: u64, budget: u64) {
+        println!("{name}: {consumed} compute units (budget {budget})");
+        if consumed > budget {
+            self.over
+                .push(format!("{name} used {consumed} of {budget}"));
+        }
+    }
+
+    fn assert_within(self) {
+        assert!(
+            self.over.is_empty(),
+            "over budget: {}",
+            self.over.join(", ")
+        );
+    }
+}
+
+fn owner_accounts(owner: &Pubkey) -> crate::accounts::UpdateWithdrawers {
+    crate::accounts::UpdateWithdrawers {
+        vault: vault_address(owner),
+        owner: *owner,
+    }
+}
+
+#[tokio::test]
+#[ignore = "meters the SBF build; see the module comment"]
+async fn owner_instructions_stay_within_budget() {
+    let mut harness = sbf_harness().await;
+    let mut budgets = Budgets::default();
+    let user = harness.wallet(10 * SOL);
+    let owner = user.pubkey();
+    let friend = harness.wallet(SOL);
+
+    let consumed = harness.metered(initialize(&owner), &[&user]).await;
+    budgets.check("initialize", consumed, INITIALIZE);
+    let consumed = harness.metered(deposit(&owner, 2 * SOL), &[&user]).await;
+    budgets.check("deposit", consumed, DEPOSIT);
+    let accounts = crate::accounts::Deposit {
+        vault: vault_address(&owner),
+        config: config_address(),
+        user: owner,
+        system_program: system_program::ID,
+        #[cfg(feature = "cpi-events")]
+        event_authority: event_authority(),
+        #[cfg(feature = "cpi-events")]
+        program: ID,
+    };
+    let data = crate::instruction::DepositWithReference {
+        amount: SOL,
+        reference: [7; 16],
+    };
+    let consumed = harness.metered(instruction(accounts, data), &[&user]).await;
+    budgets.check("deposit_with_reference", consumed, DEPOSIT_WITH_REFERENCE);
+    let consumed = harness
+        .metered(withdraw(&owner, &owner, None, SOL / 2), &[&user])
+        .await;
+    budgets.check("withdraw", consumed, WITHDRAW);
+    let accounts = crate::accounts::WithdrawTo {
+        vault: vault_address(&owner),
+        user: owner,
+        recipient: friend.pubkey(),
+        config: config_address(),
+        treasury: None,
+        #[cfg(feature = "cpi-events")]
+        event_authority: event_authority(),
+        #[cfg(feature = "cpi-events")]
+        program: ID,
+    };
+    let data = crate::instruction::WithdrawTo { amount: SOL / 4 };
+    let consumed = harness.metered(instruction(accounts, data), &[&user]).await;
+    budgets.check("withdraw_to", consumed, WITHDRAW_TO);
+    let consumed = harness
+        .metered(invariants::withdraw_all(&owner, None), &[&user])
+        .await;
+    budgets.check("withdraw_all", consumed, WITHDRAW_ALL);
+
+    let data = crate::instruction::AddWithdrawer {
+        withdrawer: friend.pubkey(),
+    };
+    let consumed = harness
+        .metered(instruction(owner_accounts(&owner), data), &[&user])
+        .await;
+    budgets.check("add_withdrawer", consumed, ADD_WITHDRAWER);
+    let data = crate::instruction::RemoveWithdrawer {
+        withdrawer: friend.pubkey(),
+    };
+    let consumed = harness
+        .metered(instruction(owner_accounts(&owner), data), &[&user])
+        .await;
+    budgets.check("remove_withdrawer", consumed, REMOVE_WITHDRAWER);
+    let data = crate::instruction::SetWithdrawLimit {
+        max_withdraw_per_epoch: 5 * SOL,
+    };
+    let consumed = harness
+        .metered(instruction(owner_accounts(&owner), data), &[&user])
+        .await;
+    budgets.check("set_withdraw_limit", consumed, SET_WITHDRAW_LIMIT);
+    let consumed = harness
+        .metered(set_cosigners(&owner, vec![friend.pubkey()], 0), &[&user])
+        .await;
+    budgets.check("set_cosigners", consumed, SET_COSIGNERS);
+    let consumed = harness
+        .metered(set_payout_address(&owner, None), &[&user])
+        .await;
+    budgets.check("set_payout_address", consumed, SET_PAYOUT_ADDRESS);
+    let consumed = harness
+        .metered(set_beneficiary(&owner, &friend.pubkey(), 60), &[&user])
+        .await;
+    budgets.check("set_beneficiary", consumed, SET_BENEFICIARY);
+    let consumed = harness.metered(clear_beneficiary(&owner), &[&user]).await;
+    budgets.check("clear_beneficiary", consumed, CLEAR_BENEFICIARY);
+
+    let consumed = harness.metered(deposit(&owner, SOL), &[&user]).await;
+    budgets.check("deposit", consumed, DEPOSIT);
+    let consumed = harness.metered(close_vault(&owner, None), &[&user]).await;
+    budgets.check("close_vault", consumed, CLOSE_VAULT);
+
+    let consumed = harness.metered(initialize(&owner), &[&user]).await;
+    budgets.check("initialize", consumed, INITIALIZE);
+    let consumed = harness
+        .metered(propose_owner(&owner, &friend.pubkey()), &[&user])
+        .await;
+    budgets.check("propose_owner", consumed, PROPOSE_OWNER);
+    let consumed = harness
+        .metered(accept_ownership(&owner, &friend.pubkey()), &[&friend])
+        .await;
+    budgets.check("accept_ownership", consumed, ACCEPT_OWNERSHIP);
+
+    budgets.assert_within();
+}
+
+#[tokio::test]
+#[ignore = "meters the SBF build; see the module comment"]
+async fn deposit_instructions_stay_within_budget() {
+    let mut harness = sbf_harness().await;
+    let mut budgets = Budgets::default();
+    let user = harness.wallet(20 * SOL);
+    let owner = user.pubkey();
+
+    let accounts = crate::accounts::InitializeWithDeposit {
+        vault: vault_address(&owner),
+        config: config_address(),
+        user: owner,
+        system_program: system_program::ID,
+        #[cfg(feature = "cpi-events")]
+        event_authority: event_authority(),
+        #[cfg(feature = "cpi-events")]
+        program: ID,
+    };
+    let data = crate::instruction::InitializeWithDeposit {
+        amount: SOL,
+        delayed: false,
+    };
+    let consumed = harness.metered(instruction(accounts, data), &[&user]).await;
+    budgets.check("initialize_with_deposit", consumed, INITIALIZE_WITH_DEPOSIT);
+
+    let newcomer = harness.wallet(2 * SOL);
+    let accounts = crate::accounts::DepositInitIfNeeded {
+        vault: vault_address(&newcomer.pubkey()),
+        config: config_address(),
+        user: newcomer.pubkey(),
+        system_program: system_program::ID,
+        #[cfg(feature = "cpi-events")]
+        event_authority: event_authority(),
+        #[cfg(feature = "cpi-events")]
+        program: ID,
+    };
+    let data = crate::instruction::DepositInitIfNeeded { amount: SOL };
+    let consumed = harness
+        .metered(instruction(accounts, data), &[&newcomer])
+        .await;
+    budgets.check(
+        "deposit_init_if_needed, creating the vault",
+        consumed,
+        DEPOSIT_INIT_IF_NEEDED,
+    );
+
+    let accounts = crate::accounts::DepositFor {
+        vault: vault_address(&newcomer.pubkey()),
+        config: config_address(),
+        beneficiary: newcomer.pubkey(),
+        payer: owner,
+        system_program: system_program::ID,
+        #[cfg(feature = "cpi-events")]
+        event_authority: event_authority(),
+        #[cfg(feature = "cpi-events")]
+        program: ID,
+    };
+    let data = crate::instruction::DepositFor { amount: SOL };
+    let consumed = harness.metered(instruction(accounts, data), &[&user]).await;
+    budgets.check("deposit_for", consumed, DEPOSIT_FOR);
+
+    let third = harness.funded_vault(0).await;
+    let accounts = crate::accounts::BatchDeposit {
+        config: config_address(),
+        payer: owner,
+        system_program: system_program::ID,
+        #[cfg(feature = "cpi-events")]
+        event_authority: event_authority(),
+        #[cfg(feature = "cpi-events")]
+        program: ID,
+    };
+    let mut batch = instruction(
+        accounts,
+        crate::instruction::BatchDeposit {
+            amounts: vec![SOL / 10; 3],
+        },
+    );
+    for beneficiary in [owner, newcomer.pubkey(), third.pubkey()] {
+        batch
+            .accounts
+            .push(AccountMeta::new_readonly(beneficiary, false));
+        batch
+            .accounts
+            .push(AccountMeta::new(vault_address(&beneficiary), false));
+    }
+    let consumed = harness.metered(batch, &[&user]).await;
+    budgets.check("batch_deposit of three vaults", consumed, BATCH_DEPOSIT);
+
+    let depositor = harness.wallet(2 * SOL);
+    let vault = vault_address(&owner);
+    let receipt = receipt_address(&vault, &depositor.pubkey());
+    let accounts = crate::accounts::DepositShared {
+        vault,
+        receipt,
+        config: config_address(),
+        depositor: depositor.pubkey(),
+        system_program: system_program::ID,
+    };
+    let data = crate::instruction::DepositShared { amount: SOL };
+    let consumed = harness
+        .metered(instruction(accounts, data), &[&depositor])
+        .await;
+    budgets.check("deposit_shared", consumed, DEPOSIT_SHARED);
+    let accounts = crate::accounts::WithdrawShared {
+        vault,
+        receipt,
+        depositor: depositor.pubkey(),
+    };
+    let data = crate::instruction::WithdrawShared { amount: SOL };
+    let consumed = harness
+        .metered(instruction(accounts, data), &[&depositor])
+        .await;
+    budgets.check("withdraw_shared", consumed, WITHDRAW_SHARED);
+    let accounts = crate::accounts::CloseReceipt {
+        receipt,
+        depositor: depositor.pubkey(),
+    };
+    let consumed = harness
+        .metered(
+            instruction(accounts, crate::instruction::CloseReceipt {}),
+            &[&depositor],
+        )
+        .await;
+    budgets.check("close_receipt", consumed, CLOSE_RECEIPT);
+
+    let accounts = || crate::accounts::Deposit {
+        vault: vault_address(&owner),
+        config: config_address(),
+        user: owner,
+        system_program: system_program::ID,
+        #[cfg(feature = "cpi-events")]
+        event_authority: event_authority(),
+        #[cfg(feature = "cpi-events")]
+        program: ID,
+    };
+    let data = crate::instruction::DepositVested {
+        amount: SOL,
+        start_ts: 0,
+        duration_secs: 3_600,
+    };
+    let consumed = harness
+        .metered(instruction(accounts(), data), &[&user])
+        .await;
+    budgets.check("deposit_vested", consumed, DEPOSIT_VESTED);
+    let data = crate::instruction::DepositLocked {
+        amount: SOL,
+        lock_duration_secs: 3_600,
+    };
+    let consumed = harness
+        .metered(instruction(accounts(), data), &[&user])
+        .await;
+    budgets.check("deposit_locked", consumed, DEPOSIT_LOCKED);
+
+    let legacy = harness.legacy_vault(SOL).await;
+    let accounts = crate::accounts::MigrateVault {
+        vault: vault_address(&legacy.pubkey()),
+        user: legacy.pubkey(),
+        system_program: system_program::ID,
+    };
+    let consumed = harness
+        .metered(
+            instruction(accounts, crate::instruction::MigrateVault {}),
+            &[&legacy],
+        )
+        .await;
+    budgets.check("migrate_vault", consumed, MIGRATE_VAULT);
+
+    budgets.assert_within();
+}
+
+#[tokio::test]
+#[ignore = "meters the SBF build; see the module comment"]
+async fn delayed_vault_instructions_stay_within_budget() {
+    let mut harness = sbf_harness().await;
+    let mut budgets = Budgets::default();
+    let user = harness.wallet(10 * SOL);
+    let owner = user.pubkey();
+    let accounts = crate::accounts::Initialize {
+        vault: vault_address(&owner),
+        user: owner,
+        system_program: system_program::ID,
+    };
+    let data = crate::instruction::Initialize { delayed: true };
+    harness
+        .send(instruction(accounts, data), &[&user])
+        .await
+        .unwrap();
+    harness
+        .send(deposit(&owner, 2 * SOL), &[&user])
+        .await
+        .unwrap();
+
+    let accounts = || crate::accounts::Withdraw {
+        vault: vault_address(&owner),
+        config: config_address(),
+        treasury: None,
+        payout: None,
+        user: owner,
+        #[cfg(feature = "cpi-events")]
+        event_authority: event_authority(),
+        #[cfg(feature = "cpi-events")]
+        program: ID,
+    };
+    let data = crate::instruction::RequestWithdraw { amount: SOL };
+    let consumed = harness
+        .metered(instruction(accounts(), data), &[&user])
+        .await;
+    budgets.check("request_withdraw", consumed, REQUEST_WITHDRAW);
+    let consumed = harness
+        .metered(
+            instruction(accounts(), crate::instruction::CancelWithdraw {}),
+            &[&user],
+        )
+        .await;
+    budgets.check("cancel_withdraw", consumed, CANCEL_WITHDRAW);
+
+    let data = crate::instruction::RequestWithdraw { amount: SOL };
+    harness
+        .send(instruction(accounts(), data), &[&user])
+        .await
+        .unwrap();
+    let available_at = harness.vault(&owner).await.unwrap().available_at;
+    harness.warp_to(available_at).await;
+    let consumed = harness
+        .metered(
+            instruction(accounts(), crate::instruction::ExecuteWithdraw {}),
+            &[&user],
+        )
+        .await;
+    budgets.check("execute_withdraw", consumed, EXECUTE_WITHDRAW);
+
+    budgets.assert_within();
+}
+
+#[tokio::test]
+#[ignore = "meters the SBF build; see the module comment"]
+async fn config_and_recovery_instructions_stay_within_budget() {
+    let mut harness = sbf_harness().await;
+    let mut budgets = Budgets::default();
+    let admin = harness.wallet(SOL);
+    let treasury = harness.wallet(SOL).pubkey();
+    let accounts = crate::accounts::InitializeConfig {
+        config: config_address(),
+        admin: admin.pubkey(),
+        system_program: system_program::ID,
+    };
+    let data = crate::instruction::InitializeConfig {
+        max_deposit_per_vault: 0,
+    };
+    let consumed = harness
+        .metered(instruction(accounts, data), &[&admin])
+        .await;
+    budgets.check("initialize_config", consumed, INITIALIZE_CONFIG);
+    let data = crate::instruction::SetFee {
+        fee_bps: 100,
+        treasury,
+    };
+    let consumed = harness
+        .metered(update_config(&admin.pubkey(), data), &[&admin])
+        .await;
+    budgets.check("set_fee", consumed, SET_FEE);
+    let data = crate::instruction::SetDepositCap {
+        max_deposit_per_vault: 100 * SOL,
+    };
+    let consumed = harness
+        .metered(update_config(&admin.pubkey(), data), &[&admin])
+        .await;
+    budgets.check("set_deposit_cap", consumed, SET_DEPOSIT_CAP);
+
+    let user = harness.funded_vault(2 * SOL).await;
+    let owner = user.pubkey();
+    let beneficiary = harness.wallet(SOL);
+    let consumed = harness
+        .metered(withdraw(&owner, &owner, Some(treasury), SOL / 2), &[&user])
+        .await;
+    budgets.check("withdraw with a fee", consumed, WITHDRAW_WITH_FEE);
+    harness
+        .send(set_beneficiary(&owner, &beneficiary.pubkey(), 60), &[&user])
+        .await
+        .unwrap();
+    let vault = harness.vault(&owner).await.unwrap();
+    harness.warp_to(vault.beneficiary_change_at + 61).await;
+    let mut claim = claim_inactive(&owner, &beneficiary.pubkey());
+    claim.accounts[2] = AccountMeta::new(treasury, false);
+    let consumed = harness.metered(claim, &[&beneficiary]).await;
+    budgets.check("claim_inactive", consumed, CLAIM_INACTIVE);
+
+    let leaving = harness.funded_vault(SOL).await;
+    let mut close = withdraw_and_close(&leaving.pubkey());
+    close.accounts[2] = AccountMeta::new(treasury, false);
+    let consumed = harness.metered(close, &[&leaving]).await;
+    budgets.check("withdraw_and_close", consumed, WITHDRAW_AND_CLOSE);
+
+    let stranded = harness.funded_vault(SOL).await;
+    let consumed = harness
+        .metered(
+            update_config(
+                &admin.pubkey(),
+                crate::instruction::SetPaused { paused: true },
+            ),
+            &[&admin],
+        )
+        .await;
+    budgets.check("set_paused", consumed, SET_PAUSED);
+    let data = crate::instruction::SetEmergencyGracePeriod {
+        emergency_grace_secs: 0,
+    };
+    let consumed = harness
+        .metered(update_config(&admin.pubkey(), data), &[&admin])
+        .await;
+    budgets.check(
+        "set_emergency_grace_period",
+        consumed,
+        SET_EMERGENCY_GRACE_PERIOD,
+    );
+    let accounts = crate::accounts::EmergencyWithdraw {
+        config: config_address(),
+        admin: admin.pubkey(),
+        vault: vault_address(&stranded.pubkey()),
+        owner: stranded.pubkey(),
+        payout: None,
+        #[cfg(feature = "cpi-events")]
+        event_authority: event_authority(),
+        #[cfg(feature = "cpi-events")]
+        program: ID,
+    };
+    let consumed = harness
+        .metered(
+            instruction(accounts, crate::instruction::AdminEmergencyWithdraw {}),
+            &[&admin],
+        )
+        .await;
+    budgets.check(
+        "admin_emergency_withdraw",
+        consumed,
+        ADMIN_EMERGENCY_WITHDRAW,
+    );
+
+    budgets.assert_within();
+}
+
+#[tokio::test]
+#[ignore = "meters the SBF build; see the module comment"]
+async fn token_instructions_stay_within_budget() {
+    let mut harness = sbf_harness().await;
+    let mut budgets = Budgets::default();
+
+    for (name, token_program) in [("token", spl_token::ID), ("token-2022", spl_token_2022::ID)] {
+        let user = harness.wallet(SOL);
+        let owner = user.pubkey();
+        let mint = harness.mint(&token_program, None).await;
+        let user_tokens = harness
+            .token_account(&owner, &mint, &token_program, 1_000_000)
+            .await;
+        let token_vault =
+            Pubkey::find_program_address(&[b"token_vault", owner.as_ref(), mint.as_ref()], &ID).0;
+        let vault_tokens = associated_token_address(&token_vault, &mint, &token_program);
+
+        let accounts = crate::accounts::InitializeTokenVault {
+            token_vault,
+            vault_tokens,
+            mint,
+            user: owner,
+            token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        };
+        let consumed = harness
+            .metered(
+                instruction(accounts, crate::instruction::InitializeTokenVault {}),
+                &[&user],
+            )
+            .await;
+        budgets.check(
+            &format!("initialize_token_vault ({name})"),
+            consumed,
+            INITIALIZE_TOKEN_VAULT,
+        );
+        let accounts = crate::accounts::DepositTokens {
+            token_vault,
+            vault_tokens,
+            user_tokens,
+            mint,
+            user: owner,
+            token_program,
+        };
+        let data = crate::instruction::DepositTokens { amount: 600_000 };
+        let consumed = harness.metered(instruction(accounts, data), &[&user]).await;
+        budgets.check(
+            &format!("deposit_tokens ({name})"),
+            consumed,
+            DEPOSIT_TOKENS,
+        );
+        let accounts = crate::accounts::WithdrawTokens {
+            token_vault,
+            vault_tokens,
+            user_tokens,
+            mint,
+            user: owner,
+            token_program,
+        };
+        let data = crate::instruction::WithdrawTokens { amount: 200_000 };
+        let consumed = harness.metered(instruction(accounts, data), &[&user]).await;
+        budgets.check(
+            &format!("withdraw_tokens ({name})"),
+            consumed,
+            WITHDRAW_TOKENS,
+        );
+    }
+
+    budgets.assert_within();
+}
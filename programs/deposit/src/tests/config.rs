@@ -0,0 +1,178 @@
+use super::*;
+
+fn set_paused(admin: &Pubkey, paused: bool) -> Instruction {
+    update_config(admin, crate::instruction::SetPaused { paused })
+}
+
+#[tokio::test]
+async fn pausing_stops_deposits_but_not_withdrawals() {
+    let mut harness = Harness::new().await;
+    let admin = harness.init_config().await;
+    let user = harness.funded_vault(SOL).await;
+    harness
+        .send(set_paused(&admin.pubkey(), true), &[&admin])
+        .await
+        .unwrap();
+
+    let result = harness.send(deposit(&user.pubkey(), SOL), &[&user]).await;
+    assert_eq!(error_code(result), u32::from(VaultError::ProgramPaused));
+    harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL / 2),
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    harness
+        .send(set_paused(&admin.pubkey(), false), &[&admin])
+        .await
+        .unwrap();
+    harness
+        .send(deposit(&user.pubkey(), SOL), &[&user])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn deposits_fill_the_cap_exactly() {
+    let mut harness = Harness::new().await;
+    let admin = harness.init_config().await;
+    let user = harness.funded_vault(SOL).await;
+    let set_cap = crate::instruction::SetDepositCap {
+        max_deposit_per_vault: 3 * SOL,
+    };
+    harness
+        .send(update_config(&admin.pubkey(), set_cap), &[&admin])
+        .await
+        .unwrap();
+
+    let result = harness
+        .send(deposit(&user.pubkey(), 2 * SOL + 1), &[&user])
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::DepositCapExceeded)
+    );
+    harness
+        .send(deposit(&user.pubkey(), 2 * SOL), &[&user])
+        .await
+        .unwrap();
+    let result = harness.send(deposit(&user.pubkey(), 1), &[&user]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::DepositCapExceeded)
+    );
+
+    // withdrawals make room again
+    harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL),
+            &[&user],
+        )
+        .await
+        .unwrap();
+    harness
+        .send(deposit(&user.pubkey(), SOL), &[&user])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn only_the_admin_can_change_the_config() {
+    let mut harness = Harness::new().await;
+    harness.init_config().await;
+    let stranger = harness.wallet(SOL);
+
+    let result = harness
+        .send(set_paused(&stranger.pubkey(), true), &[&stranger])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotAdmin));
+    let set_cap = crate::instruction::SetDepositCap {
+        max_deposit_per_vault: 1,
+    };
+    let result = harness
+        .send(update_config(&stranger.pubkey(), set_cap), &[&stranger])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotAdmin));
+}
+
+#[tokio::test]
+async fn without_a_config_deposits_are_unpaused_and_uncapped() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(0).await;
+
+    harness
+        .send(deposit(&user.pubkey(), 5 * SOL), &[&user])
+        .await
+        .unwrap();
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.total_deposited, 5 * SOL);
+}
+
+// How a client would have left out the config when it was optional: Anchor
+// reads the program id as `None`.
+fn without_config(mut ix: Instruction) -> Instruction {
+    // vault, config, ...
+    ix.accounts[1] = AccountMeta::new_readonly(ID, false);
+    ix
+}
+
+#[tokio::test]
+async fn leaving_out_the_config_cannot_skip_the_pause() {
+    let mut harness = Harness::new().await;
+    let admin = harness.init_config().await;
+    let user = harness.funded_vault(SOL).await;
+    let sponsor = harness.wallet(2 * SOL);
+    harness
+        .send(set_paused(&admin.pubkey(), true), &[&admin])
+        .await
+        .unwrap();
+
+    let result = harness
+        .send(without_config(deposit(&user.pubkey(), SOL)), &[&user])
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(anchor_lang::error::ErrorCode::ConstraintSeeds)
+    );
+    let result = harness
+        .send(
+            without_config(vault::deposit_for(&sponsor.pubkey(), &user.pubkey(), SOL)),
+            &[&sponsor],
+        )
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(anchor_lang::error::ErrorCode::ConstraintSeeds)
+    );
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.total_deposited, SOL);
+}
+
+#[tokio::test]
+async fn leaving_out_the_config_cannot_skip_the_cap() {
+    let mut harness = Harness::new().await;
+    let admin = harness.init_config().await;
+    let user = harness.funded_vault(SOL).await;
+    let set_cap = crate::instruction::SetDepositCap {
+        max_deposit_per_vault: SOL,
+    };
+    harness
+        .send(update_config(&admin.pubkey(), set_cap), &[&admin])
+        .await
+        .unwrap();
+
+    let result = harness
+        .send(without_config(deposit(&user.pubkey(), SOL)), &[&user])
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(anchor_lang::error::ErrorCode::ConstraintSeeds)
+    );
+    let result = harness.send(deposit(&user.pubkey(), SOL), &[&user]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::DepositCapExceeded)
+    );
+}
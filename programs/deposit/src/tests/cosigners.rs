@@ -0,0 +1,149 @@
+use super::*;
+
+// A funded vault whose payouts need 2 of 3 cosigners, and the cosigners.
+async fn two_of_three(harness: &mut Harness) -> (Keypair, [Keypair; 3]) {
+    let owner = harness.funded_vault(3 * SOL).await;
+    let cosigners = [Keypair::new(), Keypair::new(), Keypair::new()];
+    let keys = cosigners.iter().map(Keypair::pubkey).collect();
+    harness
+        .send(set_cosigners(&owner.pubkey(), keys, 2), &[&owner])
+        .await
+        .unwrap();
+    (owner, cosigners)
+}
+
+fn owner_withdraw(owner: &Keypair) -> Instruction {
+    withdraw(&owner.pubkey(), &owner.pubkey(), None, SOL)
+}
+
+#[tokio::test]
+async fn two_of_three_cosigners_release_a_withdrawal() {
+    let mut harness = Harness::new().await;
+    let (owner, [first, _, third]) = two_of_three(&mut harness).await;
+    let before = harness.lamports(&owner.pubkey()).await;
+
+    let ix = cosigned(owner_withdraw(&owner), &[&first, &third]);
+    harness.send(ix, &[&owner, &first, &third]).await.unwrap();
+    assert_eq!(harness.lamports(&owner.pubkey()).await, before + SOL);
+}
+
+#[tokio::test]
+async fn one_cosigner_short_of_the_threshold_fails() {
+    let mut harness = Harness::new().await;
+    let (owner, [first, ..]) = two_of_three(&mut harness).await;
+
+    let result = harness.send(owner_withdraw(&owner), &[&owner]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::NotEnoughCosigners)
+    );
+    let ix = cosigned(owner_withdraw(&owner), &[&first]);
+    let result = harness.send(ix, &[&owner, &first]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::NotEnoughCosigners)
+    );
+}
+
+#[tokio::test]
+async fn unlisted_signers_do_not_count() {
+    let mut harness = Harness::new().await;
+    let (owner, [first, ..]) = two_of_three(&mut harness).await;
+    let stranger = Keypair::new();
+
+    let ix = cosigned(owner_withdraw(&owner), &[&first, &stranger]);
+    let result = harness.send(ix, &[&owner, &first, &stranger]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::NotEnoughCosigners)
+    );
+}
+
+#[tokio::test]
+async fn changing_the_cosigners_needs_the_current_ones() {
+    let mut harness = Harness::new().await;
+    let (owner, [first, second, _]) = two_of_three(&mut harness).await;
+
+    let ix = set_cosigners(&owner.pubkey(), Vec::new(), 0);
+    let result = harness.send(ix, &[&owner]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::NotEnoughCosigners)
+    );
+
+    let ix = cosigned(
+        set_cosigners(&owner.pubkey(), Vec::new(), 0),
+        &[&first, &second],
+    );
+    harness.send(ix, &[&owner, &first, &second]).await.unwrap();
+    harness
+        .send(owner_withdraw(&owner), &[&owner])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn claim_needs_the_cosigners() {
+    let mut harness = Harness::new().await;
+    let (owner, [first, second, _]) = two_of_three(&mut harness).await;
+    let beneficiary = harness.wallet(SOL);
+    harness
+        .send(
+            set_beneficiary(&owner.pubkey(), &beneficiary.pubkey(), 60),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    harness.warp_to(vault.beneficiary_change_at + 61).await;
+
+    let claim = claim_inactive(&owner.pubkey(), &beneficiary.pubkey());
+    let result = harness.send(claim, &[&beneficiary]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::NotEnoughCosigners)
+    );
+
+    let claim = cosigned(
+        claim_inactive(&owner.pubkey(), &beneficiary.pubkey()),
+        &[&first, &second],
+    );
+    harness
+        .send(claim, &[&beneficiary, &first, &second])
+        .await
+        .unwrap();
+    assert!(harness.vault(&owner.pubkey()).await.is_none());
+}
+
+#[tokio::test]
+async fn accepting_ownership_needs_the_cosigners() {
+    let mut harness = Harness::new().await;
+    let (owner, [_, second, third]) = two_of_three(&mut harness).await;
+    let new_owner = harness.wallet(SOL);
+    harness
+        .send(
+            propose_owner(&owner.pubkey(), &new_owner.pubkey()),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let accept = accept_ownership(&owner.pubkey(), &new_owner.pubkey());
+    let result = harness.send(accept, &[&new_owner]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::NotEnoughCosigners)
+    );
+
+    let accept = cosigned(
+        accept_ownership(&owner.pubkey(), &new_owner.pubkey()),
+        &[&second, &third],
+    );
+    harness
+        .send(accept, &[&new_owner, &second, &third])
+        .await
+        .unwrap();
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(vault.owner, new_owner.pubkey());
+    assert!(vault.cosigners.is_empty());
+}
@@ -0,0 +1,90 @@
+use anchor_lang::event::EVENT_IX_TAG_LE;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use super::*;
+
+// Every `T` the program emitted through a self-CPI, in order, from the
+// instruction data `LogData` logs for it.
+fn cpi_events<T: anchor_lang::Event + AnchorDeserialize>(logs: &[String]) -> Vec<T> {
+    logs.iter()
+        .filter_map(|line| line.strip_prefix("Program log: self-cpi: "))
+        .filter_map(|data| STANDARD.decode(data).ok())
+        .filter_map(|data| {
+            let body = data
+                .strip_prefix(EVENT_IX_TAG_LE)?
+                .strip_prefix(T::DISCRIMINATOR)?;
+            Some(T::try_from_slice(body).unwrap())
+        })
+        .collect()
+}
+
+// The self-CPI carries the same bytes as the logged event.
+fn assert_same_events<T: anchor_lang::Event + AnchorDeserialize>(logs: &[String]) -> T {
+    let logged = events::events::<T>(logs);
+    let mut emitted = cpi_events::<T>(logs);
+    assert_eq!(emitted.len(), 1, "expected one self-CPI event in {logs:?}");
+    assert_eq!(
+        emitted.iter().map(T::data).collect::<Vec<_>>(),
+        logged.iter().map(T::data).collect::<Vec<_>>()
+    );
+    emitted.remove(0)
+}
+
+#[tokio::test]
+async fn deposit_emits_its_event_through_a_self_cpi() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let rent = harness.rent(Vault::SPACE).await;
+
+    let logs = harness.logs(deposit(&user.pubkey(), 250), &[&user]).await;
+
+    let event = assert_same_events::<DepositEvent>(&logs);
+    assert_eq!(event.user, user.pubkey());
+    assert_eq!(event.vault, vault_address(&user.pubkey()));
+    assert_eq!(event.amount, 250);
+    assert_eq!(event.new_balance, rent + SOL + 250);
+    assert_eq!(event.deposit_count, 2);
+}
+
+#[tokio::test]
+async fn withdraw_emits_its_event_through_a_self_cpi() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let rent = harness.rent(Vault::SPACE).await;
+
+    let logs = harness
+        .logs(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL / 4),
+            &[&user],
+        )
+        .await;
+
+    let event = assert_same_events::<WithdrawEvent>(&logs);
+    assert_eq!(event.user, user.pubkey());
+    assert_eq!(event.recipient, user.pubkey());
+    assert_eq!(event.amount, SOL / 4);
+    assert_eq!(event.new_balance, rent + 3 * SOL / 4);
+    assert!(cpi_events::<DepositEvent>(&logs).is_empty());
+}
+
+#[tokio::test]
+async fn the_event_instruction_only_runs_as_a_self_cpi() {
+    let mut harness = Harness::new().await;
+    let user = harness.wallet(SOL);
+
+    // a forged event sent straight to the program, without the event
+    // authority's signature
+    let mut data = EVENT_IX_TAG_LE.to_vec();
+    data.extend_from_slice(DepositEvent::DISCRIMINATOR);
+    let ix = Instruction {
+        program_id: ID,
+        accounts: vec![AccountMeta::new_readonly(event_authority(), false)],
+        data,
+    };
+    let result = harness.send(ix, &[&user]).await;
+
+    assert_eq!(
+        error_code(result),
+        u32::from(anchor_lang::error::ErrorCode::ConstraintSigner)
+    );
+}
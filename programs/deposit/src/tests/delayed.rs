@@ -0,0 +1,196 @@
+use super::*;
+
+// A delayed vault holding 2 SOL.
+async fn delayed_vault(harness: &mut Harness) -> Keypair {
+    let user = harness.wallet(10 * SOL);
+    let accounts = crate::accounts::Initialize {
+        vault: vault_address(&user.pubkey()),
+        user: user.pubkey(),
+        system_program: system_program::ID,
+    };
+    let data = crate::instruction::Initialize { delayed: true };
+    harness
+        .send(instruction(accounts, data), &[&user])
+        .await
+        .unwrap();
+    harness
+        .send(deposit(&user.pubkey(), 2 * SOL), &[&user])
+        .await
+        .unwrap();
+    user
+}
+
+fn delayed(user: &Pubkey, data: impl InstructionData) -> Instruction {
+    instruction(
+        crate::accounts::Withdraw {
+            vault: vault_address(user),
+            config: config_address(),
+            treasury: None,
+            payout: None,
+            user: *user,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        data,
+    )
+}
+
+fn request_withdraw(user: &Pubkey, amount: u64) -> Instruction {
+    delayed(user, crate::instruction::RequestWithdraw { amount })
+}
+
+fn execute_withdraw(user: &Pubkey) -> Instruction {
+    delayed(user, crate::instruction::ExecuteWithdraw {})
+}
+
+fn cancel_withdraw(user: &Pubkey) -> Instruction {
+    delayed(user, crate::instruction::CancelWithdraw {})
+}
+
+#[tokio::test]
+async fn delayed_vault_refuses_instant_withdrawals() {
+    let mut harness = Harness::new().await;
+    let user = delayed_vault(&mut harness).await;
+
+    let result = harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL),
+            &[&user],
+        )
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::DelayedWithdrawals)
+    );
+    let result = harness
+        .send(invariants::withdraw_all(&user.pubkey(), None), &[&user])
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::DelayedWithdrawals)
+    );
+}
+
+#[tokio::test]
+async fn requested_withdrawal_executes_once_the_delay_has_passed() {
+    let mut harness = Harness::new().await;
+    let user = delayed_vault(&mut harness).await;
+    harness.warp_to(1_700_000_000).await;
+    harness
+        .send(request_withdraw(&user.pubkey(), SOL), &[&user])
+        .await
+        .unwrap();
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.pending_amount, SOL);
+    assert_eq!(vault.available_at, 1_700_000_000 + WITHDRAW_DELAY_SECS);
+
+    harness.warp_to(vault.available_at - 1).await;
+    let result = harness
+        .send(execute_withdraw(&user.pubkey()), &[&user])
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::WithdrawalNotReady)
+    );
+
+    harness.warp_to(vault.available_at).await;
+    let before = harness.lamports(&user.pubkey()).await;
+    harness
+        .send(execute_withdraw(&user.pubkey()), &[&user])
+        .await
+        .unwrap();
+    assert_eq!(harness.lamports(&user.pubkey()).await, before + SOL);
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.pending_amount, 0);
+    assert_eq!(vault.total_withdrawn, SOL);
+
+    let result = harness
+        .send(execute_withdraw(&user.pubkey()), &[&user])
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::NoPendingWithdrawal)
+    );
+}
+
+#[tokio::test]
+async fn cancelled_withdrawal_cannot_be_executed() {
+    let mut harness = Harness::new().await;
+    let user = delayed_vault(&mut harness).await;
+    harness
+        .send(request_withdraw(&user.pubkey(), SOL), &[&user])
+        .await
+        .unwrap();
+    harness
+        .send(cancel_withdraw(&user.pubkey()), &[&user])
+        .await
+        .unwrap();
+
+    harness.warp_to(i64::MAX / 2).await;
+    let result = harness
+        .send(execute_withdraw(&user.pubkey()), &[&user])
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::NoPendingWithdrawal)
+    );
+    let result = harness
+        .send(cancel_withdraw(&user.pubkey()), &[&user])
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::NoPendingWithdrawal)
+    );
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.total_withdrawn, 0);
+}
+
+#[tokio::test]
+async fn a_new_request_replaces_the_old_one_and_restarts_the_delay() {
+    let mut harness = Harness::new().await;
+    let user = delayed_vault(&mut harness).await;
+    harness.warp_to(1_700_000_000).await;
+    harness
+        .send(request_withdraw(&user.pubkey(), SOL), &[&user])
+        .await
+        .unwrap();
+    harness.warp_to(1_700_000_000 + WITHDRAW_DELAY_SECS).await;
+    harness
+        .send(request_withdraw(&user.pubkey(), SOL / 2), &[&user])
+        .await
+        .unwrap();
+
+    let result = harness
+        .send(execute_withdraw(&user.pubkey()), &[&user])
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::WithdrawalNotReady)
+    );
+    harness
+        .warp_to(1_700_000_000 + 2 * WITHDRAW_DELAY_SECS)
+        .await;
+    let before = harness.lamports(&user.pubkey()).await;
+    harness
+        .send(execute_withdraw(&user.pubkey()), &[&user])
+        .await
+        .unwrap();
+    assert_eq!(harness.lamports(&user.pubkey()).await, before + SOL / 2);
+}
+
+#[tokio::test]
+async fn requests_are_capped_at_the_deposits() {
+    let mut harness = Harness::new().await;
+    let user = delayed_vault(&mut harness).await;
+
+    let result = harness
+        .send(request_withdraw(&user.pubkey(), 2 * SOL + 1), &[&user])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::ExceedsDeposits));
+    let result = harness
+        .send(request_withdraw(&user.pubkey(), 0), &[&user])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::ZeroAmount));
+}
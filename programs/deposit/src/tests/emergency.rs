@@ -0,0 +1,203 @@
+use super::*;
+
+const GRACE_SECS: i64 = 3 * 24 * 60 * 60;
+
+fn emergency_withdraw(admin: &Pubkey, owner: &Pubkey) -> Instruction {
+    instruction(
+        crate::accounts::EmergencyWithdraw {
+            config: config_address(),
+            admin: *admin,
+            vault: vault_address(owner),
+            owner: *owner,
+            payout: None,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::AdminEmergencyWithdraw {},
+    )
+}
+
+// A config paused at 1_700_000_000 with a grace period of GRACE_SECS, its
+// admin, and a vault holding 2 SOL.
+async fn paused(harness: &mut Harness) -> (Keypair, Keypair) {
+    let admin = harness.init_config().await;
+    let owner = harness.funded_vault(2 * SOL).await;
+    let data = crate::instruction::SetEmergencyGracePeriod {
+        emergency_grace_secs: GRACE_SECS,
+    };
+    harness
+        .send(update_config(&admin.pubkey(), data), &[&admin])
+        .await
+        .unwrap();
+    harness.warp_to(1_700_000_000).await;
+    let data = crate::instruction::SetPaused { paused: true };
+    harness
+        .send(update_config(&admin.pubkey(), data), &[&admin])
+        .await
+        .unwrap();
+    (admin, owner)
+}
+
+#[tokio::test]
+async fn emergency_withdrawal_waits_out_the_grace_period() {
+    let mut harness = Harness::new().await;
+    let (admin, owner) = paused(&mut harness).await;
+
+    harness.warp_to(1_700_000_000 + GRACE_SECS - 1).await;
+    let result = harness
+        .send(
+            emergency_withdraw(&admin.pubkey(), &owner.pubkey()),
+            &[&admin],
+        )
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::GracePeriodNotOver)
+    );
+
+    harness.warp_to(1_700_000_000 + GRACE_SECS).await;
+    let before = harness.lamports(&owner.pubkey()).await;
+    let admin_before = harness.lamports(&admin.pubkey()).await;
+    let logs = harness
+        .logs(
+            emergency_withdraw(&admin.pubkey(), &owner.pubkey()),
+            &[&admin],
+        )
+        .await;
+    assert_eq!(harness.lamports(&owner.pubkey()).await, before + 2 * SOL);
+    assert_eq!(harness.lamports(&admin.pubkey()).await, admin_before);
+    let [event] = &events::events::<EmergencyWithdrawEvent>(&logs)[..] else {
+        panic!("expected one EmergencyWithdrawEvent in {logs:?}");
+    };
+    assert_eq!(event.admin, admin.pubkey());
+    assert_eq!(event.owner, owner.pubkey());
+    assert_eq!(event.recipient, owner.pubkey());
+    assert_eq!(event.amount, 2 * SOL);
+}
+
+#[tokio::test]
+async fn emergency_withdrawal_needs_deposits_paused() {
+    let mut harness = Harness::new().await;
+    let (admin, owner) = paused(&mut harness).await;
+    let data = crate::instruction::SetPaused { paused: false };
+    harness
+        .send(update_config(&admin.pubkey(), data), &[&admin])
+        .await
+        .unwrap();
+
+    harness.warp_to(1_700_000_000 + 2 * GRACE_SECS).await;
+    let result = harness
+        .send(
+            emergency_withdraw(&admin.pubkey(), &owner.pubkey()),
+            &[&admin],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotPaused));
+}
+
+#[tokio::test]
+async fn emergency_withdrawal_only_pays_the_owner() {
+    let mut harness = Harness::new().await;
+    let (admin, owner) = paused(&mut harness).await;
+    harness.warp_to(1_700_000_000 + GRACE_SECS).await;
+
+    let mut ix = emergency_withdraw(&admin.pubkey(), &owner.pubkey());
+    // config, admin, vault, owner
+    ix.accounts[3] = AccountMeta::new(admin.pubkey(), false);
+    let result = harness.send(ix, &[&admin]).await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::NotOwner));
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(vault.total_withdrawn, 0);
+}
+
+#[tokio::test]
+async fn only_the_admin_can_force_a_withdrawal() {
+    let mut harness = Harness::new().await;
+    let (_, owner) = paused(&mut harness).await;
+    let stranger = harness.wallet(SOL);
+    harness.warp_to(1_700_000_000 + GRACE_SECS).await;
+
+    let result = harness
+        .send(
+            emergency_withdraw(&stranger.pubkey(), &owner.pubkey()),
+            &[&stranger],
+        )
+        .await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::NotAdmin));
+}
+
+#[tokio::test]
+async fn emergency_withdrawal_follows_the_payout_address() {
+    let mut harness = Harness::new().await;
+    let (admin, owner) = paused(&mut harness).await;
+    let payout = harness.wallet(SOL).pubkey();
+    harness
+        .send(set_payout_address(&owner.pubkey(), Some(payout)), &[&owner])
+        .await
+        .unwrap();
+    // the binding takes effect before the grace period ends
+    harness.warp_to(1_700_000_000 + GRACE_SECS).await;
+
+    let result = harness
+        .send(
+            emergency_withdraw(&admin.pubkey(), &owner.pubkey()),
+            &[&admin],
+        )
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::WrongPayoutAddress)
+    );
+
+    let owner_before = harness.lamports(&owner.pubkey()).await;
+    let mut ix = emergency_withdraw(&admin.pubkey(), &owner.pubkey());
+    // config, admin, vault, owner, payout
+    ix.accounts[4] = AccountMeta::new(payout, false);
+    let logs = harness.logs(ix, &[&admin]).await;
+    assert_eq!(harness.lamports(&payout).await, 3 * SOL);
+    assert_eq!(harness.lamports(&owner.pubkey()).await, owner_before);
+    let [event] = &events::events::<EmergencyWithdrawEvent>(&logs)[..] else {
+        panic!("expected one EmergencyWithdrawEvent in {logs:?}");
+    };
+    assert_eq!(event.owner, owner.pubkey());
+    assert_eq!(event.recipient, payout);
+}
+
+#[tokio::test]
+async fn emergency_withdrawal_drops_a_requested_withdrawal() {
+    let mut harness = Harness::new().await;
+    let (admin, owner) = paused(&mut harness).await;
+    let request = instruction(
+        crate::accounts::Withdraw {
+            vault: vault_address(&owner.pubkey()),
+            config: config_address(),
+            treasury: None,
+            payout: None,
+            user: owner.pubkey(),
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::RequestWithdraw { amount: SOL },
+    );
+    harness.send(request, &[&owner]).await.unwrap();
+
+    harness.warp_to(1_700_000_000 + GRACE_SECS).await;
+    harness
+        .send(
+            emergency_withdraw(&admin.pubkey(), &owner.pubkey()),
+            &[&admin],
+        )
+        .await
+        .unwrap();
+
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(vault.pending_amount, 0);
+    assert_eq!(vault.available_at, 0);
+    assert_eq!(vault.total_withdrawn, 2 * SOL);
+}
@@ -0,0 +1,107 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use super::*;
+
+// Every `T` the program logged through `emit!`, in order, whether it ran
+// natively (see `LogData`) or on the SBF build.
+pub(super) fn events<T: anchor_lang::Event + AnchorDeserialize>(logs: &[String]) -> Vec<T> {
+    logs.iter()
+        .filter_map(|line| {
+            line.strip_prefix("Program data: ")
+                .or_else(|| line.strip_prefix("Program log: data: "))
+        })
+        .filter_map(|data| STANDARD.decode(data).ok())
+        .filter_map(|data| {
+            let body = data.strip_prefix(T::DISCRIMINATOR)?;
+            Some(T::try_from_slice(body).unwrap())
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn initialize_logs_an_initialize_event() {
+    let mut harness = Harness::new().await;
+    let user = harness.wallet(SOL);
+
+    let logs = harness.logs(initialize(&user.pubkey()), &[&user]).await;
+
+    let [event] = &events::<InitializeEvent>(&logs)[..] else {
+        panic!("expected one InitializeEvent in {logs:?}");
+    };
+    assert_eq!(event.user, user.pubkey());
+    assert_eq!(event.vault, vault_address(&user.pubkey()));
+}
+
+#[tokio::test]
+async fn deposit_logs_the_new_balance() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let rent = harness.rent(Vault::SPACE).await;
+    harness.warp_to(1_700_000_000).await;
+
+    let logs = harness.logs(deposit(&user.pubkey(), 250), &[&user]).await;
+
+    let [event] = &events::<DepositEvent>(&logs)[..] else {
+        panic!("expected one DepositEvent in {logs:?}");
+    };
+    assert_eq!(event.user, user.pubkey());
+    assert_eq!(event.vault, vault_address(&user.pubkey()));
+    assert_eq!(event.amount, 250);
+    assert_eq!(event.new_balance, rent + SOL + 250);
+    assert_eq!(event.reference, [0; 16]);
+    assert_eq!(event.deposit_count, 2);
+    assert_eq!(event.last_deposit_ts, 1_700_000_000);
+}
+
+#[tokio::test]
+async fn withdraw_logs_the_new_balance() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let rent = harness.rent(Vault::SPACE).await;
+
+    let logs = harness
+        .logs(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL / 4),
+            &[&user],
+        )
+        .await;
+
+    let [event] = &events::<WithdrawEvent>(&logs)[..] else {
+        panic!("expected one WithdrawEvent in {logs:?}");
+    };
+    assert_eq!(event.user, user.pubkey());
+    assert_eq!(event.vault, vault_address(&user.pubkey()));
+    assert_eq!(event.recipient, user.pubkey());
+    assert_eq!(event.amount, SOL / 4);
+    assert_eq!(event.fee, 0);
+    assert_eq!(event.new_balance, rent + 3 * SOL / 4);
+    assert_eq!(event.withdraw_count, 1);
+    assert!(events::<DepositEvent>(&logs).is_empty());
+}
+
+#[tokio::test]
+async fn closing_logs_what_the_owner_got_back() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let rent = harness.rent(Vault::SPACE).await;
+
+    let closed = harness
+        .logs(close_vault(&user.pubkey(), None), &[&user])
+        .await;
+    harness
+        .send(initialize(&user.pubkey()), &[&user])
+        .await
+        .unwrap();
+    let withdrawn_and_closed = harness
+        .logs(withdraw_and_close(&user.pubkey()), &[&user])
+        .await;
+
+    for (logs, amount) in [(closed, rent + SOL), (withdrawn_and_closed, rent)] {
+        let [event] = &events::<VaultClosedEvent>(&logs)[..] else {
+            panic!("expected one VaultClosedEvent in {logs:?}");
+        };
+        assert_eq!(event.owner, user.pubkey());
+        assert_eq!(event.vault, vault_address(&user.pubkey()));
+        assert_eq!(event.amount, amount);
+    }
+}
@@ -0,0 +1,131 @@
+use super::*;
+
+#[test]
+fn fee_is_floored() {
+    assert_eq!(fee_amount(1, 100).unwrap(), 0);
+    assert_eq!(fee_amount(1, 10_000).unwrap(), 1);
+    assert_eq!(fee_amount(99, 100).unwrap(), 0);
+    assert_eq!(fee_amount(100, 100).unwrap(), 1);
+    assert_eq!(fee_amount(199, 100).unwrap(), 1);
+    assert_eq!(fee_amount(0, 10_000).unwrap(), 0);
+    assert_eq!(fee_amount(u64::MAX, 10_000).unwrap(), u64::MAX);
+    assert_eq!(fee_amount(u64::MAX, 1).unwrap(), u64::MAX / 10_000);
+}
+
+#[tokio::test]
+async fn one_lamport_withdrawal_pays_no_fee() {
+    let mut harness = Harness::new().await;
+    let treasury = harness.charge_fee(100).await;
+    let user = harness.funded_vault(SOL).await;
+    let before = harness.lamports(&user.pubkey()).await;
+    let treasury_before = harness.lamports(&treasury).await;
+
+    harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), Some(treasury), 1),
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(harness.lamports(&user.pubkey()).await, before + 1);
+    assert_eq!(harness.lamports(&treasury).await, treasury_before);
+    assert_eq!(
+        harness.vault(&user.pubkey()).await.unwrap().total_withdrawn,
+        1
+    );
+}
+
+#[tokio::test]
+async fn fee_goes_to_the_treasury() {
+    let mut harness = Harness::new().await;
+    let treasury = harness.charge_fee(250).await;
+    let user = harness.funded_vault(SOL).await;
+    let before = harness.lamports(&user.pubkey()).await;
+    let treasury_before = harness.lamports(&treasury).await;
+
+    // 2.5% of 1999 is 49.975, floored to 49
+    harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), Some(treasury), 1_999),
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(harness.lamports(&user.pubkey()).await, before + 1_950);
+    assert_eq!(harness.lamports(&treasury).await, treasury_before + 49);
+    assert_eq!(
+        harness.vault(&user.pubkey()).await.unwrap().total_withdrawn,
+        1_999
+    );
+}
+
+#[tokio::test]
+async fn mismatched_treasury_is_rejected() {
+    let mut harness = Harness::new().await;
+    harness.charge_fee(100).await;
+    let user = harness.funded_vault(SOL).await;
+    let impostor = harness.wallet(SOL).pubkey();
+
+    let result = harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), Some(impostor), SOL / 2),
+            &[&user],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::WrongTreasury));
+
+    let result = harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL / 2),
+            &[&user],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::WrongTreasury));
+}
+
+#[tokio::test]
+async fn closing_charges_the_fee_on_remaining_deposits() {
+    let mut harness = Harness::new().await;
+    let treasury = harness.charge_fee(100).await;
+    let user = harness.funded_vault(SOL).await;
+    let rent = harness.rent(Vault::SPACE).await;
+    let before = harness.lamports(&user.pubkey()).await;
+    let treasury_before = harness.lamports(&treasury).await;
+
+    let result = harness
+        .send(close_vault(&user.pubkey(), None), &[&user])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::WrongTreasury));
+    harness
+        .send(close_vault(&user.pubkey(), Some(treasury)), &[&user])
+        .await
+        .unwrap();
+
+    // the fee is on the deposits, not the rent
+    assert_eq!(
+        harness.lamports(&treasury).await,
+        treasury_before + SOL / 100
+    );
+    assert_eq!(
+        harness.lamports(&user.pubkey()).await,
+        before + SOL - SOL / 100 + rent
+    );
+    assert!(harness.vault(&user.pubkey()).await.is_none());
+}
+
+#[tokio::test]
+async fn closing_without_a_fee_needs_no_treasury() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let rent = harness.rent(Vault::SPACE).await;
+    let before = harness.lamports(&user.pubkey()).await;
+
+    harness
+        .send(close_vault(&user.pubkey(), None), &[&user])
+        .await
+        .unwrap();
+
+    assert_eq!(harness.lamports(&user.pubkey()).await, before + SOL + rent);
+}
@@ -0,0 +1,115 @@
+// Random deposit and withdrawal sequences, checked after every step.
+// proptest shrinks a failing sequence to the shortest one that still
+// breaks an invariant.
+
+use proptest::prelude::*;
+
+use super::*;
+
+#[derive(Debug, Clone)]
+enum Step {
+    Deposit(u64),
+    Withdraw(u64),
+    WithdrawAll,
+    StrangerWithdraw(u64),
+}
+
+fn amount() -> impl Strategy<Value = u64> {
+    prop_oneof![Just(0), 1..=12 * SOL, u64::MAX - 12 * SOL..=u64::MAX,]
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    prop_oneof![
+        amount().prop_map(Step::Deposit),
+        amount().prop_map(Step::Withdraw),
+        Just(Step::WithdrawAll),
+        amount().prop_map(Step::StrangerWithdraw),
+    ]
+}
+
+pub(super) fn withdraw_all(user: &Pubkey, treasury: Option<Pubkey>) -> Instruction {
+    instruction(
+        crate::accounts::Withdraw {
+            vault: vault_address(user),
+            config: config_address(),
+            treasury,
+            payout: None,
+            user: *user,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::WithdrawAll {},
+    )
+}
+
+async fn run(fee_bps: u16, steps: Vec<Step>) -> std::result::Result<(), TestCaseError> {
+    let mut harness = Harness::new().await;
+    let treasury = match fee_bps {
+        0 => None,
+        fee_bps => Some(harness.charge_fee(fee_bps).await),
+    };
+    let user = harness.funded_vault(0).await;
+    let stranger = harness.wallet(SOL);
+    let address = vault_address(&user.pubkey());
+    let rent = harness.rent(Vault::SPACE).await;
+    let treasury_balance = match treasury {
+        Some(treasury) => harness.lamports(&treasury).await,
+        None => 0,
+    };
+    let total = harness.lamports(&user.pubkey()).await
+        + harness.lamports(&address).await
+        + treasury_balance;
+
+    for step in steps {
+        let owner = user.pubkey();
+        let result = match step {
+            Step::Deposit(amount) => harness.send(deposit(&owner, amount), &[&user]).await,
+            Step::Withdraw(amount) => {
+                let ix = withdraw(&owner, &owner, treasury, amount);
+                harness.send(ix, &[&user]).await
+            }
+            Step::WithdrawAll => harness.send(withdraw_all(&owner, treasury), &[&user]).await,
+            Step::StrangerWithdraw(amount) => {
+                let ix = withdraw(&owner, &stranger.pubkey(), treasury, amount);
+                let result = harness.send(ix, &[&stranger]).await;
+                prop_assert!(result.is_err(), "a stranger withdrew {amount}");
+                result
+            }
+        };
+        // failures are expected; they just mustn't break anything
+        drop(result);
+
+        let vault_lamports = harness.lamports(&address).await;
+        prop_assert!(vault_lamports >= rent, "vault below rent exemption");
+        let vault = harness.vault(&owner).await.unwrap();
+        prop_assert_eq!(vault.remaining_deposits().unwrap(), vault_lamports - rent);
+        let treasury_balance = match treasury {
+            Some(treasury) => harness.lamports(&treasury).await,
+            None => 0,
+        };
+        prop_assert_eq!(
+            harness.lamports(&owner).await + vault_lamports + treasury_balance,
+            total
+        );
+        prop_assert_eq!(harness.lamports(&stranger.pubkey()).await, SOL);
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(48))]
+
+    #[test]
+    fn vault_invariants_hold(
+        fee_bps in prop_oneof![Just(0u16), 1..=1_000u16, Just(10_000u16)],
+        steps in prop::collection::vec(step(), 1..12),
+    ) {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(run(fee_bps, steps))?;
+    }
+}
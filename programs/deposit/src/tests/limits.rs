@@ -0,0 +1,121 @@
+use super::*;
+
+// A vault holding 5 SOL with a limit of 2 SOL per epoch, in epoch 10.
+async fn limited_vault(harness: &mut Harness) -> Keypair {
+    let user = harness.funded_vault(5 * SOL).await;
+    let accounts = crate::accounts::UpdateWithdrawLimit {
+        vault: vault_address(&user.pubkey()),
+        owner: user.pubkey(),
+    };
+    let data = crate::instruction::SetWithdrawLimit {
+        max_withdraw_per_epoch: 2 * SOL,
+    };
+    harness
+        .send(instruction(accounts, data), &[&user])
+        .await
+        .unwrap();
+    harness.warp_to_epoch(10).await;
+    user
+}
+
+#[tokio::test]
+async fn withdrawals_fill_the_epoch_limit_exactly() {
+    let mut harness = Harness::new().await;
+    let user = limited_vault(&mut harness).await;
+
+    for amount in [SOL, SOL] {
+        harness
+            .send(
+                withdraw(&user.pubkey(), &user.pubkey(), None, amount),
+                &[&user],
+            )
+            .await
+            .unwrap();
+    }
+    let result = harness
+        .send(withdraw(&user.pubkey(), &user.pubkey(), None, 1), &[&user])
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::WithdrawLimitExceeded)
+    );
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.last_withdraw_epoch, 10);
+    assert_eq!(vault.epoch_withdrawn, 2 * SOL);
+}
+
+#[tokio::test]
+async fn the_limit_resets_at_the_epoch_boundary() {
+    let mut harness = Harness::new().await;
+    let user = limited_vault(&mut harness).await;
+    harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, 2 * SOL),
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    harness.warp_to_epoch(11).await;
+    let result = harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, 2 * SOL + 1),
+            &[&user],
+        )
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::WithdrawLimitExceeded)
+    );
+    harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, 2 * SOL),
+            &[&user],
+        )
+        .await
+        .unwrap();
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.last_withdraw_epoch, 11);
+    assert_eq!(vault.epoch_withdrawn, 2 * SOL);
+}
+
+#[tokio::test]
+async fn withdraw_all_takes_what_is_left_of_the_limit() {
+    let mut harness = Harness::new().await;
+    let user = limited_vault(&mut harness).await;
+    harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL / 2),
+            &[&user],
+        )
+        .await
+        .unwrap();
+    let before = harness.lamports(&user.pubkey()).await;
+
+    harness
+        .send(invariants::withdraw_all(&user.pubkey(), None), &[&user])
+        .await
+        .unwrap();
+
+    assert_eq!(harness.lamports(&user.pubkey()).await, before + 3 * SOL / 2);
+    let result = harness
+        .send(invariants::withdraw_all(&user.pubkey(), None), &[&user])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NothingToWithdraw));
+}
+
+#[tokio::test]
+async fn closing_cannot_get_around_the_limit() {
+    let mut harness = Harness::new().await;
+    let user = limited_vault(&mut harness).await;
+
+    let result = harness
+        .send(close_vault(&user.pubkey(), None), &[&user])
+        .await;
+
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::WithdrawLimitExceeded)
+    );
+    assert!(harness.vault(&user.pubkey()).await.is_some());
+}
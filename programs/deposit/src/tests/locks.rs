@@ -0,0 +1,121 @@
+use super::*;
+
+const LOCK_SECS: u64 = 7 * 24 * 60 * 60;
+
+fn deposit_locked(user: &Pubkey, amount: u64, lock_duration_secs: u64) -> Instruction {
+    instruction(
+        crate::accounts::Deposit {
+            vault: vault_address(user),
+            config: config_address(),
+            user: *user,
+            system_program: system_program::ID,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::DepositLocked {
+            amount,
+            lock_duration_secs,
+        },
+    )
+}
+
+// A vault holding `SOL`, locked for LOCK_SECS from 1_700_000_000.
+async fn locked_vault(harness: &mut Harness) -> Keypair {
+    let user = harness.funded_vault(0).await;
+    harness.warp_to(1_700_000_000).await;
+    harness
+        .send(deposit_locked(&user.pubkey(), SOL, LOCK_SECS), &[&user])
+        .await
+        .unwrap();
+    user
+}
+
+#[tokio::test]
+async fn locked_vault_pays_out_from_lock_until() {
+    let mut harness = Harness::new().await;
+    let user = locked_vault(&mut harness).await;
+    let lock_until = harness.vault(&user.pubkey()).await.unwrap().lock_until;
+    assert_eq!(lock_until, 1_700_000_000 + LOCK_SECS as i64);
+
+    harness.warp_to(lock_until - 1).await;
+    let result = harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL / 2),
+            &[&user],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::StillLocked));
+    let result = harness
+        .send(invariants::withdraw_all(&user.pubkey(), None), &[&user])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::StillLocked));
+
+    harness.warp_to(lock_until).await;
+    harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL / 2),
+            &[&user],
+        )
+        .await
+        .unwrap();
+    harness
+        .send(invariants::withdraw_all(&user.pubkey(), None), &[&user])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn plain_deposits_leave_the_lock_alone() {
+    let mut harness = Harness::new().await;
+    let user = locked_vault(&mut harness).await;
+    let lock_until = harness.vault(&user.pubkey()).await.unwrap().lock_until;
+
+    harness.warp_to(lock_until - 10).await;
+    harness
+        .send(deposit(&user.pubkey(), SOL), &[&user])
+        .await
+        .unwrap();
+
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.lock_until, lock_until);
+}
+
+#[tokio::test]
+async fn a_shorter_lock_never_shortens_the_current_one() {
+    let mut harness = Harness::new().await;
+    let user = locked_vault(&mut harness).await;
+    let lock_until = harness.vault(&user.pubkey()).await.unwrap().lock_until;
+
+    harness
+        .send(deposit_locked(&user.pubkey(), SOL, 60), &[&user])
+        .await
+        .unwrap();
+    assert_eq!(
+        harness.vault(&user.pubkey()).await.unwrap().lock_until,
+        lock_until
+    );
+
+    harness
+        .send(deposit_locked(&user.pubkey(), SOL, 2 * LOCK_SECS), &[&user])
+        .await
+        .unwrap();
+    assert_eq!(
+        harness.vault(&user.pubkey()).await.unwrap().lock_until,
+        1_700_000_000 + 2 * LOCK_SECS as i64
+    );
+}
+
+#[tokio::test]
+async fn locked_vault_cannot_be_closed() {
+    let mut harness = Harness::new().await;
+    let user = locked_vault(&mut harness).await;
+
+    let result = harness
+        .send(close_vault(&user.pubkey(), None), &[&user])
+        .await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::StillLocked));
+    assert!(harness.vault(&user.pubkey()).await.is_some());
+}
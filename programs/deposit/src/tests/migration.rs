@@ -0,0 +1,125 @@
+use super::*;
+
+fn migrate_vault(user: &Pubkey) -> Instruction {
+    instruction(
+        crate::accounts::MigrateVault {
+            vault: vault_address(user),
+            user: *user,
+            system_program: system_program::ID,
+        },
+        crate::instruction::MigrateVault {},
+    )
+}
+
+#[tokio::test]
+async fn legacy_vault_migrates_with_its_balance_as_deposits() {
+    let mut harness = Harness::new().await;
+    let user = harness.legacy_vault(SOL).await;
+    let address = vault_address(&user.pubkey());
+    let rent = harness.rent(Vault::SPACE).await;
+    let extra_rent = rent - harness.rent(Vault::LEGACY_SPACE).await;
+
+    harness
+        .send(migrate_vault(&user.pubkey()), &[&user])
+        .await
+        .unwrap();
+
+    assert_eq!(harness.lamports(&user.pubkey()).await, 2 * SOL - extra_rent);
+    assert_eq!(harness.lamports(&address).await, rent + SOL);
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.version, Vault::VERSION);
+    assert_eq!(vault.owner, user.pubkey());
+    assert_eq!(vault.creator, user.pubkey());
+    assert_eq!(
+        vault.bump,
+        Pubkey::find_program_address(&[b"vault", user.pubkey().as_ref()], &ID).1
+    );
+    assert_eq!(vault.total_deposited, SOL);
+    assert_eq!(vault.total_withdrawn, 0);
+
+    harness
+        .send(deposit(&user.pubkey(), SOL / 2), &[&user])
+        .await
+        .unwrap();
+    harness
+        .send(invariants::withdraw_all(&user.pubkey(), None), &[&user])
+        .await
+        .unwrap();
+    assert_eq!(harness.lamports(&address).await, rent);
+}
+
+#[tokio::test]
+async fn legacy_vault_refuses_deposits_until_migrated() {
+    let mut harness = Harness::new().await;
+    let user = harness.legacy_vault(SOL).await;
+    let sponsor = harness.wallet(2 * SOL);
+
+    let result = harness.send(deposit(&user.pubkey(), SOL), &[&user]).await;
+    assert_eq!(error_code(result), u32::from(VaultError::NeedsMigration));
+    let result = harness
+        .send(
+            vault::deposit_for(&sponsor.pubkey(), &user.pubkey(), SOL),
+            &[&sponsor],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NeedsMigration));
+    let result = harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL),
+            &[&user],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NeedsMigration));
+
+    let rent = harness.rent(Vault::LEGACY_SPACE).await;
+    assert_eq!(
+        harness.lamports(&vault_address(&user.pubkey())).await,
+        rent + SOL
+    );
+}
+
+#[tokio::test]
+async fn a_vault_migrates_only_once() {
+    let mut harness = Harness::new().await;
+    let user = harness.legacy_vault(SOL).await;
+    harness
+        .send(migrate_vault(&user.pubkey()), &[&user])
+        .await
+        .unwrap();
+
+    let result = harness.send(migrate_vault(&user.pubkey()), &[&user]).await;
+    assert_eq!(error_code(result), u32::from(VaultError::AlreadyMigrated));
+
+    let current = harness.funded_vault(SOL).await;
+    let result = harness
+        .send(migrate_vault(&current.pubkey()), &[&current])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::AlreadyMigrated));
+}
+
+#[tokio::test]
+async fn a_later_layout_needs_migration() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let address = vault_address(&user.pubkey());
+    let mut account = harness
+        .ctx
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap();
+    // the version follows the discriminator
+    account.data[8] = Vault::VERSION + 1;
+    harness.ctx.set_account(&address, &account.into());
+
+    let result = harness.send(deposit(&user.pubkey(), SOL), &[&user]).await;
+    assert_eq!(error_code(result), u32::from(VaultError::NeedsMigration));
+    let result = harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL),
+            &[&user],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NeedsMigration));
+}
@@ -0,0 +1,611 @@
+// Runs the program natively under solana-program-test, so `cargo test` needs
+// neither an SBF build nor a validator. The instruction builders add the
+// `cpi-events` accounts when that feature is on, so the tests run with or
+// without it.
+
+use std::sync::{Once, OnceLock};
+
+use anchor_lang::{
+    solana_program::{
+        entrypoint::ProgramResult, instruction::Instruction, program_stubs::SyscallStubs,
+    },
+    system_program, InstructionData, ToAccountMetas,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::AccountSharedData,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::InstructionError,
+    signature::Keypair,
+    signer::Signer,
+    transaction::{Transaction, TransactionError},
+};
+
+use super::*;
+
+mod batch;
+mod claim;
+mod compute_units;
+mod config;
+mod cosigners;
+#[cfg(feature = "cpi-events")]
+mod cpi_events;
+mod delayed;
+mod emergency;
+mod events;
+mod fees;
+mod invariants;
+mod limits;
+mod locks;
+mod migration;
+mod ownership;
+mod payout;
+mod receipts;
+mod references;
+mod tokens;
+mod vault;
+mod vesting;
+mod withdrawers;
+
+const SOL: u64 = 1_000_000_000;
+
+type SendResult = std::result::Result<(), BanksClientError>;
+
+// Natively, `sol_log_data` only prints to stdout, so events would never
+// reach the transaction's logs. `LogData` wraps program-test's syscall stubs
+// and routes it through their `sol_log`, which logs it as
+// "Program log: data: <base64>". Banks doesn't hand back inner instructions
+// either, so each self-CPI's data is logged as
+// "Program log: self-cpi: <base64>".
+struct LogData;
+
+static NATIVE_STUBS: OnceLock<Box<dyn SyscallStubs>> = OnceLock::new();
+
+impl LogData {
+    fn install() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            let native = solana_sdk::program_stubs::set_syscall_stubs(Box::new(LogData));
+            let _ = NATIVE_STUBS.set(native);
+        });
+    }
+
+    // a program running between the two steps of `install` waits here
+    fn native() -> &'static dyn SyscallStubs {
+        NATIVE_STUBS.wait().as_ref()
+    }
+}
+
+impl SyscallStubs for LogData {
+    fn sol_log(&self, message: &str) {
+        Self::native().sol_log(message)
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        let fields: Vec<String> = fields.iter().map(|field| STANDARD.encode(field)).collect();
+        Self::native().sol_log(&format!("data: {}", fields.join(" ")))
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        if instruction.program_id == ID {
+            let data = STANDARD.encode(&instruction.data);
+            Self::native().sol_log(&format!("self-cpi: {data}"));
+        }
+        Self::native().sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::native().sol_get_clock_sysvar(var_addr)
+    }
+
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::native().sol_get_epoch_schedule_sysvar(var_addr)
+    }
+
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::native().sol_get_epoch_rewards_sysvar(var_addr)
+    }
+
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::native().sol_get_fees_sysvar(var_addr)
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::native().sol_get_rent_sysvar(var_addr)
+    }
+
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        Self::native().sol_get_last_restart_slot(var_addr)
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        Self::native().sol_get_return_data()
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        Self::native().sol_set_return_data(data)
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        Self::native().sol_get_stack_height()
+    }
+}
+
+// `entry` wants the account slice to live as long as the accounts in it.
+fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(accounts.to_vec().into_boxed_slice());
+    entry(program_id, accounts, data)
+}
+
+fn vault_address(creator: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault", creator.as_ref()], &ID).0
+}
+
+fn config_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &ID).0
+}
+
+#[cfg(feature = "cpi-events")]
+fn event_authority() -> Pubkey {
+    Pubkey::find_program_address(&[b"__event_authority"], &ID).0
+}
+
+fn receipt_address(vault: &Pubkey, depositor: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"receipt", vault.as_ref(), depositor.as_ref()], &ID).0
+}
+
+fn instruction(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+fn initialize(user: &Pubkey) -> Instruction {
+    instruction(
+        crate::accounts::Initialize {
+            vault: vault_address(user),
+            user: *user,
+            system_program: system_program::ID,
+        },
+        crate::instruction::Initialize { delayed: false },
+    )
+}
+
+fn deposit(user: &Pubkey, amount: u64) -> Instruction {
+    instruction(
+        crate::accounts::Deposit {
+            vault: vault_address(user),
+            config: config_address(),
+            user: *user,
+            system_program: system_program::ID,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::Deposit { amount },
+    )
+}
+
+// `user` withdrawing from `creator`'s vault.
+fn withdraw(creator: &Pubkey, user: &Pubkey, treasury: Option<Pubkey>, amount: u64) -> Instruction {
+    instruction(
+        crate::accounts::Withdraw {
+            vault: vault_address(creator),
+            config: config_address(),
+            treasury,
+            payout: None,
+            user: *user,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::Withdraw { amount },
+    )
+}
+
+fn close_vault(user: &Pubkey, treasury: Option<Pubkey>) -> Instruction {
+    instruction(
+        crate::accounts::CloseVault {
+            vault: vault_address(user),
+            config: config_address(),
+            treasury,
+            user: *user,
+        },
+        crate::instruction::CloseVault {},
+    )
+}
+
+fn withdraw_and_close(user: &Pubkey) -> Instruction {
+    instruction(
+        crate::accounts::CloseVault {
+            vault: vault_address(user),
+            config: config_address(),
+            treasury: None,
+            user: *user,
+        },
+        crate::instruction::WithdrawAndClose {},
+    )
+}
+
+fn propose_owner(owner: &Pubkey, new_owner: &Pubkey) -> Instruction {
+    instruction(
+        crate::accounts::ProposeOwner {
+            vault: vault_address(owner),
+            owner: *owner,
+        },
+        crate::instruction::ProposeOwner {
+            new_owner: *new_owner,
+        },
+    )
+}
+
+fn accept_ownership(creator: &Pubkey, new_owner: &Pubkey) -> Instruction {
+    instruction(
+        crate::accounts::AcceptOwnership {
+            vault: vault_address(creator),
+            new_owner: *new_owner,
+        },
+        crate::instruction::AcceptOwnership {},
+    )
+}
+
+fn set_beneficiary(owner: &Pubkey, beneficiary: &Pubkey, inactivity_secs: i64) -> Instruction {
+    instruction(
+        crate::accounts::UpdateBeneficiary {
+            vault: vault_address(owner),
+            owner: *owner,
+        },
+        crate::instruction::SetBeneficiary {
+            beneficiary: *beneficiary,
+            inactivity_secs,
+        },
+    )
+}
+
+fn clear_beneficiary(owner: &Pubkey) -> Instruction {
+    instruction(
+        crate::accounts::UpdateBeneficiary {
+            vault: vault_address(owner),
+            owner: *owner,
+        },
+        crate::instruction::ClearBeneficiary {},
+    )
+}
+
+fn set_payout_address(owner: &Pubkey, address: Option<Pubkey>) -> Instruction {
+    instruction(
+        crate::accounts::UpdatePayoutAddress {
+            vault: vault_address(owner),
+            owner: *owner,
+        },
+        crate::instruction::SetPayoutAddress { address },
+    )
+}
+
+fn set_cosigners(owner: &Pubkey, cosigners: Vec<Pubkey>, threshold: u8) -> Instruction {
+    instruction(
+        crate::accounts::UpdateCosigners {
+            vault: vault_address(owner),
+            owner: *owner,
+        },
+        crate::instruction::SetCosigners {
+            cosigners,
+            threshold,
+        },
+    )
+}
+
+// `instruction` with `cosigners` signing as remaining accounts.
+fn cosigned(mut instruction: Instruction, cosigners: &[&Keypair]) -> Instruction {
+    for cosigner in cosigners {
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(cosigner.pubkey(), true));
+    }
+    instruction
+}
+
+fn claim_inactive(creator: &Pubkey, beneficiary: &Pubkey) -> Instruction {
+    instruction(
+        crate::accounts::ClaimInactive {
+            vault: vault_address(creator),
+            config: config_address(),
+            treasury: None,
+            beneficiary: *beneficiary,
+        },
+        crate::instruction::ClaimInactive {},
+    )
+}
+
+fn update_config(admin: &Pubkey, data: impl InstructionData) -> Instruction {
+    instruction(
+        crate::accounts::UpdateConfig {
+            config: config_address(),
+            admin: *admin,
+        },
+        data,
+    )
+}
+
+fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    anchor_spl::associated_token::get_associated_token_address_with_program_id(
+        owner,
+        mint,
+        token_program,
+    )
+}
+
+// The `VaultError` or Anchor error code a transaction failed with.
+fn error_code(result: SendResult) -> u32 {
+    match result.expect_err("the transaction succeeded").unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => code,
+        err => panic!("expected a program error, got {err}"),
+    }
+}
+
+struct Harness {
+    ctx: ProgramTestContext,
+    // keeps otherwise identical transactions apart
+    sent: u32,
+}
+
+impl Harness {
+    async fn new() -> Self {
+        let mut program = ProgramTest::new("deposit", ID, processor!(process));
+        program.prefer_bpf(false);
+        Self::start(program).await
+    }
+
+    async fn start(program: ProgramTest) -> Self {
+        let ctx = program.start_with_context().await;
+        // program-test has installed its stubs by now
+        LogData::install();
+        Self { ctx, sent: 0 }
+    }
+
+    fn wallet(&mut self, lamports: u64) -> Keypair {
+        let wallet = Keypair::new();
+        self.ctx.set_account(
+            &wallet.pubkey(),
+            &AccountSharedData::new(lamports, 0, &system_program::ID),
+        );
+        wallet
+    }
+
+    // The context's payer pays the fee, so `signers` only see what the
+    // program moves.
+    fn transaction(&mut self, instruction: Instruction, signers: &[&Keypair]) -> Transaction {
+        self.sent += 1;
+        let payer = self.ctx.payer.insecure_clone();
+        let mut all = vec![&payer];
+        all.extend(signers);
+        Transaction::new_signed_with_payer(
+            &[
+                ComputeBudgetInstruction::set_compute_unit_limit(1_400_000 - self.sent),
+                instruction,
+            ],
+            Some(&payer.pubkey()),
+            &all,
+            self.ctx.last_blockhash,
+        )
+    }
+
+    async fn send(&mut self, instruction: Instruction, signers: &[&Keypair]) -> SendResult {
+        let tx = self.transaction(instruction, signers);
+        self.ctx.banks_client.process_transaction(tx).await
+    }
+
+    // Like `send`, returning the compute units the transaction used.
+    async fn metered(&mut self, instruction: Instruction, signers: &[&Keypair]) -> u64 {
+        let tx = self.transaction(instruction, signers);
+        let processed = self
+            .ctx
+            .banks_client
+            .process_transaction_with_metadata(tx)
+            .await
+            .unwrap();
+        processed.result.unwrap();
+        processed.metadata.unwrap().compute_units_consumed
+    }
+
+    // Like `send`, returning the transaction's log messages.
+    async fn logs(&mut self, instruction: Instruction, signers: &[&Keypair]) -> Vec<String> {
+        let tx = self.transaction(instruction, signers);
+        let processed = self
+            .ctx
+            .banks_client
+            .process_transaction_with_metadata(tx)
+            .await
+            .unwrap();
+        processed.result.unwrap();
+        processed.metadata.unwrap().log_messages
+    }
+
+    async fn lamports(&mut self, address: &Pubkey) -> u64 {
+        self.ctx.banks_client.get_balance(*address).await.unwrap()
+    }
+
+    async fn vault(&mut self, creator: &Pubkey) -> Option<Vault> {
+        let account = self
+            .ctx
+            .banks_client
+            .get_account(vault_address(creator))
+            .await
+            .unwrap()?;
+        Some(Vault::try_deserialize(&mut &account.data[..]).unwrap())
+    }
+
+    async fn warp_to(&mut self, unix_timestamp: i64) {
+        let mut clock: Clock = self.ctx.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp = unix_timestamp;
+        self.ctx.set_sysvar(&clock);
+    }
+
+    // Only the clock's epoch moves; the bank's own epoch stays put.
+    async fn warp_to_epoch(&mut self, epoch: u64) {
+        let mut clock: Clock = self.ctx.banks_client.get_sysvar().await.unwrap();
+        clock.epoch = epoch;
+        self.ctx.set_sysvar(&clock);
+    }
+
+    async fn rent(&mut self, space: usize) -> u64 {
+        let rent = self.ctx.banks_client.get_rent().await.unwrap();
+        rent.minimum_balance(space)
+    }
+
+    // A wallet with an initialized vault holding `deposited`.
+    async fn funded_vault(&mut self, deposited: u64) -> Keypair {
+        let user = self.wallet(10 * SOL);
+        self.send(initialize(&user.pubkey()), &[&user])
+            .await
+            .unwrap();
+        if deposited > 0 {
+            self.send(deposit(&user.pubkey(), deposited), &[&user])
+                .await
+                .unwrap();
+        }
+        user
+    }
+
+    // A wallet whose vault is still on the owner-only layout, holding
+    // `deposited` above its rent.
+    async fn legacy_vault(&mut self, deposited: u64) -> Keypair {
+        let user = self.wallet(2 * SOL);
+        let mut data = Vault::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(user.pubkey().as_ref());
+        let lamports = self.rent(Vault::LEGACY_SPACE).await + deposited;
+        self.ctx.set_account(
+            &vault_address(&user.pubkey()),
+            &solana_sdk::account::Account {
+                lamports,
+                data,
+                owner: ID,
+                executable: false,
+                rent_epoch: 0,
+            }
+            .into(),
+        );
+        user
+    }
+
+    // Creates the config with no cap, returning its admin.
+    async fn init_config(&mut self) -> Keypair {
+        let admin = self.wallet(SOL);
+        let accounts = crate::accounts::InitializeConfig {
+            config: config_address(),
+            admin: admin.pubkey(),
+            system_program: system_program::ID,
+        };
+        let init = crate::instruction::InitializeConfig {
+            max_deposit_per_vault: 0,
+        };
+        self.send(instruction(accounts, init), &[&admin])
+            .await
+            .unwrap();
+        admin
+    }
+
+    // Creates the config and sets its fee, returning the treasury.
+    async fn charge_fee(&mut self, fee_bps: u16) -> Pubkey {
+        let admin = self.init_config().await;
+        let treasury = self.wallet(SOL).pubkey();
+        let set_fee = crate::instruction::SetFee { fee_bps, treasury };
+        self.send(update_config(&admin.pubkey(), set_fee), &[&admin])
+            .await
+            .unwrap();
+        treasury
+    }
+
+    // A mint with 6 decimals under `token_program`, classic or token-2022,
+    // whose mint authority is the context's payer. `transfer_fee_bps`
+    // adds token-2022's transfer fee extension.
+    async fn mint(&mut self, token_program: &Pubkey, transfer_fee_bps: Option<u16>) -> Pubkey {
+        use anchor_spl::token_2022::spl_token_2022::{
+            extension::{transfer_fee, ExtensionType},
+            instruction::initialize_mint2,
+            state::Mint,
+        };
+
+        let mint = Keypair::new();
+        let authority = self.ctx.payer.pubkey();
+        let extensions: &[ExtensionType] = match transfer_fee_bps {
+            Some(_) => &[ExtensionType::TransferFeeConfig],
+            None => &[],
+        };
+        let space = ExtensionType::try_calculate_account_len::<Mint>(extensions).unwrap();
+        let lamports = self.rent(space).await;
+        let create = solana_sdk::system_instruction::create_account(
+            &authority,
+            &mint.pubkey(),
+            lamports,
+            space as u64,
+            token_program,
+        );
+        self.send(create, &[&mint]).await.unwrap();
+        if let Some(fee_bps) = transfer_fee_bps {
+            let init_fee = transfer_fee::instruction::initialize_transfer_fee_config(
+                token_program,
+                &mint.pubkey(),
+                None,
+                None,
+                fee_bps,
+                u64::MAX,
+            )
+            .unwrap();
+            self.send(init_fee, &[]).await.unwrap();
+        }
+        let init = initialize_mint2(token_program, &mint.pubkey(), &authority, None, 6).unwrap();
+        self.send(init, &[]).await.unwrap();
+        mint.pubkey()
+    }
+
+    async fn token_balance(&mut self, account: &Pubkey) -> u64 {
+        let account = self
+            .ctx
+            .banks_client
+            .get_account(*account)
+            .await
+            .unwrap()
+            .unwrap();
+        // the amount follows the mint and owner in both token programs
+        u64::from_le_bytes(account.data[64..72].try_into().unwrap())
+    }
+
+    // Creates `owner`'s associated token account and mints `amount` into
+    // it.
+    async fn token_account(
+        &mut self,
+        owner: &Pubkey,
+        mint: &Pubkey,
+        token_program: &Pubkey,
+        amount: u64,
+    ) -> Pubkey {
+        use anchor_spl::{
+            associated_token::spl_associated_token_account::instruction::create_associated_token_account,
+            token_2022::spl_token_2022::instruction::mint_to,
+        };
+
+        let payer = self.ctx.payer.pubkey();
+        let create = create_associated_token_account(&payer, owner, mint, token_program);
+        self.send(create, &[]).await.unwrap();
+        let account = associated_token_address(owner, mint, token_program);
+        if amount > 0 {
+            let mint_to = mint_to(token_program, mint, &account, &payer, &[], amount).unwrap();
+            self.send(mint_to, &[]).await.unwrap();
+        }
+        account
+    }
+}
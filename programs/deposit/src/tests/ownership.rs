@@ -0,0 +1,142 @@
+use super::*;
+
+#[tokio::test]
+async fn accepted_proposal_hands_over_the_vault() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(SOL).await;
+    let successor = harness.wallet(SOL);
+    harness
+        .send(
+            propose_owner(&owner.pubkey(), &successor.pubkey()),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    harness
+        .send(
+            accept_ownership(&owner.pubkey(), &successor.pubkey()),
+            &[&successor],
+        )
+        .await
+        .unwrap();
+
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(vault.owner, successor.pubkey());
+    assert_eq!(vault.creator, owner.pubkey());
+    assert_eq!(vault.pending_owner, None);
+
+    let result = harness
+        .send(
+            withdraw(&owner.pubkey(), &owner.pubkey(), None, SOL / 2),
+            &[&owner],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotAuthorized));
+    harness
+        .send(
+            withdraw(&owner.pubkey(), &successor.pubkey(), None, SOL / 2),
+            &[&successor],
+        )
+        .await
+        .unwrap();
+    assert_eq!(harness.lamports(&successor.pubkey()).await, SOL + SOL / 2);
+}
+
+#[tokio::test]
+async fn only_the_proposed_owner_can_accept() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(SOL).await;
+    let successor = harness.wallet(SOL);
+    let stranger = harness.wallet(SOL);
+    harness
+        .send(
+            propose_owner(&owner.pubkey(), &successor.pubkey()),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let result = harness
+        .send(
+            accept_ownership(&owner.pubkey(), &stranger.pubkey()),
+            &[&stranger],
+        )
+        .await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::NotPendingOwner));
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(vault.owner, owner.pubkey());
+    assert_eq!(vault.pending_owner, Some(successor.pubkey()));
+}
+
+#[tokio::test]
+async fn a_new_proposal_replaces_the_pending_one() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(SOL).await;
+    let first = harness.wallet(SOL);
+    let second = harness.wallet(SOL);
+    for proposed in [&first, &second] {
+        harness
+            .send(
+                propose_owner(&owner.pubkey(), &proposed.pubkey()),
+                &[&owner],
+            )
+            .await
+            .unwrap();
+    }
+
+    let result = harness
+        .send(
+            accept_ownership(&owner.pubkey(), &first.pubkey()),
+            &[&first],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotPendingOwner));
+    harness
+        .send(
+            accept_ownership(&owner.pubkey(), &second.pubkey()),
+            &[&second],
+        )
+        .await
+        .unwrap();
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(vault.owner, second.pubkey());
+}
+
+#[tokio::test]
+async fn the_current_owner_keeps_the_vault_while_a_proposal_is_pending() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(SOL).await;
+    let successor = harness.wallet(SOL);
+    harness
+        .send(
+            propose_owner(&owner.pubkey(), &successor.pubkey()),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let result = harness
+        .send(
+            withdraw(&owner.pubkey(), &successor.pubkey(), None, SOL / 2),
+            &[&successor],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotAuthorized));
+    harness
+        .send(deposit(&owner.pubkey(), SOL), &[&owner])
+        .await
+        .unwrap();
+    harness
+        .send(
+            withdraw(&owner.pubkey(), &owner.pubkey(), None, SOL / 2),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let mut repropose = propose_owner(&successor.pubkey(), &successor.pubkey());
+    repropose.accounts[0] = AccountMeta::new(vault_address(&owner.pubkey()), false);
+    let result = harness.send(repropose, &[&successor]).await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotOwner));
+}
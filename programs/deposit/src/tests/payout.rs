@@ -0,0 +1,169 @@
+use super::*;
+
+// A vault holding 3 SOL whose payout address has taken effect, and that
+// address.
+async fn bound_vault(harness: &mut Harness) -> (Keypair, Pubkey) {
+    let owner = harness.funded_vault(3 * SOL).await;
+    let payout = harness.wallet(SOL).pubkey();
+    harness
+        .send(set_payout_address(&owner.pubkey(), Some(payout)), &[&owner])
+        .await
+        .unwrap();
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    harness.warp_to(vault.payout_change_at).await;
+    (owner, payout)
+}
+
+fn paying(mut ix: Instruction, payout: Pubkey) -> Instruction {
+    // vault, config, treasury, payout, user
+    ix.accounts[3] = AccountMeta::new(payout, false);
+    ix
+}
+
+#[tokio::test]
+async fn payout_address_applies_only_after_the_delay() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(3 * SOL).await;
+    let payout = harness.wallet(SOL).pubkey();
+    harness
+        .send(set_payout_address(&owner.pubkey(), Some(payout)), &[&owner])
+        .await
+        .unwrap();
+    let change_at = harness
+        .vault(&owner.pubkey())
+        .await
+        .unwrap()
+        .payout_change_at;
+
+    harness.warp_to(change_at - 1).await;
+    let ix = withdraw(&owner.pubkey(), &owner.pubkey(), None, SOL);
+    harness.send(ix, &[&owner]).await.unwrap();
+
+    harness.warp_to(change_at).await;
+    let ix = withdraw(&owner.pubkey(), &owner.pubkey(), None, SOL);
+    let result = harness.send(ix, &[&owner]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::WrongPayoutAddress)
+    );
+}
+
+#[tokio::test]
+async fn bound_withdraw_pays_only_the_payout_address() {
+    let mut harness = Harness::new().await;
+    let (owner, payout) = bound_vault(&mut harness).await;
+    let elsewhere = harness.wallet(SOL).pubkey();
+
+    let ix = withdraw(&owner.pubkey(), &owner.pubkey(), None, SOL);
+    let result = harness.send(ix, &[&owner]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::WrongPayoutAddress)
+    );
+    let ix = withdraw(&owner.pubkey(), &owner.pubkey(), None, SOL);
+    let result = harness.send(paying(ix, elsewhere), &[&owner]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::WrongPayoutAddress)
+    );
+
+    let owner_before = harness.lamports(&owner.pubkey()).await;
+    let ix = withdraw(&owner.pubkey(), &owner.pubkey(), None, SOL);
+    harness.send(paying(ix, payout), &[&owner]).await.unwrap();
+    assert_eq!(harness.lamports(&payout).await, 2 * SOL);
+    assert_eq!(harness.lamports(&owner.pubkey()).await, owner_before);
+}
+
+#[tokio::test]
+async fn bound_withdraw_all_pays_only_the_payout_address() {
+    let mut harness = Harness::new().await;
+    let (owner, payout) = bound_vault(&mut harness).await;
+
+    let ix = invariants::withdraw_all(&owner.pubkey(), None);
+    let result = harness.send(ix, &[&owner]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::WrongPayoutAddress)
+    );
+
+    let ix = invariants::withdraw_all(&owner.pubkey(), None);
+    harness.send(paying(ix, payout), &[&owner]).await.unwrap();
+    assert_eq!(harness.lamports(&payout).await, 4 * SOL);
+}
+
+#[tokio::test]
+async fn bound_vault_cannot_be_closed_to_the_owner() {
+    let mut harness = Harness::new().await;
+    let (owner, _) = bound_vault(&mut harness).await;
+
+    let result = harness
+        .send(close_vault(&owner.pubkey(), None), &[&owner])
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::PayoutAddressBound)
+    );
+    let result = harness
+        .send(withdraw_and_close(&owner.pubkey()), &[&owner])
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::PayoutAddressBound)
+    );
+    assert!(harness.vault(&owner.pubkey()).await.is_some());
+}
+
+#[tokio::test]
+async fn bound_vault_can_only_be_claimed_by_the_payout_address() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(3 * SOL).await;
+    let beneficiary = harness.wallet(SOL);
+    let payout = harness.wallet(SOL);
+    harness
+        .send(
+            set_payout_address(&owner.pubkey(), Some(payout.pubkey())),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    harness
+        .send(
+            set_beneficiary(&owner.pubkey(), &beneficiary.pubkey(), 60),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    harness.warp_to(vault.beneficiary_change_at + 61).await;
+
+    let result = harness
+        .send(
+            claim_inactive(&owner.pubkey(), &beneficiary.pubkey()),
+            &[&beneficiary],
+        )
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::WrongPayoutAddress)
+    );
+
+    harness
+        .send(
+            set_beneficiary(&owner.pubkey(), &payout.pubkey(), 60),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    harness.warp_to(vault.beneficiary_change_at + 61).await;
+    let rent = harness.rent(Vault::SPACE).await;
+    harness
+        .send(
+            claim_inactive(&owner.pubkey(), &payout.pubkey()),
+            &[&payout],
+        )
+        .await
+        .unwrap();
+    assert_eq!(harness.lamports(&payout.pubkey()).await, 4 * SOL + rent);
+    assert_eq!(harness.lamports(&beneficiary.pubkey()).await, SOL);
+}
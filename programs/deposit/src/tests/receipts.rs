@@ -0,0 +1,165 @@
+use super::*;
+
+fn deposit_shared(owner: &Pubkey, depositor: &Pubkey, amount: u64) -> Instruction {
+    let vault = vault_address(owner);
+    instruction(
+        crate::accounts::DepositShared {
+            vault,
+            receipt: receipt_address(&vault, depositor),
+            config: config_address(),
+            depositor: *depositor,
+            system_program: system_program::ID,
+        },
+        crate::instruction::DepositShared { amount },
+    )
+}
+
+fn withdraw_shared(owner: &Pubkey, depositor: &Pubkey, amount: u64) -> Instruction {
+    let vault = vault_address(owner);
+    instruction(
+        crate::accounts::WithdrawShared {
+            vault,
+            receipt: receipt_address(&vault, depositor),
+            depositor: *depositor,
+        },
+        crate::instruction::WithdrawShared { amount },
+    )
+}
+
+fn close_receipt(owner: &Pubkey, depositor: &Pubkey) -> Instruction {
+    instruction(
+        crate::accounts::CloseReceipt {
+            receipt: receipt_address(&vault_address(owner), depositor),
+            depositor: *depositor,
+        },
+        crate::instruction::CloseReceipt {},
+    )
+}
+
+// A vault with its owner's `SOL` and two depositors' shares of 2 and 3 SOL.
+async fn shared_vault(harness: &mut Harness) -> (Keypair, Keypair, Keypair) {
+    let owner = harness.funded_vault(SOL).await;
+    let alice = harness.wallet(5 * SOL);
+    let bob = harness.wallet(5 * SOL);
+    for (depositor, amount) in [(&alice, 2 * SOL), (&bob, 3 * SOL)] {
+        harness
+            .send(
+                deposit_shared(&owner.pubkey(), &depositor.pubkey(), amount),
+                &[depositor],
+            )
+            .await
+            .unwrap();
+    }
+    (owner, alice, bob)
+}
+
+#[tokio::test]
+async fn each_depositor_takes_back_exactly_their_share() {
+    let mut harness = Harness::new().await;
+    let (owner, alice, bob) = shared_vault(&mut harness).await;
+    let rent = harness.rent(Vault::SPACE).await;
+    let receipt_rent = harness.rent(DepositReceipt::SPACE).await;
+    assert_eq!(
+        harness.vault(&owner.pubkey()).await.unwrap().shared_balance,
+        5 * SOL
+    );
+
+    for (depositor, amount) in [(&alice, 2 * SOL), (&bob, 3 * SOL)] {
+        let result = harness
+            .send(
+                withdraw_shared(&owner.pubkey(), &depositor.pubkey(), amount + 1),
+                &[depositor],
+            )
+            .await;
+        assert_eq!(error_code(result), u32::from(VaultError::ExceedsDeposits));
+        harness
+            .send(
+                withdraw_shared(&owner.pubkey(), &depositor.pubkey(), amount),
+                &[depositor],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            harness.lamports(&depositor.pubkey()).await,
+            5 * SOL - receipt_rent
+        );
+    }
+
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(vault.shared_balance, 0);
+    assert_eq!(
+        harness.lamports(&vault_address(&owner.pubkey())).await,
+        rent + SOL
+    );
+}
+
+#[tokio::test]
+async fn depositors_cannot_withdraw_through_each_others_receipts() {
+    let mut harness = Harness::new().await;
+    let (owner, alice, bob) = shared_vault(&mut harness).await;
+    let receipt_rent = harness.rent(DepositReceipt::SPACE).await;
+
+    let mut ix = withdraw_shared(&owner.pubkey(), &bob.pubkey(), SOL);
+    // vault, receipt, depositor
+    ix.accounts[1] = AccountMeta::new(
+        receipt_address(&vault_address(&owner.pubkey()), &alice.pubkey()),
+        false,
+    );
+    let result = harness.send(ix, &[&bob]).await;
+
+    assert_eq!(
+        error_code(result),
+        u32::from(anchor_lang::error::ErrorCode::ConstraintSeeds)
+    );
+    assert_eq!(
+        harness.lamports(&bob.pubkey()).await,
+        2 * SOL - receipt_rent
+    );
+}
+
+#[tokio::test]
+async fn the_owner_cannot_take_shared_funds() {
+    let mut harness = Harness::new().await;
+    let (owner, _, _) = shared_vault(&mut harness).await;
+
+    let result = harness
+        .send(
+            withdraw(&owner.pubkey(), &owner.pubkey(), None, SOL + 1),
+            &[&owner],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::ExceedsDeposits));
+    let result = harness
+        .send(close_vault(&owner.pubkey(), None), &[&owner])
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::SharedFundsOutstanding)
+    );
+}
+
+#[tokio::test]
+async fn an_emptied_receipt_closes_and_returns_its_rent() {
+    let mut harness = Harness::new().await;
+    let (owner, alice, _) = shared_vault(&mut harness).await;
+    let receipt = receipt_address(&vault_address(&owner.pubkey()), &alice.pubkey());
+
+    let result = harness
+        .send(close_receipt(&owner.pubkey(), &alice.pubkey()), &[&alice])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::ReceiptNotEmpty));
+
+    harness
+        .send(
+            withdraw_shared(&owner.pubkey(), &alice.pubkey(), 2 * SOL),
+            &[&alice],
+        )
+        .await
+        .unwrap();
+    harness
+        .send(close_receipt(&owner.pubkey(), &alice.pubkey()), &[&alice])
+        .await
+        .unwrap();
+    assert_eq!(harness.lamports(&alice.pubkey()).await, 5 * SOL);
+    assert_eq!(harness.lamports(&receipt).await, 0);
+}
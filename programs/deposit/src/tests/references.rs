@@ -0,0 +1,88 @@
+use super::*;
+
+fn deposit_with_reference(user: &Pubkey, amount: u64, reference: [u8; 16]) -> Instruction {
+    instruction(
+        crate::accounts::Deposit {
+            vault: vault_address(user),
+            config: config_address(),
+            user: *user,
+            system_program: system_program::ID,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::DepositWithReference { amount, reference },
+    )
+}
+
+#[tokio::test]
+async fn the_ring_keeps_the_last_four_deposits() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(0).await;
+    for i in 1..=6 {
+        harness.warp_to(1_700_000_000 + i64::from(i)).await;
+        harness
+            .send(
+                deposit_with_reference(&user.pubkey(), u64::from(i) * 100, [i; 16]),
+                &[&user],
+            )
+            .await
+            .unwrap();
+    }
+
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    // the fifth and sixth overwrote the first and second
+    let references: Vec<u8> = vault
+        .recent_deposits
+        .iter()
+        .map(|record| record.reference[0])
+        .collect();
+    assert_eq!(references, [5, 6, 3, 4]);
+    assert_eq!(vault.recent_deposits[1].amount, 600);
+    assert_eq!(vault.recent_deposits[1].timestamp, 1_700_000_006);
+    // the oldest entry is next in line
+    assert_eq!(vault.next_deposit, 2);
+}
+
+#[tokio::test]
+async fn plain_deposits_record_a_zero_reference() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(0).await;
+    harness
+        .send(
+            deposit_with_reference(&user.pubkey(), 100, [9; 16]),
+            &[&user],
+        )
+        .await
+        .unwrap();
+    harness
+        .send(deposit(&user.pubkey(), 200), &[&user])
+        .await
+        .unwrap();
+
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.recent_deposits[0].reference, [9; 16]);
+    assert_eq!(vault.recent_deposits[1].reference, [0; 16]);
+    assert_eq!(vault.recent_deposits[1].amount, 200);
+}
+
+#[tokio::test]
+async fn the_reference_is_in_the_deposit_event() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(0).await;
+    let reference = *b"invoice-00000042";
+
+    let logs = harness
+        .logs(
+            deposit_with_reference(&user.pubkey(), 100, reference),
+            &[&user],
+        )
+        .await;
+
+    let [event] = &events::events::<DepositEvent>(&logs)[..] else {
+        panic!("expected one DepositEvent in {logs:?}");
+    };
+    assert_eq!(event.reference, reference);
+    assert_eq!(event.amount, 100);
+}
@@ -0,0 +1,225 @@
+use anchor_spl::{token::spl_token, token_2022::spl_token_2022};
+
+use super::*;
+
+fn token_vault_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"token_vault", owner.as_ref(), mint.as_ref()], &ID).0
+}
+
+// The vault's own token account, owned by its state PDA.
+fn vault_tokens(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    associated_token_address(&token_vault_address(owner, mint), mint, token_program)
+}
+
+fn initialize_token_vault(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Instruction {
+    instruction(
+        crate::accounts::InitializeTokenVault {
+            token_vault: token_vault_address(owner, mint),
+            vault_tokens: vault_tokens(owner, mint, token_program),
+            mint: *mint,
+            user: *owner,
+            token_program: *token_program,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        },
+        crate::instruction::InitializeTokenVault {},
+    )
+}
+
+fn deposit_tokens(
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    instruction(
+        crate::accounts::DepositTokens {
+            token_vault: token_vault_address(owner, mint),
+            vault_tokens: vault_tokens(owner, mint, token_program),
+            user_tokens: associated_token_address(owner, mint, token_program),
+            mint: *mint,
+            user: *owner,
+            token_program: *token_program,
+        },
+        crate::instruction::DepositTokens { amount },
+    )
+}
+
+fn withdraw_tokens(
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    instruction(
+        crate::accounts::WithdrawTokens {
+            token_vault: token_vault_address(owner, mint),
+            vault_tokens: vault_tokens(owner, mint, token_program),
+            user_tokens: associated_token_address(owner, mint, token_program),
+            mint: *mint,
+            user: *owner,
+            token_program: *token_program,
+        },
+        crate::instruction::WithdrawTokens { amount },
+    )
+}
+
+// A wallet holding `amount` of a new mint under `token_program`, with a
+// token vault for it.
+async fn token_holder(
+    harness: &mut Harness,
+    token_program: &Pubkey,
+    transfer_fee_bps: Option<u16>,
+    amount: u64,
+) -> (Keypair, Pubkey) {
+    let user = harness.wallet(SOL);
+    let mint = harness.mint(token_program, transfer_fee_bps).await;
+    harness
+        .token_account(&user.pubkey(), &mint, token_program, amount)
+        .await;
+    harness
+        .send(
+            initialize_token_vault(&user.pubkey(), &mint, token_program),
+            &[&user],
+        )
+        .await
+        .unwrap();
+    (user, mint)
+}
+
+#[tokio::test]
+async fn tokens_go_in_and_partly_back_out() {
+    let mut harness = Harness::new().await;
+    let token_program = spl_token::ID;
+    let (user, mint) = token_holder(&mut harness, &token_program, None, 1_000_000).await;
+    let owner = user.pubkey();
+    let user_tokens = associated_token_address(&owner, &mint, &token_program);
+    let vault_tokens = vault_tokens(&owner, &mint, &token_program);
+
+    harness
+        .send(
+            deposit_tokens(&owner, &mint, &token_program, 600_000),
+            &[&user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(harness.token_balance(&vault_tokens).await, 600_000);
+    assert_eq!(harness.token_balance(&user_tokens).await, 400_000);
+
+    harness
+        .send(
+            withdraw_tokens(&owner, &mint, &token_program, 250_000),
+            &[&user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(harness.token_balance(&vault_tokens).await, 350_000);
+    assert_eq!(harness.token_balance(&user_tokens).await, 650_000);
+}
+
+#[tokio::test]
+async fn withdrawing_more_tokens_than_the_vault_holds_fails() {
+    let mut harness = Harness::new().await;
+    let token_program = spl_token::ID;
+    let (user, mint) = token_holder(&mut harness, &token_program, None, 1_000_000).await;
+    let owner = user.pubkey();
+    harness
+        .send(
+            deposit_tokens(&owner, &mint, &token_program, 600_000),
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    let result = harness
+        .send(
+            withdraw_tokens(&owner, &mint, &token_program, 600_001),
+            &[&user],
+        )
+        .await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::InsufficientFunds));
+    let vault_tokens = vault_tokens(&owner, &mint, &token_program);
+    assert_eq!(harness.token_balance(&vault_tokens).await, 600_000);
+}
+
+#[tokio::test]
+async fn a_token_vault_only_takes_its_own_mint() {
+    let mut harness = Harness::new().await;
+    let token_program = spl_token::ID;
+    let (user, mint) = token_holder(&mut harness, &token_program, None, 1_000_000).await;
+    let owner = user.pubkey();
+    let other_mint = harness.mint(&token_program, None).await;
+    let other_tokens = harness
+        .token_account(&owner, &other_mint, &token_program, 1_000_000)
+        .await;
+
+    let mut ix = deposit_tokens(&owner, &mint, &token_program, 1_000);
+    // vault, vault tokens, user tokens, mint
+    ix.accounts[2] = AccountMeta::new(other_tokens, false);
+    ix.accounts[3] = AccountMeta::new_readonly(other_mint, false);
+    let result = harness.send(ix, &[&user]).await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::WrongMint));
+    assert_eq!(harness.token_balance(&other_tokens).await, 1_000_000);
+}
+
+#[tokio::test]
+async fn transfer_fees_show_in_the_received_amounts() {
+    let mut harness = Harness::new().await;
+    let token_program = spl_token_2022::ID;
+    // 1% on every transfer, in and out of the vault
+    let (user, mint) = token_holder(&mut harness, &token_program, Some(100), 1_000_000).await;
+    let owner = user.pubkey();
+    let user_tokens = associated_token_address(&owner, &mint, &token_program);
+    let vault_tokens = vault_tokens(&owner, &mint, &token_program);
+
+    let logs = harness
+        .logs(
+            deposit_tokens(&owner, &mint, &token_program, 500_000),
+            &[&user],
+        )
+        .await;
+    assert_eq!(harness.token_balance(&user_tokens).await, 500_000);
+    assert_eq!(harness.token_balance(&vault_tokens).await, 495_000);
+    let [event] = &events::events::<TokenDepositEvent>(&logs)[..] else {
+        panic!("expected one TokenDepositEvent in {logs:?}");
+    };
+    assert_eq!(event.user, owner);
+    assert_eq!(event.mint, mint);
+    assert_eq!(event.amount, 500_000);
+    assert_eq!(event.received, 495_000);
+
+    let logs = harness
+        .logs(
+            withdraw_tokens(&owner, &mint, &token_program, 100_000),
+            &[&user],
+        )
+        .await;
+    assert_eq!(harness.token_balance(&vault_tokens).await, 395_000);
+    assert_eq!(harness.token_balance(&user_tokens).await, 599_000);
+    let [event] = &events::events::<TokenWithdrawEvent>(&logs)[..] else {
+        panic!("expected one TokenWithdrawEvent in {logs:?}");
+    };
+    assert_eq!(event.user, owner);
+    assert_eq!(event.mint, mint);
+    assert_eq!(event.amount, 100_000);
+    assert_eq!(event.received, 99_000);
+}
+
+#[tokio::test]
+async fn a_token_vault_only_takes_its_own_token_program() {
+    let mut harness = Harness::new().await;
+    let token_program = spl_token_2022::ID;
+    let (user, mint) = token_holder(&mut harness, &token_program, Some(100), 1_000_000).await;
+    let owner = user.pubkey();
+
+    let mut ix = deposit_tokens(&owner, &mint, &token_program, 1_000);
+    // vault, vault tokens, user tokens, mint, user, token program
+    ix.accounts[5] = AccountMeta::new_readonly(spl_token::ID, false);
+    let result = harness.send(ix, &[&user]).await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::WrongTokenProgram));
+    let user_tokens = associated_token_address(&owner, &mint, &token_program);
+    assert_eq!(harness.token_balance(&user_tokens).await, 1_000_000);
+}
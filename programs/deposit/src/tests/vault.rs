@@ -0,0 +1,787 @@
+use super::*;
+
+#[tokio::test]
+async fn initialize_creates_the_vault_for_the_signer() {
+    let mut harness = Harness::new().await;
+    let user = harness.wallet(SOL);
+    harness
+        .send(initialize(&user.pubkey()), &[&user])
+        .await
+        .unwrap();
+
+    let address = vault_address(&user.pubkey());
+    let account = harness
+        .ctx
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, ID);
+    assert_eq!(account.lamports, harness.rent(Vault::SPACE).await);
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.version, Vault::VERSION);
+    assert_eq!(vault.owner, user.pubkey());
+    assert_eq!(vault.creator, user.pubkey());
+    assert_eq!(
+        vault.bump,
+        Pubkey::find_program_address(&[b"vault", user.pubkey().as_ref()], &ID).1
+    );
+    assert_eq!(vault.remaining_deposits().unwrap(), 0);
+
+    let result = harness.send(initialize(&user.pubkey()), &[&user]).await;
+    assert!(result.is_err(), "a second initialize can't reset the vault");
+}
+
+#[tokio::test]
+async fn deposit_moves_exactly_the_amount() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(0).await;
+    let address = vault_address(&user.pubkey());
+    let user_before = harness.lamports(&user.pubkey()).await;
+    let vault_before = harness.lamports(&address).await;
+
+    harness
+        .send(deposit(&user.pubkey(), 123_456_789), &[&user])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        harness.lamports(&user.pubkey()).await,
+        user_before - 123_456_789
+    );
+    assert_eq!(harness.lamports(&address).await, vault_before + 123_456_789);
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.total_deposited, 123_456_789);
+    assert_eq!(vault.deposit_count, 1);
+}
+
+#[tokio::test]
+async fn only_the_owner_can_deposit_through_deposit() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(0).await;
+    let stranger = harness.wallet(SOL);
+
+    let mut ix = deposit(&owner.pubkey(), SOL / 2);
+    ix.accounts[2] = AccountMeta::new(stranger.pubkey(), true);
+    let result = harness.send(ix, &[&stranger]).await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotOwner));
+}
+
+#[tokio::test]
+async fn owner_can_withdraw() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let before = harness.lamports(&user.pubkey()).await;
+
+    harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL / 4),
+            &[&user],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(harness.lamports(&user.pubkey()).await, before + SOL / 4);
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.total_withdrawn, SOL / 4);
+    assert_eq!(vault.withdraw_count, 1);
+}
+
+#[tokio::test]
+async fn stranger_cannot_withdraw() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(SOL).await;
+    let stranger = harness.wallet(SOL);
+    let vault_before = harness.lamports(&vault_address(&owner.pubkey())).await;
+
+    let result = harness
+        .send(
+            withdraw(&owner.pubkey(), &stranger.pubkey(), None, SOL / 4),
+            &[&stranger],
+        )
+        .await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::NotAuthorized));
+    assert_eq!(
+        harness.lamports(&vault_address(&owner.pubkey())).await,
+        vault_before
+    );
+}
+
+#[tokio::test]
+async fn withdrawing_more_than_the_balance_fails() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let balance = harness.lamports(&vault_address(&user.pubkey())).await;
+
+    let result = harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, balance + 1),
+            &[&user],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::InsufficientFunds));
+
+    // within the balance, but into the rent deposit
+    let result = harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL + 1),
+            &[&user],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::ExceedsDeposits));
+}
+
+#[tokio::test]
+async fn round_trip_is_exact_to_the_lamport() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(0).await;
+    let address = vault_address(&user.pubkey());
+    let user_start = harness.lamports(&user.pubkey()).await;
+    let vault_start = harness.lamports(&address).await;
+
+    for amount in [1, 999_999_999, 3 * SOL] {
+        harness
+            .send(deposit(&user.pubkey(), amount), &[&user])
+            .await
+            .unwrap();
+    }
+    let deposited = 1 + 999_999_999 + 3 * SOL;
+    for amount in [deposited / 3, deposited - deposited / 3] {
+        harness
+            .send(
+                withdraw(&user.pubkey(), &user.pubkey(), None, amount),
+                &[&user],
+            )
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(harness.lamports(&user.pubkey()).await, user_start);
+    assert_eq!(harness.lamports(&address).await, vault_start);
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.total_deposited, deposited);
+    assert_eq!(vault.total_withdrawn, deposited);
+    assert_eq!(
+        error_code(
+            harness
+                .send(withdraw(&user.pubkey(), &user.pubkey(), None, 1), &[&user])
+                .await
+        ),
+        u32::from(VaultError::ExceedsDeposits)
+    );
+}
+
+#[tokio::test]
+async fn withdraw_and_close_returns_deposits_and_rent() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(2 * SOL).await;
+    let rent = harness.rent(Vault::SPACE).await;
+    let before = harness.lamports(&user.pubkey()).await;
+
+    harness
+        .send(withdraw_and_close(&user.pubkey()), &[&user])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        harness.lamports(&user.pubkey()).await,
+        before + 2 * SOL + rent
+    );
+    assert!(harness.vault(&user.pubkey()).await.is_none());
+}
+
+#[tokio::test]
+async fn withdraw_and_close_on_an_empty_vault_returns_the_rent() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(0).await;
+    let rent = harness.rent(Vault::SPACE).await;
+    let before = harness.lamports(&user.pubkey()).await;
+
+    harness
+        .send(withdraw_and_close(&user.pubkey()), &[&user])
+        .await
+        .unwrap();
+
+    assert_eq!(harness.lamports(&user.pubkey()).await, before + rent);
+    assert_eq!(harness.lamports(&vault_address(&user.pubkey())).await, 0);
+}
+
+#[tokio::test]
+async fn only_the_owner_can_withdraw_and_close() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let stranger = harness.wallet(SOL);
+    let mut close = withdraw_and_close(&stranger.pubkey());
+    close.accounts[0] = AccountMeta::new(vault_address(&user.pubkey()), false);
+
+    let result = harness.send(close, &[&stranger]).await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::NotOwner));
+    assert!(harness.vault(&user.pubkey()).await.is_some());
+}
+
+#[tokio::test]
+async fn closed_vault_takes_no_deposits_until_reinitialized() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    harness
+        .send(withdraw_and_close(&user.pubkey()), &[&user])
+        .await
+        .unwrap();
+
+    let result = harness.send(deposit(&user.pubkey(), SOL), &[&user]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(anchor_lang::error::ErrorCode::AccountNotInitialized)
+    );
+
+    harness
+        .send(initialize(&user.pubkey()), &[&user])
+        .await
+        .unwrap();
+    harness
+        .send(deposit(&user.pubkey(), SOL), &[&user])
+        .await
+        .unwrap();
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.total_deposited, SOL);
+}
+
+#[tokio::test]
+async fn close_vault_returns_an_empty_vaults_rent() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(0).await;
+    let rent = harness.rent(Vault::SPACE).await;
+    let before = harness.lamports(&user.pubkey()).await;
+
+    harness
+        .send(close_vault(&user.pubkey(), None), &[&user])
+        .await
+        .unwrap();
+
+    assert_eq!(harness.lamports(&user.pubkey()).await, before + rent);
+    assert!(harness.vault(&user.pubkey()).await.is_none());
+}
+
+#[tokio::test]
+async fn close_vault_returns_deposits_and_rent() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(3 * SOL).await;
+    let rent = harness.rent(Vault::SPACE).await;
+    let before = harness.lamports(&user.pubkey()).await;
+
+    harness
+        .send(close_vault(&user.pubkey(), None), &[&user])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        harness.lamports(&user.pubkey()).await,
+        before + 3 * SOL + rent
+    );
+    assert_eq!(harness.lamports(&vault_address(&user.pubkey())).await, 0);
+}
+
+#[tokio::test]
+async fn only_the_owner_can_close_the_vault() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let stranger = harness.wallet(SOL);
+    let vault_before = harness.lamports(&vault_address(&user.pubkey())).await;
+    let mut close = close_vault(&stranger.pubkey(), None);
+    close.accounts[0] = AccountMeta::new(vault_address(&user.pubkey()), false);
+
+    let result = harness.send(close, &[&stranger]).await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::NotOwner));
+    assert_eq!(
+        harness.lamports(&vault_address(&user.pubkey())).await,
+        vault_before
+    );
+}
+
+#[tokio::test]
+async fn withdraw_all_leaves_exactly_the_rent_exempt_minimum() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(2 * SOL + 7).await;
+    let rent = harness.rent(Vault::SPACE).await;
+    let before = harness.lamports(&user.pubkey()).await;
+
+    harness
+        .send(invariants::withdraw_all(&user.pubkey(), None), &[&user])
+        .await
+        .unwrap();
+
+    assert_eq!(harness.lamports(&vault_address(&user.pubkey())).await, rent);
+    assert_eq!(harness.lamports(&user.pubkey()).await, before + 2 * SOL + 7);
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.remaining_deposits().unwrap(), 0);
+}
+
+#[tokio::test]
+async fn withdraw_all_with_nothing_to_withdraw_fails() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(0).await;
+
+    let result = harness
+        .send(invariants::withdraw_all(&user.pubkey(), None), &[&user])
+        .await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::NothingToWithdraw));
+    let rent = harness.rent(Vault::SPACE).await;
+    assert_eq!(harness.lamports(&vault_address(&user.pubkey())).await, rent);
+}
+
+#[tokio::test]
+async fn withdraw_stops_at_the_rent_exempt_minimum() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let rent = harness.rent(Vault::SPACE).await;
+    // half the deposits gone without being booked, as if the rent-exempt
+    // minimum had risen under the vault
+    let address = vault_address(&user.pubkey());
+    let mut account = harness
+        .ctx
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap();
+    account.lamports = rent + SOL / 2;
+    harness.ctx.set_account(&address, &account.into());
+
+    let result = harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL / 2 + 1),
+            &[&user],
+        )
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::WouldBreakRentExemption)
+    );
+
+    harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL / 2),
+            &[&user],
+        )
+        .await
+        .unwrap();
+    assert_eq!(harness.lamports(&address).await, rent);
+}
+
+#[tokio::test]
+async fn lamports_sent_straight_to_the_vault_are_not_deposits() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let donor = harness.wallet(2 * SOL);
+    let address = vault_address(&user.pubkey());
+    let transfer = solana_sdk::system_instruction::transfer(&donor.pubkey(), &address, SOL);
+    harness.send(transfer, &[&donor]).await.unwrap();
+
+    let result = harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL + 1),
+            &[&user],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::ExceedsDeposits));
+
+    harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL / 4),
+            &[&user],
+        )
+        .await
+        .unwrap();
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.total_deposited, SOL);
+    assert_eq!(vault.total_withdrawn, SOL / 4);
+    assert_eq!(vault.remaining_deposits().unwrap(), 3 * SOL / 4);
+}
+
+#[tokio::test]
+async fn instructions_derive_the_vault_from_its_stored_bump() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    let address = vault_address(&user.pubkey());
+    let mut account = harness
+        .ctx
+        .banks_client
+        .get_account(address)
+        .await
+        .unwrap()
+        .unwrap();
+    // discriminator + version + owner + totals
+    let bump = 8 + 1 + 32 + 8 + 8;
+    assert_eq!(
+        account.data[bump],
+        Pubkey::find_program_address(&[b"vault", user.pubkey().as_ref()], &ID).1
+    );
+    account.data[bump] = account.data[bump].wrapping_sub(1);
+    harness.ctx.set_account(&address, &account.into());
+
+    let result = harness.send(deposit(&user.pubkey(), SOL), &[&user]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(anchor_lang::error::ErrorCode::ConstraintSeeds)
+    );
+    let result = harness
+        .send(
+            withdraw(&user.pubkey(), &user.pubkey(), None, SOL),
+            &[&user],
+        )
+        .await;
+    assert_eq!(
+        error_code(result),
+        u32::from(anchor_lang::error::ErrorCode::ConstraintSeeds)
+    );
+}
+
+#[tokio::test]
+async fn zero_amounts_are_rejected() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+
+    let result = harness.send(deposit(&user.pubkey(), 0), &[&user]).await;
+    assert_eq!(error_code(result), u32::from(VaultError::ZeroAmount));
+    let result = harness
+        .send(withdraw(&user.pubkey(), &user.pubkey(), None, 0), &[&user])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::ZeroAmount));
+
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.deposit_count, 1);
+    assert_eq!(vault.withdraw_count, 0);
+}
+
+#[tokio::test]
+async fn withdrawal_overflowing_the_recipient_fails() {
+    let mut harness = Harness::new().await;
+    let user = harness.funded_vault(SOL).await;
+    harness.ctx.set_account(
+        &user.pubkey(),
+        &AccountSharedData::new(u64::MAX - 1, 0, &system_program::ID),
+    );
+
+    let result = harness
+        .send(withdraw(&user.pubkey(), &user.pubkey(), None, 2), &[&user])
+        .await;
+
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::ArithmeticOverflow)
+    );
+    assert_eq!(harness.lamports(&user.pubkey()).await, u64::MAX - 1);
+}
+
+fn withdraw_to(owner: &Pubkey, recipient: &Pubkey, amount: u64) -> Instruction {
+    instruction(
+        crate::accounts::WithdrawTo {
+            vault: vault_address(owner),
+            user: *owner,
+            recipient: *recipient,
+            config: config_address(),
+            treasury: None,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::WithdrawTo { amount },
+    )
+}
+
+#[tokio::test]
+async fn withdraw_to_pays_a_third_party() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(SOL).await;
+    let recipient = harness.wallet(SOL).pubkey();
+    let owner_before = harness.lamports(&owner.pubkey()).await;
+
+    let logs = harness
+        .logs(withdraw_to(&owner.pubkey(), &recipient, SOL / 4), &[&owner])
+        .await;
+
+    assert_eq!(harness.lamports(&recipient).await, SOL + SOL / 4);
+    assert_eq!(harness.lamports(&owner.pubkey()).await, owner_before);
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(vault.total_withdrawn, SOL / 4);
+    let [event] = &events::events::<WithdrawEvent>(&logs)[..] else {
+        panic!("expected one WithdrawEvent in {logs:?}");
+    };
+    assert_eq!(event.user, owner.pubkey());
+    assert_eq!(event.recipient, recipient);
+}
+
+#[tokio::test]
+async fn withdraw_to_cannot_pay_the_vault_itself() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(SOL).await;
+    let address = vault_address(&owner.pubkey());
+
+    let result = harness
+        .send(withdraw_to(&owner.pubkey(), &address, SOL / 4), &[&owner])
+        .await;
+
+    // the vault isn't a system account, which Anchor checks before the
+    // RecipientIsVault constraint
+    assert_eq!(
+        error_code(result),
+        u32::from(anchor_lang::error::ErrorCode::AccountNotSystemOwned)
+    );
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(vault.total_withdrawn, 0);
+}
+
+pub(super) fn deposit_for(payer: &Pubkey, beneficiary: &Pubkey, amount: u64) -> Instruction {
+    instruction(
+        crate::accounts::DepositFor {
+            vault: vault_address(beneficiary),
+            config: config_address(),
+            beneficiary: *beneficiary,
+            payer: *payer,
+            system_program: system_program::ID,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::DepositFor { amount },
+    )
+}
+
+#[tokio::test]
+async fn deposit_for_credits_the_beneficiarys_vault() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(0).await;
+    let sponsor = harness.funded_vault(0).await;
+    let sponsor_before = harness.lamports(&sponsor.pubkey()).await;
+
+    let logs = harness
+        .logs(
+            deposit_for(&sponsor.pubkey(), &owner.pubkey(), SOL),
+            &[&sponsor],
+        )
+        .await;
+
+    assert_eq!(
+        harness.lamports(&sponsor.pubkey()).await,
+        sponsor_before - SOL
+    );
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(vault.total_deposited, SOL);
+    assert_eq!(vault.deposit_count, 1);
+    let sponsor_vault = harness.vault(&sponsor.pubkey()).await.unwrap();
+    assert_eq!(sponsor_vault.total_deposited, 0);
+    let [event] = &events::events::<DepositForEvent>(&logs)[..] else {
+        panic!("expected one DepositForEvent in {logs:?}");
+    };
+    assert_eq!(event.payer, sponsor.pubkey());
+    assert_eq!(event.beneficiary, owner.pubkey());
+    assert_eq!(event.vault, vault_address(&owner.pubkey()));
+
+    harness
+        .send(
+            withdraw(&owner.pubkey(), &owner.pubkey(), None, SOL),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn deposit_for_a_missing_vault_fails() {
+    let mut harness = Harness::new().await;
+    let sponsor = harness.wallet(2 * SOL);
+    let nobody = harness.wallet(0).pubkey();
+
+    let result = harness
+        .send(deposit_for(&sponsor.pubkey(), &nobody, SOL), &[&sponsor])
+        .await;
+
+    assert_eq!(
+        error_code(result),
+        u32::from(anchor_lang::error::ErrorCode::AccountNotInitialized)
+    );
+    assert_eq!(harness.lamports(&sponsor.pubkey()).await, 2 * SOL);
+}
+
+fn initialize_with_deposit(user: &Pubkey, amount: u64) -> Instruction {
+    instruction(
+        crate::accounts::InitializeWithDeposit {
+            vault: vault_address(user),
+            config: config_address(),
+            user: *user,
+            system_program: system_program::ID,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::InitializeWithDeposit {
+            amount,
+            delayed: false,
+        },
+    )
+}
+
+#[tokio::test]
+async fn initialize_with_deposit_creates_a_funded_vault() {
+    let mut harness = Harness::new().await;
+    let user = harness.wallet(3 * SOL);
+    let rent = harness.rent(Vault::SPACE).await;
+
+    let logs = harness
+        .logs(initialize_with_deposit(&user.pubkey(), SOL), &[&user])
+        .await;
+
+    assert_eq!(
+        harness.lamports(&vault_address(&user.pubkey())).await,
+        rent + SOL
+    );
+    assert_eq!(harness.lamports(&user.pubkey()).await, 2 * SOL - rent);
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.owner, user.pubkey());
+    assert_eq!(vault.total_deposited, SOL);
+    assert_eq!(events::events::<InitializeEvent>(&logs).len(), 1);
+    let [event] = &events::events::<DepositEvent>(&logs)[..] else {
+        panic!("expected one DepositEvent in {logs:?}");
+    };
+    assert_eq!(event.new_balance, rent + SOL);
+}
+
+#[tokio::test]
+async fn failed_initialize_with_deposit_leaves_no_vault() {
+    let mut harness = Harness::new().await;
+    let user = harness.wallet(3 * SOL);
+
+    let result = harness
+        .send(initialize_with_deposit(&user.pubkey(), 0), &[&user])
+        .await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::ZeroAmount));
+    assert!(harness.vault(&user.pubkey()).await.is_none());
+    assert_eq!(harness.lamports(&user.pubkey()).await, 3 * SOL);
+
+    harness
+        .send(initialize_with_deposit(&user.pubkey(), SOL), &[&user])
+        .await
+        .unwrap();
+    let result = harness
+        .send(initialize_with_deposit(&user.pubkey(), SOL), &[&user])
+        .await;
+    assert!(result.is_err(), "a second call can't reset the vault");
+}
+
+fn deposit_init_if_needed(user: &Pubkey, amount: u64) -> Instruction {
+    instruction(
+        crate::accounts::DepositInitIfNeeded {
+            vault: vault_address(user),
+            config: config_address(),
+            user: *user,
+            system_program: system_program::ID,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::DepositInitIfNeeded { amount },
+    )
+}
+
+#[tokio::test]
+async fn first_deposit_creates_the_vault_and_later_ones_reuse_it() {
+    let mut harness = Harness::new().await;
+    let user = harness.wallet(3 * SOL);
+    let rent = harness.rent(Vault::SPACE).await;
+
+    harness
+        .send(deposit_init_if_needed(&user.pubkey(), SOL), &[&user])
+        .await
+        .unwrap();
+    assert_eq!(harness.lamports(&user.pubkey()).await, 2 * SOL - rent);
+    let created = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(created.owner, user.pubkey());
+    assert_eq!(created.total_deposited, SOL);
+
+    harness
+        .send(deposit_init_if_needed(&user.pubkey(), SOL), &[&user])
+        .await
+        .unwrap();
+    assert_eq!(harness.lamports(&user.pubkey()).await, SOL - rent);
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.total_deposited, 2 * SOL);
+    assert_eq!(vault.deposit_count, 2);
+    assert_eq!(vault.bump, created.bump);
+}
+
+#[tokio::test]
+async fn deposit_init_if_needed_refuses_a_transferred_vaults_creator() {
+    let mut harness = Harness::new().await;
+    let creator = harness.funded_vault(SOL).await;
+    let successor = harness.wallet(SOL);
+    harness
+        .send(
+            propose_owner(&creator.pubkey(), &successor.pubkey()),
+            &[&creator],
+        )
+        .await
+        .unwrap();
+    harness
+        .send(
+            accept_ownership(&creator.pubkey(), &successor.pubkey()),
+            &[&successor],
+        )
+        .await
+        .unwrap();
+
+    // the creator's signature still derives the vault, but the vault is no
+    // longer theirs to pay into
+    let result = harness
+        .send(deposit_init_if_needed(&creator.pubkey(), SOL), &[&creator])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotOwner));
+    let vault = harness.vault(&creator.pubkey()).await.unwrap();
+    assert_eq!(vault.owner, successor.pubkey());
+    assert_eq!(vault.total_deposited, SOL);
+
+    let deposit = instruction(
+        crate::accounts::Deposit {
+            vault: vault_address(&creator.pubkey()),
+            config: config_address(),
+            user: successor.pubkey(),
+            system_program: system_program::ID,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::Deposit { amount: SOL / 2 },
+    );
+    harness.send(deposit, &[&successor]).await.unwrap();
+    let vault = harness.vault(&creator.pubkey()).await.unwrap();
+    assert_eq!(vault.total_deposited, SOL + SOL / 2);
+}
+
+#[tokio::test]
+async fn deposit_init_if_needed_cannot_reach_another_wallets_vault() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(SOL).await;
+    let stranger = harness.wallet(2 * SOL);
+
+    let mut ix = deposit_init_if_needed(&stranger.pubkey(), SOL);
+    ix.accounts[0] = AccountMeta::new(vault_address(&owner.pubkey()), false);
+    let result = harness.send(ix, &[&stranger]).await;
+
+    assert_eq!(
+        error_code(result),
+        u32::from(anchor_lang::error::ErrorCode::ConstraintSeeds)
+    );
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(vault.owner, owner.pubkey());
+    assert_eq!(vault.total_deposited, SOL);
+}
@@ -0,0 +1,142 @@
+use super::*;
+
+const START: i64 = 1_700_000_000;
+const DURATION_SECS: u64 = 1_000;
+
+fn deposit_vested(user: &Pubkey, amount: u64, start_ts: i64, duration_secs: u64) -> Instruction {
+    instruction(
+        crate::accounts::Deposit {
+            vault: vault_address(user),
+            config: config_address(),
+            user: *user,
+            system_program: system_program::ID,
+            #[cfg(feature = "cpi-events")]
+            event_authority: event_authority(),
+            #[cfg(feature = "cpi-events")]
+            program: ID,
+        },
+        crate::instruction::DepositVested {
+            amount,
+            start_ts,
+            duration_secs,
+        },
+    )
+}
+
+// A vault holding 1 SOL of liquid deposits and 4 SOL vesting over
+// DURATION_SECS from START.
+async fn vesting_vault(harness: &mut Harness) -> Keypair {
+    let user = harness.funded_vault(SOL).await;
+    harness.warp_to(START).await;
+    harness
+        .send(
+            deposit_vested(&user.pubkey(), 4 * SOL, START, DURATION_SECS),
+            &[&user],
+        )
+        .await
+        .unwrap();
+    user
+}
+
+async fn withdraw_at(harness: &mut Harness, user: &Keypair, at: i64, amount: u64) -> SendResult {
+    harness.warp_to(at).await;
+    let ix = withdraw(&user.pubkey(), &user.pubkey(), None, amount);
+    harness.send(ix, &[user]).await
+}
+
+#[tokio::test]
+async fn vested_deposits_release_linearly() {
+    let mut harness = Harness::new().await;
+    let user = vesting_vault(&mut harness).await;
+
+    // 0%: only the liquid deposit
+    let result = withdraw_at(&mut harness, &user, START, SOL + 1).await;
+    assert_eq!(error_code(result), u32::from(VaultError::StillVesting));
+    withdraw_at(&mut harness, &user, START, SOL).await.unwrap();
+
+    // 50%: half the schedule
+    let half = START + DURATION_SECS as i64 / 2;
+    let result = withdraw_at(&mut harness, &user, half, 2 * SOL + 1).await;
+    assert_eq!(error_code(result), u32::from(VaultError::StillVesting));
+    withdraw_at(&mut harness, &user, half, 2 * SOL)
+        .await
+        .unwrap();
+
+    // a second before the end the last lamports are still held back
+    let end = START + DURATION_SECS as i64;
+    let result = withdraw_at(&mut harness, &user, end - 1, 2 * SOL).await;
+    assert_eq!(error_code(result), u32::from(VaultError::StillVesting));
+
+    // 100%: everything
+    withdraw_at(&mut harness, &user, end, 2 * SOL)
+        .await
+        .unwrap();
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.unvested(end), 0);
+    assert_eq!(vault.total_withdrawn, 5 * SOL);
+}
+
+#[tokio::test]
+async fn withdrawals_draw_on_liquid_and_vested_deposits_together() {
+    let mut harness = Harness::new().await;
+    let user = vesting_vault(&mut harness).await;
+    let half = START + DURATION_SECS as i64 / 2;
+
+    let result = withdraw_at(&mut harness, &user, half, 3 * SOL + 1).await;
+    assert_eq!(error_code(result), u32::from(VaultError::StillVesting));
+    withdraw_at(&mut harness, &user, half, 3 * SOL)
+        .await
+        .unwrap();
+
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.remaining_deposits().unwrap(), 2 * SOL);
+    assert_eq!(vault.unvested(half), 2 * SOL);
+}
+
+#[tokio::test]
+async fn a_vesting_vault_closes_only_after_the_schedule_ends() {
+    let mut harness = Harness::new().await;
+    let user = vesting_vault(&mut harness).await;
+    let end = START + DURATION_SECS as i64;
+
+    harness.warp_to(end - 1).await;
+    let result = harness
+        .send(close_vault(&user.pubkey(), None), &[&user])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::StillVesting));
+
+    harness.warp_to(end + 1).await;
+    harness
+        .send(close_vault(&user.pubkey(), None), &[&user])
+        .await
+        .unwrap();
+    assert!(harness.vault(&user.pubkey()).await.is_none());
+}
+
+#[tokio::test]
+async fn a_new_schedule_waits_for_the_current_one() {
+    let mut harness = Harness::new().await;
+    let user = vesting_vault(&mut harness).await;
+    let end = START + DURATION_SECS as i64;
+
+    harness.warp_to(end - 1).await;
+    let ix = deposit_vested(&user.pubkey(), SOL, end, DURATION_SECS);
+    let result = harness.send(ix, &[&user]).await;
+    assert_eq!(error_code(result), u32::from(VaultError::VestingInProgress));
+
+    harness.warp_to(end + 1).await;
+    let ix = deposit_vested(&user.pubkey(), SOL, end + 1, 0);
+    let result = harness.send(ix, &[&user]).await;
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::InvalidVestingSchedule)
+    );
+    let ix = deposit_vested(&user.pubkey(), SOL, end + 1, DURATION_SECS);
+    harness.send(ix, &[&user]).await.unwrap();
+
+    let vault = harness.vault(&user.pubkey()).await.unwrap();
+    assert_eq!(vault.vesting_amount, SOL);
+    assert_eq!(vault.vesting_start, end + 1);
+    // the earlier schedule has fully vested, so only the new one holds back
+    assert_eq!(vault.available_deposits(end + 1).unwrap(), 5 * SOL);
+}
@@ -0,0 +1,139 @@
+use super::*;
+
+fn add_withdrawer(owner: &Pubkey, withdrawer: &Pubkey) -> Instruction {
+    instruction(
+        crate::accounts::UpdateWithdrawers {
+            vault: vault_address(owner),
+            owner: *owner,
+        },
+        crate::instruction::AddWithdrawer {
+            withdrawer: *withdrawer,
+        },
+    )
+}
+
+fn remove_withdrawer(owner: &Pubkey, withdrawer: &Pubkey) -> Instruction {
+    instruction(
+        crate::accounts::UpdateWithdrawers {
+            vault: vault_address(owner),
+            owner: *owner,
+        },
+        crate::instruction::RemoveWithdrawer {
+            withdrawer: *withdrawer,
+        },
+    )
+}
+
+#[tokio::test]
+async fn listed_withdrawer_can_withdraw_until_removed() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(2 * SOL).await;
+    let delegate = harness.wallet(SOL);
+    harness
+        .send(
+            add_withdrawer(&owner.pubkey(), &delegate.pubkey()),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let logs = harness
+        .logs(
+            withdraw(&owner.pubkey(), &delegate.pubkey(), None, SOL / 2),
+            &[&delegate],
+        )
+        .await;
+    assert_eq!(harness.lamports(&delegate.pubkey()).await, SOL + SOL / 2);
+    let [event] = &events::events::<WithdrawEvent>(&logs)[..] else {
+        panic!("expected one WithdrawEvent in {logs:?}");
+    };
+    assert_eq!(event.user, delegate.pubkey());
+
+    harness
+        .send(
+            remove_withdrawer(&owner.pubkey(), &delegate.pubkey()),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+    let result = harness
+        .send(
+            withdraw(&owner.pubkey(), &delegate.pubkey(), None, SOL / 2),
+            &[&delegate],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotAuthorized));
+}
+
+#[tokio::test]
+async fn withdrawers_cannot_manage_the_list() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(SOL).await;
+    let delegate = harness.wallet(SOL);
+    let friend = harness.wallet(SOL).pubkey();
+    harness
+        .send(
+            add_withdrawer(&owner.pubkey(), &delegate.pubkey()),
+            &[&owner],
+        )
+        .await
+        .unwrap();
+
+    let mut ix = add_withdrawer(&delegate.pubkey(), &friend);
+    ix.accounts[0] = AccountMeta::new(vault_address(&owner.pubkey()), false);
+    let result = harness.send(ix, &[&delegate]).await;
+
+    assert_eq!(error_code(result), u32::from(VaultError::NotOwner));
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(vault.withdrawers, vec![delegate.pubkey()]);
+}
+
+#[tokio::test]
+async fn the_list_holds_at_most_max_withdrawers() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(0).await;
+    for _ in 0..Vault::MAX_WITHDRAWERS {
+        let withdrawer = Pubkey::new_unique();
+        harness
+            .send(add_withdrawer(&owner.pubkey(), &withdrawer), &[&owner])
+            .await
+            .unwrap();
+    }
+
+    let result = harness
+        .send(
+            add_withdrawer(&owner.pubkey(), &Pubkey::new_unique()),
+            &[&owner],
+        )
+        .await;
+
+    assert_eq!(
+        error_code(result),
+        u32::from(VaultError::TooManyWithdrawers)
+    );
+    let vault = harness.vault(&owner.pubkey()).await.unwrap();
+    assert_eq!(vault.withdrawers.len(), Vault::MAX_WITHDRAWERS);
+}
+
+#[tokio::test]
+async fn duplicate_and_unknown_withdrawers_are_rejected() {
+    let mut harness = Harness::new().await;
+    let owner = harness.funded_vault(0).await;
+    let delegate = Pubkey::new_unique();
+    harness
+        .send(add_withdrawer(&owner.pubkey(), &delegate), &[&owner])
+        .await
+        .unwrap();
+
+    let result = harness
+        .send(add_withdrawer(&owner.pubkey(), &delegate), &[&owner])
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::AlreadyWithdrawer));
+    let result = harness
+        .send(
+            remove_withdrawer(&owner.pubkey(), &Pubkey::new_unique()),
+            &[&owner],
+        )
+        .await;
+    assert_eq!(error_code(result), u32::from(VaultError::NotWithdrawer));
+}
@@ -0,0 +1,277 @@
+// Names for pubkeys shared by every tool, read from
+// ~/.config/solana-tools/addressbook.yaml unless `--address-book` names
+// another file. Configs can write `@name` wherever they take a pubkey, and
+// output shows the name next to pubkeys in the book.
+
+use std::{
+    collections::BTreeMap,
+    env, fmt,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, MapAccess, Visitor},
+};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    config::load_config,
+    error::{Error, Result},
+};
+
+static BOOK: OnceLock<AddressBook> = OnceLock::new();
+static EMPTY: AddressBook = AddressBook {
+    names: BTreeMap::new(),
+    labels: BTreeMap::new(),
+};
+
+#[derive(Debug, Default)]
+pub struct AddressBook {
+    names: BTreeMap<String, Pubkey>,
+    // one name per pubkey; see `from_names`
+    labels: BTreeMap<Pubkey, String>,
+}
+
+impl AddressBook {
+    // When several names share a pubkey, output uses the alphabetically first
+    // one; `@` references work with all of them.
+    pub fn from_names(names: BTreeMap<String, String>) -> Result<Self> {
+        let mut book = Self::default();
+        for (name, value) in names {
+            if name.is_empty() || name.starts_with('@') || name.contains(char::is_whitespace) {
+                return Err(Error::AddressBook(format!("invalid name `{name}`")));
+            }
+            let pubkey: Pubkey = value
+                .trim()
+                .parse()
+                .map_err(|_| Error::AddressBook(format!("`{name}` is not a pubkey: {value}")))?;
+            book.labels.entry(pubkey).or_insert_with(|| name.clone());
+            book.names.insert(name, pubkey);
+        }
+        Ok(book)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let Names(names) = load_config(path)?;
+        Self::from_names(names).map_err(|err| match err {
+            Error::AddressBook(reason) => {
+                Error::AddressBook(format!("{}: {reason}", path.display()))
+            }
+            err => err,
+        })
+    }
+
+    // A base58 pubkey, or `@name` for an entry of the book.
+    pub fn resolve(&self, value: &str) -> Result<Pubkey> {
+        let value = value.trim();
+        match value.strip_prefix('@') {
+            Some(name) => self
+                .names
+                .get(name)
+                .copied()
+                .ok_or_else(|| Error::UnknownAddress(name.to_string())),
+            None => value
+                .parse()
+                .map_err(|_| Error::InvalidAddress(value.to_string())),
+        }
+    }
+
+    pub fn label(&self, pubkey: &Pubkey) -> Option<&str> {
+        self.labels.get(pubkey).map(String::as_str)
+    }
+
+    // `name (pubkey)` for pubkeys in the book, the bare pubkey otherwise.
+    pub fn display(&self, pubkey: &Pubkey) -> String {
+        match self.label(pubkey) {
+            Some(name) => format!("{name} ({pubkey})"),
+            None => pubkey.to_string(),
+        }
+    }
+}
+
+// A book file's entries. A name listed twice is an error rather than the
+// last entry silently winning.
+struct Names(BTreeMap<String, String>);
+
+impl<'de> Deserialize<'de> for Names {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NamesVisitor;
+
+        impl<'de> Visitor<'de> for NamesVisitor {
+            type Value = Names;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of names to pubkeys")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Names, A::Error> {
+                let mut names = BTreeMap::new();
+                while let Some((name, value)) = map.next_entry::<String, String>()? {
+                    if names.contains_key(&name) {
+                        return Err(de::Error::custom(format!("`{name}` is listed twice")));
+                    }
+                    names.insert(name, value);
+                }
+                Ok(Names(names))
+            }
+        }
+
+        deserializer.deserialize_map(NamesVisitor)
+    }
+}
+
+// $XDG_CONFIG_HOME/solana-tools/addressbook.yaml, falling back to ~/.config.
+pub fn default_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("solana-tools").join("addressbook.yaml"))
+}
+
+// Makes the book at `path`, or at `default_path` when there's none, the one
+// `get` returns. A missing default book is an empty one; a missing `path` is
+// an error. Only the first call has an effect.
+pub fn init(path: Option<&Path>) -> Result<()> {
+    if BOOK.get().is_some() {
+        return Ok(());
+    }
+    let book = match (path, default_path()) {
+        (Some(path), _) => AddressBook::load(path)?,
+        (None, Some(path)) if path.exists() => AddressBook::load(path)?,
+        (None, _) => AddressBook::default(),
+    };
+    if !book.names.is_empty() {
+        tracing::debug!(names = book.names.len(), "loaded address book");
+    }
+    let _ = BOOK.set(book);
+    Ok(())
+}
+
+// Empty until `init`.
+pub fn get() -> &'static AddressBook {
+    BOOK.get().unwrap_or(&EMPTY)
+}
+
+pub fn resolve(value: &str) -> Result<Pubkey> {
+    get().resolve(value)
+}
+
+pub fn label(pubkey: &Pubkey) -> Option<&'static str> {
+    get().label(pubkey)
+}
+
+pub fn display(pubkey: &Pubkey) -> String {
+    get().display(pubkey)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    const ALICE: &str = "D6U1xDjZK15v25mx7z8Nr23EcoZGZvqs3BpWL9E4zyst";
+    const BOB: &str = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin";
+
+    fn book(entries: &[(&str, &str)]) -> Result<AddressBook> {
+        AddressBook::from_names(
+            entries
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+
+    // Loads `contents` as an address book file.
+    fn load(name: &str, contents: &str) -> Result<AddressBook> {
+        let path = env::temp_dir().join(format!("addressbook-test-{}-{name}", std::process::id()));
+        fs::write(&path, contents).unwrap();
+        let book = AddressBook::load(&path);
+        fs::remove_file(&path).unwrap();
+        book
+    }
+
+    #[test]
+    fn resolves_names_and_plain_pubkeys() {
+        let book = book(&[("alice", ALICE)]).unwrap();
+        let alice: Pubkey = ALICE.parse().unwrap();
+        assert_eq!(book.resolve("@alice").unwrap(), alice);
+        assert_eq!(book.resolve(" @alice ").unwrap(), alice);
+        assert_eq!(book.resolve(BOB).unwrap(), BOB.parse().unwrap());
+    }
+
+    #[test]
+    fn unknown_names_are_reported_by_name() {
+        let book = book(&[("alice", ALICE)]).unwrap();
+        match book.resolve("@alcie") {
+            Err(Error::UnknownAddress(name)) => assert_eq!(name, "alcie"),
+            other => panic!("expected an unknown name, got {other:?}"),
+        }
+        assert!(matches!(
+            book.resolve("alice"),
+            Err(Error::InvalidAddress(_))
+        ));
+    }
+
+    #[test]
+    fn labels_pubkeys_in_the_book() {
+        let book = book(&[("alice", ALICE)]).unwrap();
+        let alice: Pubkey = ALICE.parse().unwrap();
+        let bob: Pubkey = BOB.parse().unwrap();
+        assert_eq!(book.label(&alice), Some("alice"));
+        assert_eq!(book.label(&bob), None);
+        assert_eq!(book.display(&alice), format!("alice ({ALICE})"));
+        assert_eq!(book.display(&bob), BOB);
+    }
+
+    #[test]
+    fn shared_pubkeys_label_with_the_first_name() {
+        let book = book(&[("treasury", ALICE), ("alice", ALICE)]).unwrap();
+        let alice: Pubkey = ALICE.parse().unwrap();
+        assert_eq!(book.label(&alice), Some("alice"));
+        assert_eq!(book.resolve("@treasury").unwrap(), alice);
+        assert_eq!(book.resolve("@alice").unwrap(), alice);
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        let yaml = format!("alice: {ALICE}\nbob: {BOB}\nalice: {BOB}\n");
+        let json = format!(r#"{{"alice": "{ALICE}", "alice": "{BOB}"}}"#);
+        for (name, contents) in [("duplicate.yaml", yaml), ("duplicate.json", json)] {
+            match load(name, &contents) {
+                Err(Error::ConfigParse { source, .. }) => {
+                    assert!(
+                        source.to_string().contains("`alice` is listed twice"),
+                        "{source}"
+                    );
+                }
+                other => panic!("expected a parse error, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_entries_name_the_file() {
+        for (name, value) in [("@alice", ALICE), ("two words", ALICE), ("", ALICE)] {
+            assert!(matches!(book(&[(name, value)]), Err(Error::AddressBook(_))));
+        }
+        match load("invalid.yaml", "alice: not-a-pubkey\n") {
+            Err(Error::AddressBook(reason)) => {
+                assert!(reason.contains("invalid.yaml"), "{reason}");
+                assert!(reason.contains("`alice` is not a pubkey"), "{reason}");
+            }
+            other => panic!("expected an address book error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn loads_any_config_format() {
+        let yaml = load("book.yaml", &format!("alice: {ALICE}\n")).unwrap();
+        let json = load("book.json", &format!(r#"{{"alice": "{ALICE}"}}"#)).unwrap();
+        assert_eq!(yaml.names, json.names);
+        assert_eq!(yaml.labels, json.labels);
+    }
+}
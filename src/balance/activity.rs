@@ -0,0 +1,77 @@
+use chrono::DateTime;
+use futures::future::join_all;
+use serde::Serialize;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+
+use super::{fetch::Fetcher, output::Line};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Activity {
+    // `None` when the address has no history
+    pub last_slot: Option<u64>,
+    pub last_time: Option<String>,
+    // signatures seen, capped at the lookup limit
+    pub recent: usize,
+}
+
+// One RPC call per address, so only the `limit` largest balances are checked
+// when a limit is given. Calls go through the fetcher's concurrency limit.
+pub async fn lookup(
+    fetcher: &Fetcher,
+    lines: &mut [Line],
+    signatures: usize,
+    limit: Option<usize>,
+) -> anyhow::Result<()> {
+    let mut indices: Vec<usize> = (0..lines.len()).collect();
+    indices.sort_by(|a, b| lines[*b].info.lamports.cmp(&lines[*a].info.lamports));
+    indices.truncate(limit.unwrap_or(usize::MAX));
+
+    let lookups = indices.iter().map(|i| {
+        let address = lines[*i].address;
+        fetcher.call(move |client| async move {
+            client
+                .get_signatures_for_address_with_config(
+                    &address,
+                    GetConfirmedSignaturesForAddress2Config {
+                        limit: Some(signatures),
+                        ..GetConfirmedSignaturesForAddress2Config::default()
+                    },
+                )
+                .await
+        })
+    });
+    let results = join_all(lookups).await;
+
+    for (i, result) in indices.into_iter().zip(results) {
+        let statuses = result?;
+        let latest = statuses.first();
+        lines[i].activity = Some(Activity {
+            last_slot: latest.map(|status| status.slot),
+            last_time: latest
+                .and_then(|status| status.block_time)
+                .and_then(|time| DateTime::from_timestamp(time, 0))
+                .map(|time| time.to_rfc3339()),
+            recent: statuses.len(),
+        });
+    }
+    Ok(())
+}
+
+pub fn describe(activity: &Activity) -> String {
+    match (activity.last_slot, &activity.last_time) {
+        (None, _) => "never".to_string(),
+        (Some(slot), Some(time)) => format!("{time} (slot {slot})"),
+        (Some(slot), None) => format!("slot {slot}"),
+    }
+}
+
+pub fn print(activity: &Activity) {
+    println!(
+        "    last activity: {}{}",
+        describe(activity),
+        match activity.recent {
+            0 | 1 => String::new(),
+            recent => format!(", {recent} recent signatures"),
+        }
+    );
+}
@@ -0,0 +1,125 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use super::{
+    entry::sol_to_lamports,
+    output::{signed_sol, sol_string},
+};
+
+const DELIVERY_ATTEMPTS: u32 = 3;
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertConfig {
+    pub webhook_url: String,
+    pub min_delta_sol: f64,
+    // at most one alert per address within this window
+    #[serde(default = "AlertConfig::default_cooldown_minutes")]
+    pub cooldown_minutes: u64,
+}
+
+impl AlertConfig {
+    fn default_cooldown_minutes() -> u64 {
+        10
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Payload {
+    ts: String,
+    slot: u64,
+    address: String,
+    label: Option<String>,
+    old_lamports: u64,
+    new_lamports: u64,
+    old_sol: String,
+    new_sol: String,
+    delta_lamports: i128,
+    delta_sol: String,
+}
+
+pub struct Alerter {
+    config: AlertConfig,
+    min_delta_lamports: u64,
+    client: reqwest::Client,
+    last_sent: HashMap<Pubkey, Instant>,
+}
+
+impl Alerter {
+    pub fn new(config: AlertConfig) -> Self {
+        Self {
+            min_delta_lamports: sol_to_lamports(config.min_delta_sol),
+            config,
+            client: reqwest::Client::new(),
+            last_sent: HashMap::new(),
+        }
+    }
+
+    // Delivery runs in the background so a slow webhook never holds up the
+    // watch loop.
+    pub fn check(&mut self, address: &Pubkey, label: Option<&str>, old: u64, new: u64, slot: u64) {
+        if old.abs_diff(new) <= self.min_delta_lamports {
+            return;
+        }
+        let cooldown = Duration::from_secs(self.config.cooldown_minutes * 60);
+        let since_last = self.last_sent.get(address).map(Instant::elapsed);
+        if let Some(elapsed) = since_last.filter(|elapsed| *elapsed < cooldown) {
+            tracing::info!(
+                %address,
+                since_last_secs = elapsed.as_secs(),
+                "alert suppressed by cooldown"
+            );
+            return;
+        }
+        self.last_sent.insert(*address, Instant::now());
+
+        let delta = new as i128 - old as i128;
+        let payload = Payload {
+            ts: Utc::now().to_rfc3339(),
+            slot,
+            address: address.to_string(),
+            label: label.map(str::to_string),
+            old_lamports: old,
+            new_lamports: new,
+            old_sol: sol_string(old),
+            new_sol: sol_string(new),
+            delta_lamports: delta,
+            delta_sol: signed_sol(delta),
+        };
+        tokio::spawn(deliver(
+            self.client.clone(),
+            self.config.webhook_url.clone(),
+            payload,
+        ));
+    }
+}
+
+async fn deliver(client: reqwest::Client, url: String, payload: Payload) {
+    for attempt in 1..=DELIVERY_ATTEMPTS {
+        let result = client
+            .post(&url)
+            .timeout(DELIVERY_TIMEOUT)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => return,
+            Err(err) => tracing::warn!(
+                address = %payload.address,
+                attempt,
+                error = %err,
+                "alert delivery failed"
+            ),
+        }
+        if attempt < DELIVERY_ATTEMPTS {
+            tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+        }
+    }
+}
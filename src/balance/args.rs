@@ -0,0 +1,182 @@
+use std::net::SocketAddr;
+
+use anyhow::{Context, bail};
+use solana_sdk::pubkey::Pubkey;
+
+use super::{sample::Sample, summary};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortKey {
+    #[default]
+    Balance,
+    Label,
+    Address,
+}
+
+fn parse_mint(value: &str) -> anyhow::Result<Pubkey> {
+    let mint = value
+        .strip_prefix("mint=")
+        .with_context(|| format!("expected mint=PUBKEY, got `{value}`"))?;
+    mint.parse().context("mint")
+}
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Addresses or keypair files to check; `-` reads more from stdin
+    #[arg(value_name = "ADDRESS")]
+    pub addresses: Vec<String>,
+    #[arg(skip)]
+    pub stdin: bool,
+    #[arg(long, value_enum, default_value_t)]
+    pub format: Format,
+    /// Poll every SECS seconds
+    #[arg(
+        long,
+        value_name = "SECS",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with = "stream"
+    )]
+    pub watch: Option<u64>,
+    /// Print watch or stream changes as JSON lines
+    #[arg(long)]
+    pub watch_json: bool,
+    #[arg(long, short)]
+    pub quiet: bool,
+    #[arg(long, value_enum, default_value_t)]
+    pub sort: SortKey,
+    #[arg(long)]
+    pub desc: bool,
+    #[arg(long)]
+    pub no_color: bool,
+    #[arg(long)]
+    pub labels_only: bool,
+    #[arg(long)]
+    pub only_nonzero: bool,
+    #[arg(long = "min", value_name = "SOL")]
+    pub min_sol: Option<f64>,
+    #[arg(long = "max", value_name = "SOL")]
+    pub max_sol: Option<f64>,
+    #[arg(long)]
+    pub details: bool,
+    #[arg(long)]
+    pub all_tokens: bool,
+    /// Only list each owner's N largest holdings
+    #[arg(long, value_name = "N", requires = "all_tokens")]
+    pub top: Option<usize>,
+    /// List the N largest balances in the summary
+    #[arg(long, value_name = "N", default_value_t = summary::DEFAULT_TOP)]
+    pub summary_top: usize,
+    /// Write the balances to a snapshot file
+    #[arg(long, value_name = "PATH")]
+    pub snapshot: Option<String>,
+    /// Compare against an earlier snapshot
+    #[arg(long, value_name = "PATH")]
+    pub diff: Option<String>,
+    /// Serve balances over HTTP on this address
+    #[arg(long, value_name = "ADDR")]
+    pub serve: Option<SocketAddr>,
+    /// Refresh interval for --serve
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 30,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub interval: u64,
+    #[arg(long)]
+    pub stake: bool,
+    /// Fail on invalid or duplicate addresses instead of skipping them
+    #[arg(long)]
+    pub strict: bool,
+    /// Read every balance at the same slot
+    #[arg(long)]
+    pub consistent: bool,
+    #[arg(long)]
+    pub nonce: bool,
+    /// List the deposit program vaults owned by the addresses
+    #[arg(long, value_name = "PROGRAM_ID")]
+    pub vaults: Option<Pubkey>,
+    /// Follow balances over a websocket subscription
+    #[arg(long)]
+    pub stream: bool,
+    /// Sample balances into a CSV series
+    #[arg(
+        long = "sample",
+        num_args = 0..,
+        value_name = "interval=60 count=60",
+        requires = "out"
+    )]
+    pub sample_settings: Option<Vec<String>>,
+    #[arg(skip)]
+    pub sample: Option<Sample>,
+    #[arg(long, value_name = "PATH", requires = "sample_settings")]
+    pub out: Option<String>,
+    /// Check the addresses' associated token accounts for a mint
+    #[arg(long, value_name = "mint=PUBKEY", value_parser = parse_mint)]
+    pub ata_check: Option<Pubkey>,
+    /// Keypair that would pay for missing token accounts
+    #[arg(long, value_name = "KEYPAIR", requires = "ata_check")]
+    pub ata_sender: Option<String>,
+    /// List every vault of a deposit program
+    #[arg(long, value_name = "PROGRAM_ID")]
+    pub program_scan: Option<Pubkey>,
+    #[arg(long, value_name = "PUBKEY", requires = "program_scan")]
+    pub owner: Option<Pubkey>,
+    /// Show recent transactions
+    #[arg(long)]
+    pub activity: bool,
+    #[arg(long, value_name = "N", requires = "activity")]
+    pub activity_limit: Option<usize>,
+    /// Check that the keys in a send config can cover its transfers
+    #[arg(long, value_name = "PATH")]
+    pub check_send: Option<String>,
+    /// Record balances in a SQLite database
+    #[arg(long, value_name = "PATH")]
+    pub db: Option<String>,
+    /// Report on the last N runs in --db instead of fetching
+    #[arg(long, value_name = "N", requires = "db")]
+    pub db_report: Option<usize>,
+    #[arg(long)]
+    pub vote: bool,
+}
+
+impl Args {
+    // The checks clap can't express, and the values built from raw settings.
+    // `has_config` is whether a config file was given.
+    pub fn finish(&mut self, has_config: bool) -> anyhow::Result<()> {
+        let before = self.addresses.len();
+        self.addresses.retain(|address| address != "-");
+        self.stdin = self.addresses.len() != before;
+
+        if let Some(settings) = &self.sample_settings {
+            let mut sample = Sample::default();
+            for setting in settings {
+                sample.set(setting)?;
+            }
+            self.sample = Some(sample);
+        }
+
+        // scans, send checks and reports need no tracked addresses
+        if !has_config
+            && self.addresses.is_empty()
+            && !self.stdin
+            && self.program_scan.is_none()
+            && self.check_send.is_none()
+            && self.db_report.is_none()
+        {
+            bail!("no config file or addresses given");
+        }
+        if self.watch_json && self.watch.is_none() && !self.stream {
+            bail!("--watch-json requires --watch or --stream");
+        }
+        Ok(())
+    }
+}
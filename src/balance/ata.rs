@@ -0,0 +1,100 @@
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+
+use super::{
+    entry::Tracked,
+    fetch::Fetcher,
+    output::sol_string,
+    tokens::{self, TOKEN_2022_PROGRAM_ID},
+};
+
+// A plain token account; token-2022 ATAs also carry the account type byte and
+// the ImmutableOwner extension header.
+const TOKEN_ACCOUNT_LEN: usize = 165;
+const TOKEN_2022_ATA_LEN: usize = 170;
+
+pub struct AtaReport<'a> {
+    pub mint: Pubkey,
+    pub existing: usize,
+    pub missing: Vec<&'a Tracked>,
+    pub rent_lamports: u64,
+}
+
+// one entry of a send-binary `pairs:` list
+#[derive(Debug, Serialize)]
+struct Pair<'a> {
+    sender_keypair: &'a str,
+    recipient: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<&'a str>,
+    mint: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SendConfig<'a> {
+    pairs: Vec<Pair<'a>>,
+}
+
+pub async fn check<'a>(
+    fetcher: &Fetcher,
+    tracked: &'a [Tracked],
+    mint: Pubkey,
+) -> anyhow::Result<AtaReport<'a>> {
+    let program = tokens::get_mints(fetcher, &[mint]).await?[&mint].program;
+    let atas: Vec<Pubkey> = tracked
+        .iter()
+        .map(|tracked| {
+            get_associated_token_address_with_program_id(&tracked.address, &mint, &program)
+        })
+        .collect();
+    let accounts = fetcher.get_accounts(&atas).await?;
+
+    let missing: Vec<&Tracked> = tracked
+        .iter()
+        .zip(&accounts)
+        .filter(|(_, account)| account.is_none())
+        .map(|(tracked, _)| tracked)
+        .collect();
+    let len = if program == TOKEN_2022_PROGRAM_ID {
+        TOKEN_2022_ATA_LEN
+    } else {
+        TOKEN_ACCOUNT_LEN
+    };
+    Ok(AtaReport {
+        mint,
+        existing: tracked.len() - missing.len(),
+        rent_lamports: fetcher.rent_minimums([len]).await?[&len],
+        missing,
+    })
+}
+
+impl AtaReport<'_> {
+    // logged so the pairs on stdout can be redirected straight into a config
+    pub fn print_summary(&self) {
+        let total = self.rent_lamports * self.missing.len() as u64;
+        tracing::info!(
+            mint = %self.mint,
+            existing = self.existing,
+            missing = self.missing.len(),
+            rent_sol = %sol_string(total),
+            rent_each_sol = %sol_string(self.rent_lamports),
+            "associated token accounts"
+        );
+    }
+
+    pub fn missing_pairs(&self, sender_keypair: &str) -> serde_yaml::Result<String> {
+        serde_yaml::to_string(&SendConfig {
+            pairs: self
+                .missing
+                .iter()
+                .map(|tracked| Pair {
+                    sender_keypair,
+                    recipient: tracked.address.to_string(),
+                    label: tracked.label.as_deref(),
+                    mint: self.mint.to_string(),
+                })
+                .collect(),
+        })
+    }
+}
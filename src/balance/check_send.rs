@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signer::Signer};
+use solana_test::{
+    config::load_config,
+    keypair::load_keypair,
+    send_config::{Config, TransferPairRead},
+};
+
+use super::{fetch::Fetcher, output::sol_string};
+
+// base fee only; priority fees depend on the send run's compute settings
+const FEE_PER_SIGNATURE: u64 = 5_000;
+// nonce::State::size()
+const NONCE_ACCOUNT_LEN: usize = 80;
+
+#[derive(Debug, Default, Serialize)]
+pub struct Need {
+    pub address: String,
+    pub pairs: usize,
+    pub transfer_lamports: u64,
+    pub fee_lamports: u64,
+    pub balance_lamports: u64,
+    // `None` when the balance covers everything
+    pub short_lamports: Option<u64>,
+}
+
+fn signatures(pair: &TransferPairRead) -> u64 {
+    1 + pair.extra_signers.len() as u64 + u64::from(pair.fee_payer.is_some())
+}
+
+// What the sender itself pays out, mirroring how send turns a pair into
+// instructions. Withdrawals and authority changes pay nothing.
+fn outgoing(pair: &TransferPairRead, nonce_rent: u64) -> u64 {
+    if let Some(stake) = &pair.stake {
+        (stake.amount_sol * LAMPORTS_PER_SOL as f64).round() as u64
+    } else if let Some(create) = &pair.create_with_seed {
+        create.lamports
+    } else if let Some(create) = &pair.create_nonce {
+        create.lamports + nonce_rent
+    } else if pair.withdraw_nonce.is_some() || pair.authorize_nonce.is_some() {
+        0
+    } else {
+        pair.lamports.unwrap_or_default()
+    }
+}
+
+// Per fee-paying or sending key, sorted by address.
+pub async fn check(fetcher: &Fetcher, path: &str) -> anyhow::Result<Vec<Need>> {
+    let config: Config = load_config(path)?;
+    let nonce_rent = if config.pairs.iter().any(|pair| pair.create_nonce.is_some()) {
+        fetcher.rent_minimums([NONCE_ACCOUNT_LEN]).await?[&NONCE_ACCOUNT_LEN]
+    } else {
+        0
+    };
+
+    let mut needs: BTreeMap<Pubkey, Need> = BTreeMap::new();
+    for pair in &config.pairs {
+        let sender = load_keypair(&pair.sender_keypair)?.pubkey();
+        let need = needs.entry(sender).or_default();
+        need.pairs += 1;
+        need.transfer_lamports += outgoing(pair, nonce_rent);
+
+        let payer = match &pair.fee_payer {
+            Some(path) => load_keypair(path)?.pubkey(),
+            None => sender,
+        };
+        needs.entry(payer).or_default().fee_lamports += signatures(pair) * FEE_PER_SIGNATURE;
+    }
+
+    let addresses: Vec<Pubkey> = needs.keys().copied().collect();
+    let accounts = fetcher.get_accounts(&addresses).await?;
+    Ok(needs
+        .into_iter()
+        .zip(accounts)
+        .map(|((address, need), account)| {
+            let balance = account.map(|account| account.lamports).unwrap_or_default();
+            let total = need.transfer_lamports + need.fee_lamports;
+            Need {
+                address: address.to_string(),
+                balance_lamports: balance,
+                short_lamports: total.checked_sub(balance).filter(|short| *short > 0),
+                ..need
+            }
+        })
+        .collect())
+}
+
+pub fn print(needs: &[Need]) {
+    println!(
+        "{:<44} {:>5} {:>20} {:>20} {:>20}  verdict",
+        "sender", "pairs", "needs", "fees", "balance"
+    );
+    for need in needs {
+        let verdict = match need.short_lamports {
+            Some(short) => format!("SHORT by {} SOL", sol_string(short)),
+            None => "sufficient".to_string(),
+        };
+        println!(
+            "{:<44} {:>5} {:>20} {:>20} {:>20}  {verdict}",
+            need.address,
+            need.pairs,
+            sol_string(need.transfer_lamports),
+            sol_string(need.fee_lamports),
+            sol_string(need.balance_lamports)
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    time::Duration,
+};
+
+use futures::future::join_all;
+use serde_json::{Map, json};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use solana_test::rpc::{connect, resolve_url};
+
+use super::{
+    args::{Args, Format},
+    entry::Tracked,
+    fetch::{Fetcher, Retry, SlotSpan, Snapshot},
+    output::{self, Failure, Line, View},
+    summary, table,
+};
+
+struct ClusterResult {
+    name: String,
+    endpoint: String,
+    slots: SlotSpan,
+    lines: Vec<Line>,
+    failures: Vec<Failure>,
+}
+
+pub fn lines_for(tracked: &[Tracked], snapshot: &Snapshot) -> (Vec<Line>, Vec<Failure>) {
+    let by_address: HashMap<Pubkey, _> = tracked
+        .iter()
+        .map(|tracked| (tracked.address, tracked))
+        .collect();
+    let lines = snapshot
+        .balances
+        .iter()
+        .map(|(address, info)| Line::new(by_address[address], *info))
+        .collect();
+    let failures = snapshot
+        .errors
+        .iter()
+        .map(|(address, error)| Failure {
+            address: address.to_string(),
+            error: error.clone(),
+        })
+        .collect();
+    (lines, failures)
+}
+
+// Each cluster gets its own fetcher, so concurrency limits and retries are
+// per cluster while the clusters themselves are queried in parallel.
+pub async fn run(
+    clusters: &BTreeMap<String, String>,
+    commitment: CommitmentConfig,
+    timeout: Duration,
+    retry: Retry,
+    max_concurrent_requests: usize,
+    tracked: &[Tracked],
+    args: &Args,
+) -> anyhow::Result<bool> {
+    let addresses: Vec<Pubkey> = tracked.iter().map(|tracked| tracked.address).collect();
+    let fetches = clusters.iter().map(|(name, url)| {
+        let endpoint = resolve_url(url).to_string();
+        let client = connect(&endpoint, commitment, timeout);
+        let fetcher =
+            Fetcher::new(client, retry, max_concurrent_requests).consistent(args.consistent);
+        let addresses = addresses.clone();
+        async move {
+            let snapshot = fetcher.get_snapshot(addresses).await;
+            let (mut lines, failures) = lines_for(tracked, &snapshot);
+            output::sort(&mut lines, args.sort, args.desc);
+            ClusterResult {
+                name: name.clone(),
+                endpoint,
+                slots: snapshot.slots(),
+                lines,
+                failures,
+            }
+        }
+    });
+    let results = join_all(fetches).await;
+
+    let existing: HashSet<Pubkey> = results
+        .iter()
+        .flat_map(|result| &result.lines)
+        .filter(|line| line.info.exists)
+        .map(|line| line.address)
+        .collect();
+    for address in addresses
+        .iter()
+        .filter(|address| !existing.contains(address))
+    {
+        tracing::warn!(%address, "exists on none of the configured clusters");
+    }
+
+    let view = View {
+        labels_only: args.labels_only,
+        details: false,
+        color: table::use_color(args.no_color),
+        price: None,
+    };
+    match args.format {
+        Format::Table => {
+            for result in &results {
+                println!(
+                    "== {} ({}) slot {} ==",
+                    result.name, result.endpoint, result.slots
+                );
+                table::print(&result.lines, &result.failures, view);
+                summary::print(&summary::summarize(
+                    &result.lines,
+                    args.labels_only,
+                    args.top.unwrap_or(summary::DEFAULT_TOP),
+                ));
+                println!();
+            }
+        }
+        Format::Json => {
+            let mut out = Map::new();
+            for result in &results {
+                let summary = summary::summarize(
+                    &result.lines,
+                    args.labels_only,
+                    args.top.unwrap_or(summary::DEFAULT_TOP),
+                );
+                let document = output::to_json(
+                    result.slots,
+                    &result.endpoint,
+                    &result.lines,
+                    &summary,
+                    &result.failures,
+                    view,
+                )?;
+                out.insert(result.name.clone(), serde_json::from_str(&document)?);
+            }
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json!({ "clusters": out }))?
+            );
+        }
+        Format::Csv => {
+            let mut header = true;
+            for result in &results {
+                for (i, row) in output::to_csv(&result.lines, view).lines().enumerate() {
+                    match i {
+                        0 if header => println!("cluster,{row}"),
+                        0 => {}
+                        _ => println!("{},{row}", result.name),
+                    }
+                }
+                header = false;
+                for failure in &result.failures {
+                    tracing::warn!(
+                        cluster = %result.name,
+                        address = %failure.address,
+                        error = %failure.error,
+                        "fetch failed"
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(results.iter().any(|result| !result.failures.is_empty()))
+}
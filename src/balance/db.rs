@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use rusqlite::{Connection, params};
+
+use super::output::{Line, signed_sol, sol_string};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    run_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ts TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    endpoint TEXT NOT NULL,
+    addresses INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS balances (
+    run_id INTEGER NOT NULL REFERENCES runs(run_id),
+    ts TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    address TEXT NOT NULL,
+    label TEXT,
+    lamports INTEGER NOT NULL,
+    PRIMARY KEY (run_id, address)
+);
+";
+
+pub fn open(path: &str) -> anyhow::Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(conn)
+}
+
+// One transaction per run, so a crash never leaves a partial run behind.
+pub fn record(
+    conn: &mut Connection,
+    slot: u64,
+    endpoint: &str,
+    lines: &[Line],
+) -> anyhow::Result<i64> {
+    let ts = Utc::now().to_rfc3339();
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO runs (ts, slot, endpoint, addresses) VALUES (?1, ?2, ?3, ?4)",
+        params![ts, slot as i64, endpoint, lines.len() as i64],
+    )?;
+    let run_id = tx.last_insert_rowid();
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO balances (run_id, ts, slot, address, label, lamports)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for line in lines {
+            insert.execute(params![
+                run_id,
+                ts,
+                slot as i64,
+                line.address.to_string(),
+                line.label,
+                line.info.lamports as i64
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(run_id)
+}
+
+// Deltas between consecutive runs among the last `runs`, read only from the
+// database.
+pub fn report(conn: &Connection, runs: usize) -> anyhow::Result<()> {
+    let mut select =
+        conn.prepare("SELECT run_id, ts, slot FROM runs ORDER BY run_id DESC LIMIT ?1")?;
+    let mut run_rows: Vec<(i64, String, i64)> = select
+        .query_map([runs as i64], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?
+        .collect::<Result<_, _>>()?;
+    run_rows.reverse();
+    let Some(first) = run_rows.first() else {
+        println!("no runs recorded");
+        return Ok(());
+    };
+
+    for (run_id, ts, slot) in &run_rows {
+        println!("run {run_id}: {ts} slot {slot}");
+    }
+    println!();
+
+    // address -> (label, lamports per run)
+    let mut history: BTreeMap<String, (Option<String>, BTreeMap<i64, u64>)> = BTreeMap::new();
+    let mut select =
+        conn.prepare("SELECT run_id, address, label, lamports FROM balances WHERE run_id >= ?1")?;
+    let rows = select.query_map([first.0], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, i64>(3)?,
+        ))
+    })?;
+    for row in rows {
+        let (run_id, address, label, lamports) = row?;
+        let entry = history.entry(address).or_default();
+        if label.is_some() {
+            entry.0 = label;
+        }
+        entry.1.insert(run_id, lamports as u64);
+    }
+
+    let mut header = format!("{:<44} {:>20}", "address", "latest");
+    for (run_id, _, _) in run_rows.iter().skip(1) {
+        header += &format!(" {:>20}", format!("Δ run {run_id}"));
+    }
+    println!("{header}");
+    for (address, (label, balances)) in &history {
+        let latest = balances.values().next_back().copied().unwrap_or_default();
+        let mut row = format!("{address:<44} {:>20}", sol_string(latest));
+        for pair in run_rows.windows(2) {
+            let cell = match (balances.get(&pair[0].0), balances.get(&pair[1].0)) {
+                (Some(old), Some(new)) => signed_sol(*new as i128 - *old as i128),
+                _ => "-".to_string(),
+            };
+            row += &format!(" {cell:>20}");
+        }
+        if let Some(label) = label {
+            row += &format!("  ({label})");
+        }
+        println!("{row}");
+    }
+    Ok(())
+}
@@ -0,0 +1,346 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufRead},
+    path::Path,
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signer::Signer};
+use solana_test::{Error, addressbook, config::load_config, keypair::load_keypair};
+
+use super::output::Failure;
+
+// Plain strings stay valid next to the richer form. A plain string ending in
+// `.json` is read as a keypair file.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum AddressEntry {
+    Plain(String),
+    Detailed {
+        address: String,
+        #[serde(default)]
+        label: Option<String>,
+        #[serde(default)]
+        min_sol: Option<f64>,
+    },
+    Keypair {
+        keypair: String,
+        #[serde(default)]
+        label: Option<String>,
+        #[serde(default)]
+        min_sol: Option<f64>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Tracked {
+    pub address: Pubkey,
+    pub label: Option<String>,
+    pub min_lamports: Option<u64>,
+}
+
+pub fn sol_to_lamports(sol: f64) -> u64 {
+    (sol * LAMPORTS_PER_SOL as f64).round() as u64
+}
+
+impl AddressEntry {
+    pub fn address(&self) -> &str {
+        match self {
+            Self::Plain(address)
+            | Self::Detailed { address, .. }
+            | Self::Keypair {
+                keypair: address, ..
+            } => address,
+        }
+    }
+
+    pub fn resolve(self, default_min_sol: Option<f64>) -> anyhow::Result<Tracked> {
+        let (address, label, min_sol) = match self {
+            Self::Plain(path) if path.trim().ends_with(".json") => {
+                let (address, label) = from_keypair(path.trim(), None)?;
+                (address, label, None)
+            }
+            Self::Plain(address) => (parse_address(&address)?, None, None),
+            Self::Detailed {
+                address,
+                label,
+                min_sol,
+            } => (parse_address(&address)?, label, min_sol),
+            Self::Keypair {
+                keypair,
+                label,
+                min_sol,
+            } => {
+                let (address, label) = from_keypair(&keypair, label)?;
+                (address, label, min_sol)
+            }
+        };
+        Ok(Tracked {
+            address,
+            label,
+            min_lamports: min_sol.or(default_min_sol).map(sol_to_lamports),
+        })
+    }
+}
+
+// Where an entry came from, for error messages.
+#[derive(Debug, Clone, Copy)]
+pub enum Source {
+    // 0-based index into the config's `addresses`
+    Config(usize),
+    // 0-based index among the command-line addresses
+    Arg(usize),
+    // 1-based line number
+    Stdin(usize),
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Config(index) => write!(f, "addresses[{index}]"),
+            Self::Arg(index) => write!(f, "argument {}", index + 1),
+            Self::Stdin(line) => write!(f, "stdin line {line}"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Validated {
+    pub tracked: Vec<Tracked>,
+    pub failures: Vec<Failure>,
+    // the later occurrence of an address is dropped
+    pub duplicates: Vec<(Source, Pubkey)>,
+}
+
+impl Validated {
+    // `--strict` treats duplicates as errors too.
+    pub fn strict(&self) -> Result<(), Error> {
+        if self.failures.is_empty() && self.duplicates.is_empty() {
+            return Ok(());
+        }
+        Err(Error::InvalidAddresses {
+            invalid: self.failures.len(),
+            duplicates: self.duplicates.len(),
+        })
+    }
+}
+
+// Newline-separated addresses, tagged with their line number. Blank lines
+// and `#` comments are skipped.
+pub fn parse_lines(reader: impl BufRead) -> io::Result<Vec<(Source, AddressEntry)>> {
+    let mut entries = vec![];
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            entries.push((Source::Stdin(i + 1), AddressEntry::Plain(line.to_string())));
+        }
+    }
+    Ok(entries)
+}
+
+// Resolves every entry instead of stopping at the first bad one. Earlier
+// entries win on duplicates, so config labels and thresholds beat CLI ones.
+pub fn validate(
+    entries: impl IntoIterator<Item = (Source, AddressEntry)>,
+    default_min_sol: Option<f64>,
+) -> Validated {
+    let mut out = Validated::default();
+    let mut seen = HashSet::new();
+    for (source, entry) in entries {
+        let address = entry.address().to_string();
+        match entry.resolve(default_min_sol) {
+            Ok(tracked) if !seen.insert(tracked.address) => {
+                out.duplicates.push((source, tracked.address))
+            }
+            Ok(tracked) => out.tracked.push(tracked),
+            Err(err) => out.failures.push(Failure {
+                address: format!("{address:?}"),
+                error: format!("{source}: {err:#}"),
+            }),
+        }
+    }
+    out
+}
+
+// a pubkey or an `@name` from the shared address book
+fn parse_address(address: &str) -> anyhow::Result<Pubkey> {
+    Ok(addressbook::resolve(address)?)
+}
+
+// The file stem is the default label.
+fn from_keypair(path: &str, label: Option<String>) -> anyhow::Result<(Pubkey, Option<String>)> {
+    let address = load_keypair(path)?.pubkey();
+    let label = label.or_else(|| {
+        Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+    });
+    Ok((address, label))
+}
+
+// `pubkey: name` pairs; labels set on the address entry itself win.
+pub fn load_address_book(path: &str) -> anyhow::Result<HashMap<Pubkey, String>> {
+    let book: HashMap<String, String> =
+        load_config(path).with_context(|| format!("Can't read address book {path}"))?;
+    book.into_iter()
+        .map(|(address, name)| {
+            let address = address
+                .parse()
+                .with_context(|| format!("address book {path}: invalid pubkey {address}"))?;
+            Ok((address, name))
+        })
+        .collect()
+}
+
+pub fn apply_address_book(tracked: &mut [Tracked], book: &HashMap<Pubkey, String>) {
+    for entry in tracked.iter_mut().filter(|entry| entry.label.is_none()) {
+        entry.label = book.get(&entry.address).cloned();
+    }
+}
+
+// Names from the shared address book, for entries still without a label.
+pub fn apply_shared_labels(tracked: &mut [Tracked]) {
+    for entry in tracked.iter_mut().filter(|entry| entry.label.is_none()) {
+        entry.label = addressbook::label(&entry.address).map(str::to_string);
+    }
+}
+
+pub fn warn_duplicate_labels(tracked: &[Tracked]) {
+    let mut seen = HashSet::new();
+    for entry in tracked {
+        let Some(label) = &entry.label else {
+            continue;
+        };
+        if !seen.insert(label) {
+            tracing::warn!(%label, "label is used by more than one address");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::ExitCode;
+
+    use anyhow::Context;
+    use solana_sdk::system_program;
+    use solana_test::error;
+
+    use super::*;
+
+    // A pasted list: a comment, a blank line, trailing whitespace, a
+    // duplicate, characters outside base58 and a key of the wrong length.
+    const MESSY: &str = "# treasury wallets
+4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi
+
+11111111111111111111111111111111 \t
+not-base58-0OIl
+4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi
+    # moved to cold storage
+abc
+";
+
+    fn messy() -> Validated {
+        validate(parse_lines(MESSY.as_bytes()).unwrap(), None)
+    }
+
+    #[test]
+    fn blank_lines_and_comments_are_skipped() {
+        let lines: Vec<String> = parse_lines(MESSY.as_bytes())
+            .unwrap()
+            .iter()
+            .map(|(source, entry)| format!("{source}: {}", entry.address()))
+            .collect();
+
+        assert_eq!(
+            lines,
+            [
+                "stdin line 2: 4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi",
+                "stdin line 4: 11111111111111111111111111111111",
+                "stdin line 5: not-base58-0OIl",
+                "stdin line 6: 4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi",
+                "stdin line 8: abc",
+            ]
+        );
+    }
+
+    #[test]
+    fn every_bad_line_is_reported_with_its_line_number() {
+        let validated = messy();
+
+        let tracked: Vec<Pubkey> = validated
+            .tracked
+            .iter()
+            .map(|entry| entry.address)
+            .collect();
+        assert_eq!(
+            tracked,
+            [Pubkey::new_from_array([1; 32]), system_program::ID]
+        );
+        let failures: Vec<(&str, &str)> = validated
+            .failures
+            .iter()
+            .map(|failure| (failure.address.as_str(), failure.error.as_str()))
+            .collect();
+        assert_eq!(
+            failures,
+            [
+                (
+                    "\"not-base58-0OIl\"",
+                    "stdin line 5: invalid pubkey `not-base58-0OIl`"
+                ),
+                ("\"abc\"", "stdin line 8: invalid pubkey `abc`"),
+            ]
+        );
+    }
+
+    #[test]
+    fn the_later_duplicate_is_dropped() {
+        let duplicates: Vec<String> = messy()
+            .duplicates
+            .iter()
+            .map(|(source, address)| format!("{source}: {address}"))
+            .collect();
+
+        assert_eq!(
+            duplicates,
+            ["stdin line 6: 4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi"]
+        );
+    }
+
+    #[test]
+    fn config_entries_win_over_stdin_duplicates() {
+        let config = AddressEntry::Detailed {
+            address: " 4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi ".to_string(),
+            label: Some("treasury".to_string()),
+            min_sol: Some(2.0),
+        };
+        let entries = [(Source::Config(0), config)]
+            .into_iter()
+            .chain(parse_lines(MESSY.as_bytes()).unwrap());
+
+        let validated = validate(entries, None);
+
+        assert_eq!(validated.tracked[0].label.as_deref(), Some("treasury"));
+        assert_eq!(
+            validated.tracked[0].min_lamports,
+            Some(2 * LAMPORTS_PER_SOL)
+        );
+        assert_eq!(validated.duplicates.len(), 2);
+    }
+
+    #[test]
+    fn strict_fails_with_the_config_exit_code() {
+        let clean = validate(
+            parse_lines("11111111111111111111111111111111\n".as_bytes()).unwrap(),
+            None,
+        );
+        assert!(clean.strict().is_ok());
+
+        let err = messy().strict().unwrap_err();
+        assert_eq!(err.to_string(), "2 invalid and 1 duplicate address(es)");
+        let result = Err(err).context("--strict");
+        assert_eq!(error::exit(result), ExitCode::from(78));
+    }
+}
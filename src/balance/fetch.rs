@@ -0,0 +1,625 @@
+use std::{
+    collections::{BTreeMap, HashMap, hash_map::Entry},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    client_error::Result as ClientResult, rpc_config::RpcAccountInfoConfig, rpc_response::Response,
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use solana_test::{
+    Error,
+    retry::{RetryPolicy, retry},
+    rpc::SolanaRpc,
+};
+use tokio::{sync::Semaphore, task};
+
+const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+// Two requests in flight keeps the public mainnet endpoint happy for a few
+// hundred addresses.
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 2;
+// the public endpoint occasionally hangs a request for a minute or more
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BalanceInfo {
+    pub lamports: u64,
+    pub exists: bool,
+    // `None` when the account doesn't exist
+    pub owner: Option<Pubkey>,
+    pub executable: bool,
+    pub data_len: usize,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Retry {
+    #[serde(default = "Retry::default_count")]
+    pub count: u32,
+    #[serde(default = "Retry::default_base_delay_ms")]
+    pub base_delay_ms: u64,
+    // give up on a request once retrying it has taken this long
+    #[serde(default)]
+    pub max_elapsed_secs: Option<u64>,
+}
+
+impl Retry {
+    fn default_count() -> u32 {
+        4
+    }
+
+    fn default_base_delay_ms() -> u64 {
+        500
+    }
+
+    // `count` is retries on top of the first attempt
+    pub fn policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.count.saturating_add(1),
+            base_delay: Duration::from_millis(self.base_delay_ms),
+            max_elapsed: self.max_elapsed_secs.map(Duration::from_secs),
+            ..RetryPolicy::default()
+        }
+    }
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            count: Self::default_count(),
+            base_delay_ms: Self::default_base_delay_ms(),
+            max_elapsed_secs: None,
+        }
+    }
+}
+
+// Context slots seen across the chunked requests.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SlotSpan {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl SlotSpan {
+    fn add(span: Option<Self>, slot: u64) -> Option<Self> {
+        Some(match span {
+            Some(span) => Self {
+                min: span.min.min(slot),
+                max: span.max.max(slot),
+            },
+            None => Self {
+                min: slot,
+                max: slot,
+            },
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Snapshot {
+    // lowest context slot among the chunked requests
+    pub slot: u64,
+    pub max_slot: u64,
+    pub balances: Vec<(Pubkey, BalanceInfo)>,
+    pub errors: Vec<(Pubkey, String)>,
+}
+
+impl Snapshot {
+    pub fn slots(&self) -> SlotSpan {
+        SlotSpan {
+            min: self.slot,
+            max: self.max_slot,
+        }
+    }
+}
+
+impl std::fmt::Display for SlotSpan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.min == self.max {
+            write!(f, "{}", self.min)
+        } else {
+            write!(
+                f,
+                "{}..={} (span {})",
+                self.min,
+                self.max,
+                self.max - self.min
+            )
+        }
+    }
+}
+
+pub fn default_max_concurrent_requests() -> usize {
+    DEFAULT_MAX_CONCURRENT_REQUESTS
+}
+
+pub fn default_timeout_secs() -> u64 {
+    DEFAULT_TIMEOUT_SECS
+}
+
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub requests: AtomicU64,
+    pub retries: AtomicU64,
+    pub rpc_micros: AtomicU64,
+    // requests per endpoint url
+    pub served: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Stats {
+    pub fn log(&self) {
+        tracing::info!(
+            requests = self.requests.load(Ordering::Relaxed),
+            retries = self.retries.load(Ordering::Relaxed),
+            secs = format_args!(
+                "{:.2}",
+                self.rpc_micros.load(Ordering::Relaxed) as f64 / 1e6
+            ),
+            "RPC totals"
+        );
+        let served = self.served.lock().expect("stats lock poisoned");
+        if served.len() > 1 {
+            for (url, requests) in served.iter() {
+                tracing::info!(endpoint = %url, requests, "RPC requests per endpoint");
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Fetcher {
+    // the primary endpoint first, then its fallbacks
+    clients: Arc<Vec<Arc<dyn SolanaRpc>>>,
+    active: Arc<AtomicUsize>,
+    pub retry: Retry,
+    permits: Arc<Semaphore>,
+    pub stats: Arc<Stats>,
+    consistent: bool,
+}
+
+impl Fetcher {
+    pub fn new(client: Arc<dyn SolanaRpc>, retry: Retry, max_concurrent_requests: usize) -> Self {
+        Self {
+            clients: Arc::new(vec![client]),
+            active: Arc::default(),
+            retry,
+            permits: Arc::new(Semaphore::new(max_concurrent_requests.max(1))),
+            stats: Arc::default(),
+            consistent: false,
+        }
+    }
+
+    // See `get_accounts_with_slot`.
+    pub fn consistent(self, consistent: bool) -> Self {
+        Self { consistent, ..self }
+    }
+
+    // Tried in order once the current endpoint exhausts its retries.
+    pub fn fallbacks(self, fallbacks: impl IntoIterator<Item = Arc<dyn SolanaRpc>>) -> Self {
+        let mut clients = self.clients.as_ref().clone();
+        clients.extend(fallbacks);
+        Self {
+            clients: Arc::new(clients),
+            ..self
+        }
+    }
+
+    // the endpoint currently serving requests
+    pub fn client(&self) -> Arc<dyn SolanaRpc> {
+        Arc::clone(&self.clients[self.active.load(Ordering::Relaxed)])
+    }
+
+    pub fn primary_url(&self) -> String {
+        self.clients[0].url()
+    }
+
+    pub fn failed_over(&self) -> bool {
+        self.active.load(Ordering::Relaxed) > 0
+    }
+
+    // Later requests, including those of concurrent callers, go to the next
+    // endpoint. Returns false when there is none left.
+    fn fail_over(&self, from: usize) -> bool {
+        let to = from + 1;
+        if to >= self.clients.len() {
+            return false;
+        }
+        if self
+            .active
+            .compare_exchange(from, to, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            tracing::warn!(
+                from = %self.clients[from].url(),
+                to = %self.clients[to].url(),
+                "endpoint keeps failing, switching"
+            );
+        }
+        true
+    }
+
+    // One attempt under the concurrency limit, counted in the stats.
+    async fn timed<T>(
+        &self,
+        client: &dyn SolanaRpc,
+        request: impl Future<Output = ClientResult<T>>,
+    ) -> Result<T, Error> {
+        let _permit = self.permits.acquire().await.expect("semaphore closed");
+        let start = Instant::now();
+        let result = request.await.map_err(Error::from);
+        self.stats.requests.fetch_add(1, Ordering::Relaxed);
+        self.stats
+            .rpc_micros
+            .fetch_add(start.elapsed().as_micros() as u64, Ordering::Relaxed);
+        *self
+            .stats
+            .served
+            .lock()
+            .expect("stats lock poisoned")
+            .entry(client.url())
+            .or_default() += 1;
+        result
+    }
+
+    // Runs one RPC call under the concurrency limit, retrying transient errors
+    // and failing over to the next endpoint once the retries are used up.
+    // Anything `Error::is_transient` doesn't cover fails straight away.
+    pub async fn call<T, F, Fut>(&self, request: F) -> Result<T, Error>
+    where
+        F: FnMut(Arc<dyn SolanaRpc>) -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        self.call_checked(request, |_| Ok(())).await
+    }
+
+    // `call` with `check` turning an answer into an error, retried like the
+    // call's own. A lagging node (`Error::RpcBehind`) is asked again but not
+    // failed over from: it is catching up, not failing.
+    async fn call_checked<T, F, Fut>(
+        &self,
+        mut request: F,
+        check: impl Fn(&T) -> Result<(), Error>,
+    ) -> Result<T, Error>
+    where
+        F: FnMut(Arc<dyn SolanaRpc>) -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        let policy = self.retry.policy();
+        let check = &check;
+        loop {
+            let index = self.active.load(Ordering::Relaxed);
+            let client = &self.clients[index];
+            let mut attempts = 0;
+            let result = retry(&policy, Error::is_transient, || {
+                if attempts > 0 {
+                    self.stats.retries.fetch_add(1, Ordering::Relaxed);
+                }
+                attempts += 1;
+                let answer = self.timed(client.as_ref(), request(Arc::clone(client)));
+                async move {
+                    let value = answer.await?;
+                    check(&value)?;
+                    Ok(value)
+                }
+            })
+            .await;
+            match result {
+                Err(Error::RpcBehind(_)) => return result,
+                Err(err) if err.is_transient() && self.fail_over(index) => {}
+                result => return result,
+            }
+        }
+    }
+
+    async fn get_chunk(
+        &self,
+        chunk: &[Pubkey],
+        min_context_slot: Option<u64>,
+    ) -> Result<Response<Vec<Option<Account>>>, Error> {
+        let request = |client: Arc<dyn SolanaRpc>| async move {
+            let config = RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64Zstd),
+                commitment: Some(client.commitment()),
+                min_context_slot,
+                ..RpcAccountInfoConfig::default()
+            };
+            client
+                .get_multiple_accounts_with_config(chunk, config)
+                .await
+        };
+        // a node may still answer from before `min_context_slot`
+        let check = |response: &Response<Vec<Option<Account>>>| match min_context_slot {
+            Some(min) if response.context.slot < min => Err(Error::RpcBehind(format!(
+                "context slot {} still older than {min}",
+                response.context.slot
+            ))),
+            _ => Ok(()),
+        };
+        self.call_checked(request, check).await
+    }
+
+    // A chunk that still fails after its retries marks every address in it
+    // as failed; the other chunks are unaffected.
+    //
+    // Chunks can be answered at different slots. In consistent mode the first
+    // chunk is fetched on its own and every other chunk is pinned to at least
+    // its slot via `min_context_slot`, retrying stale answers, so all numbers
+    // reflect that slot or a later one.
+    pub async fn get_accounts_with_slot(
+        &self,
+        addresses: &[Pubkey],
+    ) -> (SlotSpan, Vec<Result<Option<Account>, String>>) {
+        let mut span = None;
+        let mut out = Vec::with_capacity(addresses.len());
+        let mut chunks = addresses.chunks(MAX_ACCOUNTS_PER_REQUEST);
+
+        let mut min_context_slot = None;
+        let first = if self.consistent { chunks.next() } else { None };
+        if let Some(first) = first {
+            match self.get_chunk(first, None).await {
+                Ok(response) => {
+                    span = SlotSpan::add(span, response.context.slot);
+                    min_context_slot = Some(response.context.slot);
+                    out.extend(response.value.into_iter().map(Ok));
+                }
+                Err(err) => out.extend(first.iter().map(|_| Err(err.to_string()))),
+            }
+        }
+
+        let mut handles = vec![];
+        for chunk in chunks {
+            let fetcher = self.clone();
+            let chunk = chunk.to_vec();
+            handles.push((
+                chunk.len(),
+                task::spawn(async move { fetcher.get_chunk(&chunk, min_context_slot).await }),
+            ));
+        }
+
+        for (len, handle) in handles {
+            let response = handle
+                .await
+                .map_err(|err| err.to_string())
+                .and_then(|result| result.map_err(|err| err.to_string()));
+            match response {
+                Ok(response) => {
+                    span = SlotSpan::add(span, response.context.slot);
+                    out.extend(response.value.into_iter().map(Ok));
+                }
+                Err(err) => out.extend((0..len).map(|_| Err(err.clone()))),
+            }
+        }
+        (span.unwrap_or_default(), out)
+    }
+
+    pub async fn get_accounts(&self, addresses: &[Pubkey]) -> anyhow::Result<Vec<Option<Account>>> {
+        self.get_accounts_with_slot(addresses)
+            .await
+            .1
+            .into_iter()
+            .map(|account| account.map_err(anyhow::Error::msg))
+            .collect()
+    }
+
+    // one lookup per distinct data length
+    pub async fn rent_minimums(
+        &self,
+        data_lens: impl IntoIterator<Item = usize>,
+    ) -> Result<HashMap<usize, u64>, Error> {
+        let mut out = HashMap::new();
+        for data_len in data_lens {
+            if let Entry::Vacant(entry) = out.entry(data_len) {
+                entry.insert(
+                    self.call(|client| async move {
+                        client
+                            .get_minimum_balance_for_rent_exemption(data_len)
+                            .await
+                    })
+                    .await?,
+                );
+            }
+        }
+        Ok(out)
+    }
+
+    pub async fn get_snapshot(&self, addresses: impl IntoIterator<Item = Pubkey>) -> Snapshot {
+        let addresses: Vec<Pubkey> = addresses.into_iter().collect();
+        let (span, accounts) = self.get_accounts_with_slot(&addresses).await;
+
+        let mut balances = vec![];
+        let mut errors = vec![];
+        for (address, account) in addresses.into_iter().zip(accounts) {
+            match account {
+                Ok(Some(account)) => balances.push((
+                    address,
+                    BalanceInfo {
+                        lamports: account.lamports,
+                        exists: true,
+                        owner: Some(account.owner),
+                        executable: account.executable,
+                        data_len: account.data.len(),
+                    },
+                )),
+                Ok(None) => balances.push((
+                    address,
+                    BalanceInfo {
+                        lamports: 0,
+                        exists: false,
+                        owner: None,
+                        executable: false,
+                        data_len: 0,
+                    },
+                )),
+                Err(err) => errors.push((address, err)),
+            }
+        }
+        Snapshot {
+            slot: span.min,
+            max_slot: span.max,
+            balances,
+            errors,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::transaction::TransactionError;
+    use solana_test::rpc::mock::{Failure, MockRpc};
+
+    use super::*;
+
+    const FETCH: &str = "get_multiple_accounts_with_config";
+
+    fn fetcher(client: &Arc<MockRpc>) -> Fetcher {
+        Fetcher::new(client.clone(), Retry::default(), 2)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn transient_failures_are_retried() {
+        let client = Arc::new(MockRpc::new());
+        let funded = Pubkey::new_unique();
+        let missing = Pubkey::new_unique();
+        client.set_balance(funded, 5);
+        client.fail(FETCH, Failure::RateLimited);
+        client.fail(FETCH, Failure::Transport);
+        let fetcher = fetcher(&client);
+
+        let snapshot = fetcher.get_snapshot([funded, missing]).await;
+
+        assert!(snapshot.errors.is_empty());
+        let (_, funded) = snapshot.balances[0];
+        let (_, missing) = snapshot.balances[1];
+        assert_eq!((funded.lamports, funded.exists), (5, true));
+        assert_eq!((missing.lamports, missing.exists), (0, false));
+        assert_eq!(client.calls(FETCH), 3);
+        assert_eq!(fetcher.stats.retries.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_chunk_only_fails_its_addresses() {
+        let client = Arc::new(MockRpc::new());
+        let addresses: Vec<Pubkey> = (0..MAX_ACCOUNTS_PER_REQUEST + 50)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+        // fatal, so not retried; consistent mode fetches the first chunk first
+        client.fail(
+            FETCH,
+            Failure::Transaction(TransactionError::AccountNotFound),
+        );
+        let fetcher = fetcher(&client).consistent(true);
+
+        let snapshot = fetcher.get_snapshot(addresses.clone()).await;
+
+        let failed: Vec<Pubkey> = snapshot
+            .errors
+            .iter()
+            .map(|(address, _)| *address)
+            .collect();
+        assert_eq!(failed, addresses[..MAX_ACCOUNTS_PER_REQUEST]);
+        assert_eq!(snapshot.balances.len(), 50);
+        assert_eq!(client.calls(FETCH), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn exhausted_endpoint_fails_over() {
+        let primary = Arc::new(MockRpc::new());
+        let fallback = Arc::new(MockRpc::new());
+        let address = Pubkey::new_unique();
+        fallback.set_balance(address, 7);
+        let retry = Retry::default();
+        primary.fail_times(
+            FETCH,
+            retry.policy().max_attempts as usize,
+            Failure::Transport,
+        );
+        let fetcher = Fetcher::new(primary.clone(), retry, 2).fallbacks([fallback.clone() as _]);
+
+        let snapshot = fetcher.get_snapshot([address]).await;
+
+        assert!(snapshot.errors.is_empty());
+        assert_eq!(snapshot.balances[0].1.lamports, 7);
+        assert!(fetcher.failed_over());
+        assert_eq!(fallback.calls(FETCH), 1);
+    }
+
+    // The first chunk pins the rest to its slot 10.
+    fn consistent_fetch(client: &Arc<MockRpc>, fallback: &Arc<MockRpc>) -> (Fetcher, Vec<Pubkey>) {
+        client.answer_from(10);
+        let addresses = (0..MAX_ACCOUNTS_PER_REQUEST + 1)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+        let fetcher = fetcher(client)
+            .consistent(true)
+            .fallbacks([fallback.clone() as _]);
+        (fetcher, addresses)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stale_contexts_are_retried_on_the_same_endpoint() {
+        let client = Arc::new(MockRpc::new());
+        let fallback = Arc::new(MockRpc::new());
+        client.set_slot(11);
+        let (fetcher, addresses) = consistent_fetch(&client, &fallback);
+        client.answer_from(8);
+        client.answer_from(9);
+
+        let snapshot = fetcher.get_snapshot(addresses).await;
+
+        assert!(snapshot.errors.is_empty());
+        assert_eq!((snapshot.slot, snapshot.max_slot), (10, 11));
+        assert_eq!(client.calls(FETCH), 4);
+        assert_eq!(fetcher.stats.retries.load(Ordering::Relaxed), 2);
+        assert!(!fetcher.failed_over());
+        assert_eq!(fallback.calls(FETCH), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_node_that_stays_behind_fails_its_chunk_without_failing_over() {
+        let client = Arc::new(MockRpc::new());
+        let fallback = Arc::new(MockRpc::new());
+        client.set_slot(9);
+        let (fetcher, addresses) = consistent_fetch(&client, &fallback);
+
+        let snapshot = fetcher.get_snapshot(addresses.clone()).await;
+
+        let attempts = Retry::default().policy().max_attempts as usize;
+        assert_eq!(snapshot.balances.len(), MAX_ACCOUNTS_PER_REQUEST);
+        let [(address, err)] = &snapshot.errors[..] else {
+            panic!("expected one failed address, got {:?}", snapshot.errors);
+        };
+        assert_eq!(*address, addresses[MAX_ACCOUNTS_PER_REQUEST]);
+        assert!(err.contains("still older than 10"));
+        // one layer of retries, each counted once
+        assert_eq!(client.calls(FETCH), 1 + attempts);
+        assert_eq!(
+            fetcher.stats.retries.load(Ordering::Relaxed),
+            attempts as u64 - 1
+        );
+        assert!(!fetcher.failed_over());
+        assert_eq!(fallback.calls(FETCH), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn last_endpoint_failing_reports_every_address() {
+        let client = Arc::new(MockRpc::new());
+        let retry = Retry {
+            count: 1,
+            ..Retry::default()
+        };
+        client.fail_times(FETCH, 2, Failure::Transport);
+        let fetcher = Fetcher::new(client.clone(), retry, 2);
+        let addresses = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+        let snapshot = fetcher.get_snapshot(addresses).await;
+
+        assert!(snapshot.balances.is_empty());
+        assert_eq!(snapshot.errors.len(), 2);
+        assert!(snapshot.errors[0].1.contains("mock timeout"));
+    }
+}
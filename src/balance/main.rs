@@ -1,58 +1,563 @@
-use std::{collections::HashMap, env, fs::File, io::BufReader, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashSet},
+    io,
+    process::ExitCode,
+    time::Duration,
+};
 
+use anyhow::{Context, bail};
+use clap::Parser;
 use serde::Deserialize;
-use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{account::Account, commitment_config::CommitmentLevel, pubkey::Pubkey};
+use solana_test::{
+    Error,
+    cli::{CommonArgs, parse_commitment},
+    config::load_config_as,
+    error, logging,
+    pubkey::parse_pubkeys,
+    rpc::{self, resolve_url},
+};
 use tokio::task;
 
+mod activity;
+mod alert;
+pub mod args;
+mod ata;
+mod check_send;
+mod clusters;
+mod db;
+mod entry;
+mod fetch;
+mod nonce;
+mod output;
+mod price;
+mod sample;
+mod serve;
+mod snapshot;
+mod stake;
+mod stream;
+mod summary;
+mod table;
+mod tokens;
+mod vault;
+mod vote;
+mod watch;
+
+use args::Format;
+use entry::{AddressEntry, Source, Validated};
+use fetch::{Fetcher, Retry};
+use output::{Failure, Line, View};
+
 #[derive(Debug, Deserialize)]
 struct Config {
-    addresses: Vec<String>,
+    #[serde(default)]
+    addresses: Vec<AddressEntry>,
+    min_balance_sol: Option<f64>,
+    address_book: Option<String>,
+    #[serde(default)]
+    mints: Vec<String>,
+    rpc_endpoint: Option<String>,
+    commitment: Option<String>,
+    #[serde(default)]
+    retry: Retry,
+    #[serde(default = "fetch::default_max_concurrent_requests")]
+    max_concurrent_requests: usize,
+    #[serde(default = "fetch::default_timeout_secs")]
+    timeout_secs: u64,
+    // tried in order when `rpc_endpoint` keeps failing
+    #[serde(default)]
+    fallback_endpoints: Vec<String>,
+    // name -> url or shorthand; replaces `rpc_endpoint` when set
+    #[serde(default)]
+    clusters: BTreeMap<String, String>,
+    // signatures fetched per address with `--activity`
+    #[serde(default = "default_activity_signatures")]
+    activity_signatures: usize,
+    // adds USD values to one-shot output
+    price: Option<price::PriceConfig>,
+    // webhook for large changes seen by `--watch`
+    alert: Option<alert::AlertConfig>,
+    // same keys as the blocks binary, used by `--stream`
+    grpc_endpoint: Option<String>,
+    grpc_token: Option<String>,
 }
 
-async fn get_balance(client: Arc<RpcClient>, address: Pubkey) -> anyhow::Result<u64> {
-    Ok(client.get_balance(&address).await?)
+fn default_activity_signatures() -> usize {
+    1
 }
 
-async fn get_balances(
-    client: Arc<RpcClient>,
-    addresses: impl IntoIterator<Item = Pubkey>,
-) -> anyhow::Result<HashMap<Pubkey, u64>> {
-    let mut handles = vec![];
-    for address in addresses {
-        let client = Arc::clone(&client);
-        handles.push((address, task::spawn(get_balance(client, address))));
+// Fetches full account data for the lines a detail mode cares about.
+async fn fetch_matching(
+    fetcher: &Fetcher,
+    lines: &[Line],
+    predicate: impl Fn(&Line) -> bool,
+) -> anyhow::Result<Vec<(usize, Account)>> {
+    let indices: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| predicate(line))
+        .map(|(i, _)| i)
+        .collect();
+    if indices.is_empty() {
+        return Ok(vec![]);
     }
+    let addresses: Vec<Pubkey> = indices.iter().map(|i| lines[*i].address).collect();
+    let accounts = fetcher.get_accounts(&addresses).await?;
+    Ok(indices
+        .into_iter()
+        .zip(accounts)
+        .filter_map(|(i, account)| Some((i, account?)))
+        .collect())
+}
 
-    let mut out = HashMap::new();
-    for (address, handle) in handles {
-        let balance = handle.await??;
-        out.insert(address, balance);
-    }
-    Ok(out)
+/// Checks SOL and token balances
+#[derive(Parser)]
+#[command(name = "balance", version)]
+struct Cli {
+    #[command(flatten)]
+    common: CommonArgs,
+    #[command(flatten)]
+    args: args::Args,
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let path = env::args().nth(1).expect("Usage: balance <config.yaml>");
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    logging::init(&cli.common.log_options());
+    error::exit(run(cli.common, cli.args).await)
+}
 
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let config: Config = serde_yaml::from_reader(reader)?;
-    let addresses: Vec<Pubkey> = config
+pub async fn run(common: CommonArgs, mut args: args::Args) -> anyhow::Result<()> {
+    args.finish(common.config.is_some())?;
+
+    // straight from the database, no RPC
+    if let (Some(path), Some(runs)) = (&args.db, args.db_report) {
+        return db::report(&db::open(path)?, runs);
+    }
+
+    common.load_address_book()?;
+    let config: Config = match &common.config {
+        Some(path) => load_config_as(path, common.config_format)?,
+        // an empty document, so serde's field defaults still apply
+        None => serde_yaml::from_str("{}")?,
+    };
+    let cli_entries = args
+        .addresses
+        .iter()
+        .enumerate()
+        .map(|(i, address)| (Source::Arg(i), AddressEntry::Plain(address.clone())));
+    let stdin_entries = if args.stdin {
+        entry::parse_lines(io::stdin().lock())?
+    } else {
+        vec![]
+    };
+    let entries = config
         .addresses
         .into_iter()
-        .map(|addr| addr.parse::<Pubkey>())
-        .collect::<Result<_, _>>()?;
+        .enumerate()
+        .map(|(i, entry)| (Source::Config(i), entry))
+        .chain(cli_entries)
+        .chain(stdin_entries);
+    let validated = entry::validate(entries, config.min_balance_sol);
+    for (source, address) in &validated.duplicates {
+        tracing::warn!(%source, %address, "duplicate address");
+    }
+    if args.strict {
+        validated
+            .strict()
+            .inspect_err(|_| output::print_failures(&validated.failures))
+            .context("--strict")?;
+    }
+    let Validated {
+        mut tracked,
+        mut failures,
+        ..
+    } = validated;
+    if let Some(path) = &config.address_book {
+        entry::apply_address_book(&mut tracked, &entry::load_address_book(path)?);
+    }
+    entry::apply_shared_labels(&mut tracked);
+    entry::warn_duplicate_labels(&tracked);
+    let addresses: Vec<Pubkey> = tracked.iter().map(|tracked| tracked.address).collect();
+    let mints = parse_pubkeys(&config.mints).context("mints")?;
+
+    // unlike the other binaries, which default to devnet
+    let url = common.url(config.rpc_endpoint.as_deref(), rpc::MAINNET_URL);
+    let commitment = config
+        .commitment
+        .as_deref()
+        .map(parse_commitment)
+        .transpose()?;
+    let commitment = common.commitment(commitment, CommitmentLevel::Finalized);
+
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let connect = |url: &str| rpc::connect(url, commitment, timeout);
+
+    if !config.clusters.is_empty() && common.url.is_none() {
+        if args.watch.is_some()
+            || args.serve.is_some()
+            || args.program_scan.is_some()
+            || args.check_send.is_some()
+            || !mints.is_empty()
+        {
+            bail!("clusters only support one-shot SOL balance runs");
+        }
+        let partial = clusters::run(
+            &config.clusters,
+            commitment,
+            timeout,
+            config.retry,
+            config.max_concurrent_requests,
+            &tracked,
+            &args,
+        )
+        .await?;
+        if partial || !failures.is_empty() {
+            output::print_failures(&failures);
+            return Err(Error::PartialFailure.into());
+        }
+        return Ok(());
+    }
+
+    // stderr so json/csv output on stdout stays parseable
+    tracing::info!(endpoint = %url, commitment = ?commitment.commitment, "using endpoint");
+    for fallback in &config.fallback_endpoints {
+        tracing::info!(endpoint = %resolve_url(fallback), "fallback endpoint");
+    }
+    let fetcher = Fetcher::new(connect(&url), config.retry, config.max_concurrent_requests)
+        .consistent(args.consistent)
+        .fallbacks(config.fallback_endpoints.iter().map(|url| connect(url)));
+
+    if let Some(addr) = args.serve {
+        if !mints.is_empty() {
+            bail!("--serve only supports SOL balances");
+        }
+        return serve::run(fetcher, tracked, addr, Duration::from_secs(args.interval)).await;
+    }
+
+    if let Some(secs) = args.watch {
+        if !mints.is_empty() {
+            bail!("--watch only supports SOL balances");
+        }
+        let labels = tracked
+            .iter()
+            .filter_map(|tracked| Some((tracked.address, tracked.label.clone()?)))
+            .collect();
+        return watch::run(
+            fetcher,
+            addresses,
+            labels,
+            Duration::from_secs(secs),
+            args.watch_json,
+            config.alert.map(alert::Alerter::new),
+        )
+        .await;
+    }
+
+    if let Some(path) = &args.check_send {
+        let needs = check_send::check(&fetcher, path).await?;
+        match args.format {
+            Format::Json => println!("{}", serde_json::to_string_pretty(&needs)?),
+            _ => check_send::print(&needs),
+        }
+        fetcher.stats.log();
+        if needs.iter().any(|need| need.short_lamports.is_some()) {
+            return Err(Error::BelowMinimum.into());
+        }
+        return Ok(());
+    }
+
+    if let Some(program_id) = args.program_scan {
+        let scan = vault::scan(&fetcher, program_id, args.owner).await?;
+        match args.format {
+            Format::Json => println!("{}", serde_json::to_string_pretty(&scan)?),
+            _ => vault::print_scan(&scan),
+        }
+        fetcher.stats.log();
+        return Ok(());
+    }
+
+    if let Some(mint) = args.ata_check {
+        let report = ata::check(&fetcher, &tracked, mint).await?;
+        report.print_summary();
+        if !report.missing.is_empty() {
+            let sender = args.ata_sender.as_deref().unwrap_or("sender.json");
+            print!("{}", report.missing_pairs(sender)?);
+        }
+        fetcher.stats.log();
+        return Ok(());
+    }
 
-    let client = Arc::new(RpcClient::new(
-        "https://api.mainnet-beta.solana.com".to_string(),
-    ));
+    if let (Some(sample), Some(out)) = (args.sample, &args.out) {
+        if !mints.is_empty() {
+            bail!("--sample only supports SOL balances");
+        }
+        return sample::run(fetcher, &tracked, sample, out).await;
+    }
+
+    if args.stream {
+        if !mints.is_empty() {
+            bail!("--stream only supports SOL balances");
+        }
+        let Some(endpoint) = &config.grpc_endpoint else {
+            bail!("--stream needs grpc_endpoint in the config");
+        };
+        return stream::run(
+            fetcher,
+            endpoint,
+            config.grpc_token.clone(),
+            addresses,
+            args.watch_json,
+        )
+        .await;
+    }
+
+    if !mints.is_empty() {
+        if args.format != Format::Table {
+            bail!("token balances only support the table format");
+        }
+        let balances = tokens::get_token_balances(&fetcher, &addresses, &mints).await?;
+        tokens::print_table(&addresses, &mints, &balances);
+        return Ok(());
+    }
+
+    // addresses only known from the old snapshot are fetched too
+    let previous = args.diff.as_deref().map(snapshot::load).transpose()?;
+    let mut not_queried = HashSet::new();
+    if let Some(previous) = &previous {
+        let known: HashSet<Pubkey> = addresses.iter().copied().collect();
+        for (address, saved) in previous.addresses()?.into_iter().zip(&previous.balances) {
+            if !known.contains(&address) && not_queried.insert(address) {
+                tracked.push(entry::Tracked {
+                    address,
+                    label: saved.label.clone(),
+                    min_lamports: None,
+                });
+            }
+        }
+    }
 
-    let balances = get_balances(client, addresses).await?;
-    for (k, v) in balances {
-        println!("{k}: {v}");
+    let snapshot = fetcher
+        .get_snapshot(addresses.into_iter().chain(not_queried.iter().copied()))
+        .await;
+    let (mut lines, fetch_failures) = clusters::lines_for(&tracked, &snapshot);
+    failures.extend(fetch_failures);
+    if args.details {
+        let minimums = fetcher
+            .rent_minimums(
+                lines
+                    .iter()
+                    .filter(|line| line.info.exists)
+                    .map(|line| line.info.data_len),
+            )
+            .await?;
+        for line in lines.iter_mut().filter(|line| line.info.exists) {
+            line.rent_exempt = Some(line.info.lamports >= minimums[&line.info.data_len]);
+        }
+    }
+    if lines.is_empty() && !failures.is_empty() {
+        output::print_failures(&failures);
+        fetcher.stats.log();
+        bail!("all {} addresses failed", failures.len());
     }
 
+    if args.all_tokens {
+        let handles: Vec<_> = lines
+            .iter()
+            .map(|line| {
+                let fetcher = fetcher.clone();
+                let owner = line.address;
+                let top = args.top;
+                task::spawn(async move { tokens::get_holdings(&fetcher, &owner, top).await })
+            })
+            .collect();
+        for (line, handle) in lines.iter_mut().zip(handles) {
+            match handle.await? {
+                Ok(holdings) => line.holdings = Some(holdings),
+                Err(err) => failures.push(Failure {
+                    address: line.address.to_string(),
+                    error: format!("token accounts: {err:#}"),
+                }),
+            }
+        }
+    }
+
+    if args.stake {
+        let owner = solana_sdk::stake::program::id();
+        let accounts =
+            fetch_matching(&fetcher, &lines, |line| line.info.owner == Some(owner)).await?;
+        if !accounts.is_empty() {
+            let epoch = fetcher
+                .call(|client| async move { client.get_epoch_info().await })
+                .await?
+                .epoch;
+            for (i, account) in accounts {
+                lines[i].stake = stake::decode(&account, epoch);
+            }
+        }
+    }
+    if args.vote {
+        let owner = solana_sdk::vote::program::id();
+        let accounts =
+            fetch_matching(&fetcher, &lines, |line| line.info.owner == Some(owner)).await?;
+        if !accounts.is_empty() {
+            let epoch = fetcher
+                .call(|client| async move { client.get_epoch_info().await })
+                .await?
+                .epoch;
+            let status = fetcher
+                .call(|client| async move { client.get_vote_accounts().await })
+                .await?;
+            for (i, account) in accounts {
+                let line = &mut lines[i];
+                line.vote = vote::decode(&account, epoch).map(|mut info| {
+                    vote::apply_status(&mut info, &line.address, &status);
+                    info
+                });
+            }
+        }
+    }
+    if args.nonce {
+        let accounts = fetch_matching(&fetcher, &lines, |line| {
+            nonce::is_nonce_sized(line.info.owner, line.info.data_len)
+        })
+        .await?;
+        for (i, account) in accounts {
+            lines[i].nonce = nonce::decode(&account);
+        }
+    }
+
+    if let Some(program_id) = args.vaults {
+        let vaults: Vec<Pubkey> = lines
+            .iter()
+            .map(|line| vault::vault_address(&program_id, &line.address))
+            .collect();
+        let accounts = fetcher.get_accounts(&vaults).await?;
+        let rent = fetcher
+            .rent_minimums(accounts.iter().flatten().map(|account| account.data.len()))
+            .await?;
+        for ((line, address), account) in lines.iter_mut().zip(vaults).zip(accounts) {
+            line.vault = Some(vault::inspect(
+                address,
+                &line.address,
+                account.as_ref(),
+                &rent,
+            ));
+        }
+    }
+
+    if args.activity {
+        activity::lookup(
+            &fetcher,
+            &mut lines,
+            config.activity_signatures.max(1),
+            args.activity_limit,
+        )
+        .await?;
+    }
+
+    if let Some(path) = &args.snapshot {
+        let queried: Vec<Line> = lines
+            .iter()
+            .filter(|line| !not_queried.contains(&line.address))
+            .cloned()
+            .collect();
+        snapshot::save(path, snapshot.slot, &fetcher.client().url(), &queried)?;
+    }
+    if let Some(path) = &args.db {
+        let queried: Vec<Line> = lines
+            .iter()
+            .filter(|line| !not_queried.contains(&line.address))
+            .cloned()
+            .collect();
+        let run_id = db::record(
+            &mut db::open(path)?,
+            snapshot.slot,
+            &fetcher.client().url(),
+            &queried,
+        )?;
+        tracing::info!(%run_id, %path, "recorded run");
+    }
+
+    let violations = lines.iter().filter(|line| line.below_min()).count();
+
+    let min = args.min_sol.map(entry::sol_to_lamports);
+    let max = args.max_sol.map(entry::sol_to_lamports);
+    lines.retain(|line| {
+        let lamports = line.info.lamports;
+        !(args.only_nonzero && lamports == 0)
+            && min.is_none_or(|min| lamports >= min)
+            && max.is_none_or(|max| lamports <= max)
+    });
+    let price = match &config.price {
+        Some(config) => Some(price::get(config).await?),
+        None => None,
+    };
+    let view = View {
+        labels_only: args.labels_only,
+        details: args.details,
+        color: table::use_color(args.no_color),
+        price,
+    };
+    let mut summary = summary::summarize(&lines, args.labels_only, args.summary_top);
+    summary.total_usd = price.map(|price| price.usd(summary.total_lamports));
+
+    if args.quiet {
+        lines.retain(Line::below_min);
+    }
+    output::sort(&mut lines, args.sort, args.desc);
+    if let Some(previous) = &previous {
+        let diff = snapshot::diff(previous, snapshot.slot, &lines, &not_queried);
+        match args.format {
+            Format::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+            _ => snapshot::print_diff(&diff),
+        }
+    } else {
+        match args.format {
+            Format::Table if args.quiet && lines.is_empty() => output::print_failures(&failures),
+            Format::Table => {
+                if fetcher.failed_over() {
+                    println!(
+                        "Endpoint: {} (failed over from {})",
+                        fetcher.client().url(),
+                        fetcher.primary_url()
+                    );
+                }
+                println!("Slot: {}", snapshot.slots());
+                table::print(&lines, &failures, view);
+                if !args.quiet {
+                    summary::print(&summary);
+                }
+            }
+            Format::Json => println!(
+                "{}",
+                output::to_json(
+                    snapshot.slots(),
+                    &fetcher.client().url(),
+                    &lines,
+                    &summary,
+                    &failures,
+                    view
+                )?
+            ),
+            Format::Csv => {
+                print!("{}", output::to_csv(&lines, view));
+                for failure in &failures {
+                    tracing::warn!(address = %failure.address, error = %failure.error, "fetch failed");
+                }
+            }
+        }
+    }
+
+    fetcher.stats.log();
+
+    if !failures.is_empty() {
+        tracing::error!(failed = failures.len(), "some addresses failed");
+        return Err(Error::PartialFailure.into());
+    }
+    if violations > 0 {
+        tracing::warn!(violations, "addresses below their minimum balance");
+        return Err(Error::BelowMinimum.into());
+    }
     Ok(())
 }
@@ -0,0 +1,124 @@
+use serde::Serialize;
+use solana_sdk::{
+    account::Account,
+    nonce::{
+        self,
+        state::{State, Versions},
+    },
+    pubkey::Pubkey,
+    system_program,
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NonceInfo {
+    pub initialized: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authority: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blockhash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lamports_per_signature: Option<u64>,
+}
+
+pub fn is_nonce_sized(owner: Option<Pubkey>, data_len: usize) -> bool {
+    owner == Some(system_program::id()) && data_len == nonce::State::size()
+}
+
+pub fn decode(account: &Account) -> Option<NonceInfo> {
+    let versions = account.deserialize_data::<Versions>().ok()?;
+    let info = match versions.state() {
+        State::Uninitialized => NonceInfo {
+            initialized: false,
+            authority: None,
+            blockhash: None,
+            lamports_per_signature: None,
+        },
+        State::Initialized(data) => NonceInfo {
+            initialized: true,
+            authority: Some(data.authority.to_string()),
+            blockhash: Some(data.blockhash().to_string()),
+            lamports_per_signature: Some(data.fee_calculator.lamports_per_signature),
+        },
+    };
+    Some(info)
+}
+
+pub fn print(info: &NonceInfo) {
+    match (&info.authority, &info.blockhash) {
+        (Some(authority), Some(blockhash)) => println!(
+            "    nonce: authority {authority}, blockhash {blockhash}, {} lamports/signature",
+            info.lamports_per_signature.unwrap_or_default()
+        ),
+        _ => println!("    nonce: UNINITIALIZED"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::hash::Hash;
+
+    use super::*;
+
+    // A nonce account in the current layout: version, state, then the
+    // authority, stored blockhash and lamports per signature.
+    fn fixture(state: u32, authority: &Pubkey, blockhash: &Hash) -> Account {
+        let mut data = vec![];
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&state.to_le_bytes());
+        data.extend_from_slice(authority.as_ref());
+        data.extend_from_slice(blockhash.as_ref());
+        data.extend_from_slice(&5_000u64.to_le_bytes());
+        Account {
+            lamports: 1_447_680,
+            data,
+            owner: system_program::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn decodes_an_initialized_nonce() {
+        let authority = Pubkey::new_unique();
+        let blockhash = Hash::new_unique();
+        let account = fixture(1, &authority, &blockhash);
+        assert!(is_nonce_sized(Some(account.owner), account.data.len()));
+
+        let info = decode(&account).unwrap();
+
+        assert!(info.initialized);
+        assert_eq!(info.authority, Some(authority.to_string()));
+        assert_eq!(info.blockhash, Some(blockhash.to_string()));
+        assert_eq!(info.lamports_per_signature, Some(5_000));
+    }
+
+    #[test]
+    fn an_uninitialized_nonce_is_flagged() {
+        let account = fixture(0, &Pubkey::default(), &Hash::default());
+        assert!(is_nonce_sized(Some(account.owner), account.data.len()));
+
+        let info = decode(&account).unwrap();
+
+        assert!(!info.initialized);
+        assert_eq!(info.authority, None);
+        assert_eq!(info.blockhash, None);
+        assert_eq!(info.lamports_per_signature, None);
+    }
+
+    #[test]
+    fn only_system_accounts_of_nonce_size_are_checked() {
+        assert!(!is_nonce_sized(None, State::size()));
+        assert!(!is_nonce_sized(Some(Pubkey::new_unique()), State::size()));
+        assert!(!is_nonce_sized(Some(system_program::id()), 0));
+    }
+
+    #[test]
+    fn garbled_data_is_not_a_nonce() {
+        let account = Account {
+            data: vec![9; 8],
+            ..fixture(1, &Pubkey::default(), &Hash::default())
+        };
+
+        assert!(decode(&account).is_none());
+    }
+}
@@ -0,0 +1,279 @@
+use std::cmp::Ordering;
+
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+
+use super::{
+    activity::Activity,
+    args::SortKey,
+    entry::Tracked,
+    fetch::{BalanceInfo, SlotSpan},
+    nonce::NonceInfo,
+    price::Price,
+    stake::StakeInfo,
+    summary::Summary,
+    tokens::{Holding, format_ui_amount},
+    vault::VaultInfo,
+    vote::VoteInfo,
+};
+
+const SOL_DECIMALS: u8 = 9;
+
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub address: Pubkey,
+    pub label: Option<String>,
+    pub info: BalanceInfo,
+    pub min_lamports: Option<u64>,
+    // only looked up with `--details`
+    pub rent_exempt: Option<bool>,
+    // only looked up with `--all-tokens`
+    pub holdings: Option<Vec<Holding>>,
+    // only looked up with `--stake`
+    pub stake: Option<StakeInfo>,
+    // only looked up with `--nonce`
+    pub nonce: Option<NonceInfo>,
+    // only looked up with `--vaults`
+    pub vault: Option<VaultInfo>,
+    // only looked up with `--activity`
+    pub activity: Option<Activity>,
+    // only looked up with `--vote`
+    pub vote: Option<VoteInfo>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct View {
+    pub labels_only: bool,
+    pub details: bool,
+    // table output only
+    pub color: bool,
+    // set when a price source is configured
+    pub price: Option<Price>,
+}
+
+impl Line {
+    pub fn new(tracked: &Tracked, info: BalanceInfo) -> Self {
+        Self {
+            address: tracked.address,
+            label: tracked.label.clone(),
+            info,
+            min_lamports: tracked.min_lamports,
+            rent_exempt: None,
+            holdings: None,
+            stake: None,
+            nonce: None,
+            vault: None,
+            activity: None,
+            vote: None,
+        }
+    }
+
+    // the label in place of the pubkey with `--labels-only`
+    pub fn name(&self, labels_only: bool) -> String {
+        match &self.label {
+            Some(label) if labels_only => label.clone(),
+            _ => self.address.to_string(),
+        }
+    }
+
+    pub fn below_min(&self) -> bool {
+        self.min_lamports
+            .is_some_and(|min| self.info.lamports < min)
+    }
+}
+
+// an address that could not be parsed or fetched
+#[derive(Debug, Clone, Serialize)]
+pub struct Failure {
+    pub address: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Row {
+    address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    lamports: u64,
+    // fixed 9 decimals, kept as a string to avoid float rounding
+    sol: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_sol: Option<String>,
+    below_min: bool,
+    exists: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    executable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data_len: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rent_exempt: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tokens: Option<Vec<Holding>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stake: Option<StakeInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nonce: Option<NonceInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vault: Option<VaultInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    activity: Option<Activity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vote: Option<VoteInfo>,
+}
+
+#[derive(Debug, Serialize)]
+struct Document<'a> {
+    slot: u64,
+    max_slot: u64,
+    endpoint: &'a str,
+    balances: Vec<Row>,
+    summary: &'a Summary,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    errors: &'a [Failure],
+}
+
+pub fn sol_string(lamports: u64) -> String {
+    format_ui_amount(lamports, SOL_DECIMALS)
+}
+
+pub fn signed_sol(delta: i128) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{sign}{}", sol_string(delta.unsigned_abs() as u64))
+}
+
+// Ties are broken by address so output is stable. Balances always sort
+// largest first, labels and addresses ascending unless `desc`; unlabeled
+// addresses sort after labeled ones either way.
+pub fn sort(lines: &mut [Line], key: SortKey, desc: bool) {
+    let direction = |ordering: Ordering| if desc { ordering.reverse() } else { ordering };
+    lines.sort_by(|a, b| {
+        let address = || a.address.to_string().cmp(&b.address.to_string());
+        match key {
+            SortKey::Balance => b.info.lamports.cmp(&a.info.lamports).then_with(address),
+            SortKey::Label => match (&a.label, &b.label) {
+                (Some(x), Some(y)) => direction(x.cmp(y).then_with(address)),
+                (x, y) => y
+                    .is_some()
+                    .cmp(&x.is_some())
+                    .then_with(|| direction(address())),
+            },
+            SortKey::Address => direction(address()),
+        }
+    });
+}
+
+fn rows(lines: &[Line], view: View) -> Vec<Row> {
+    lines
+        .iter()
+        .map(|line| {
+            let details = view.details && line.info.exists;
+            Row {
+                address: line.name(view.labels_only),
+                label: line.label.clone().filter(|_| !view.labels_only),
+                lamports: line.info.lamports,
+                sol: sol_string(line.info.lamports),
+                usd: view.price.map(|price| price.usd(line.info.lamports)),
+                min_sol: line.min_lamports.map(sol_string),
+                below_min: line.below_min(),
+                exists: line.info.exists,
+                owner: line
+                    .info
+                    .owner
+                    .filter(|_| details)
+                    .map(|owner| owner.to_string()),
+                executable: Some(line.info.executable).filter(|_| details),
+                data_len: Some(line.info.data_len).filter(|_| details),
+                rent_exempt: line.rent_exempt.filter(|_| details),
+                tokens: line.holdings.clone(),
+                stake: line.stake.clone(),
+                nonce: line.nonce.clone(),
+                vault: line.vault.clone(),
+                activity: line.activity.clone(),
+                vote: line.vote.clone(),
+            }
+        })
+        .collect()
+}
+
+pub fn print_failures(failures: &[Failure]) {
+    if failures.is_empty() {
+        return;
+    }
+    println!("\nErrors:");
+    for failure in failures {
+        println!("{:<44} {}", failure.address, failure.error);
+    }
+}
+
+pub fn to_json(
+    slots: SlotSpan,
+    endpoint: &str,
+    lines: &[Line],
+    summary: &Summary,
+    failures: &[Failure],
+    view: View,
+) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&Document {
+        slot: slots.min,
+        max_slot: slots.max,
+        endpoint,
+        balances: rows(lines, view),
+        summary,
+        errors: failures,
+    })
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn to_csv(lines: &[Line], view: View) -> String {
+    let mut out = String::from("address,label,lamports,sol,min_sol,below_min,exists");
+    if view.details {
+        out += ",owner,executable,data_len,rent_exempt";
+    }
+    if view.price.is_some() {
+        out += ",usd";
+    }
+    out.push('\n');
+    for row in rows(lines, view) {
+        out += &format!(
+            "{},{},{},{},{},{},{}",
+            row.address,
+            csv_field(&row.label.unwrap_or_default()),
+            row.lamports,
+            row.sol,
+            row.min_sol.unwrap_or_default(),
+            row.below_min,
+            row.exists
+        );
+        if view.details {
+            out += &format!(
+                ",{},{},{},{}",
+                row.owner.unwrap_or_default(),
+                row.executable
+                    .map(|value| value.to_string())
+                    .unwrap_or_default(),
+                row.data_len
+                    .map(|value| value.to_string())
+                    .unwrap_or_default(),
+                row.rent_exempt
+                    .map(|value| value.to_string())
+                    .unwrap_or_default()
+            );
+        }
+        if let Some(usd) = &row.usd {
+            out += &format!(",{usd}");
+        }
+        out.push('\n');
+    }
+    out
+}
@@ -0,0 +1,156 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::Deserialize;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+
+const COINGECKO_URL: &str =
+    "https://api.coingecko.com/api/v3/simple/price?ids=solana&vs_currencies=usd";
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Coingecko,
+    Fixed,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PriceConfig {
+    pub source: Source,
+    pub fixed_usd: Option<f64>,
+}
+
+pub trait PriceSource {
+    fn sol_usd(&self) -> impl Future<Output = anyhow::Result<f64>> + Send;
+}
+
+pub struct Fixed(pub f64);
+
+impl PriceSource for Fixed {
+    async fn sol_usd(&self) -> anyhow::Result<f64> {
+        Ok(self.0)
+    }
+}
+
+pub struct Coingecko {
+    client: reqwest::Client,
+}
+
+impl PriceSource for Coingecko {
+    async fn sol_usd(&self) -> anyhow::Result<f64> {
+        let body: serde_json::Value = self
+            .client
+            .get(COINGECKO_URL)
+            .timeout(FETCH_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        body["solana"]["usd"]
+            .as_f64()
+            .context("unexpected coingecko response")
+    }
+}
+
+// SOL/USD for this run; `None` inside means the lookup failed and values
+// show as "n/a".
+#[derive(Debug, Clone, Copy)]
+pub struct Price(pub Option<f64>);
+
+impl Price {
+    pub fn usd(self, lamports: u64) -> String {
+        match self.0 {
+            Some(rate) => format!("{:.2}", lamports as f64 / LAMPORTS_PER_SOL as f64 * rate),
+            None => "n/a".to_string(),
+        }
+    }
+}
+
+pub async fn lookup(source: &impl PriceSource) -> Price {
+    match source.sol_usd().await {
+        Ok(rate) => Price(Some(rate)),
+        Err(err) => {
+            tracing::warn!(error = %format!("{err:#}"), "SOL price unavailable, USD shown as n/a");
+            Price(None)
+        }
+    }
+}
+
+pub async fn get(config: &PriceConfig) -> anyhow::Result<Price> {
+    Ok(match config.source {
+        Source::Fixed => {
+            let rate = config
+                .fixed_usd
+                .context("price source `fixed` needs fixed_usd")?;
+            lookup(&Fixed(rate)).await
+        }
+        Source::Coingecko => {
+            lookup(&Coingecko {
+                client: reqwest::Client::new(),
+            })
+            .await
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::bail;
+
+    use super::super::{
+        output::{View, to_csv},
+        summary::tests::line,
+    };
+    use super::*;
+
+    struct Unreachable;
+
+    impl PriceSource for Unreachable {
+        async fn sol_usd(&self) -> anyhow::Result<f64> {
+            bail!("connection refused")
+        }
+    }
+
+    #[tokio::test]
+    async fn a_price_converts_lamports_to_usd() {
+        let price = lookup(&Fixed(150.0)).await;
+
+        assert_eq!(price.usd(3 * LAMPORTS_PER_SOL / 2), "225.00");
+        assert_eq!(price.usd(0), "0.00");
+        assert_eq!(price.usd(1), "0.00");
+    }
+
+    #[tokio::test]
+    async fn a_failed_lookup_shows_usd_as_na() {
+        let price = lookup(&Unreachable).await;
+
+        assert!(price.0.is_none());
+        assert_eq!(price.usd(LAMPORTS_PER_SOL), "n/a");
+
+        let view = View {
+            price: Some(price),
+            ..View::default()
+        };
+        let csv = to_csv(&[line(None, LAMPORTS_PER_SOL)], view);
+        let mut rows = csv.lines();
+        assert!(rows.next().unwrap().ends_with(",usd"));
+        assert!(rows.next().unwrap().ends_with(",n/a"));
+    }
+
+    #[tokio::test]
+    async fn a_fixed_source_needs_its_rate() {
+        let config = PriceConfig {
+            source: Source::Fixed,
+            fixed_usd: None,
+        };
+        assert!(get(&config).await.is_err());
+
+        let config = PriceConfig {
+            fixed_usd: Some(20.0),
+            ..config
+        };
+        assert_eq!(get(&config).await.unwrap().usd(LAMPORTS_PER_SOL), "20.00");
+    }
+}
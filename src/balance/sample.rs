@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::Write,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::DateTime;
+use solana_sdk::pubkey::Pubkey;
+use tokio::time::{self, Instant, MissedTickBehavior};
+
+use super::{
+    entry::Tracked,
+    fetch::Fetcher,
+    output::{csv_field, sol_string},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub interval: u64,
+    pub count: u32,
+}
+
+impl Default for Sample {
+    fn default() -> Self {
+        Self {
+            interval: 60,
+            count: 60,
+        }
+    }
+}
+
+impl Sample {
+    // `interval=60` or `count=60`
+    pub fn set(&mut self, setting: &str) -> anyhow::Result<()> {
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("expected key=value, got `{setting}`"))?;
+        match key {
+            "interval" => self.interval = value.parse()?,
+            "count" => self.count = value.parse()?,
+            _ => anyhow::bail!("unknown --sample setting `{key}`, expected interval or count"),
+        }
+        if self.interval == 0 || self.count == 0 {
+            anyhow::bail!("--sample interval and count must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Series {
+    first: Option<u64>,
+    last: Option<u64>,
+    min: Option<u64>,
+    max: Option<u64>,
+    missed: u32,
+}
+
+impl Series {
+    fn add(&mut self, lamports: u64) {
+        self.first.get_or_insert(lamports);
+        self.last = Some(lamports);
+        self.min = Some(self.min.map_or(lamports, |min| min.min(lamports)));
+        self.max = Some(self.max.map_or(lamports, |max| max.max(lamports)));
+    }
+}
+
+// Samples are scheduled on multiples of the interval in wall-clock time, so
+// a slow fetch delays one row but never shifts the ones after it.
+pub async fn run(
+    fetcher: Fetcher,
+    tracked: &[Tracked],
+    sample: Sample,
+    out: &str,
+) -> anyhow::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(out)?;
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "timestamp,slot,address,label,lamports,error")?;
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    let first = (now.as_secs() / sample.interval + 1) * sample.interval;
+    let mut ticker = time::interval_at(
+        Instant::now() + (Duration::from_secs(first) - now),
+        Duration::from_secs(sample.interval),
+    );
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    tracing::info!(
+        addresses = tracked.len(),
+        interval_secs = sample.interval,
+        samples = sample.count,
+        %out,
+        "sampling, Ctrl-C to stop early"
+    );
+
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut series: HashMap<Pubkey, Series> = HashMap::new();
+    let mut taken = 0;
+    while taken < sample.count {
+        tokio::select! {
+            _ = &mut ctrl_c => break,
+            _ = ticker.tick() => {}
+        }
+        // nearest boundary, the timer may fire a little early or late
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let scheduled = (now + sample.interval / 2) / sample.interval * sample.interval;
+        let ts = DateTime::from_timestamp(scheduled as i64, 0)
+            .unwrap_or_default()
+            .to_rfc3339();
+
+        let snapshot = fetcher
+            .get_snapshot(tracked.iter().map(|tracked| tracked.address))
+            .await;
+        let balances: HashMap<Pubkey, u64> = snapshot
+            .balances
+            .iter()
+            .map(|(address, info)| (*address, info.lamports))
+            .collect();
+        let errors: HashMap<Pubkey, &str> = snapshot
+            .errors
+            .iter()
+            .map(|(address, err)| (*address, err.as_str()))
+            .collect();
+        // a fully failed fetch has no slot
+        let slot = if balances.is_empty() {
+            String::new()
+        } else {
+            snapshot.slot.to_string()
+        };
+
+        for tracked in tracked {
+            let label = csv_field(tracked.label.as_deref().unwrap_or_default());
+            let entry = series.entry(tracked.address).or_default();
+            match balances.get(&tracked.address) {
+                Some(lamports) => {
+                    entry.add(*lamports);
+                    writeln!(file, "{ts},{slot},{},{label},{lamports},", tracked.address)?;
+                }
+                None => {
+                    entry.missed += 1;
+                    let err = errors.get(&tracked.address).copied().unwrap_or("missing");
+                    writeln!(
+                        file,
+                        "{ts},{slot},{},{label},,{}",
+                        tracked.address,
+                        csv_field(err)
+                    )?;
+                }
+            }
+        }
+        file.flush()?;
+        taken += 1;
+        if !snapshot.errors.is_empty() {
+            tracing::warn!(
+                sample = taken,
+                failed = snapshot.errors.len(),
+                "addresses failed"
+            );
+        }
+    }
+
+    println!("{taken} samples written to {out}");
+    println!(
+        "{:<44} {:>20} {:>20} {:>20} {:>20} {:>6}",
+        "address", "first", "last", "min", "max", "missed"
+    );
+    let sol = |lamports: Option<u64>| lamports.map(sol_string).unwrap_or_else(|| "-".into());
+    for tracked in tracked {
+        let Some(series) = series.get(&tracked.address) else {
+            continue;
+        };
+        println!(
+            "{:<44} {:>20} {:>20} {:>20} {:>20} {:>6}",
+            tracked.address,
+            sol(series.first),
+            sol(series.last),
+            sol(series.min),
+            sol(series.max),
+            series.missed
+        );
+    }
+    Ok(())
+}
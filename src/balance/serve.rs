@@ -0,0 +1,183 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::Utc;
+use solana_sdk::pubkey::Pubkey;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::{self, MissedTickBehavior},
+};
+
+use super::{entry::Tracked, fetch::Fetcher};
+
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default)]
+struct State {
+    balances: HashMap<Pubkey, u64>,
+    slot: u64,
+    last_success: Option<i64>,
+    consecutive_failures: u64,
+    failed_addresses: usize,
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn render(state: &State, tracked: &[Tracked]) -> String {
+    let mut out = String::new();
+    out += "# HELP solana_balance_lamports Account balance in lamports.\n";
+    out += "# TYPE solana_balance_lamports gauge\n";
+    for entry in tracked {
+        let Some(lamports) = state.balances.get(&entry.address) else {
+            continue;
+        };
+        let _ = writeln!(
+            out,
+            "solana_balance_lamports{{address=\"{}\",label=\"{}\"}} {lamports}",
+            entry.address,
+            escape(entry.label.as_deref().unwrap_or_default())
+        );
+    }
+    out += "# HELP solana_balance_last_success_timestamp_seconds Unix time of the last refresh without errors.\n";
+    out += "# TYPE solana_balance_last_success_timestamp_seconds gauge\n";
+    let _ = writeln!(
+        out,
+        "solana_balance_last_success_timestamp_seconds {}",
+        state.last_success.unwrap_or_default()
+    );
+    out += "# HELP solana_balance_consecutive_failures Refreshes in a row that had errors.\n";
+    out += "# TYPE solana_balance_consecutive_failures gauge\n";
+    let _ = writeln!(
+        out,
+        "solana_balance_consecutive_failures {}",
+        state.consecutive_failures
+    );
+    out += "# HELP solana_balance_failed_addresses Addresses that failed in the last refresh.\n";
+    out += "# TYPE solana_balance_failed_addresses gauge\n";
+    let _ = writeln!(
+        out,
+        "solana_balance_failed_addresses {}",
+        state.failed_addresses
+    );
+    out += "# HELP solana_balance_slot Context slot of the last refresh.\n";
+    out += "# TYPE solana_balance_slot gauge\n";
+    let _ = writeln!(out, "solana_balance_slot {}", state.slot);
+    out
+}
+
+async fn refresh_loop(
+    fetcher: Fetcher,
+    addresses: Vec<Pubkey>,
+    interval: Duration,
+    state: Arc<Mutex<State>>,
+) {
+    let mut ticker = time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let snapshot = fetcher.get_snapshot(addresses.iter().copied()).await;
+        let ts = Utc::now();
+
+        let mut state = state.lock().unwrap();
+        // failed addresses keep their last known balance
+        state.balances.extend(
+            snapshot
+                .balances
+                .iter()
+                .map(|(address, info)| (*address, info.lamports)),
+        );
+        state.failed_addresses = snapshot.errors.len();
+        if snapshot.errors.is_empty() {
+            state.slot = snapshot.slot;
+            state.last_success = Some(ts.timestamp());
+            state.consecutive_failures = 0;
+        } else {
+            state.consecutive_failures += 1;
+            tracing::warn!(
+                failed = snapshot.errors.len(),
+                error = %snapshot.errors[0].1,
+                "refresh had failed addresses"
+            );
+        }
+    }
+}
+
+async fn handle(
+    mut stream: TcpStream,
+    state: &Mutex<State>,
+    tracked: &[Tracked],
+) -> std::io::Result<()> {
+    let mut buf = [0u8; 4096];
+    let len = match time::timeout(READ_TIMEOUT, stream.read(&mut buf)).await {
+        Ok(len) => len?,
+        Err(_) => return Ok(()),
+    };
+    let request = String::from_utf8_lossy(&buf[..len]);
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+    let (status, body) = match path {
+        "/metrics" => ("200 OK", render(&state.lock().unwrap(), tracked)),
+        _ => ("404 Not Found", "not found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+async fn serve_loop(
+    listener: TcpListener,
+    state: Arc<Mutex<State>>,
+    tracked: Arc<Vec<Tracked>>,
+) -> std::io::Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let state = Arc::clone(&state);
+        let tracked = Arc::clone(&tracked);
+        tokio::spawn(async move {
+            if let Err(err) = handle(stream, &state, &tracked).await {
+                tracing::warn!(error = %err, "metrics request failed");
+            }
+        });
+    }
+}
+
+// Runs until Ctrl-C; dropping both loops stops the listener and the refresh.
+pub async fn run(
+    fetcher: Fetcher,
+    tracked: Vec<Tracked>,
+    addr: SocketAddr,
+    interval: Duration,
+) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!(
+        addresses = tracked.len(),
+        url = %format!("http://{addr}/metrics"),
+        interval_secs = interval.as_secs(),
+        "serving metrics"
+    );
+
+    let addresses = tracked.iter().map(|entry| entry.address).collect();
+    let state = Arc::new(Mutex::new(State::default()));
+    let tracked = Arc::new(tracked);
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => tracing::info!("shutting down"),
+        result = serve_loop(listener, Arc::clone(&state), tracked) => result?,
+        _ = refresh_loop(fetcher, addresses, interval, state) => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+};
+
+use anyhow::{Context, bail};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use super::output::{Line, signed_sol, sol_string};
+
+// Bump on any schema change; older files are rejected instead of misparsed.
+const SNAPSHOT_VERSION: u64 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedBalance {
+    pub address: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub lamports: u64,
+    pub exists: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    pub version: u64,
+    pub ts: String,
+    pub slot: u64,
+    pub endpoint: String,
+    pub balances: Vec<SavedBalance>,
+}
+
+pub fn save(path: &str, slot: u64, endpoint: &str, lines: &[Line]) -> anyhow::Result<()> {
+    let snapshot = SnapshotFile {
+        version: SNAPSHOT_VERSION,
+        ts: Utc::now().to_rfc3339(),
+        slot,
+        endpoint: endpoint.to_string(),
+        balances: lines
+            .iter()
+            .map(|line| SavedBalance {
+                address: line.address.to_string(),
+                label: line.label.clone(),
+                lamports: line.info.lamports,
+                exists: line.info.exists,
+            })
+            .collect(),
+    };
+    fs::write(path, serde_json::to_string_pretty(&snapshot)?)
+        .with_context(|| format!("Can't write snapshot {path}"))
+}
+
+pub fn load(path: &str) -> anyhow::Result<SnapshotFile> {
+    let data = fs::read_to_string(path).with_context(|| format!("Can't read snapshot {path}"))?;
+    let value: serde_json::Value = serde_json::from_str(&data)?;
+    match value["version"].as_u64() {
+        Some(SNAPSHOT_VERSION) => {}
+        Some(version) => {
+            bail!("snapshot {path} has version {version}, expected {SNAPSHOT_VERSION}")
+        }
+        None => bail!("snapshot {path} has no version"),
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+impl SnapshotFile {
+    pub fn addresses(&self) -> anyhow::Result<Vec<Pubkey>> {
+        self.balances
+            .iter()
+            .map(|saved| {
+                saved
+                    .address
+                    .parse()
+                    .with_context(|| format!("snapshot has invalid address {}", saved.address))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Changed,
+    Unchanged,
+    // in the config but not in the snapshot
+    New,
+    // in the snapshot but no longer in the config; fetched anyway
+    NotQueried,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffEntry {
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub status: Status,
+    pub old_lamports: Option<u64>,
+    pub new_lamports: u64,
+    pub delta_lamports: i128,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Diff {
+    pub old_slot: u64,
+    pub old_ts: String,
+    pub new_slot: u64,
+    pub entries: Vec<DiffEntry>,
+    pub inflow_lamports: u64,
+    pub outflow_lamports: u64,
+}
+
+pub fn diff(
+    old: &SnapshotFile,
+    new_slot: u64,
+    lines: &[Line],
+    not_queried: &HashSet<Pubkey>,
+) -> Diff {
+    let previous: HashMap<&str, u64> = old
+        .balances
+        .iter()
+        .map(|saved| (saved.address.as_str(), saved.lamports))
+        .collect();
+
+    let mut entries = vec![];
+    let (mut inflow, mut outflow) = (0u64, 0u64);
+    for line in lines {
+        let address = line.address.to_string();
+        let old_lamports = previous.get(address.as_str()).copied();
+        let new_lamports = line.info.lamports;
+        let delta = new_lamports as i128 - old_lamports.unwrap_or_default() as i128;
+        if delta > 0 {
+            inflow += delta as u64;
+        } else {
+            outflow += delta.unsigned_abs() as u64;
+        }
+        let status = match old_lamports {
+            None => Status::New,
+            Some(_) if not_queried.contains(&line.address) => Status::NotQueried,
+            Some(_) if delta != 0 => Status::Changed,
+            Some(_) => Status::Unchanged,
+        };
+        entries.push(DiffEntry {
+            address,
+            label: line.label.clone(),
+            status,
+            old_lamports,
+            new_lamports,
+            delta_lamports: delta,
+        });
+    }
+
+    Diff {
+        old_slot: old.slot,
+        old_ts: old.ts.clone(),
+        new_slot,
+        entries,
+        inflow_lamports: inflow,
+        outflow_lamports: outflow,
+    }
+}
+
+pub fn print_diff(diff: &Diff) {
+    println!(
+        "Diff against snapshot from {} (slot {} → {})",
+        diff.old_ts, diff.old_slot, diff.new_slot
+    );
+    for entry in diff
+        .entries
+        .iter()
+        .filter(|entry| entry.status != Status::Unchanged)
+    {
+        let old = entry
+            .old_lamports
+            .map(sol_string)
+            .unwrap_or_else(|| "-".to_string());
+        let note = match entry.status {
+            Status::New => "  (new)",
+            Status::NotQueried => "  (not queried)",
+            _ => "",
+        };
+        println!(
+            "{:<44} {:>20} → {:>20} {:>21}{note}",
+            entry.address,
+            old,
+            sol_string(entry.new_lamports),
+            signed_sol(entry.delta_lamports)
+        );
+    }
+    let unchanged = diff
+        .entries
+        .iter()
+        .filter(|entry| entry.status == Status::Unchanged)
+        .count();
+    println!("\n{unchanged} unchanged");
+    println!("Inflow:  {} SOL", sol_string(diff.inflow_lamports));
+    println!("Outflow: {} SOL", sol_string(diff.outflow_lamports));
+}
@@ -0,0 +1,174 @@
+use serde::Serialize;
+use solana_sdk::{account::Account, clock::Epoch, stake::state::StakeStateV2};
+
+use super::output::sol_string;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StakeInfo {
+    pub state: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vote_account: Option<String>,
+    pub delegated_lamports: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activation_epoch: Option<Epoch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deactivation_epoch: Option<Epoch>,
+}
+
+// Ignores warmup/cooldown rate limiting, so a large delegation can still be
+// partly activating or deactivating an epoch after the one reported here.
+fn state_name(activation: Epoch, deactivation: Epoch, current: Epoch) -> &'static str {
+    if deactivation != Epoch::MAX {
+        if deactivation < current {
+            "inactive"
+        } else {
+            "deactivating"
+        }
+    } else if activation >= current {
+        "activating"
+    } else {
+        "active"
+    }
+}
+
+pub fn decode(account: &Account, current_epoch: Epoch) -> Option<StakeInfo> {
+    let info = match account.deserialize_data::<StakeStateV2>().ok()? {
+        StakeStateV2::Stake(_, stake, _) => {
+            let delegation = stake.delegation;
+            StakeInfo {
+                state: state_name(
+                    delegation.activation_epoch,
+                    delegation.deactivation_epoch,
+                    current_epoch,
+                ),
+                vote_account: Some(delegation.voter_pubkey.to_string()),
+                delegated_lamports: delegation.stake,
+                activation_epoch: Some(delegation.activation_epoch),
+                deactivation_epoch: Some(delegation.deactivation_epoch)
+                    .filter(|epoch| *epoch != Epoch::MAX),
+            }
+        }
+        StakeStateV2::Initialized(_) => StakeInfo {
+            state: "undelegated",
+            vote_account: None,
+            delegated_lamports: 0,
+            activation_epoch: None,
+            deactivation_epoch: None,
+        },
+        StakeStateV2::Uninitialized | StakeStateV2::RewardsPool => return None,
+    };
+    Some(info)
+}
+
+pub fn print(info: &StakeInfo) {
+    let mut line = format!(
+        "    stake: {} {} SOL",
+        info.state,
+        sol_string(info.delegated_lamports)
+    );
+    if let Some(vote_account) = &info.vote_account {
+        line += &format!(" to {vote_account}");
+    }
+    if let Some(epoch) = info.activation_epoch {
+        line += &format!(", activated epoch {epoch}");
+    }
+    if let Some(epoch) = info.deactivation_epoch {
+        line += &format!(", deactivated epoch {epoch}");
+    }
+    println!("{line}");
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{
+        pubkey::Pubkey,
+        stake::{
+            self,
+            stake_flags::StakeFlags,
+            state::{Delegation, Meta, Stake},
+        },
+    };
+
+    use super::*;
+
+    const CURRENT: Epoch = 10;
+
+    fn account(state: &StakeStateV2) -> Account {
+        Account::new_data(3_000_000_000, state, &stake::program::id()).unwrap()
+    }
+
+    fn delegated(voter: Pubkey, activation: Epoch, deactivation: Epoch) -> Account {
+        let mut delegation = Delegation::new(&voter, 2_000_000_000, activation);
+        delegation.deactivation_epoch = deactivation;
+        account(&StakeStateV2::Stake(
+            Meta::default(),
+            Stake {
+                delegation,
+                credits_observed: 0,
+            },
+            StakeFlags::empty(),
+        ))
+    }
+
+    #[test]
+    fn delegated_stake_reports_its_vote_account_and_epochs() {
+        let voter = Pubkey::new_unique();
+
+        let info = decode(&delegated(voter, 4, Epoch::MAX), CURRENT).unwrap();
+
+        assert_eq!(info.state, "active");
+        assert_eq!(info.vote_account, Some(voter.to_string()));
+        assert_eq!(info.delegated_lamports, 2_000_000_000);
+        assert_eq!(info.activation_epoch, Some(4));
+        assert_eq!(info.deactivation_epoch, None);
+    }
+
+    #[test]
+    fn the_state_follows_the_current_epoch() {
+        let voter = Pubkey::new_unique();
+        let state = |activation, deactivation| {
+            decode(&delegated(voter, activation, deactivation), CURRENT)
+                .unwrap()
+                .state
+        };
+
+        assert_eq!(state(CURRENT, Epoch::MAX), "activating");
+        assert_eq!(state(CURRENT - 1, Epoch::MAX), "active");
+        assert_eq!(state(4, CURRENT), "deactivating");
+        assert_eq!(state(4, CURRENT - 1), "inactive");
+    }
+
+    #[test]
+    fn deactivating_stake_reports_its_deactivation_epoch() {
+        let info = decode(&delegated(Pubkey::new_unique(), 4, CURRENT), CURRENT).unwrap();
+
+        assert_eq!(info.state, "deactivating");
+        assert_eq!(info.delegated_lamports, 2_000_000_000);
+        assert_eq!(info.deactivation_epoch, Some(CURRENT));
+    }
+
+    #[test]
+    fn initialized_stake_is_undelegated() {
+        let info = decode(
+            &account(&StakeStateV2::Initialized(Meta::default())),
+            CURRENT,
+        )
+        .unwrap();
+
+        assert_eq!(info.state, "undelegated");
+        assert_eq!(info.vote_account, None);
+        assert_eq!(info.delegated_lamports, 0);
+        assert_eq!(info.activation_epoch, None);
+    }
+
+    #[test]
+    fn uninitialized_or_garbled_data_is_not_stake() {
+        assert!(decode(&account(&StakeStateV2::Uninitialized), CURRENT).is_none());
+
+        let garbled = Account {
+            data: vec![9; 4],
+            ..account(&StakeStateV2::Uninitialized)
+        };
+        assert!(decode(&garbled, CURRENT).is_none());
+    }
+}
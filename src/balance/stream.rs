@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+use chrono::Utc;
+use solana_sdk::{commitment_config::CommitmentLevel, pubkey::Pubkey};
+use solana_test::{Error, geyser::subscribe};
+use yellowstone_grpc_proto::geyser::{
+    self, SubscribeRequest, SubscribeRequestFilterAccounts, subscribe_update::UpdateOneof,
+};
+
+use super::{
+    fetch::Fetcher,
+    watch::{delta, emit, lamports},
+};
+
+fn geyser_commitment(commitment: CommitmentLevel) -> geyser::CommitmentLevel {
+    match commitment {
+        CommitmentLevel::Processed => geyser::CommitmentLevel::Processed,
+        CommitmentLevel::Confirmed => geyser::CommitmentLevel::Confirmed,
+        CommitmentLevel::Finalized => geyser::CommitmentLevel::Finalized,
+    }
+}
+
+// Seeds balances from one RPC fetch, then follows geyser account updates
+// for the same addresses.
+pub async fn run(
+    fetcher: Fetcher,
+    endpoint: &str,
+    token: Option<String>,
+    addresses: Vec<Pubkey>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let snapshot = fetcher.get_snapshot(addresses.iter().copied()).await;
+    if let Some((address, err)) = snapshot.errors.first() {
+        bail!("initial fetch failed for {address}: {err}");
+    }
+    let seed_slot = snapshot.slot;
+    let initial = lamports(snapshot);
+    let mut current = initial.clone();
+    tracing::info!(
+        addresses = addresses.len(),
+        slot = seed_slot,
+        "streaming, Ctrl-C to stop"
+    );
+
+    let request = SubscribeRequest {
+        accounts: HashMap::from([(
+            "balances".to_owned(),
+            SubscribeRequestFilterAccounts {
+                account: addresses.iter().map(Pubkey::to_string).collect(),
+                ..Default::default()
+            },
+        )]),
+        commitment: Some(geyser_commitment(fetcher.client().commitment().commitment) as i32),
+        ..Default::default()
+    };
+
+    let handle = |update| {
+        let UpdateOneof::Account(update) = update else {
+            return Ok(());
+        };
+        let Some(account) = update.account else {
+            return Ok(());
+        };
+        let address = Pubkey::try_from(account.pubkey.as_slice())
+            .map_err(|_| Error::GrpcStream("malformed pubkey in an account update".to_string()))?;
+        // updates older than the RPC seed would move balances backwards
+        if update.slot < seed_slot {
+            return Ok(());
+        }
+        let Some(previous) = current.get_mut(&address) else {
+            return Ok(());
+        };
+        if *previous != account.lamports {
+            let ts = Utc::now().to_rfc3339();
+            if !json {
+                println!("[{ts}] slot {}", update.slot);
+            }
+            emit(
+                &delta(
+                    "delta",
+                    &ts,
+                    Some(update.slot),
+                    &address,
+                    *previous,
+                    account.lamports,
+                ),
+                json,
+            )?;
+            *previous = account.lamports;
+        }
+        Ok(())
+    };
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        result = subscribe(endpoint, token, request, handle) => result?,
+    }
+
+    let ts = Utc::now().to_rfc3339();
+    if !json {
+        println!("\n[{ts}] net change since start");
+    }
+    for address in &addresses {
+        emit(
+            &delta(
+                "total",
+                &ts,
+                None,
+                address,
+                initial[address],
+                current[address],
+            ),
+            json,
+        )?;
+    }
+    Ok(())
+}
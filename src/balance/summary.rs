@@ -0,0 +1,260 @@
+use serde::Serialize;
+
+use super::output::{Line, sol_string};
+
+pub const DEFAULT_TOP: usize = 5;
+
+#[derive(Debug, Serialize)]
+pub struct Top {
+    pub address: String,
+    pub lamports: u64,
+    pub sol: String,
+    // of the total across all lines
+    pub share_percent: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub count: usize,
+    pub zero: usize,
+    pub total_lamports: u64,
+    pub total_sol: String,
+    pub mean_sol: String,
+    pub median_sol: String,
+    // filled in when a price source is configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_usd: Option<String>,
+    // 0 when balances are equal, approaching 1 when one address holds it all
+    pub gini: f64,
+    pub top: Vec<Top>,
+}
+
+// Gini coefficient of balances sorted ascending; 0 for an empty or all-zero
+// list.
+pub fn gini(sorted: &[u64]) -> f64 {
+    let n = sorted.len() as f64;
+    let total: f64 = sorted.iter().map(|lamports| *lamports as f64).sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+    let weighted: f64 = sorted
+        .iter()
+        .enumerate()
+        .map(|(i, lamports)| (i + 1) as f64 * *lamports as f64)
+        .sum();
+    2.0 * weighted / (n * total) - (n + 1.0) / n
+}
+
+pub fn summarize(lines: &[Line], labels_only: bool, top: usize) -> Summary {
+    let mut lamports: Vec<u64> = lines.iter().map(|line| line.info.lamports).collect();
+    lamports.sort_unstable();
+
+    let total: u128 = lamports.iter().map(|lamports| *lamports as u128).sum();
+    let mean = total
+        .checked_div(lamports.len() as u128)
+        .unwrap_or_default();
+    let mid = lamports.len() / 2;
+    let median = match lamports.len() {
+        0 => 0,
+        len if len % 2 == 1 => lamports[mid],
+        _ => ((lamports[mid - 1] as u128 + lamports[mid] as u128) / 2) as u64,
+    };
+
+    let mut largest: Vec<&Line> = lines.iter().collect();
+    largest.sort_by_key(|line| std::cmp::Reverse(line.info.lamports));
+
+    Summary {
+        count: lines.len(),
+        zero: lamports.iter().filter(|lamports| **lamports == 0).count(),
+        total_lamports: total as u64,
+        total_sol: sol_string(total as u64),
+        mean_sol: sol_string(mean as u64),
+        median_sol: sol_string(median),
+        total_usd: None,
+        gini: gini(&lamports),
+        top: largest
+            .into_iter()
+            .take(top)
+            .map(|line| Top {
+                address: line.name(labels_only),
+                lamports: line.info.lamports,
+                sol: sol_string(line.info.lamports),
+                share_percent: if total == 0 {
+                    0.0
+                } else {
+                    line.info.lamports as f64 * 100.0 / total as f64
+                },
+            })
+            .collect(),
+    }
+}
+
+pub fn print(summary: &Summary) {
+    println!();
+    println!(
+        "Addresses: {} ({} with zero balance)",
+        summary.count, summary.zero
+    );
+    println!("Total:     {} SOL", summary.total_sol);
+    if let Some(usd) = &summary.total_usd {
+        println!("           {usd} USD");
+    }
+    println!("Mean:      {} SOL", summary.mean_sol);
+    println!("Median:    {} SOL", summary.median_sol);
+    println!("Gini:      {:.3}", summary.gini);
+    if !summary.top.is_empty() {
+        println!("Largest:");
+        for top in &summary.top {
+            println!(
+                "  {:<44} {:>20} {:>7.2}%",
+                top.address, top.sol, top.share_percent
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+
+    use super::super::{entry::Tracked, fetch::BalanceInfo};
+    use super::*;
+
+    // A line for an existing system account holding `lamports`.
+    pub(crate) fn line(label: Option<&str>, lamports: u64) -> Line {
+        let tracked = Tracked {
+            address: Pubkey::new_unique(),
+            label: label.map(str::to_string),
+            min_lamports: None,
+        };
+        let info = BalanceInfo {
+            lamports,
+            exists: true,
+            owner: Some(solana_sdk::system_program::ID),
+            executable: false,
+            data_len: 0,
+        };
+        Line::new(&tracked, info)
+    }
+
+    #[test]
+    fn no_lines_summarize_to_zero() {
+        let summary = summarize(&[], false, DEFAULT_TOP);
+
+        assert_eq!((summary.count, summary.zero), (0, 0));
+        assert_eq!(summary.total_lamports, 0);
+        assert_eq!(summary.total_sol, "0.000000000");
+        assert_eq!(summary.mean_sol, "0.000000000");
+        assert_eq!(summary.median_sol, "0.000000000");
+        assert_eq!(summary.gini, 0.0);
+        assert!(summary.top.is_empty());
+    }
+
+    #[test]
+    fn a_single_line_is_its_own_mean_median_and_top() {
+        let lines = [line(Some("alice"), 2 * LAMPORTS_PER_SOL)];
+
+        let summary = summarize(&lines, true, DEFAULT_TOP);
+
+        assert_eq!((summary.count, summary.zero), (1, 0));
+        assert_eq!(summary.total_lamports, 2 * LAMPORTS_PER_SOL);
+        assert_eq!(summary.total_sol, "2.000000000");
+        assert_eq!(summary.mean_sol, "2.000000000");
+        assert_eq!(summary.median_sol, "2.000000000");
+        assert_eq!(summary.gini, 0.0);
+        let [top] = &summary.top[..] else {
+            panic!("expected one top entry, got {:?}", summary.top);
+        };
+        assert_eq!(top.address, "alice");
+        assert_eq!(top.sol, "2.000000000");
+        assert_eq!(top.share_percent, 100.0);
+    }
+
+    #[test]
+    fn totals_mean_and_median_over_several_lines() {
+        let lines = [
+            line(None, 3 * LAMPORTS_PER_SOL),
+            line(None, 0),
+            line(None, 1),
+            line(None, 4 * LAMPORTS_PER_SOL),
+        ];
+
+        let summary = summarize(&lines, false, DEFAULT_TOP);
+
+        assert_eq!((summary.count, summary.zero), (4, 1));
+        assert_eq!(summary.total_lamports, 7 * LAMPORTS_PER_SOL + 1);
+        assert_eq!(summary.total_sol, "7.000000001");
+        // rounded down to the lamport
+        assert_eq!(summary.mean_sol, "1.750000000");
+        // halfway between the middle two
+        assert_eq!(summary.median_sol, "1.500000000");
+        assert!(summary.total_usd.is_none());
+    }
+
+    #[test]
+    fn an_odd_count_takes_the_middle_balance() {
+        let lines = [line(None, 5), line(None, 1), line(None, 9)];
+
+        let summary = summarize(&lines, false, DEFAULT_TOP);
+
+        assert_eq!(summary.median_sol, "0.000000005");
+    }
+
+    #[test]
+    fn gini_of_equal_or_empty_balances_is_zero() {
+        assert_eq!(gini(&[]), 0.0);
+        assert_eq!(gini(&[0, 0, 0]), 0.0);
+        assert_eq!(gini(&[7, 7, 7, 7]), 0.0);
+    }
+
+    #[test]
+    fn gini_grows_as_one_address_holds_more() {
+        // 1 - 1/n when one of n addresses holds everything
+        assert!((gini(&[0, 0, 0, 100]) - 0.75).abs() < 1e-12);
+        assert!((gini(&[0, 1, 3, 4]) - 0.4375).abs() < 1e-12);
+        assert!(gini(&[1, 2, 3, 4]) < gini(&[0, 1, 3, 4]));
+    }
+
+    #[test]
+    fn top_lists_the_largest_balances_with_their_shares() {
+        let lines = [
+            line(Some("small"), 10),
+            line(Some("large"), 60),
+            line(Some("medium"), 30),
+        ];
+
+        let summary = summarize(&lines, true, 2);
+
+        let top: Vec<_> = summary
+            .top
+            .iter()
+            .map(|top| (top.address.as_str(), top.lamports, top.share_percent))
+            .collect();
+        assert_eq!(top, [("large", 60, 60.0), ("medium", 30, 30.0)]);
+        // every line still counts towards the rest of the summary
+        assert_eq!(summary.count, 3);
+        assert_eq!(summarize(&lines, true, 0).top.len(), 0);
+        assert_eq!(summarize(&lines, true, 10).top.len(), 3);
+    }
+
+    #[test]
+    fn all_zero_balances_have_no_shares() {
+        let lines = [line(None, 0), line(None, 0)];
+
+        let summary = summarize(&lines, false, DEFAULT_TOP);
+
+        assert_eq!(summary.zero, 2);
+        assert_eq!(summary.gini, 0.0);
+        assert_eq!(summary.top.len(), 2);
+        assert!(summary.top.iter().all(|top| top.share_percent == 0.0));
+    }
+
+    #[test]
+    fn top_shows_addresses_unless_labels_only() {
+        let lines = [line(Some("alice"), 1)];
+
+        let summary = summarize(&lines, false, DEFAULT_TOP);
+
+        assert_eq!(summary.top[0].address, lines[0].address.to_string());
+    }
+}
@@ -0,0 +1,303 @@
+use std::io::{self, IsTerminal};
+
+use super::{
+    activity, nonce,
+    output::{Failure, Line, View, print_failures, sol_string},
+    stake, tokens, vault, vote,
+};
+
+const ADDRESS_WIDTH: usize = 44;
+// room for the whole SOL supply with separators
+const LAMPORTS_WIDTH: usize = 26;
+const SOL_WIDTH: usize = 22;
+const USD_WIDTH: usize = 16;
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+pub fn use_color(no_color: bool) -> bool {
+    !no_color && io::stdout().is_terminal()
+}
+
+// 1234567 -> "1,234,567"
+pub fn thousands(digits: &str) -> String {
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+fn sol_column(lamports: u64) -> String {
+    let sol = sol_string(lamports);
+    match sol.split_once('.') {
+        Some((whole, fraction)) => format!("{}.{fraction}", thousands(whole)),
+        None => thousands(&sol),
+    }
+}
+
+// Zero balances are dimmed; addresses with a minimum are green above it and
+// red below.
+fn paint(text: String, line: &Line, color: bool) -> String {
+    let code = match line.min_lamports {
+        _ if !color => return text,
+        _ if line.info.lamports == 0 => DIM,
+        Some(min) if line.info.lamports < min => RED,
+        Some(_) => GREEN,
+        None => return text,
+    };
+    format!("{code}{text}{RESET}")
+}
+
+fn details_columns(line: &Line) -> String {
+    match line.info.owner {
+        Some(owner) => format!(
+            " {:<44} {:>5} {:>10} {:>11}",
+            owner.to_string(),
+            if line.info.executable { "yes" } else { "no" },
+            line.info.data_len,
+            match line.rent_exempt {
+                Some(true) => "yes",
+                Some(false) => "no",
+                None => "?",
+            }
+        ),
+        None => format!(" {:<44}", "(no account)"),
+    }
+}
+
+// Columns are padded before coloring so escape codes don't break alignment.
+pub fn render_row(line: &Line, view: View) -> String {
+    let marker = if line.info.exists { " " } else { "*" };
+    let balances = format!(
+        "{:>LAMPORTS_WIDTH$} {:>SOL_WIDTH$}",
+        thousands(&line.info.lamports.to_string()),
+        sol_column(line.info.lamports)
+    );
+    let mut row = format!(
+        "{:<ADDRESS_WIDTH$} {}{marker}",
+        line.name(view.labels_only),
+        paint(balances, line, view.color)
+    );
+    if let Some(price) = view.price {
+        row += &format!(" {:>USD_WIDTH$}", price.usd(line.info.lamports));
+    }
+    if view.details {
+        row += &details_columns(line);
+    }
+    if let Some(label) = line.label.as_ref().filter(|_| !view.labels_only) {
+        row += &format!("  ({label})");
+    }
+    if let Some(min) = line.min_lamports.filter(|_| line.below_min()) {
+        row += &paint(format!("  BELOW MIN {}", sol_string(min)), line, view.color);
+    }
+    row.trim_end().to_string()
+}
+
+// the trailing space after `sol` lines up with the missing-account marker
+pub fn header(view: View) -> String {
+    let mut header = format!(
+        "{:<ADDRESS_WIDTH$} {:>LAMPORTS_WIDTH$} {:>SOL_WIDTH$} ",
+        "address", "lamports", "sol"
+    );
+    if view.price.is_some() {
+        header += &format!(" {:>USD_WIDTH$}", "usd");
+    }
+    if view.details {
+        header += &format!(
+            " {:<44} {:>5} {:>10} {:>11}",
+            "owner", "exec", "data_len", "rent_exempt"
+        );
+    }
+    header.trim_end().to_string()
+}
+
+pub fn print(lines: &[Line], failures: &[Failure], view: View) {
+    println!("{}", header(view));
+    for line in lines {
+        println!("{}", render_row(line, view));
+        if let Some(info) = &line.stake {
+            stake::print(info);
+        }
+        if let Some(info) = &line.nonce {
+            nonce::print(info);
+        }
+        if let Some(info) = &line.vault {
+            vault::print(info);
+        }
+        if let Some(info) = &line.vote {
+            vote::print(info);
+        }
+        if let Some(info) = &line.activity {
+            activity::print(info);
+        }
+        if let Some(holdings) = &line.holdings {
+            tokens::print_holdings(holdings);
+        }
+    }
+    if lines.iter().any(|line| !line.info.exists) {
+        println!("\n* no account");
+    }
+    print_failures(failures);
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, system_program};
+
+    use super::super::{entry::Tracked, fetch::BalanceInfo, price::Price};
+    use super::*;
+
+    fn line(label: Option<&str>, lamports: u64) -> Line {
+        let tracked = Tracked {
+            address: Pubkey::new_from_array([1; 32]),
+            label: label.map(str::to_string),
+            min_lamports: None,
+        };
+        let info = BalanceInfo {
+            lamports,
+            exists: true,
+            owner: Some(system_program::ID),
+            executable: false,
+            data_len: 0,
+        };
+        Line::new(&tracked, info)
+    }
+
+    fn missing() -> Line {
+        let mut line = line(None, 0);
+        line.info.exists = false;
+        line.info.owner = None;
+        line
+    }
+
+    fn with_min(lamports: u64, min_lamports: u64) -> Line {
+        Line {
+            min_lamports: Some(min_lamports),
+            ..line(None, lamports)
+        }
+    }
+
+    // The header and then a row per line.
+    fn render(lines: &[Line], view: View) -> Vec<String> {
+        let rows = lines.iter().map(|line| render_row(line, view));
+        [header(view)].into_iter().chain(rows).collect()
+    }
+
+    #[test]
+    fn thousands_groups_by_three() {
+        assert_eq!(thousands("0"), "0");
+        assert_eq!(thousands("999"), "999");
+        assert_eq!(thousands("1000"), "1,000");
+        assert_eq!(thousands("1234567"), "1,234,567");
+    }
+
+    #[test]
+    fn narrow_values_are_padded_to_the_columns() {
+        let view = View {
+            labels_only: true,
+            ..View::default()
+        };
+
+        assert_eq!(
+            render(&[line(Some("alice"), 1)], view),
+            [
+                "address                                                        lamports                    sol",
+                "alice                                                                 1            0.000000001",
+            ]
+        );
+    }
+
+    #[test]
+    fn the_widest_balance_fills_the_lamports_column() {
+        let row = render_row(&line(Some("treasury"), u64::MAX), View::default());
+
+        // the SOL column overflows by its separators and pushes the label out
+        assert_eq!(
+            row,
+            "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi  18,446,744,073,709,551,615 18,446,744,073.709551615   (treasury)"
+        );
+    }
+
+    #[test]
+    fn details_and_usd_add_columns() {
+        let view = View {
+            details: true,
+            price: Some(Price(Some(150.0))),
+            ..View::default()
+        };
+
+        assert_eq!(
+            render(&[line(None, 3 * LAMPORTS_PER_SOL / 2), missing()], view),
+            [
+                "address                                                        lamports                    sol               usd owner                                         exec   data_len rent_exempt",
+                "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi               1,500,000,000            1.500000000            225.00 11111111111111111111111111111111                no          0           ?",
+                "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi                           0            0.000000000*             0.00 (no account)",
+            ]
+        );
+    }
+
+    #[test]
+    fn a_missing_price_shows_as_na() {
+        let view = View {
+            price: Some(Price(None)),
+            ..View::default()
+        };
+
+        assert_eq!(
+            render_row(&line(None, 3 * LAMPORTS_PER_SOL / 2), view),
+            "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi               1,500,000,000            1.500000000               n/a"
+        );
+    }
+
+    // Padding counts chars, so wide characters take more columns on screen
+    // than the padding allows for.
+    #[test]
+    fn unicode_labels_are_padded_by_chars() {
+        let labels_only = View {
+            labels_only: true,
+            ..View::default()
+        };
+
+        assert_eq!(
+            render_row(&line(Some("日本語ラベル"), LAMPORTS_PER_SOL), labels_only),
+            "日本語ラベル                                                    1,000,000,000            1.000000000"
+        );
+        assert_eq!(
+            render_row(&line(Some("café ☕"), LAMPORTS_PER_SOL), View::default()),
+            "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi               1,000,000,000            1.000000000   (café ☕)"
+        );
+    }
+
+    #[test]
+    fn color_marks_balances_against_their_minimum() {
+        let view = View {
+            color: true,
+            ..View::default()
+        };
+        let rows: Vec<String> = [
+            with_min(LAMPORTS_PER_SOL, 2 * LAMPORTS_PER_SOL),
+            with_min(3 * LAMPORTS_PER_SOL, 2 * LAMPORTS_PER_SOL),
+            with_min(0, 2 * LAMPORTS_PER_SOL),
+            line(None, 5),
+        ]
+        .iter()
+        .map(|line| render_row(line, view))
+        .collect();
+
+        assert_eq!(
+            rows,
+            [
+                "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi  \x1b[31m             1,000,000,000            1.000000000\x1b[0m \x1b[31m  BELOW MIN 2.000000000\x1b[0m",
+                "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi  \x1b[32m             3,000,000,000            3.000000000\x1b[0m",
+                "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi  \x1b[2m                         0            0.000000000\x1b[0m \x1b[2m  BELOW MIN 2.000000000\x1b[0m",
+                "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi                           5            0.000000005",
+            ]
+        );
+    }
+}
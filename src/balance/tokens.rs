@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, bail};
+use serde::Serialize;
+use solana_client::rpc_request::TokenAccountsFilter;
+use solana_sdk::{pubkey, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+
+use super::fetch::Fetcher;
+
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+// Both token programs share the base mint and account layouts; token-2022
+// extensions are appended after them.
+const MINT_DECIMALS_OFFSET: usize = 44;
+const ACCOUNT_AMOUNT_OFFSET: usize = 64;
+
+#[derive(Debug, Clone, Copy)]
+pub struct MintInfo {
+    pub program: Pubkey,
+    pub decimals: u8,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBalance {
+    pub amount: u64,
+    pub decimals: u8,
+    pub exists: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Holding {
+    pub mint: String,
+    pub amount: u64,
+    pub decimals: u8,
+    pub ui_amount: String,
+}
+
+impl Holding {
+    // only used for ordering holdings of different mints
+    fn ui_value(&self) -> f64 {
+        self.amount as f64 / 10f64.powi(self.decimals as i32)
+    }
+}
+
+pub fn is_token_program(program: &Pubkey) -> bool {
+    *program == spl_token::id() || *program == TOKEN_2022_PROGRAM_ID
+}
+
+pub fn decode_amount(data: &[u8]) -> Option<u64> {
+    let bytes = data.get(ACCOUNT_AMOUNT_OFFSET..ACCOUNT_AMOUNT_OFFSET + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().ok()?))
+}
+
+pub fn decode_decimals(data: &[u8]) -> Option<u8> {
+    data.get(MINT_DECIMALS_OFFSET).copied()
+}
+
+pub fn format_ui_amount(amount: u64, decimals: u8) -> String {
+    if decimals == 0 {
+        return amount.to_string();
+    }
+    let scale = 10u128.pow(decimals as u32);
+    let amount = amount as u128;
+    format!(
+        "{}.{:0width$}",
+        amount / scale,
+        amount % scale,
+        width = decimals as usize
+    )
+}
+
+pub async fn get_mints(
+    fetcher: &Fetcher,
+    mints: &[Pubkey],
+) -> anyhow::Result<HashMap<Pubkey, MintInfo>> {
+    let accounts = fetcher.get_accounts(mints).await?;
+
+    let mut out = HashMap::new();
+    for (mint, account) in mints.iter().zip(accounts) {
+        let Some(account) = account else {
+            bail!("mint {mint} does not exist");
+        };
+        if !is_token_program(&account.owner) {
+            bail!("{mint} is not a token mint (owned by {})", account.owner);
+        }
+        let Some(decimals) = decode_decimals(&account.data) else {
+            bail!("mint {mint} has malformed data");
+        };
+        out.insert(
+            *mint,
+            MintInfo {
+                program: account.owner,
+                decimals,
+            },
+        );
+    }
+    Ok(out)
+}
+
+pub async fn get_token_balances(
+    fetcher: &Fetcher,
+    owners: &[Pubkey],
+    mints: &[Pubkey],
+) -> anyhow::Result<HashMap<(Pubkey, Pubkey), TokenBalance>> {
+    let mint_infos = get_mints(fetcher, mints).await?;
+
+    let mut keys = vec![];
+    let mut token_accounts = vec![];
+    for owner in owners {
+        for mint in mints {
+            let info = mint_infos[mint];
+            keys.push((*owner, *mint));
+            token_accounts.push(get_associated_token_address_with_program_id(
+                owner,
+                mint,
+                &info.program,
+            ));
+        }
+    }
+    let accounts = fetcher.get_accounts(&token_accounts).await?;
+
+    Ok(keys
+        .into_iter()
+        .zip(accounts)
+        .map(|((owner, mint), account)| {
+            let decimals = mint_infos[&mint].decimals;
+            let balance = match account.and_then(|account| decode_amount(&account.data)) {
+                Some(amount) => TokenBalance {
+                    amount,
+                    decimals,
+                    exists: true,
+                },
+                None => TokenBalance {
+                    amount: 0,
+                    decimals,
+                    exists: false,
+                },
+            };
+            ((owner, mint), balance)
+        })
+        .collect())
+}
+
+// Every non-zero holding across both token programs, largest first.
+pub async fn get_holdings(
+    fetcher: &Fetcher,
+    owner: &Pubkey,
+    top: Option<usize>,
+) -> anyhow::Result<Vec<Holding>> {
+    let mut holdings = vec![];
+    for program in [spl_token::id(), TOKEN_2022_PROGRAM_ID] {
+        let accounts = fetcher
+            .call(|client| async move {
+                client
+                    .get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(program))
+                    .await
+            })
+            .await?;
+        for keyed in accounts {
+            // jsonParsed: {"parsed": {"info": {"mint", "tokenAmount": {..}}}}
+            let data = serde_json::to_value(&keyed.account.data)?;
+            let info = &data["parsed"]["info"];
+            let amount = &info["tokenAmount"];
+            let raw: u64 = amount["amount"]
+                .as_str()
+                .and_then(|amount| amount.parse().ok())
+                .context("token account without an amount")?;
+            if raw == 0 {
+                continue;
+            }
+            let decimals = amount["decimals"].as_u64().unwrap_or_default() as u8;
+            holdings.push(Holding {
+                mint: info["mint"]
+                    .as_str()
+                    .context("token account without a mint")?
+                    .to_string(),
+                amount: raw,
+                decimals,
+                ui_amount: format_ui_amount(raw, decimals),
+            });
+        }
+    }
+
+    holdings.sort_by(|a, b| {
+        b.ui_value()
+            .total_cmp(&a.ui_value())
+            .then_with(|| a.mint.cmp(&b.mint))
+    });
+    if let Some(top) = top {
+        holdings.truncate(top);
+    }
+    Ok(holdings)
+}
+
+pub fn print_holdings(holdings: &[Holding]) {
+    for holding in holdings {
+        println!(
+            "    {:<44} {:>28} ({} decimals)",
+            holding.mint, holding.ui_amount, holding.decimals
+        );
+    }
+}
+
+pub fn print_table(
+    owners: &[Pubkey],
+    mints: &[Pubkey],
+    balances: &HashMap<(Pubkey, Pubkey), TokenBalance>,
+) {
+    const WIDTH: usize = 24;
+
+    let mut header = format!("{:<44}", "owner");
+    for mint in mints {
+        let mint = mint.to_string();
+        header += &format!(" {:>WIDTH$}", &mint[..8]);
+    }
+    println!("{header}");
+
+    let mut missing = false;
+    for owner in owners {
+        let mut row = format!("{owner:<44}");
+        for mint in mints {
+            let balance = balances[&(*owner, *mint)];
+            let mut cell = format_ui_amount(balance.amount, balance.decimals);
+            if !balance.exists {
+                cell.push('*');
+                missing = true;
+            }
+            row += &format!(" {cell:>WIDTH$}");
+        }
+        println!("{row}");
+    }
+    if missing {
+        println!("\n* no associated token account");
+    }
+}
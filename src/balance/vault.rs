@@ -0,0 +1,258 @@
+// Vaults of the deposit program (programs/deposit): a PDA at
+// ["vault", creator] holding an 8-byte Anchor discriminator and then either
+// just the owner (the legacy layout) or a version byte followed by the owner,
+// the deposit totals, the bump and the creator.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use serde::Serialize;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{account::Account, hash::hash, pubkey::Pubkey};
+use solana_test::vault_stats::{self, VaultStats};
+
+use super::{fetch::Fetcher, output::sol_string};
+
+const LEGACY_LEN: usize = 8 + 32;
+// after the version byte
+const OWNER_START: usize = 8 + 1;
+const TOTALS_START: usize = OWNER_START + 32;
+// after the two totals and the bump
+const CREATOR_START: usize = TOTALS_START + 8 + 8 + 1;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultInfo {
+    pub address: String,
+    pub exists: bool,
+    pub lamports: u64,
+    // lamports above the rent-exempt minimum
+    pub withdrawable: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stored_owner: Option<String>,
+    pub owner_matches: bool,
+}
+
+pub fn vault_address(program_id: &Pubkey, owner: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault", owner.as_ref()], program_id).0
+}
+
+fn discriminator() -> [u8; 8] {
+    let mut out = [0; 8];
+    out.copy_from_slice(&hash(b"account:Vault").to_bytes()[..8]);
+    out
+}
+
+pub struct VaultState {
+    pub owner: Pubkey,
+    // `None` for vaults still on the legacy layout
+    pub remaining_deposits: Option<u64>,
+    // the wallet the PDA is derived from; the owner until ownership moves
+    pub creator: Pubkey,
+}
+
+pub fn decode(data: &[u8]) -> Option<VaultState> {
+    if data.len() < LEGACY_LEN || data[..8] != discriminator() {
+        return None;
+    }
+    if data.len() == LEGACY_LEN {
+        let owner = Pubkey::try_from(&data[8..]).ok()?;
+        return Some(VaultState {
+            owner,
+            remaining_deposits: None,
+            creator: owner,
+        });
+    }
+    let read_pubkey = |offset: usize| Pubkey::try_from(data.get(offset..offset + 32)?).ok();
+    let read_u64 = |offset: usize| {
+        data.get(offset..offset + 8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+    };
+    let deposited = read_u64(TOTALS_START)?;
+    let withdrawn = read_u64(TOTALS_START + 8)?;
+    Some(VaultState {
+        owner: read_pubkey(OWNER_START)?,
+        remaining_deposits: Some(deposited.saturating_sub(withdrawn)),
+        creator: read_pubkey(CREATOR_START)?,
+    })
+}
+
+// What the program lets the owner take out: lamports above rent, capped at
+// the deposits not yet withdrawn.
+fn withdrawable(account: &Account, state: Option<&VaultState>, rent: &HashMap<usize, u64>) -> u64 {
+    let above_rent = account
+        .lamports
+        .saturating_sub(rent.get(&account.data.len()).copied().unwrap_or_default());
+    match state.and_then(|state| state.remaining_deposits) {
+        Some(remaining) => above_rent.min(remaining),
+        None => above_rent,
+    }
+}
+
+pub fn inspect(
+    address: Pubkey,
+    owner: &Pubkey,
+    account: Option<&Account>,
+    rent: &HashMap<usize, u64>,
+) -> VaultInfo {
+    let Some(account) = account else {
+        return VaultInfo {
+            address: address.to_string(),
+            exists: false,
+            lamports: 0,
+            withdrawable: 0,
+            stored_owner: None,
+            owner_matches: false,
+        };
+    };
+    let state = decode(&account.data);
+    VaultInfo {
+        address: address.to_string(),
+        exists: true,
+        lamports: account.lamports,
+        withdrawable: withdrawable(account, state.as_ref(), rent),
+        stored_owner: state.as_ref().map(|state| state.owner.to_string()),
+        owner_matches: state.is_some_and(|state| state.owner == *owner),
+    }
+}
+
+pub fn print(info: &VaultInfo) {
+    if !info.exists {
+        println!("    vault {}: none", info.address);
+        return;
+    }
+    let owner = match (&info.stored_owner, info.owner_matches) {
+        (Some(_), true) => "owner ok".to_string(),
+        (Some(owner), false) => format!("OWNER MISMATCH ({owner})"),
+        (None, _) => "UNDECODABLE".to_string(),
+    };
+    println!(
+        "    vault {}: {} SOL ({} withdrawable), {owner}",
+        info.address,
+        sol_string(info.lamports),
+        sol_string(info.withdrawable)
+    );
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScannedVault {
+    pub address: String,
+    pub owner: String,
+    pub lamports: u64,
+    pub withdrawable: u64,
+    // the address is the ["vault", creator] PDA
+    pub pda_matches: bool,
+    // `None` for vaults on a layout without them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<VaultStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Scan {
+    pub program_id: String,
+    pub vaults: Vec<ScannedVault>,
+    pub total_lamports: u64,
+    pub total_withdrawable: u64,
+}
+
+// Filters on the discriminator server-side. The owner sits at a different
+// offset in each layout, so `owner` is matched after decoding.
+pub async fn scan(
+    fetcher: &Fetcher,
+    program_id: Pubkey,
+    owner: Option<Pubkey>,
+) -> anyhow::Result<Scan> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            0,
+            discriminator().to_vec(),
+        ))]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+        // the client unwraps both the plain and the `withContext` response
+        with_context: Some(true),
+        ..RpcProgramAccountsConfig::default()
+    };
+    let accounts = fetcher
+        .call(|client| {
+            let config = config.clone();
+            async move {
+                client
+                    .get_program_accounts_with_config(&program_id, config)
+                    .await
+            }
+        })
+        .await
+        .context("getProgramAccounts failed; some providers disable it, try another --url")?;
+    let rent = fetcher
+        .rent_minimums(accounts.iter().map(|(_, account)| account.data.len()))
+        .await?;
+
+    let mut vaults: Vec<ScannedVault> = accounts
+        .into_iter()
+        .filter_map(|(address, account)| {
+            let state = decode(&account.data)?;
+            if owner.is_some_and(|owner| owner != state.owner) {
+                return None;
+            }
+            Some(ScannedVault {
+                address: address.to_string(),
+                owner: state.owner.to_string(),
+                lamports: account.lamports,
+                withdrawable: withdrawable(&account, Some(&state), &rent),
+                pda_matches: vault_address(&program_id, &state.creator) == address,
+                stats: vault_stats::decode_stats(&account.data),
+            })
+        })
+        .collect();
+    vaults.sort_by(|a, b| {
+        b.lamports
+            .cmp(&a.lamports)
+            .then_with(|| a.address.cmp(&b.address))
+    });
+    Ok(Scan {
+        program_id: program_id.to_string(),
+        total_lamports: vaults.iter().map(|vault| vault.lamports).sum(),
+        total_withdrawable: vaults.iter().map(|vault| vault.withdrawable).sum(),
+        vaults,
+    })
+}
+
+pub fn print_scan(scan: &Scan) {
+    println!(
+        "{:<44} {:<44} {:>20} {:>20} {:>9} {:>9}",
+        "vault", "owner", "sol", "withdrawable", "deposits", "withdraws"
+    );
+    for vault in &scan.vaults {
+        let marker = if vault.pda_matches {
+            ""
+        } else {
+            "  NOT THE CREATOR'S PDA"
+        };
+        let (deposits, withdraws) = match &vault.stats {
+            Some(stats) => (
+                stats.deposit_count.to_string(),
+                stats.withdraw_count.to_string(),
+            ),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        println!(
+            "{:<44} {:<44} {:>20} {:>20} {deposits:>9} {withdraws:>9}{marker}",
+            vault.address,
+            vault.owner,
+            sol_string(vault.lamports),
+            sol_string(vault.withdrawable)
+        );
+    }
+    println!(
+        "\n{} vaults, {} SOL total, {} SOL withdrawable",
+        scan.vaults.len(),
+        sol_string(scan.total_lamports),
+        sol_string(scan.total_withdrawable)
+    );
+}
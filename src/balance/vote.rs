@@ -0,0 +1,192 @@
+use serde::Serialize;
+use solana_client::rpc_response::RpcVoteAccountStatus;
+use solana_sdk::{account::Account, clock::Epoch, pubkey::Pubkey, vote::state::VoteState};
+
+use super::output::sol_string;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VoteInfo {
+    pub node: String,
+    pub commission: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_vote_slot: Option<u64>,
+    // earned so far in the current epoch
+    pub epoch_credits: u64,
+    pub activated_stake: u64,
+    // "current", "delinquent", or "unknown" when `getVoteAccounts` doesn't
+    // list it
+    pub status: &'static str,
+}
+
+pub fn decode(account: &Account, current_epoch: Epoch) -> Option<VoteInfo> {
+    let state = VoteState::deserialize(&account.data).ok()?;
+    let epoch_credits = state
+        .epoch_credits
+        .iter()
+        .find(|(epoch, _, _)| *epoch == current_epoch)
+        .map(|(_, credits, previous)| credits - previous)
+        .unwrap_or_default();
+    Some(VoteInfo {
+        node: state.node_pubkey.to_string(),
+        commission: state.commission,
+        last_vote_slot: state.last_voted_slot(),
+        epoch_credits,
+        activated_stake: 0,
+        status: "unknown",
+    })
+}
+
+pub fn apply_status(info: &mut VoteInfo, address: &Pubkey, status: &RpcVoteAccountStatus) {
+    let address = address.to_string();
+    let lists = [
+        ("current", &status.current),
+        ("delinquent", &status.delinquent),
+    ];
+    for (name, accounts) in lists {
+        if let Some(account) = accounts
+            .iter()
+            .find(|account| account.vote_pubkey == address)
+        {
+            info.activated_stake = account.activated_stake;
+            info.status = name;
+        }
+    }
+}
+
+pub fn print(info: &VoteInfo) {
+    let last_vote = info
+        .last_vote_slot
+        .map_or_else(|| "never".to_string(), |slot| slot.to_string());
+    println!(
+        "    vote: {} node {}, {}% commission, last vote {last_vote}, {} credits this epoch, {} SOL active stake",
+        info.status,
+        info.node,
+        info.commission,
+        info.epoch_credits,
+        sol_string(info.activated_stake)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_client::rpc_response::RpcVoteAccountInfo;
+    use solana_sdk::vote;
+
+    use super::*;
+
+    const CURRENT: Epoch = 10;
+
+    // A vote account in the current (version 2) layout, written out field by
+    // field so a change upstream shows up here rather than on mainnet.
+    fn fixture(node: &Pubkey, votes: &[u64], epoch_credits: &[(u64, u64, u64)]) -> Account {
+        let authority = Pubkey::new_from_array([7; 32]);
+        let mut data = vec![];
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(node.as_ref());
+        // authorized withdrawer
+        data.extend_from_slice(authority.as_ref());
+        // commission
+        data.push(5);
+        data.extend_from_slice(&(votes.len() as u64).to_le_bytes());
+        for (slot, confirmations) in votes.iter().zip((1..=votes.len() as u32).rev()) {
+            // latency, slot, confirmation count
+            data.push(0);
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&confirmations.to_le_bytes());
+        }
+        // no root slot
+        data.push(0);
+        // authorized voters: one, from epoch 0
+        data.extend_from_slice(&1u64.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(authority.as_ref());
+        // prior voters: an empty 32-entry ring buffer
+        data.extend_from_slice(&[0; 32 * 48]);
+        data.extend_from_slice(&31u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&(epoch_credits.len() as u64).to_le_bytes());
+        for (epoch, credits, previous) in epoch_credits {
+            data.extend_from_slice(&epoch.to_le_bytes());
+            data.extend_from_slice(&credits.to_le_bytes());
+            data.extend_from_slice(&previous.to_le_bytes());
+        }
+        // last timestamp: slot and unix time
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&0i64.to_le_bytes());
+        data.resize(VoteState::size_of(), 0);
+        Account {
+            lamports: 27_074_400,
+            data,
+            owner: vote::program::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn rpc_info(vote_pubkey: &Pubkey, activated_stake: u64) -> RpcVoteAccountInfo {
+        RpcVoteAccountInfo {
+            vote_pubkey: vote_pubkey.to_string(),
+            node_pubkey: Pubkey::new_unique().to_string(),
+            activated_stake,
+            commission: 5,
+            epoch_vote_account: true,
+            epoch_credits: vec![],
+            last_vote: 0,
+            root_slot: 0,
+        }
+    }
+
+    #[test]
+    fn decodes_the_vote_state_fixture() {
+        let node = Pubkey::new_unique();
+        let account = fixture(&node, &[100, 101, 102], &[(9, 400, 0), (CURRENT, 650, 400)]);
+
+        let info = decode(&account, CURRENT).unwrap();
+
+        assert_eq!(info.node, node.to_string());
+        assert_eq!(info.commission, 5);
+        assert_eq!(info.last_vote_slot, Some(102));
+        assert_eq!(info.epoch_credits, 250);
+        assert_eq!(info.status, "unknown");
+    }
+
+    #[test]
+    fn a_vote_account_that_never_voted() {
+        let account = fixture(&Pubkey::new_unique(), &[], &[]);
+
+        let info = decode(&account, CURRENT).unwrap();
+
+        assert_eq!(info.last_vote_slot, None);
+        assert_eq!(info.epoch_credits, 0);
+    }
+
+    #[test]
+    fn garbled_data_is_not_a_vote_account() {
+        let account = Account {
+            data: vec![9; 16],
+            ..fixture(&Pubkey::new_unique(), &[], &[])
+        };
+
+        assert!(decode(&account, CURRENT).is_none());
+    }
+
+    #[test]
+    fn status_comes_from_the_list_that_holds_the_account() {
+        let address = Pubkey::new_unique();
+        let account = fixture(&Pubkey::new_unique(), &[1], &[]);
+        let mut info = decode(&account, CURRENT).unwrap();
+
+        let status = RpcVoteAccountStatus {
+            current: vec![rpc_info(&Pubkey::new_unique(), 1)],
+            delinquent: vec![rpc_info(&address, 5_000_000_000)],
+        };
+        apply_status(&mut info, &address, &status);
+        assert_eq!(info.status, "delinquent");
+        assert_eq!(info.activated_stake, 5_000_000_000);
+
+        let mut info = decode(&account, CURRENT).unwrap();
+        apply_status(&mut info, &Pubkey::new_unique(), &status);
+        assert_eq!(info.status, "unknown");
+        assert_eq!(info.activated_stake, 0);
+    }
+}
@@ -0,0 +1,168 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::bail;
+use chrono::Utc;
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use tokio::time::{self, MissedTickBehavior};
+
+use super::{
+    alert::Alerter,
+    fetch::{Fetcher, Snapshot},
+    output::{signed_sol, sol_string},
+};
+
+#[derive(Debug, Serialize)]
+pub struct Delta {
+    event: &'static str,
+    ts: String,
+    slot: Option<u64>,
+    address: String,
+    old_lamports: u64,
+    new_lamports: u64,
+    delta_lamports: i128,
+    delta_sol: String,
+}
+
+pub fn emit(delta: &Delta, json: bool) -> anyhow::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(delta)?);
+    } else {
+        println!(
+            "  {:<44} {} → {} ({} SOL)",
+            delta.address,
+            sol_string(delta.old_lamports),
+            sol_string(delta.new_lamports),
+            delta.delta_sol
+        );
+    }
+    Ok(())
+}
+
+pub fn delta(
+    event: &'static str,
+    ts: &str,
+    slot: Option<u64>,
+    address: &Pubkey,
+    old: u64,
+    new: u64,
+) -> Delta {
+    let delta = new as i128 - old as i128;
+    Delta {
+        event,
+        ts: ts.to_string(),
+        slot,
+        address: address.to_string(),
+        old_lamports: old,
+        new_lamports: new,
+        delta_lamports: delta,
+        delta_sol: signed_sol(delta),
+    }
+}
+
+pub fn lamports(snapshot: Snapshot) -> HashMap<Pubkey, u64> {
+    snapshot
+        .balances
+        .into_iter()
+        .map(|(address, info)| (address, info.lamports))
+        .collect()
+}
+
+pub async fn run(
+    fetcher: Fetcher,
+    addresses: Vec<Pubkey>,
+    labels: HashMap<Pubkey, String>,
+    interval: Duration,
+    json: bool,
+    mut alerter: Option<Alerter>,
+) -> anyhow::Result<()> {
+    let snapshot = fetcher.get_snapshot(addresses.iter().copied()).await;
+    if let Some((address, err)) = snapshot.errors.first() {
+        bail!("initial fetch failed for {address}: {err}");
+    }
+    let initial = lamports(snapshot);
+    let mut previous = initial.clone();
+    tracing::info!(
+        addresses = addresses.len(),
+        interval_secs = interval.as_secs(),
+        "watching, Ctrl-C to stop"
+    );
+
+    let mut ticker = time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    ticker.tick().await;
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    loop {
+        let snapshot = tokio::select! {
+            _ = &mut ctrl_c => break,
+            refreshed = async {
+                ticker.tick().await;
+                fetcher.get_snapshot(addresses.iter().copied()).await
+            } => refreshed,
+        };
+        let ts = Utc::now().to_rfc3339();
+
+        // failed addresses keep their previous balance
+        for (address, err) in &snapshot.errors {
+            tracing::warn!(%address, error = %err, "refresh failed, keeping previous balance");
+        }
+        let slot = snapshot.slot;
+        let current = lamports(snapshot);
+
+        let changed: Vec<_> = addresses
+            .iter()
+            .filter(|address| {
+                current
+                    .get(address)
+                    .is_some_and(|lamports| *lamports != previous[address])
+            })
+            .collect();
+        if !json && !changed.is_empty() {
+            println!("[{ts}] slot {slot}");
+        }
+        for address in changed {
+            if let Some(alerter) = &mut alerter {
+                alerter.check(
+                    address,
+                    labels.get(address).map(String::as_str),
+                    previous[address],
+                    current[address],
+                    slot,
+                );
+            }
+            emit(
+                &delta(
+                    "delta",
+                    &ts,
+                    Some(slot),
+                    address,
+                    previous[address],
+                    current[address],
+                ),
+                json,
+            )?;
+        }
+        previous.extend(current);
+    }
+
+    let ts = Utc::now().to_rfc3339();
+    if !json {
+        println!("\n[{ts}] cumulative change since start");
+    }
+    for address in &addresses {
+        emit(
+            &delta(
+                "total",
+                &ts,
+                None,
+                address,
+                initial[address],
+                previous[address],
+            ),
+            json,
+        )?;
+    }
+    Ok(())
+}
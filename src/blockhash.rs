@@ -0,0 +1,221 @@
+// A recent blockhash kept fresh by a background task, so callers don't each
+// fetch their own. When refreshes fail the last one is still served, flagged
+// stale once it's older than `max_age`.
+
+use std::{
+    sync::{Arc, RwLock, Weak},
+    time::Duration,
+};
+
+use solana_sdk::hash::Hash;
+use tokio::{
+    sync::Mutex,
+    time::{self, Instant},
+};
+
+use crate::{error::Result, rpc::SolanaRpc};
+
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+// well inside the ~60s a blockhash stays valid for
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Blockhash {
+    pub hash: Hash,
+    pub last_valid_block_height: u64,
+    // since it was fetched
+    pub age: Duration,
+    // older than `max_age`: the refresher has been failing
+    pub stale: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Fetched {
+    hash: Hash,
+    last_valid_block_height: u64,
+    at: Instant,
+}
+
+pub struct BlockhashCache {
+    rpc: Arc<dyn SolanaRpc>,
+    max_age: Duration,
+    current: RwLock<Fetched>,
+    // one fetch at a time; readers keep getting `current` meanwhile
+    refreshing: Mutex<()>,
+}
+
+impl BlockhashCache {
+    // Fetches the first blockhash, then refreshes every `interval` until the
+    // cache is dropped.
+    pub async fn spawn(
+        rpc: Arc<dyn SolanaRpc>,
+        interval: Duration,
+        max_age: Duration,
+    ) -> Result<Arc<Self>> {
+        let first = fetch(rpc.as_ref()).await?;
+        let cache = Arc::new(Self {
+            rpc,
+            max_age,
+            current: RwLock::new(first),
+            refreshing: Mutex::new(()),
+        });
+        tokio::spawn(refresh_loop(Arc::downgrade(&cache), interval));
+        Ok(cache)
+    }
+
+    pub fn get(&self) -> Blockhash {
+        let fetched = *self.current.read().expect("blockhash lock poisoned");
+        let age = fetched.at.elapsed();
+        Blockhash {
+            hash: fetched.hash,
+            last_valid_block_height: fetched.last_valid_block_height,
+            age,
+            stale: age > self.max_age,
+        }
+    }
+
+    // Fetches a new blockhash now. Callers racing an ongoing refresh share
+    // its result instead of fetching again.
+    pub async fn force_refresh(&self) -> Result<Blockhash> {
+        let requested = Instant::now();
+        let _refreshing = self.refreshing.lock().await;
+        if self.current.read().expect("blockhash lock poisoned").at >= requested {
+            return Ok(self.get());
+        }
+        let fetched = fetch(self.rpc.as_ref()).await?;
+        *self.current.write().expect("blockhash lock poisoned") = fetched;
+        Ok(self.get())
+    }
+}
+
+async fn fetch(rpc: &dyn SolanaRpc) -> Result<Fetched> {
+    let (hash, last_valid_block_height) = rpc
+        .get_latest_blockhash_with_commitment(rpc.commitment())
+        .await?;
+    Ok(Fetched {
+        hash,
+        last_valid_block_height,
+        at: Instant::now(),
+    })
+}
+
+async fn refresh_loop(cache: Weak<BlockhashCache>, interval: Duration) {
+    let mut ticker = time::interval(interval);
+    ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    // the first tick is immediate, and `spawn` has just fetched
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let Some(cache) = cache.upgrade() else {
+            return;
+        };
+        if let Err(err) = cache.force_refresh().await {
+            let current = cache.get();
+            tracing::warn!(
+                error = %err,
+                age_secs = current.age.as_secs(),
+                stale = current.stale,
+                "blockhash refresh failed, serving the previous one"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::join_all;
+
+    use super::*;
+    use crate::rpc::mock::{Failure, MockRpc};
+
+    const FETCH: &str = "get_latest_blockhash_with_commitment";
+    const INTERVAL: Duration = Duration::from_secs(5);
+    const MAX_AGE: Duration = Duration::from_secs(30);
+
+    async fn cache(client: &Arc<MockRpc>) -> Arc<BlockhashCache> {
+        BlockhashCache::spawn(client.clone(), INTERVAL, MAX_AGE)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refreshes_every_interval() {
+        let client = Arc::new(MockRpc::new());
+        let cache = cache(&client).await;
+        let first = cache.get();
+        assert_eq!(first.age, Duration::ZERO);
+        assert!(!first.stale);
+
+        let next = client.advance_blockhash();
+        time::sleep(INTERVAL + Duration::from_millis(1)).await;
+
+        assert_eq!(cache.get().hash, next);
+        assert!(cache.get().age < INTERVAL);
+        assert_eq!(client.calls(FETCH), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_refreshes_serve_the_last_blockhash_flagged_stale() {
+        let client = Arc::new(MockRpc::new());
+        let cache = cache(&client).await;
+        let first = cache.get().hash;
+        // every refresh over the next 35 seconds
+        client.fail_times(FETCH, 7, Failure::Transport);
+        let next = client.advance_blockhash();
+
+        time::sleep(MAX_AGE - Duration::from_secs(1)).await;
+        assert_eq!(cache.get().hash, first);
+        assert!(!cache.get().stale);
+
+        time::sleep(Duration::from_secs(2)).await;
+        let stale = cache.get();
+        assert_eq!(stale.hash, first);
+        assert!(stale.stale);
+        assert!(stale.age > MAX_AGE);
+
+        time::sleep(Duration::from_secs(10)).await;
+        let recovered = cache.get();
+        assert_eq!(recovered.hash, next);
+        assert!(!recovered.stale);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn concurrent_refreshes_share_one_fetch() {
+        let client = Arc::new(MockRpc::new());
+        let cache = cache(&client).await;
+        let next = client.advance_blockhash();
+        // `force_refresh` shares fetches made since it was called
+        time::advance(Duration::from_millis(1)).await;
+
+        let refreshed = join_all((0..8).map(|_| cache.force_refresh())).await;
+
+        for blockhash in refreshed {
+            assert_eq!(blockhash.unwrap().hash, next);
+        }
+        assert_eq!(cache.get().hash, next);
+        assert_eq!(client.calls(FETCH), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_force_refresh_keeps_the_current_blockhash() {
+        let client = Arc::new(MockRpc::new());
+        let cache = cache(&client).await;
+        let first = cache.get().hash;
+        client.fail(FETCH, Failure::RateLimited);
+        client.advance_blockhash();
+        time::advance(Duration::from_millis(1)).await;
+
+        assert!(cache.force_refresh().await.is_err());
+        assert_eq!(cache.get().hash, first);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refresher_stops_with_the_cache() {
+        let client = Arc::new(MockRpc::new());
+        drop(cache(&client).await);
+
+        time::sleep(INTERVAL * 3).await;
+
+        assert_eq!(client.calls(FETCH), 1);
+    }
+}
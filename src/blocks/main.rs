@@ -1,17 +1,27 @@
-use std::{collections::HashMap, env, fs::File, io::BufReader, sync::Arc};
+use std::{collections::HashMap, process::ExitCode, sync::Arc};
 
-use futures::{SinkExt, StreamExt};
+use clap::Parser;
 use serde::Deserialize;
-use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
-    commitment_config::{CommitmentConfig, CommitmentLevel},
+    commitment_config::CommitmentLevel,
+    hash::Hash,
     native_token::LAMPORTS_PER_SOL,
-    signature::read_keypair_file,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
     signer::Signer,
     system_instruction,
     transaction::Transaction,
 };
-use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcBuilder};
+use solana_test::{
+    Error, addressbook,
+    blockhash::{self, BlockhashCache},
+    cli::CommonArgs,
+    error, geyser,
+    keypair::load_keypair,
+    logging,
+    rpc::{self, SolanaRpc},
+};
+use tokio::task::JoinHandle;
 use yellowstone_grpc_proto::geyser::{
     SubscribeRequest, SubscribeRequestFilterBlocksMeta, subscribe_update::UpdateOneof,
 };
@@ -21,34 +31,120 @@ struct Config {
     grpc_endpoint: String,
     grpc_token: String,
     sender_keypair: String,
+    // a pubkey or an address book `@name`
     recipient: String,
     sol: f64,
+    // devnet when unset
+    rpc_endpoint: Option<String>,
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let path = env::args().nth(1).expect("Usage: blocks <config.yaml>");
+// example filter condition
+fn triggers(slot: u64) -> bool {
+    slot % 10 == 5
+}
 
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let config: Config = serde_yaml::from_reader(reader)?;
+struct Transfer {
+    rpc: Arc<dyn SolanaRpc>,
+    blockhashes: Arc<BlockhashCache>,
+    sender: Keypair,
+    recipient: Pubkey,
+    lamports: u64,
+}
 
-    let rpc_client = RpcClient::new_with_commitment(
-        "https://api.devnet.solana.com".to_string(),
-        CommitmentConfig::confirmed(),
-    );
-    let rpc_client = Arc::new(rpc_client);
+impl Transfer {
+    async fn send(&self) -> solana_test::Result<Signature> {
+        // NOTE: can't use the block's blockhash since I'm sending tx on testnet :(
+        let mut recent = self.blockhashes.get();
+        if recent.stale {
+            tracing::warn!(
+                age_secs = recent.age.as_secs(),
+                "cached blockhash is stale, refetching"
+            );
+            recent = self.blockhashes.force_refresh().await?;
+        }
+        let tx = Transaction::new_signed_with_payer(
+            &[system_instruction::transfer(
+                &self.sender.pubkey(),
+                &self.recipient,
+                self.lamports,
+            )],
+            Some(&self.sender.pubkey()),
+            &[&self.sender],
+            recent.hash,
+        );
+        self.rpc
+            .send_and_confirm_transaction(&tx)
+            .await
+            .map_err(|err| Error::from_transaction(err, &tx.message))
+    }
+}
+
+// Starts a transfer for a block at a triggering slot and returns its task;
+// other updates are ignored.
+fn on_update(
+    transfer: &Arc<Transfer>,
+    update: UpdateOneof,
+) -> solana_test::Result<Option<JoinHandle<()>>> {
+    let UpdateOneof::BlockMeta(block) = update else {
+        return Ok(None);
+    };
+    if !triggers(block.slot) {
+        return Ok(None);
+    }
+    let blockhash: Hash = block
+        .blockhash
+        .parse()
+        .map_err(|_| Error::GrpcStream(format!("malformed blockhash {}", block.blockhash)))?;
+    let slot = block.slot;
+    tracing::info!(slot, %blockhash, "detected block, sending");
+    let transfer = Arc::clone(transfer);
+    Ok(Some(tokio::spawn(async move {
+        match transfer.send().await {
+            Ok(signature) => tracing::info!(
+                slot,
+                %blockhash,
+                %signature,
+                recipient = %addressbook::display(&transfer.recipient),
+                "transfer confirmed"
+            ),
+            Err(err) => tracing::warn!(
+                slot,
+                %blockhash,
+                error = %err,
+                kind = err.kind(),
+                "send failed"
+            ),
+        }
+    })))
+}
 
-    let mut grpc_client = GeyserGrpcBuilder::from_shared(config.grpc_endpoint)?
-        .x_token(Some(config.grpc_token))?
-        .tls_config(ClientTlsConfig::new().with_native_roots())?
-        .connect()
-        .await?;
+/// Sends a transfer on every tenth block seen over geyser
+#[derive(Parser)]
+#[command(name = "blocks", version)]
+struct Cli {
+    #[command(flatten)]
+    common: CommonArgs,
+}
 
-    let (mut sub, mut updates) = grpc_client.subscribe().await?;
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    logging::init(&cli.common.log_options());
+    error::exit(run(cli.common).await)
+}
+
+pub async fn run(common: CommonArgs) -> anyhow::Result<()> {
+    common.load_address_book()?;
+    let config: Config = common.load_config()?;
+
+    let rpc_client = rpc::connect(
+        &common.url(config.rpc_endpoint.as_deref(), rpc::DEVNET_URL),
+        common.commitment(None, CommitmentLevel::Confirmed),
+        rpc::DEFAULT_TIMEOUT,
+    );
 
     let commitment = CommitmentLevel::Processed;
-    sub.send(SubscribeRequest {
+    let request = SubscribeRequest {
         slots: HashMap::new(),
         accounts: HashMap::new(),
         transactions: HashMap::new(),
@@ -60,67 +156,129 @@ async fn main() -> anyhow::Result<()> {
         accounts_data_slice: vec![],
         ping: None,
         from_slot: None,
-    })
+    };
+
+    let blockhashes = BlockhashCache::spawn(
+        rpc_client.clone(),
+        blockhash::DEFAULT_REFRESH_INTERVAL,
+        blockhash::DEFAULT_MAX_AGE,
+    )
     .await?;
+    let transfer = Arc::new(Transfer {
+        rpc: rpc_client,
+        blockhashes,
+        sender: load_keypair(&config.sender_keypair)?,
+        recipient: addressbook::resolve(&config.recipient)?,
+        lamports: (config.sol * LAMPORTS_PER_SOL as f64).round() as u64,
+    });
 
-    let sender_keypair = read_keypair_file(config.sender_keypair)
-        .map_err(|err| anyhow::anyhow!("Can't read keypair file: {err}"))?;
-    let sender_keypair = Arc::new(sender_keypair);
-    let recipient = config.recipient.parse()?;
-    let lamports = (config.sol * LAMPORTS_PER_SOL as f64).round() as u64;
-    let transfer = |blockhash: solana_sdk::hash::Hash| {
-        let rpc_client = rpc_client.clone();
-        let sender_keypair = sender_keypair.clone();
-        async move {
-            // NOTE: can't use `blockhash` since I'm sending tx on testnet :(
-            let recent_blockhash = match rpc_client.get_latest_blockhash().await {
-                Ok(hash) => hash,
-                Err(err) => {
-                    eprintln!("get_latest_blockhash error: {err}");
-                    return;
-                }
-            };
-
-            let tx = Transaction::new_signed_with_payer(
-                &[system_instruction::transfer(
-                    &sender_keypair.pubkey(),
-                    &recipient,
-                    lamports,
-                )],
-                Some(&sender_keypair.pubkey()),
-                &[&sender_keypair],
-                recent_blockhash,
-            );
-            let res = rpc_client.send_and_confirm_transaction(&tx).await;
-            match res {
-                Ok(signature) => {
-                    println!("send success for block {blockhash}: tx {signature}");
-                }
-                Err(err) => {
-                    eprintln!("send error for block {blockhash}: {err}");
-                }
-            }
-        }
-    };
+    geyser::subscribe(
+        &config.grpc_endpoint,
+        Some(config.grpc_token),
+        request,
+        |update| {
+            on_update(&transfer, update)?;
+            Ok(())
+        },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_test::rpc::mock::{Failure, MockRpc};
+    use yellowstone_grpc_proto::geyser::{SubscribeUpdateBlockMeta, SubscribeUpdateSlot};
+
+    use super::*;
+
+    async fn transfer(client: &Arc<MockRpc>) -> Arc<Transfer> {
+        let blockhashes = BlockhashCache::spawn(
+            client.clone(),
+            blockhash::DEFAULT_REFRESH_INTERVAL,
+            blockhash::DEFAULT_MAX_AGE,
+        )
+        .await
+        .unwrap();
+        Arc::new(Transfer {
+            rpc: client.clone(),
+            blockhashes,
+            sender: Keypair::new(),
+            recipient: Pubkey::new_unique(),
+            lamports: 1000,
+        })
+    }
+
+    fn block(slot: u64) -> UpdateOneof {
+        UpdateOneof::BlockMeta(SubscribeUpdateBlockMeta {
+            slot,
+            blockhash: Hash::new_unique().to_string(),
+            ..SubscribeUpdateBlockMeta::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn triggering_block_sends_the_transfer() {
+        let client = Arc::new(MockRpc::new());
+        let transfer = transfer(&client).await;
 
-    while let Some(update) = updates.next().await {
-        match update {
-            Ok(msg) => {
-                if let Some(UpdateOneof::BlockMeta(block)) = msg.update_oneof {
-                    // example filter condition
-                    if block.slot % 10 == 5 {
-                        let blockhash = block.blockhash.parse()?;
-                        println!("detected block {blockhash}, sending...");
-                        tokio::spawn(transfer(blockhash));
-                    }
-                }
-            }
-            Err(error) => {
-                eprintln!("stream error: {error:?}");
-                break;
-            }
+        let task = on_update(&transfer, block(1235)).unwrap().unwrap();
+        task.await.unwrap();
+
+        let sent = client.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].message.account_keys[1], transfer.recipient);
+    }
+
+    #[tokio::test]
+    async fn other_blocks_and_updates_send_nothing() {
+        let client = Arc::new(MockRpc::new());
+        let transfer = transfer(&client).await;
+
+        for slot in [1230, 1234, 1236] {
+            assert!(on_update(&transfer, block(slot)).unwrap().is_none());
         }
+        let update = UpdateOneof::Slot(SubscribeUpdateSlot {
+            slot: 1235,
+            ..SubscribeUpdateSlot::default()
+        });
+        assert!(on_update(&transfer, update).unwrap().is_none());
+        assert_eq!(client.calls("send_and_confirm_transaction"), 0);
     }
 
-    Ok(())
+    #[tokio::test]
+    async fn malformed_blockhash_fails_the_stream() {
+        let client = Arc::new(MockRpc::new());
+        let transfer = transfer(&client).await;
+        let update = UpdateOneof::BlockMeta(SubscribeUpdateBlockMeta {
+            slot: 1235,
+            blockhash: "not a hash".to_string(),
+            ..SubscribeUpdateBlockMeta::default()
+        });
+
+        let result = on_update(&transfer, update);
+
+        assert!(matches!(result, Err(Error::GrpcStream(_))));
+        assert!(client.sent().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_send_keeps_the_stream_going() {
+        let client = Arc::new(MockRpc::new());
+        let transfer = transfer(&client).await;
+        client.fail("send_and_confirm_transaction", Failure::Transport);
+
+        on_update(&transfer, block(1235))
+            .unwrap()
+            .unwrap()
+            .await
+            .unwrap();
+        on_update(&transfer, block(1245))
+            .unwrap()
+            .unwrap()
+            .await
+            .unwrap();
+
+        assert_eq!(client.calls("send_and_confirm_transaction"), 2);
+        assert_eq!(client.sent().len(), 1);
+    }
 }
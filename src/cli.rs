@@ -0,0 +1,186 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use serde::de::DeserializeOwned;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+
+use crate::{
+    addressbook,
+    config::{ConfigFormat, load_config_as},
+    logging::LogOptions,
+    rpc::resolve_url,
+};
+
+// Flags every tool takes, before or after the subcommand. Where they overlap
+// with a config file, the flag wins, then the config, then the tool's
+// default.
+#[derive(Debug, Clone, Default, clap::Args)]
+pub struct CommonArgs {
+    /// Config file
+    #[arg(long, short, global = true, value_name = "PATH")]
+    pub config: Option<String>,
+    /// Config file format, when its extension doesn't say; YAML otherwise
+    #[arg(long, global = true, value_enum)]
+    pub config_format: Option<ConfigFormat>,
+    /// RPC endpoint, or mainnet, devnet, testnet or localhost
+    #[arg(long, short, global = true)]
+    pub url: Option<String>,
+    /// Commitment level for RPC requests
+    #[arg(long, global = true, value_parser = parse_commitment)]
+    pub commitment: Option<CommitmentLevel>,
+    /// More detailed output
+    #[arg(long, short, global = true)]
+    pub verbose: bool,
+    /// Log to stderr as JSON lines; `RUST_LOG` picks what's logged
+    #[arg(long, global = true)]
+    pub log_json: bool,
+    /// Also append debug logs to this file, as JSON
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+    /// Names for pubkeys, usable as `@name` in configs; defaults to
+    /// ~/.config/solana-tools/addressbook.yaml
+    #[arg(long, global = true, value_name = "PATH")]
+    pub address_book: Option<PathBuf>,
+}
+
+impl CommonArgs {
+    // For tools that can't run without a config file.
+    pub fn config_path(&self) -> anyhow::Result<&str> {
+        self.config.as_deref().context("--config is required")
+    }
+
+    pub fn load_config<T: DeserializeOwned>(&self) -> anyhow::Result<T> {
+        Ok(load_config_as(self.config_path()?, self.config_format)?)
+    }
+
+    // Before resolving any pubkeys from the config; see `addressbook::init`.
+    pub fn load_address_book(&self) -> crate::Result<()> {
+        addressbook::init(self.address_book.as_deref())
+    }
+
+    pub fn url(&self, config: Option<&str>, default: &str) -> String {
+        resolve_url(self.url.as_deref().or(config).unwrap_or(default)).to_string()
+    }
+
+    pub fn commitment(
+        &self,
+        config: Option<CommitmentLevel>,
+        default: CommitmentLevel,
+    ) -> CommitmentConfig {
+        CommitmentConfig {
+            commitment: self.commitment.or(config).unwrap_or(default),
+        }
+    }
+
+    pub fn log_options(&self) -> LogOptions {
+        LogOptions {
+            filter: None,
+            json: self.log_json,
+            file: self.log_file.clone(),
+        }
+    }
+}
+
+pub fn parse_commitment(value: &str) -> anyhow::Result<CommitmentLevel> {
+    match value {
+        "processed" => Ok(CommitmentLevel::Processed),
+        "confirmed" => Ok(CommitmentLevel::Confirmed),
+        "finalized" => Ok(CommitmentLevel::Finalized),
+        _ => anyhow::bail!(
+            "unknown commitment `{value}`, expected processed, confirmed or finalized"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        common: CommonArgs,
+        #[command(subcommand)]
+        command: Command,
+    }
+
+    #[derive(clap::Subcommand)]
+    enum Command {
+        Run,
+    }
+
+    fn parse(args: &[&str]) -> CommonArgs {
+        Cli::try_parse_from([&["tool"], args].concat())
+            .unwrap()
+            .common
+    }
+
+    #[test]
+    fn flags_go_before_or_after_the_subcommand() {
+        for args in [
+            &["--url", "devnet", "-c", "send.yaml", "--verbose", "run"][..],
+            &["run", "--url", "devnet", "-c", "send.yaml", "--verbose"],
+        ] {
+            let common = parse(args);
+            assert_eq!(common.url.as_deref(), Some("devnet"));
+            assert_eq!(common.config.as_deref(), Some("send.yaml"));
+            assert!(common.verbose);
+        }
+    }
+
+    #[test]
+    fn config_format_and_commitment_parse() {
+        let common = parse(&[
+            "run",
+            "--config-format",
+            "toml",
+            "--commitment",
+            "finalized",
+        ]);
+        assert_eq!(common.config_format, Some(ConfigFormat::Toml));
+        assert_eq!(common.commitment, Some(CommitmentLevel::Finalized));
+        assert!(Cli::try_parse_from(["tool", "run", "--commitment", "final"]).is_err());
+    }
+
+    #[test]
+    fn url_flag_beats_config_beats_default() {
+        let flag = parse(&["run", "--url", "localhost"]);
+        let none = parse(&["run"]);
+        assert_eq!(
+            flag.url(Some("https://rpc.example.com"), "devnet"),
+            "http://127.0.0.1:8899"
+        );
+        assert_eq!(
+            none.url(Some("https://rpc.example.com"), "devnet"),
+            "https://rpc.example.com"
+        );
+        assert_eq!(none.url(None, "devnet"), crate::rpc::DEVNET_URL);
+    }
+
+    #[test]
+    fn commitment_flag_beats_config_beats_default() {
+        let flag = parse(&["run", "--commitment", "processed"]);
+        let none = parse(&["run"]);
+        let config = Some(CommitmentLevel::Finalized);
+        assert_eq!(
+            flag.commitment(config, CommitmentLevel::Confirmed),
+            CommitmentConfig::processed()
+        );
+        assert_eq!(
+            none.commitment(config, CommitmentLevel::Confirmed),
+            CommitmentConfig::finalized()
+        );
+        assert_eq!(
+            none.commitment(None, CommitmentLevel::Confirmed),
+            CommitmentConfig::confirmed()
+        );
+    }
+
+    #[test]
+    fn config_path_is_required_when_asked_for() {
+        let error = parse(&["run"]).config_path().unwrap_err();
+        assert_eq!(error.to_string(), "--config is required");
+    }
+}
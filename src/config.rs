@@ -0,0 +1,235 @@
+use std::{fs, path::Path};
+
+use serde::de::DeserializeOwned;
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ConfigFormat {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl ConfigFormat {
+    // YAML unless the extension says otherwise, as configs used to be YAML only.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Self::Json,
+            Some("toml") => Self::Toml,
+            _ => Self::Yaml,
+        }
+    }
+}
+
+// Reads a config file into any of the binaries' config types, in the format
+// its extension names.
+pub fn load_config<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T> {
+    load_config_as(path, None)
+}
+
+// Like `load_config`, with `format` overriding the extension.
+pub fn load_config_as<T: DeserializeOwned>(
+    path: impl AsRef<Path>,
+    format: Option<ConfigFormat>,
+) -> Result<T> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|source| Error::ConfigOpen {
+        path: path.display().to_string(),
+        source,
+    })?;
+    let format = format.unwrap_or_else(|| ConfigFormat::from_path(path));
+    parse(&text, format).map_err(|(key, source)| Error::ConfigParse {
+        path: path.display().to_string(),
+        key,
+        source,
+    })
+}
+
+type ParseError = (String, Box<dyn std::error::Error + Send + Sync>);
+
+// On failure, the path of the offending key and what was wrong with it.
+fn parse<T: DeserializeOwned>(text: &str, format: ConfigFormat) -> Result<T, ParseError> {
+    fn split<E: std::error::Error + Send + Sync + 'static>(
+        err: serde_path_to_error::Error<E>,
+    ) -> ParseError {
+        (err.path().to_string(), Box::new(err.into_inner()))
+    }
+
+    match format {
+        ConfigFormat::Yaml => {
+            serde_path_to_error::deserialize(serde_yaml::Deserializer::from_str(text))
+                .map_err(split)
+        }
+        ConfigFormat::Json => {
+            let mut deserializer = serde_json::Deserializer::from_str(text);
+            let value = serde_path_to_error::deserialize(&mut deserializer).map_err(split)?;
+            deserializer
+                .end()
+                .map_err(|err| (".".to_string(), Box::new(err) as _))?;
+            Ok(value)
+        }
+        ConfigFormat::Toml => {
+            serde_path_to_error::deserialize(toml::Deserializer::new(text)).map_err(split)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::PathBuf};
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config {
+        url: String,
+        pairs: Vec<Pair>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Pair {
+        recipient: String,
+        lamports: u64,
+    }
+
+    // A file in the temp dir, removed when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &str) -> Self {
+            let path = env::temp_dir().join(format!("config-test-{}-{name}", std::process::id()));
+            fs::write(&path, contents).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn expected() -> Config {
+        Config {
+            url: "devnet".to_string(),
+            pairs: vec![Pair {
+                recipient: "D6U1xDjZK15v25mx7z8Nr23EcoZGZvqs3BpWL9E4zyst".to_string(),
+                lamports: 1000,
+            }],
+        }
+    }
+
+    const YAML: &str = "
+url: devnet
+pairs:
+  - recipient: D6U1xDjZK15v25mx7z8Nr23EcoZGZvqs3BpWL9E4zyst
+    lamports: 1000
+";
+
+    const JSON: &str = r#"{
+  "url": "devnet",
+  "pairs": [
+    { "recipient": "D6U1xDjZK15v25mx7z8Nr23EcoZGZvqs3BpWL9E4zyst", "lamports": 1000 }
+  ]
+}"#;
+
+    const TOML: &str = r#"
+url = "devnet"
+
+[[pairs]]
+recipient = "D6U1xDjZK15v25mx7z8Nr23EcoZGZvqs3BpWL9E4zyst"
+lamports = 1000
+"#;
+
+    // The key path `load_config` reports for `text`.
+    fn failing_key(name: &str, text: &str) -> String {
+        let file = TempFile::new(name, text);
+        match load_config::<Config>(&file.0) {
+            Err(Error::ConfigParse { key, .. }) => key,
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn format_follows_the_extension() {
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.json")),
+            ConfigFormat::Json
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.toml")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.yml")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("a.yaml")),
+            ConfigFormat::Yaml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("config")),
+            ConfigFormat::Yaml
+        );
+    }
+
+    #[test]
+    fn every_format_loads_the_same_config() {
+        for (name, text) in [
+            ("same.yaml", YAML),
+            ("same.json", JSON),
+            ("same.toml", TOML),
+        ] {
+            let file = TempFile::new(name, text);
+            assert_eq!(
+                load_config::<Config>(&file.0).unwrap(),
+                expected(),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn explicit_format_overrides_the_extension() {
+        let file = TempFile::new("override.conf", TOML);
+        assert!(load_config::<Config>(&file.0).is_err());
+        let config = load_config_as::<Config>(&file.0, Some(ConfigFormat::Toml)).unwrap();
+        assert_eq!(config, expected());
+    }
+
+    #[test]
+    fn parse_errors_name_the_key() {
+        let yaml = YAML.replace("lamports: 1000", "lamports: lots");
+        let json = JSON.replace("1000", "\"lots\"");
+        let toml = TOML.replace("lamports = 1000", "lamports = \"lots\"");
+        assert_eq!(failing_key("key.yaml", &yaml), "pairs[0].lamports");
+        assert_eq!(failing_key("key.json", &json), "pairs[0].lamports");
+        assert_eq!(failing_key("key.toml", &toml), "pairs[0].lamports");
+    }
+
+    #[test]
+    fn trailing_json_is_an_error() {
+        assert_eq!(failing_key("trailing.json", &format!("{JSON} {{}}")), ".");
+    }
+
+    #[test]
+    fn loads_a_config_file() {
+        let file = TempFile::new("load.yaml", YAML);
+        assert_eq!(load_config::<Config>(&file.0).unwrap(), expected());
+    }
+
+    #[test]
+    fn missing_file_names_the_path() {
+        let path = env::temp_dir().join("config-test-missing.yaml");
+        match load_config::<Config>(&path) {
+            Err(Error::ConfigOpen { path: reported, .. }) => {
+                assert_eq!(reported, path.display().to_string());
+            }
+            other => panic!("expected a config open error, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use std::{io, process::ExitCode};
+
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::RpcError,
+};
+use solana_sdk::{
+    instruction::InstructionError, message::Message, packet::PACKET_DATA_SIZE, signer::SignerError,
+    system_instruction::SystemError, system_program, transaction::TransactionError,
+};
+
+// Exit codes from sysexits.h, so scripts can tell a broken setup from an
+// unreachable cluster, 3 for a send stopped by too many failures or a balance
+// run with addresses it couldn't fetch, and 2 for balances below their
+// minimum. Anything else exits with 1.
+const EX_UNAVAILABLE: u8 = 69;
+const EX_CONFIG: u8 = 78;
+const EX_ABORTED: u8 = 3;
+const EX_PARTIAL: u8 = 3;
+const EX_BELOW_MINIMUM: u8 = 2;
+
+// node hasn't reached the requested `min_context_slot` yet
+const MIN_CONTEXT_SLOT_NOT_REACHED: i64 = -32016;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("can't open {path}")]
+    ConfigOpen {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    // `key` is the path to the offending field, e.g. `pairs[2].lamports`
+    #[error("can't parse {path} at `{key}`")]
+    ConfigParse {
+        path: String,
+        key: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    // `spec` never holds a secret; see `keypair::load_keypair`
+    #[error("can't read keypair {spec}: {reason}")]
+    KeypairLoad { spec: String, reason: String },
+    #[error("[{index}] invalid pubkey `{value}`")]
+    InvalidPubkey { index: usize, value: String },
+    #[error("invalid pubkey `{0}`")]
+    InvalidAddress(String),
+    // an `@name` the address book doesn't have
+    #[error("unknown address book name `@{0}`")]
+    UnknownAddress(String),
+    #[error("address book: {0}")]
+    AddressBook(String),
+    // what `--strict` refuses; each failure is printed before this
+    #[error("{invalid} invalid and {duplicates} duplicate address(es)")]
+    InvalidAddresses { invalid: usize, duplicates: usize },
+    // the request didn't get an answer: connection errors and timeouts
+    #[error("RPC transport error: {0}")]
+    RpcTransport(#[source] Box<ClientError>),
+    #[error("rate limited by the RPC endpoint: {0}")]
+    RpcRateLimited(#[source] Box<ClientError>),
+    // the node answered from an older slot than asked for
+    #[error("RPC node is behind: {0}")]
+    RpcBehind(String),
+    #[error("blockhash expired before the transaction landed")]
+    BlockhashExpired,
+    #[error("insufficient funds: {0}")]
+    InsufficientFunds(#[source] Box<ClientError>),
+    // rejected in preflight or failed on chain
+    #[error("transaction failed: {0}")]
+    TransactionFailed(#[source] Box<ClientError>),
+    #[error(
+        "transaction is {size} bytes, {over} over the {limit} byte limit; drop extra signers or instructions",
+        limit = PACKET_DATA_SIZE
+    )]
+    TransactionTooLarge { size: usize, over: usize },
+    #[error("signing failed: {0}")]
+    Signing(#[from] SignerError),
+    #[error("geyser stream: {0}")]
+    GrpcStream(String),
+    #[error("aborted")]
+    Aborted,
+    // the run finished, but left some addresses out
+    #[error("some addresses failed")]
+    PartialFailure,
+    #[error("addresses below their minimum balance")]
+    BelowMinimum,
+    // any other RPC error
+    #[error(transparent)]
+    Rpc(Box<ClientError>),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl Error {
+    // Short stable name for reports.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ConfigOpen { .. } => "config_open",
+            Self::ConfigParse { .. } => "config_parse",
+            Self::KeypairLoad { .. } => "keypair_load",
+            Self::InvalidPubkey { .. } => "invalid_pubkey",
+            Self::InvalidAddress(_) => "invalid_address",
+            Self::UnknownAddress(_) => "unknown_address",
+            Self::AddressBook(_) => "address_book",
+            Self::InvalidAddresses { .. } => "invalid_addresses",
+            Self::RpcTransport(_) => "rpc_transport",
+            Self::RpcRateLimited(_) => "rpc_rate_limited",
+            Self::RpcBehind(_) => "rpc_behind",
+            Self::BlockhashExpired => "blockhash_expired",
+            Self::InsufficientFunds(_) => "insufficient_funds",
+            Self::TransactionFailed(_) => "transaction_failed",
+            Self::TransactionTooLarge { .. } => "transaction_too_large",
+            Self::Signing(_) => "signing",
+            Self::GrpcStream(_) => "grpc_stream",
+            Self::Aborted => "aborted",
+            Self::PartialFailure => "partial_failure",
+            Self::BelowMinimum => "below_minimum",
+            Self::Rpc(_) => "rpc",
+        }
+    }
+
+    // Worth retrying the same request.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::RpcTransport(_) | Self::RpcRateLimited(_) | Self::RpcBehind(_)
+        )
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::ConfigOpen { .. }
+            | Self::ConfigParse { .. }
+            | Self::KeypairLoad { .. }
+            | Self::InvalidPubkey { .. }
+            | Self::InvalidAddress(_)
+            | Self::UnknownAddress(_)
+            | Self::AddressBook(_)
+            | Self::InvalidAddresses { .. } => EX_CONFIG,
+            Self::RpcTransport(_) | Self::RpcRateLimited(_) | Self::GrpcStream(_) => EX_UNAVAILABLE,
+            Self::Aborted => EX_ABORTED,
+            Self::PartialFailure => EX_PARTIAL,
+            Self::BelowMinimum => EX_BELOW_MINIMUM,
+            _ => 1,
+        }
+    }
+}
+
+// `message`, when known, is the transaction that failed.
+fn is_insufficient_funds(err: &TransactionError, message: Option<&Message>) -> bool {
+    match err {
+        TransactionError::InsufficientFundsForFee
+        | TransactionError::InsufficientFundsForRent { .. }
+        | TransactionError::InstructionError(_, InstructionError::InsufficientFunds) => true,
+        // the system program's ResultWithNegativeLamports, which SPL Token's
+        // InsufficientFunds shares the code of. Any other program may use
+        // code 1 for anything, so it only counts when one of those two is
+        // the failing instruction's program.
+        TransactionError::InstructionError(index, InstructionError::Custom(code)) => {
+            let program = message.and_then(|message| message.program_id(usize::from(*index)));
+            *code == SystemError::ResultWithNegativeLamports as u32
+                && program.is_some_and(|program| {
+                    *program == system_program::ID || *program == spl_token::ID
+                })
+        }
+        _ => false,
+    }
+}
+
+// What a `ClientError` means for the caller, shared by the `Error`
+// conversion and `retry::is_retryable`.
+pub(crate) enum Class {
+    BlockhashExpired,
+    InsufficientFunds,
+    TransactionFailed,
+    RateLimited,
+    Transport,
+    Behind,
+    Other,
+}
+
+pub(crate) fn classify(err: &ClientError, message: Option<&Message>) -> Class {
+    if let Some(tx_err) = err.get_transaction_error() {
+        return match tx_err {
+            TransactionError::BlockhashNotFound => Class::BlockhashExpired,
+            tx_err if is_insufficient_funds(&tx_err, message) => Class::InsufficientFunds,
+            _ => Class::TransactionFailed,
+        };
+    }
+    match err.kind() {
+        ClientErrorKind::Reqwest(reqwest)
+            if reqwest
+                .status()
+                .is_some_and(|status| status.as_u16() == 429) =>
+        {
+            Class::RateLimited
+        }
+        ClientErrorKind::Reqwest(reqwest) if reqwest.is_timeout() || reqwest.is_connect() => {
+            Class::Transport
+        }
+        ClientErrorKind::Io(_) => Class::Transport,
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            code: MIN_CONTEXT_SLOT_NOT_REACHED,
+            ..
+        }) => Class::Behind,
+        // some providers only say so in the message
+        _ if err.to_string().contains("429") => Class::RateLimited,
+        _ => Class::Other,
+    }
+}
+
+impl From<ClientError> for Error {
+    fn from(err: ClientError) -> Self {
+        let class = classify(&err, None);
+        Self::classified(err, class)
+    }
+}
+
+impl Error {
+    // Like the `From<ClientError>` conversion, for an error sending or
+    // confirming `message`, whose instructions tell which program a custom
+    // error came from.
+    pub fn from_transaction(err: ClientError, message: &Message) -> Self {
+        let class = classify(&err, Some(message));
+        Self::classified(err, class)
+    }
+
+    fn classified(err: ClientError, class: Class) -> Self {
+        // boxed, as `ClientError` would make every `Result<_, Error>` huge
+        let err = Box::new(err);
+        match class {
+            Class::BlockhashExpired => Self::BlockhashExpired,
+            Class::InsufficientFunds => Self::InsufficientFunds(err),
+            Class::TransactionFailed => Self::TransactionFailed(err),
+            Class::RateLimited => Self::RpcRateLimited(err),
+            Class::Transport => Self::RpcTransport(err),
+            Class::Behind => Self::RpcBehind(err.to_string()),
+            Class::Other => Self::Rpc(err),
+        }
+    }
+}
+
+// For the binaries' `main`s: prints the error like returning it would, and
+// exits with the code of the first `Error` in its chain.
+pub fn exit(result: anyhow::Result<()>) -> ExitCode {
+    let Err(err) = result else {
+        return ExitCode::SUCCESS;
+    };
+    eprintln!("Error: {err:?}");
+    let code = err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<Error>())
+        .map_or(1, Error::exit_code);
+    ExitCode::from(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+    use solana_client::rpc_request::RpcResponseErrorData;
+    use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+    use super::*;
+
+    fn rpc_error(code: i64, message: &str) -> ClientError {
+        RpcError::RpcResponseError {
+            code,
+            message: message.to_string(),
+            data: RpcResponseErrorData::Empty,
+        }
+        .into()
+    }
+
+    fn kind(err: impl Into<ClientError>) -> &'static str {
+        Error::from(err.into()).kind()
+    }
+
+    #[test]
+    fn transaction_errors_map_by_cause() {
+        assert_eq!(
+            kind(TransactionError::BlockhashNotFound),
+            "blockhash_expired"
+        );
+        assert_eq!(
+            kind(TransactionError::InsufficientFundsForFee),
+            "insufficient_funds"
+        );
+        assert_eq!(
+            kind(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(6000)
+            )),
+            "transaction_failed"
+        );
+    }
+
+    // The kind of `err` failing the only instruction of a transaction
+    // calling `program`.
+    fn kind_calling(program: Pubkey, err: TransactionError) -> &'static str {
+        let instruction = Instruction::new_with_bytes(program, &[], vec![]);
+        let message = Message::new(&[instruction], Some(&Pubkey::new_unique()));
+        Error::from_transaction(err.into(), &message).kind()
+    }
+
+    #[test]
+    fn custom_error_1_is_insufficient_funds_only_from_system_or_token() {
+        let negative_lamports = || {
+            TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(SystemError::ResultWithNegativeLamports as u32),
+            )
+        };
+        assert_eq!(
+            kind_calling(system_program::ID, negative_lamports()),
+            "insufficient_funds"
+        );
+        assert_eq!(
+            kind_calling(spl_token::ID, negative_lamports()),
+            "insufficient_funds"
+        );
+        assert_eq!(
+            kind_calling(Pubkey::new_unique(), negative_lamports()),
+            "transaction_failed"
+        );
+        // without the transaction the program is unknown
+        assert_eq!(kind(negative_lamports()), "transaction_failed");
+    }
+
+    #[test]
+    fn transport_failures_are_transient() {
+        let timeout = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+        let err = Error::from(ClientError::from(timeout));
+        assert_eq!(err.kind(), "rpc_transport");
+        assert!(err.is_transient());
+    }
+
+    #[test]
+    fn rate_limits_are_recognised_by_message() {
+        let err = Error::from(ClientError::from(RpcError::ForUser(
+            "HTTP status client error (429 Too Many Requests)".to_string(),
+        )));
+        assert_eq!(err.kind(), "rpc_rate_limited");
+        assert!(err.is_transient());
+    }
+
+    #[test]
+    fn lagging_nodes_are_transient() {
+        let err = Error::from(rpc_error(
+            MIN_CONTEXT_SLOT_NOT_REACHED,
+            "Minimum context slot has not been reached",
+        ));
+        assert_eq!(err.kind(), "rpc_behind");
+        assert!(err.is_transient());
+    }
+
+    #[test]
+    fn other_rpc_errors_are_fatal() {
+        let err = Error::from(rpc_error(-32602, "Invalid params"));
+        assert_eq!(err.kind(), "rpc");
+        assert!(!err.is_transient());
+        assert!(!Error::BlockhashExpired.is_transient());
+    }
+
+    #[test]
+    fn exit_codes_separate_setup_from_cluster_problems() {
+        let config = Error::KeypairLoad {
+            spec: "id.json".to_string(),
+            reason: "missing".to_string(),
+        };
+        assert_eq!(config.exit_code(), EX_CONFIG);
+        assert_eq!(
+            Error::UnknownAddress("alice".to_string()).exit_code(),
+            EX_CONFIG
+        );
+        let strict = Error::InvalidAddresses {
+            invalid: 0,
+            duplicates: 1,
+        };
+        assert_eq!(strict.exit_code(), EX_CONFIG);
+        assert_eq!(
+            Error::GrpcStream("reset".to_string()).exit_code(),
+            EX_UNAVAILABLE
+        );
+        let timeout = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+        assert_eq!(
+            Error::from(ClientError::from(timeout)).exit_code(),
+            EX_UNAVAILABLE
+        );
+        assert_eq!(Error::Aborted.exit_code(), EX_ABORTED);
+        assert_eq!(Error::PartialFailure.exit_code(), 3);
+        assert_eq!(Error::BelowMinimum.exit_code(), 2);
+        assert_eq!(Error::BlockhashExpired.exit_code(), 1);
+    }
+
+    #[test]
+    fn exit_uses_the_first_error_in_the_chain() {
+        assert_eq!(exit(Ok(())), ExitCode::SUCCESS);
+        let result = Err(Error::UnknownAddress("alice".to_string()))
+            .context("loading send.yaml")
+            .context("send");
+        assert_eq!(exit(result), ExitCode::from(EX_CONFIG));
+        assert_eq!(exit(Err(anyhow::anyhow!("no pairs"))), ExitCode::from(1));
+    }
+}
@@ -0,0 +1,340 @@
+// Anchor events of the deposit program (programs/deposit). `emit!` logs
+// "Program data: " followed by base64 of sha256("event:<Name>")[..8] and the
+// borsh-encoded fields. Read by the indexer and vault binaries.
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use solana_sdk::{hash::hash, pubkey::Pubkey};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Deposit,
+    Withdraw,
+    // the vault account is gone; `amount` is everything that was left in it
+    Close,
+}
+
+#[derive(Debug)]
+pub struct Event {
+    pub kind: Kind,
+    // the signer: owner, sponsor, withdrawer, shared depositor, beneficiary
+    // or, for emergency withdrawals, the admin
+    pub user: Pubkey,
+    pub vault: Pubkey,
+    pub amount: u64,
+    // withdrawal fee, 0 for deposits
+    pub fee: u64,
+    // unknown for shared deposits and withdrawals; 0 once closed
+    pub new_balance: Option<u64>,
+}
+
+fn discriminator(name: &str) -> [u8; 8] {
+    let mut out = [0; 8];
+    out.copy_from_slice(&hash(format!("event:{name}").as_bytes()).to_bytes()[..8]);
+    out
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Option<&[u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn pubkey(&mut self) -> Option<Pubkey> {
+        Pubkey::try_from(self.take(32)?).ok()
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+}
+
+// Events that don't move lamports in or out of a vault.
+const SKIPPED: [&str; 10] = [
+    "InitializeEvent",
+    "WithdrawRequestedEvent",
+    "WithdrawCancelledEvent",
+    "WithdrawersChangedEvent",
+    "CosignersChangedEvent",
+    "PayoutAddressRequestedEvent",
+    "OwnershipProposedEvent",
+    "OwnershipTransferredEvent",
+    "TokenDepositEvent",
+    "TokenWithdrawEvent",
+];
+
+fn decode(data: &[u8]) -> Option<Event> {
+    let (prefix, fields) = data.split_at_checked(8)?;
+    let mut reader = Reader(fields);
+    if prefix == discriminator("DepositEvent") {
+        return Some(Event {
+            kind: Kind::Deposit,
+            user: reader.pubkey()?,
+            vault: reader.pubkey()?,
+            amount: reader.u64()?,
+            fee: 0,
+            new_balance: Some(reader.u64()?),
+        });
+    }
+    if prefix == discriminator("DepositForEvent") {
+        let payer = reader.pubkey()?;
+        let _beneficiary = reader.pubkey()?;
+        return Some(Event {
+            kind: Kind::Deposit,
+            user: payer,
+            vault: reader.pubkey()?,
+            amount: reader.u64()?,
+            fee: 0,
+            new_balance: Some(reader.u64()?),
+        });
+    }
+    if prefix == discriminator("SharedDepositEvent") {
+        return Some(Event {
+            kind: Kind::Deposit,
+            user: reader.pubkey()?,
+            vault: reader.pubkey()?,
+            amount: reader.u64()?,
+            fee: 0,
+            new_balance: None,
+        });
+    }
+    if prefix == discriminator("WithdrawEvent") {
+        let user = reader.pubkey()?;
+        let vault = reader.pubkey()?;
+        let _recipient = reader.pubkey()?;
+        return Some(Event {
+            kind: Kind::Withdraw,
+            user,
+            vault,
+            amount: reader.u64()?,
+            fee: reader.u64()?,
+            new_balance: Some(reader.u64()?),
+        });
+    }
+    if prefix == discriminator("SharedWithdrawEvent") {
+        return Some(Event {
+            kind: Kind::Withdraw,
+            user: reader.pubkey()?,
+            vault: reader.pubkey()?,
+            amount: reader.u64()?,
+            fee: 0,
+            new_balance: None,
+        });
+    }
+    if prefix == discriminator("EmergencyWithdrawEvent") {
+        let admin = reader.pubkey()?;
+        let vault = reader.pubkey()?;
+        let _owner = reader.pubkey()?;
+        let _recipient = reader.pubkey()?;
+        return Some(Event {
+            kind: Kind::Withdraw,
+            user: admin,
+            vault,
+            amount: reader.u64()?,
+            fee: 0,
+            new_balance: Some(reader.u64()?),
+        });
+    }
+    // the owner closing, or the beneficiary claiming an inactive vault
+    if prefix == discriminator("VaultClosedEvent") || prefix == discriminator("InactiveClaimEvent")
+    {
+        return Some(Event {
+            kind: Kind::Close,
+            user: reader.pubkey()?,
+            vault: reader.pubkey()?,
+            amount: reader.u64()?,
+            fee: 0,
+            new_balance: Some(0),
+        });
+    }
+    if !SKIPPED.iter().any(|name| prefix == discriminator(name)) {
+        tracing::warn!(discriminator = ?prefix, "unknown discriminator, event skipped");
+    }
+    None
+}
+
+// Anchor's prefix for events emitted through a self-CPI (`emit_cpi!`); the
+// instruction data continues like a "Program data:" log.
+const EVENT_IX_TAG: [u8; 8] = 0x1d9a_cb51_2ea5_45e4_u64.to_le_bytes();
+
+pub fn from_cpi_data(data: &[u8]) -> Option<Event> {
+    decode(data.strip_prefix(&EVENT_IX_TAG)?)
+}
+
+// Follows the invoke/success lines so only data logged by the program itself
+// counts, not by other programs in the same transaction. Each event comes with
+// the index of its log line.
+pub fn from_logs(logs: &[String], program_id: &Pubkey) -> Vec<(usize, Event)> {
+    let program_id = program_id.to_string();
+    let mut stack: Vec<&str> = vec![];
+    let mut events = vec![];
+    for (i, line) in logs.iter().enumerate() {
+        if let Some(data) = line.strip_prefix("Program data: ") {
+            if stack.last() == Some(&program_id.as_str()) {
+                let event = STANDARD.decode(data).ok().and_then(|data| decode(&data));
+                events.extend(event.map(|event| (i, event)));
+            }
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["Program", id, "invoke", _] => stack.push(id),
+            ["Program", _, "success"] => {
+                stack.pop();
+            }
+            ["Program", _, "failed:", ..] => {
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+// Event bytes as the program encodes them, for the binaries' tests.
+pub mod fixtures {
+    use super::*;
+
+    // A DepositEvent's bytes as the program encodes them, up to
+    // `new_balance`; the fields after it aren't read.
+    pub fn deposit_event(user: &Pubkey, vault: &Pubkey, amount: u64, new_balance: u64) -> Vec<u8> {
+        let mut data = discriminator("DepositEvent").to_vec();
+        data.extend_from_slice(user.as_ref());
+        data.extend_from_slice(vault.as_ref());
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&new_balance.to_le_bytes());
+        data
+    }
+
+    pub fn withdraw_event(
+        user: &Pubkey,
+        vault: &Pubkey,
+        amount: u64,
+        fee: u64,
+        new_balance: u64,
+    ) -> Vec<u8> {
+        let mut data = discriminator("WithdrawEvent").to_vec();
+        data.extend_from_slice(user.as_ref());
+        data.extend_from_slice(vault.as_ref());
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&fee.to_le_bytes());
+        data.extend_from_slice(&new_balance.to_le_bytes());
+        data
+    }
+
+    pub fn vault_closed_event(owner: &Pubkey, vault: &Pubkey, amount: u64) -> Vec<u8> {
+        let mut data = discriminator("VaultClosedEvent").to_vec();
+        data.extend_from_slice(owner.as_ref());
+        data.extend_from_slice(vault.as_ref());
+        data.extend_from_slice(&amount.to_le_bytes());
+        data
+    }
+
+    pub fn cpi_data(event: &[u8]) -> Vec<u8> {
+        [&EVENT_IX_TAG[..], event].concat()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fixtures::*, *};
+
+    fn summary(event: &Event) -> (Kind, Pubkey, Pubkey, u64, u64, Option<u64>) {
+        (
+            event.kind,
+            event.user,
+            event.vault,
+            event.amount,
+            event.fee,
+            event.new_balance,
+        )
+    }
+
+    #[test]
+    fn cpi_data_decodes_like_the_logged_event() {
+        let program_id = Pubkey::new_unique();
+        let (user, vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let logged = [
+            deposit_event(&user, &vault, 250, 1_000),
+            withdraw_event(&user, &vault, 100, 1, 899),
+        ];
+        let logs: Vec<String> = [format!("Program {program_id} invoke [1]")]
+            .into_iter()
+            .chain(
+                logged
+                    .iter()
+                    .map(|data| format!("Program data: {}", STANDARD.encode(data))),
+            )
+            .chain([format!("Program {program_id} success")])
+            .collect();
+
+        let from_logs: Vec<_> = from_logs(&logs, &program_id)
+            .iter()
+            .map(|(_, event)| summary(event))
+            .collect();
+        let from_cpi: Vec<_> = logged
+            .iter()
+            .map(|data| summary(&from_cpi_data(&cpi_data(data)).unwrap()))
+            .collect();
+
+        assert_eq!(from_cpi, from_logs);
+        assert_eq!(
+            from_cpi,
+            [
+                (Kind::Deposit, user, vault, 250, 0, Some(1_000)),
+                (Kind::Withdraw, user, vault, 100, 1, Some(899)),
+            ]
+        );
+    }
+
+    #[test]
+    fn cpi_data_needs_the_event_tag() {
+        let event = deposit_event(&Pubkey::new_unique(), &Pubkey::new_unique(), 1, 1);
+        assert!(from_cpi_data(&event).is_none());
+        // cut short after the tag
+        assert!(from_cpi_data(&cpi_data(&event)[..40]).is_none());
+        // an event this decoder doesn't know
+        assert!(from_cpi_data(&cpi_data(&discriminator("NotAnEvent"))).is_none());
+    }
+
+    #[test]
+    fn closing_and_shared_events_are_decoded() {
+        let (user, vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let shared = |name: &str| {
+            let mut data = discriminator(name).to_vec();
+            data.extend_from_slice(user.as_ref());
+            data.extend_from_slice(vault.as_ref());
+            data.extend_from_slice(&300u64.to_le_bytes());
+            // receipt_amount
+            data.extend_from_slice(&300u64.to_le_bytes());
+            data
+        };
+        let events = [
+            shared("SharedDepositEvent"),
+            shared("SharedWithdrawEvent"),
+            vault_closed_event(&user, &vault, 1_000),
+            shared("InactiveClaimEvent"),
+        ];
+
+        let decoded: Vec<_> = events
+            .iter()
+            .map(|data| summary(&from_cpi_data(&cpi_data(data)).unwrap()))
+            .collect();
+
+        assert_eq!(
+            decoded,
+            [
+                (Kind::Deposit, user, vault, 300, 0, None),
+                (Kind::Withdraw, user, vault, 300, 0, None),
+                (Kind::Close, user, vault, 1_000, 0, Some(0)),
+                (Kind::Close, user, vault, 300, 0, Some(0)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,87 @@
+// Geyser subscription shared by the blocks and balance binaries: reconnects
+// with backoff when the stream drops and answers the server's pings so idle
+// connections aren't closed.
+
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcBuilder};
+use yellowstone_grpc_proto::geyser::{
+    SubscribeRequest, SubscribeRequestPing, subscribe_update::UpdateOneof,
+};
+
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+fn reconnect_delay(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt.min(5)).min(MAX_RECONNECT_DELAY)
+}
+
+// Runs until `handle` fails; connection and stream errors only trigger a
+// reconnect with the same request.
+pub async fn subscribe(
+    endpoint: &str,
+    token: Option<String>,
+    request: SubscribeRequest,
+    mut handle: impl FnMut(UpdateOneof) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut attempt = 0;
+    loop {
+        if attempt > 0 {
+            let delay = reconnect_delay(attempt - 1);
+            tracing::info!(delay_secs = delay.as_secs(), "reconnecting to geyser");
+            tokio::time::sleep(delay).await;
+        }
+        attempt += 1;
+
+        let client = async {
+            GeyserGrpcBuilder::from_shared(endpoint.to_string())?
+                .x_token(token.clone())?
+                .tls_config(ClientTlsConfig::new().with_native_roots())?
+                .connect()
+                .await
+                .map_err(anyhow::Error::from)
+        };
+        let mut client = match client.await {
+            Ok(client) => client,
+            Err(err) => {
+                tracing::warn!(error = %format!("{err:#}"), "geyser connect failed");
+                continue;
+            }
+        };
+        let (mut sink, mut updates) =
+            match client.subscribe_with_request(Some(request.clone())).await {
+                Ok(pair) => pair,
+                Err(err) => {
+                    tracing::warn!(error = %err, "geyser subscribe failed");
+                    continue;
+                }
+            };
+
+        while let Some(update) = updates.next().await {
+            let update = match update {
+                Ok(update) => update,
+                Err(status) => {
+                    tracing::warn!(%status, "geyser stream failed");
+                    break;
+                }
+            };
+            attempt = 0;
+            match update.update_oneof {
+                Some(UpdateOneof::Ping(_)) => {
+                    let pong = SubscribeRequest {
+                        ping: Some(SubscribeRequestPing { id: 1 }),
+                        ..Default::default()
+                    };
+                    if let Err(err) = sink.send(pong).await {
+                        tracing::warn!(error = %err, "geyser ping reply failed");
+                        break;
+                    }
+                }
+                Some(UpdateOneof::Pong(_)) | None => {}
+                Some(update) => handle(update)?,
+            }
+        }
+        // a clean end of stream is reconnected like an error
+        attempt = attempt.max(1);
+    }
+}
@@ -0,0 +1,529 @@
+use std::{collections::HashMap, env, str::FromStr, time::Duration};
+
+use anyhow::{Context, bail};
+use clap::ValueEnum;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_test::{
+    config::{ConfigFormat, load_config_as},
+    events::{self, Event, Kind},
+    logging::{self, LogOptions},
+    rpc,
+};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiInstruction, UiMessage,
+    UiTransactionEncoding,
+};
+
+const USAGE: &str = "Usage: indexer <config.yaml> [--json] [--cpi-events] \
+                     [--from-signature SIGNATURE] [--config-format yaml|json|toml]";
+
+// fetching a transaction right after its log notification can race the RPC
+const FETCH_ATTEMPTS: u32 = 3;
+const FETCH_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+fn reconnect_delay(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt.min(5)).min(MAX_RECONNECT_DELAY)
+}
+
+// Same keys as the vault binary's config; `keypair` is ignored.
+#[derive(Debug, Deserialize)]
+struct Config {
+    rpc_endpoint: String,
+    // derived from `rpc_endpoint` when unset
+    ws_endpoint: Option<String>,
+    program_id: String,
+}
+
+struct Args {
+    config: String,
+    config_format: Option<ConfigFormat>,
+    json: bool,
+    // read events from inner instructions (`emit_cpi!`) instead of logs
+    cpi_events: bool,
+    from_signature: Option<Signature>,
+}
+
+fn parse_args() -> anyhow::Result<Args> {
+    let mut config = None;
+    let mut config_format = None;
+    let mut json = false;
+    let mut cpi_events = false;
+    let mut from_signature = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--cpi-events" => cpi_events = true,
+            "--from-signature" => {
+                let signature = args.next().context("--from-signature needs a signature")?;
+                from_signature = Some(Signature::from_str(&signature).context("--from-signature")?);
+            }
+            "--config-format" => {
+                let format = args.next().context("--config-format needs a format")?;
+                config_format = Some(
+                    ConfigFormat::from_str(&format, true)
+                        .map_err(|err| anyhow::anyhow!("--config-format: {err}"))?,
+                );
+            }
+            flag if flag.starts_with("--") => bail!("unknown flag `{flag}`\n{USAGE}"),
+            _ if config.is_none() => config = Some(arg),
+            _ => bail!("unexpected argument `{arg}`\n{USAGE}"),
+        }
+    }
+    Ok(Args {
+        config: config.context(USAGE)?,
+        config_format,
+        json,
+        cpi_events,
+        from_signature,
+    })
+}
+
+fn ws_url(rpc_endpoint: &str) -> String {
+    match rpc_endpoint.split_once("://") {
+        Some(("https", rest)) => format!("wss://{rest}"),
+        Some(("http", rest)) => format!("ws://{rest}"),
+        _ => rpc_endpoint.to_string(),
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    deposited: u64,
+    withdrawn: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    event: &'static str,
+    signature: &'a str,
+    slot: u64,
+    user: String,
+    vault: String,
+    amount: u64,
+    fee: u64,
+    new_balance: Option<u64>,
+    total_deposited: u64,
+    total_withdrawn: u64,
+}
+
+struct Indexer {
+    client: RpcClient,
+    program_id: Pubkey,
+    json: bool,
+    cpi_events: bool,
+    totals: HashMap<Pubkey, Totals>,
+}
+
+impl Indexer {
+    fn process(&mut self, signature: &str, slot: u64, logs: &[String]) -> anyhow::Result<()> {
+        for (_, event) in events::from_logs(logs, &self.program_id) {
+            self.emit(signature, slot, &event)?;
+        }
+        Ok(())
+    }
+
+    // Fetches the transaction and reads its events from the logs, or from
+    // the inner instructions with `--cpi-events`. Failed transactions are
+    // skipped; their logs can hold events emitted before the failure.
+    async fn process_transaction(&mut self, signature: &str) -> anyhow::Result<()> {
+        let tx = fetch_transaction(&self.client, &Signature::from_str(signature)?).await?;
+        let Some(meta) = &tx.transaction.meta else {
+            return Ok(());
+        };
+        if meta.err.is_some() {
+            return Ok(());
+        }
+        if !self.cpi_events {
+            let logs = Option::<Vec<String>>::from(meta.log_messages.clone()).unwrap_or_default();
+            return self.process(signature, tx.slot, &logs);
+        }
+        for event in cpi_events(&tx, &self.program_id) {
+            self.emit(signature, tx.slot, &event)?;
+        }
+        Ok(())
+    }
+
+    // A closed vault's totals start over, since its address can be
+    // initialized again.
+    fn emit(&mut self, signature: &str, slot: u64, event: &Event) -> anyhow::Result<()> {
+        let (name, totals) = match event.kind {
+            Kind::Deposit => {
+                let totals = self.totals.entry(event.vault).or_default();
+                totals.deposited = totals.deposited.saturating_add(event.amount);
+                ("deposit", *totals)
+            }
+            Kind::Withdraw => {
+                let totals = self.totals.entry(event.vault).or_default();
+                totals.withdrawn = totals.withdrawn.saturating_add(event.amount);
+                ("withdraw", *totals)
+            }
+            Kind::Close => {
+                self.totals.remove(&event.vault);
+                ("close", Totals::default())
+            }
+        };
+        let record = Record {
+            event: name,
+            signature,
+            slot,
+            user: event.user.to_string(),
+            vault: event.vault.to_string(),
+            amount: event.amount,
+            fee: event.fee,
+            new_balance: event.new_balance,
+            total_deposited: totals.deposited,
+            total_withdrawn: totals.withdrawn,
+        };
+        if self.json {
+            println!("{}", serde_json::to_string(&record)?);
+        } else {
+            println!(
+                "slot {} {:<8} {} by {} amount {} (vault totals: {} in, {} out) {}",
+                record.slot,
+                record.event,
+                record.vault,
+                record.user,
+                record.amount,
+                record.total_deposited,
+                record.total_withdrawn,
+                record.signature
+            );
+        }
+        Ok(())
+    }
+}
+
+async fn fetch_transaction(
+    client: &RpcClient,
+    signature: &Signature,
+) -> anyhow::Result<EncodedConfirmedTransactionWithStatusMeta> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Json),
+        commitment: Some(client.commitment()),
+        max_supported_transaction_version: Some(0),
+    };
+    let mut attempt = 1;
+    loop {
+        match client.get_transaction_with_config(signature, config).await {
+            Ok(tx) => return Ok(tx),
+            Err(err) if attempt >= FETCH_ATTEMPTS => {
+                return Err(err).with_context(|| format!("getTransaction {signature}"));
+            }
+            Err(_) => {
+                attempt += 1;
+                tokio::time::sleep(FETCH_RETRY_DELAY).await;
+            }
+        }
+    }
+}
+
+// Events the program emitted to itself through `emit_cpi!`. The program is
+// invoked at the top level, so its id is among the static account keys.
+fn cpi_events(tx: &EncodedConfirmedTransactionWithStatusMeta, program_id: &Pubkey) -> Vec<Event> {
+    let EncodedTransaction::Json(transaction) = &tx.transaction.transaction else {
+        return vec![];
+    };
+    let UiMessage::Raw(message) = &transaction.message else {
+        return vec![];
+    };
+    let Some(meta) = &tx.transaction.meta else {
+        return vec![];
+    };
+    let program_id = program_id.to_string();
+    Option::<&Vec<_>>::from(meta.inner_instructions.as_ref())
+        .into_iter()
+        .flatten()
+        .flat_map(|inner| &inner.instructions)
+        .filter_map(|instruction| match instruction {
+            UiInstruction::Compiled(instruction) => Some(instruction),
+            UiInstruction::Parsed(_) => None,
+        })
+        .filter(|instruction| {
+            message
+                .account_keys
+                .get(usize::from(instruction.program_id_index))
+                == Some(&program_id)
+        })
+        .filter_map(|instruction| bs58::decode(&instruction.data).into_vec().ok())
+        .filter_map(|data| events::from_cpi_data(&data))
+        .collect()
+}
+
+// Replays the program's transactions from `from` (inclusive) up to now,
+// oldest first.
+async fn backfill(indexer: &mut Indexer, from: Signature) -> anyhow::Result<()> {
+    let client = &indexer.client;
+    let mut signatures = vec![];
+    let mut before = None;
+    loop {
+        let page = client
+            .get_signatures_for_address_with_config(
+                &indexer.program_id,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: Some(from),
+                    limit: None,
+                    commitment: Some(client.commitment()),
+                },
+            )
+            .await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(Signature::from_str(&last.signature)?);
+        signatures.extend(
+            page.into_iter()
+                .filter(|status| status.err.is_none())
+                .map(|status| status.signature),
+        );
+    }
+    signatures.push(from.to_string());
+    tracing::info!(transactions = signatures.len(), "backfilling");
+
+    for signature in signatures.iter().rev() {
+        indexer.process_transaction(signature).await?;
+    }
+    Ok(())
+}
+
+// Runs until the process is killed; a dropped websocket only triggers a
+// reconnect. Events in the gap before the new subscription are missed.
+async fn follow(ws_endpoint: &str, indexer: &mut Indexer) -> anyhow::Result<()> {
+    let filter = RpcTransactionLogsFilter::Mentions(vec![indexer.program_id.to_string()]);
+    let config = RpcTransactionLogsConfig {
+        commitment: Some(CommitmentConfig::confirmed()),
+    };
+    let mut attempt: u32 = 0;
+    loop {
+        if attempt > 0 {
+            let delay = reconnect_delay(attempt - 1);
+            tracing::info!(%ws_endpoint, delay_secs = delay.as_secs(), "reconnecting");
+            tokio::time::sleep(delay).await;
+        }
+        attempt += 1;
+
+        let client = match PubsubClient::new(ws_endpoint).await {
+            Ok(client) => client,
+            Err(err) => {
+                tracing::warn!(error = %err, "websocket connect failed");
+                continue;
+            }
+        };
+        let (mut logs, unsubscribe) =
+            match client.logs_subscribe(filter.clone(), config.clone()).await {
+                Ok(pair) => pair,
+                Err(err) => {
+                    tracing::warn!(error = %err, "logs subscribe failed");
+                    continue;
+                }
+            };
+        tracing::info!(program_id = %indexer.program_id, "following logs");
+        attempt = 1;
+
+        while let Some(response) = logs.next().await {
+            if response.value.err.is_some() {
+                continue;
+            }
+            if indexer.cpi_events {
+                indexer
+                    .process_transaction(&response.value.signature)
+                    .await?;
+                continue;
+            }
+            indexer.process(
+                &response.value.signature,
+                response.context.slot,
+                &response.value.logs,
+            )?;
+        }
+        unsubscribe().await;
+        tracing::warn!("log subscription dropped");
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    logging::init(&LogOptions::default());
+    let args = parse_args()?;
+    let config: Config = load_config_as(&args.config, args.config_format)?;
+    let program_id: Pubkey = config.program_id.parse().context("program_id")?;
+    let ws_endpoint = config
+        .ws_endpoint
+        .clone()
+        .unwrap_or_else(|| ws_url(rpc::resolve_url(&config.rpc_endpoint)));
+
+    let mut indexer = Indexer {
+        client: rpc::build_rpc_client(
+            &config.rpc_endpoint,
+            CommitmentConfig::confirmed(),
+            rpc::DEFAULT_TIMEOUT,
+        ),
+        program_id,
+        json: args.json,
+        cpi_events: args.cpi_events,
+        totals: HashMap::new(),
+    };
+    if let Some(from) = args.from_signature {
+        backfill(&mut indexer, from).await?;
+    }
+    follow(&ws_endpoint, &mut indexer).await
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    use serde_json::json;
+    use solana_test::events::fixtures::{
+        cpi_data, deposit_event, vault_closed_event, withdraw_event,
+    };
+
+    use super::*;
+
+    // A transaction as getTransaction returns it in JSON: the user calls the
+    // program, which transfers through the System program and emits
+    // `events` to itself.
+    fn transaction(
+        user: &Pubkey,
+        program_id: &Pubkey,
+        events: &[Vec<u8>],
+    ) -> EncodedConfirmedTransactionWithStatusMeta {
+        let data = |data: &[u8]| bs58::encode(data).into_string();
+        let mut inner = vec![json!({
+            "programIdIndex": 3,
+            "accounts": [0, 1],
+            "data": data(&[2, 0, 0, 0]),
+            "stackHeight": 2,
+        })];
+        inner.extend(events.iter().map(|event| {
+            json!({
+                "programIdIndex": 2,
+                "accounts": [4],
+                "data": data(&cpi_data(event)),
+                "stackHeight": 2,
+            })
+        }));
+        serde_json::from_value(json!({
+            "slot": 1,
+            "blockTime": null,
+            "transaction": {
+                "signatures": [Signature::default().to_string()],
+                "message": {
+                    "header": {
+                        "numRequiredSignatures": 1,
+                        "numReadonlySignedAccounts": 0,
+                        "numReadonlyUnsignedAccounts": 3,
+                    },
+                    "accountKeys": [
+                        user.to_string(),
+                        Pubkey::new_unique().to_string(),
+                        program_id.to_string(),
+                        solana_sdk::system_program::ID.to_string(),
+                        Pubkey::new_unique().to_string(),
+                    ],
+                    "recentBlockhash": Pubkey::default().to_string(),
+                    "instructions": [{
+                        "programIdIndex": 2,
+                        "accounts": [0, 1, 3, 4, 2],
+                        "data": data(&[0; 16]),
+                    }],
+                },
+            },
+            "meta": {
+                "err": null,
+                "status": { "Ok": null },
+                "fee": 5_000,
+                "preBalances": [0, 0, 0, 0, 0],
+                "postBalances": [0, 0, 0, 0, 0],
+                "innerInstructions": [{ "index": 0, "instructions": inner }],
+                "logMessages": [],
+                "preTokenBalances": [],
+                "postTokenBalances": [],
+                "rewards": [],
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn cpi_events_reads_the_programs_inner_instructions() {
+        let (user, program_id) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let vault = Pubkey::new_unique();
+        let tx = transaction(
+            &user,
+            &program_id,
+            &[
+                deposit_event(&user, &vault, 250, 1_250),
+                withdraw_event(&user, &vault, 100, 1, 1_149),
+            ],
+        );
+
+        let events = cpi_events(&tx, &program_id);
+
+        let [deposit, withdraw] = &events[..] else {
+            panic!("expected two events, got {events:?}");
+        };
+        assert_eq!(deposit.kind, Kind::Deposit);
+        assert_eq!((deposit.user, deposit.vault), (user, vault));
+        assert_eq!((deposit.amount, deposit.new_balance), (250, Some(1_250)));
+        assert_eq!(withdraw.kind, Kind::Withdraw);
+        assert_eq!((withdraw.amount, withdraw.fee), (100, 1));
+        assert_eq!(withdraw.new_balance, Some(1_149));
+    }
+
+    #[test]
+    fn cpi_events_ignores_other_programs() {
+        let user = Pubkey::new_unique();
+        let event = deposit_event(&user, &Pubkey::new_unique(), 250, 1_250);
+        let tx = transaction(&user, &Pubkey::new_unique(), &[event]);
+
+        assert!(cpi_events(&tx, &Pubkey::new_unique()).is_empty());
+        assert!(cpi_events(&tx, &solana_sdk::system_program::ID).is_empty());
+    }
+
+    #[test]
+    fn closing_a_vault_resets_its_totals() {
+        let program_id = Pubkey::new_unique();
+        let (user, vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut indexer = Indexer {
+            client: RpcClient::new("http://127.0.0.1:8899".to_string()),
+            program_id,
+            json: true,
+            cpi_events: false,
+            totals: HashMap::new(),
+        };
+        let mut run = |events: &[Vec<u8>]| {
+            let logs: Vec<String> = [format!("Program {program_id} invoke [1]")]
+                .into_iter()
+                .chain(
+                    events
+                        .iter()
+                        .map(|data| format!("Program data: {}", STANDARD.encode(data))),
+                )
+                .chain([format!("Program {program_id} success")])
+                .collect();
+            indexer.process("signature", 1, &logs).unwrap();
+            let totals = indexer.totals.get(&vault).copied().unwrap_or_default();
+            (totals.deposited, totals.withdrawn)
+        };
+
+        assert_eq!(
+            run(&[
+                deposit_event(&user, &vault, 250, 1_250),
+                withdraw_event(&user, &vault, 100, 0, 1_150),
+            ]),
+            (250, 100)
+        );
+        assert_eq!(run(&[vault_closed_event(&user, &vault, 1_150)]), (0, 0));
+        assert_eq!(run(&[deposit_event(&user, &vault, 50, 1_050)]), (50, 0));
+    }
+}
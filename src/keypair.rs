@@ -0,0 +1,120 @@
+use std::env;
+
+use solana_sdk::signature::{Keypair, read_keypair_file};
+
+use crate::error::{Error, Result};
+
+// A keypair spec is one of
+//   env:NAME       the variable holds a JSON byte array or a base58 string
+//   base58:SECRET  the base58-encoded 64-byte secret key
+//   anything else  a keypair file, as written by solana-keygen
+pub fn load_keypair(spec: &str) -> Result<Keypair> {
+    if let Some(name) = spec.strip_prefix("env:") {
+        let value = env::var(name).map_err(|err| load_error(spec, err))?;
+        return parse_secret(&value).map_err(|reason| load_error(spec, reason));
+    }
+    if let Some(secret) = spec.strip_prefix("base58:") {
+        // the spec itself is the secret, so it stays out of the error
+        return parse_base58(secret).map_err(|reason| load_error("base58:…", reason));
+    }
+    read_keypair_file(spec).map_err(|err| load_error(spec, err))
+}
+
+fn load_error(spec: &str, reason: impl ToString) -> Error {
+    Error::KeypairLoad {
+        spec: spec.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn parse_secret(value: &str) -> std::result::Result<Keypair, String> {
+    let value = value.trim();
+    if value.starts_with('[') {
+        let bytes: Vec<u8> =
+            serde_json::from_str(value).map_err(|err| format!("invalid JSON byte array: {err}"))?;
+        return from_bytes(&bytes);
+    }
+    parse_base58(value)
+}
+
+fn parse_base58(secret: &str) -> std::result::Result<Keypair, String> {
+    let bytes = bs58::decode(secret.trim())
+        .into_vec()
+        .map_err(|err| format!("invalid base58: {err}"))?;
+    from_bytes(&bytes)
+}
+
+fn from_bytes(bytes: &[u8]) -> std::result::Result<Keypair, String> {
+    if bytes.len() != 64 {
+        return Err(format!("expected 64 bytes, got {}", bytes.len()));
+    }
+    Keypair::from_bytes(bytes).map_err(|err| format!("invalid keypair: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::signer::Signer;
+
+    use super::*;
+
+    fn spec_error(spec: &str) -> String {
+        match load_keypair(spec) {
+            Err(Error::KeypairLoad { spec, reason }) => format!("{spec}: {reason}"),
+            Err(err) => panic!("expected a keypair error, got {err}"),
+            Ok(_) => panic!("`{spec}` loaded"),
+        }
+    }
+
+    #[test]
+    fn loads_keypair_files() {
+        let keypair = Keypair::new();
+        let path = env::temp_dir().join(format!("keypair-test-{}.json", std::process::id()));
+        solana_sdk::signature::write_keypair_file(&keypair, &path).unwrap();
+        let loaded = load_keypair(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().pubkey(), keypair.pubkey());
+    }
+
+    #[test]
+    fn loads_base58_secrets() {
+        let keypair = Keypair::new();
+        let loaded = load_keypair(&format!("base58:{}", keypair.to_base58_string())).unwrap();
+        assert_eq!(loaded.pubkey(), keypair.pubkey());
+    }
+
+    #[test]
+    fn loads_env_byte_arrays_and_base58() {
+        let bytes = Keypair::new();
+        let base58 = Keypair::new();
+        // SAFETY: no other test reads or writes these variables
+        unsafe {
+            env::set_var(
+                "KEYPAIR_TEST_BYTES",
+                serde_json::to_string(&bytes.to_bytes().to_vec()).unwrap(),
+            );
+            env::set_var("KEYPAIR_TEST_BASE58", base58.to_base58_string());
+        }
+        assert_eq!(
+            load_keypair("env:KEYPAIR_TEST_BYTES").unwrap().pubkey(),
+            bytes.pubkey()
+        );
+        assert_eq!(
+            load_keypair("env:KEYPAIR_TEST_BASE58").unwrap().pubkey(),
+            base58.pubkey()
+        );
+    }
+
+    #[test]
+    fn errors_name_the_spec() {
+        assert!(spec_error("/no/such/keypair.json").starts_with("/no/such/keypair.json: "));
+        assert!(spec_error("env:KEYPAIR_TEST_UNSET").starts_with("env:KEYPAIR_TEST_UNSET: "));
+    }
+
+    #[test]
+    fn base58_errors_keep_the_secret_out() {
+        let secret = bs58::encode([7u8; 32]).into_string();
+        let error = spec_error(&format!("base58:{secret}"));
+        assert_eq!(error, "base58:…: expected 64 bytes, got 32");
+        assert!(!error.contains(&secret));
+    }
+}
@@ -0,0 +1,22 @@
+// Plumbing shared by the binaries: common flags, logging, config files,
+// keypairs, pubkey lists and the address book, RPC clients, a blockhash
+// cache, retries and the errors they share, plus the send config schema, the
+// geyser subscription, the deposit program's events and the vault statistics
+// more than one binary needs.
+
+pub mod addressbook;
+pub mod blockhash;
+pub mod cli;
+pub mod config;
+pub mod error;
+pub mod events;
+pub mod geyser;
+pub mod keypair;
+pub mod logging;
+pub mod pubkey;
+pub mod retry;
+pub mod rpc;
+pub mod send_config;
+pub mod vault_stats;
+
+pub use error::{Error, Result};
@@ -0,0 +1,125 @@
+// Diagnostics go through `tracing` to stderr, leaving stdout to the reports
+// so they can be piped. `RUST_LOG` picks what's shown, `info` when unset.
+//
+// A log file gets everything at debug and above as JSON, whatever the
+// stderr filter. It can be opened after `init`, e.g. once send's run
+// directory exists.
+
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use tracing::{Level, Metadata};
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry,
+    filter::dynamic_filter_fn,
+    fmt::{self, writer::OptionalWriter},
+    layer::{Context, SubscriberExt},
+    util::SubscriberInitExt,
+};
+
+const DEFAULT_FILTER: &str = "info";
+
+static FILE: OnceLock<File> = OnceLock::new();
+
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    // a filter for when `RUST_LOG` is unset
+    pub filter: Option<String>,
+    pub json: bool,
+    pub file: Option<PathBuf>,
+}
+
+fn file_writer() -> OptionalWriter<&'static File> {
+    FILE.get().into()
+}
+
+// dynamic, as the file may open after the callsite was first hit
+fn to_file<S>(metadata: &Metadata, _: &Context<S>) -> bool {
+    *metadata.level() <= Level::DEBUG && FILE.get().is_some()
+}
+
+// `RUST_LOG`, or the options' filter when it's unset.
+fn stderr_filter(options: &LogOptions) -> EnvFilter {
+    EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(options.filter.as_deref().unwrap_or(DEFAULT_FILTER)))
+}
+
+// Installs the global subscriber. Returns false, leaving the first one in
+// place, when one is already installed.
+pub fn init(options: &LogOptions) -> bool {
+    let filter = stderr_filter(options);
+    let stderr: Box<dyn Layer<Registry> + Send + Sync> = if options.json {
+        Box::new(fmt::layer().json().with_writer(io::stderr))
+    } else {
+        Box::new(fmt::layer().with_writer(io::stderr))
+    };
+    let file = fmt::layer()
+        .json()
+        .with_writer(file_writer)
+        .with_filter(dynamic_filter_fn(to_file));
+    let installed = tracing_subscriber::registry()
+        .with(stderr.with_filter(filter))
+        .with(file)
+        .try_init()
+        .is_ok();
+
+    let opened = options
+        .file
+        .as_deref()
+        .map(|path| (path, log_to_file(path)));
+    if let Some((path, Err(err))) = opened {
+        tracing::warn!(path = %path.display(), error = %err, "can't open log file");
+    }
+    installed
+}
+
+// Appends to `path` from now on. Only the first file is kept; later calls
+// return `Ok(false)`.
+pub fn log_to_file(path: &Path) -> io::Result<bool> {
+    if FILE.get().is_some() {
+        return Ok(false);
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(FILE.set(file).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use tracing::level_filters::LevelFilter;
+
+    use super::*;
+
+    // One test, as RUST_LOG, the subscriber and the file are process-wide.
+    #[test]
+    fn init_honours_the_filter_and_only_installs_once() {
+        let options = LogOptions {
+            filter: Some("warn".to_string()),
+            ..LogOptions::default()
+        };
+        let level = |options| stderr_filter(options).max_level_hint();
+        // SAFETY: no other test reads or writes RUST_LOG
+        unsafe { env::set_var("RUST_LOG", "solana_test=debug") };
+        assert_eq!(level(&options), Some(LevelFilter::DEBUG));
+        unsafe { env::remove_var("RUST_LOG") };
+        assert_eq!(level(&options), Some(LevelFilter::WARN));
+        assert_eq!(level(&LogOptions::default()), Some(LevelFilter::INFO));
+
+        assert!(init(&options));
+        assert!(!init(&LogOptions::default()));
+
+        let path = env::temp_dir().join(format!("logging-test-{}.json", std::process::id()));
+        assert!(log_to_file(&path).unwrap());
+        assert!(!log_to_file(&path.with_extension("other")).unwrap());
+        tracing::debug!(slot = 42, "only in the file");
+        let written = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(written.contains("only in the file"), "{written}");
+        assert!(written.contains("\"slot\":42"), "{written}");
+    }
+}
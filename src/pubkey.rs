@@ -0,0 +1,52 @@
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    addressbook,
+    error::{Error, Result},
+};
+
+// Stops at the first bad value, naming its 0-based index; callers add which
+// list it came from. Values can be `@name` references to the address book.
+pub fn parse_pubkeys<S: AsRef<str>>(values: &[S]) -> Result<Vec<Pubkey>> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let value = value.as_ref();
+            addressbook::resolve(value).map_err(|err| match err {
+                Error::InvalidAddress(_) => Error::InvalidPubkey {
+                    index,
+                    value: value.to_string(),
+                },
+                err => err,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_value() {
+        let pubkeys = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let values: Vec<String> = pubkeys.iter().map(Pubkey::to_string).collect();
+        assert_eq!(parse_pubkeys(&values).unwrap(), pubkeys);
+    }
+
+    #[test]
+    fn names_the_index_of_the_first_bad_value() {
+        let values = [
+            Pubkey::new_unique().to_string(),
+            "nope".to_string(),
+            "".to_string(),
+        ];
+        match parse_pubkeys(&values) {
+            Err(Error::InvalidPubkey { index, value }) => {
+                assert_eq!((index, value.as_str()), (1, "nope"));
+            }
+            other => panic!("expected an invalid pubkey, got {other:?}"),
+        }
+    }
+}
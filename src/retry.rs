@@ -0,0 +1,224 @@
+// Retrying with exponential backoff and jitter. Callers decide per error
+// whether another attempt can help; everything else fails straight away.
+
+use std::time::Duration;
+
+use rand::Rng;
+use solana_client::client_error::ClientError;
+use tokio::time::{self, Instant};
+
+use crate::error::{self, Class};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    // including the first one
+    pub max_attempts: u32,
+    // before the second attempt, doubling after each one
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    // no attempt starts after this long since the first one
+    pub max_elapsed: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            max_elapsed: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    // The wait after the `attempt`th failure (0-based): the doubled base
+    // delay plus up to half of it again, capped at `max_delay`.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self.base_delay.saturating_mul(1 << attempt.min(16));
+        let jitter = rand::thread_rng().gen_range(0..=delay.as_millis() as u64 / 2);
+        (delay + Duration::from_millis(jitter)).min(self.max_delay)
+    }
+}
+
+// Runs `op` until it succeeds, `classify` calls its error fatal, or `policy`
+// runs out. The last error is returned as is.
+pub async fn retry<T, E, F, Fut>(
+    policy: &RetryPolicy,
+    classify: impl Fn(&E) -> bool,
+    mut op: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: std::fmt::Display,
+{
+    let start = Instant::now();
+    let mut attempt = 0;
+    loop {
+        let err = match op().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        attempt += 1;
+        if attempt >= policy.max_attempts || !classify(&err) {
+            return Err(err);
+        }
+        let delay = policy.delay(attempt - 1);
+        let out_of_time = policy
+            .max_elapsed
+            .is_some_and(|max| start.elapsed() + delay > max);
+        if out_of_time {
+            return Err(err);
+        }
+        tracing::debug!(
+            attempt,
+            delay_ms = delay.as_millis() as u64,
+            error = %err,
+            "retrying"
+        );
+        time::sleep(delay).await;
+    }
+}
+
+// The default classifier for RPC calls: rate limits, timeouts, connection
+// errors and lagging nodes are retried. Failed transactions, insufficient
+// funds included, and anything unrecognised are not.
+pub fn is_retryable(err: &ClientError) -> bool {
+    matches!(
+        error::classify(err, None),
+        Class::RateLimited | Class::Transport | Class::Behind
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use solana_sdk::transaction::TransactionError;
+
+    use super::*;
+    use crate::rpc::mock::Failure;
+
+    const BASE: Duration = Duration::from_millis(100);
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: BASE,
+            max_delay: Duration::from_secs(2),
+            max_elapsed: None,
+        }
+    }
+
+    // Runs `retry` with an op that fails `failures` times, returning the
+    // result and when each attempt started, relative to the first.
+    async fn attempts(
+        policy: &RetryPolicy,
+        failures: usize,
+        retryable: bool,
+    ) -> (Result<usize, String>, Vec<Duration>) {
+        let start = Instant::now();
+        let started = RefCell::new(vec![]);
+        let result = retry(
+            policy,
+            |_: &String| retryable,
+            || {
+                let mut started = started.borrow_mut();
+                started.push(start.elapsed());
+                let attempt = started.len();
+                async move {
+                    if attempt <= failures {
+                        Err(format!("failure {attempt}"))
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            },
+        )
+        .await;
+        (result, started.into_inner())
+    }
+
+    #[test]
+    fn delay_doubles_with_up_to_half_again_of_jitter() {
+        let policy = policy(10);
+        for attempt in 0..4 {
+            let base = BASE * (1 << attempt);
+            for _ in 0..50 {
+                let delay = policy.delay(attempt);
+                assert!(delay >= base && delay <= base * 3 / 2, "{delay:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn delay_is_capped() {
+        let policy = policy(10);
+        assert_eq!(policy.delay(5), policy.max_delay);
+        assert_eq!(policy.delay(u32::MAX), policy.max_delay);
+    }
+
+    #[test]
+    fn jitter_varies_the_delay() {
+        let policy = policy(10);
+        let delays: Vec<Duration> = (0..20).map(|_| policy.delay(2)).collect();
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_after_each_delay_until_success() {
+        let (result, started) = attempts(&policy(5), 3, true).await;
+
+        assert_eq!(result, Ok(4));
+        assert_eq!(started.len(), 4);
+        for (attempt, gap) in started.windows(2).enumerate() {
+            let base = BASE * (1 << attempt);
+            let gap = gap[1] - gap[0];
+            assert!(gap >= base && gap <= base * 3 / 2, "{gap:?}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stops_at_max_attempts_with_the_last_error() {
+        let (result, started) = attempts(&policy(3), 10, true).await;
+
+        assert_eq!(result, Err("failure 3".to_string()));
+        assert_eq!(started.len(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fatal_errors_are_not_retried() {
+        let (result, started) = attempts(&policy(5), 10, false).await;
+
+        assert_eq!(result, Err("failure 1".to_string()));
+        assert_eq!(started, [Duration::ZERO]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn no_attempt_starts_past_max_elapsed() {
+        // 100-150ms, then 200-300ms: the third attempt would start too late
+        let policy = RetryPolicy {
+            max_elapsed: Some(Duration::from_millis(250)),
+            ..policy(10)
+        };
+        let (result, started) = attempts(&policy, 10, true).await;
+
+        assert_eq!(result, Err("failure 2".to_string()));
+        assert_eq!(started.len(), 2);
+    }
+
+    #[test]
+    fn rpc_errors_are_classified() {
+        let retryable = |failure| is_retryable(&ClientError::from(failure));
+        assert!(retryable(Failure::Transport));
+        assert!(retryable(Failure::RateLimited));
+        assert!(retryable(Failure::Behind));
+        assert!(!retryable(Failure::Transaction(
+            TransactionError::InsufficientFundsForFee
+        )));
+        assert!(!retryable(Failure::Transaction(
+            TransactionError::BlockhashNotFound
+        )));
+    }
+}
@@ -0,0 +1,384 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    client_error::Result as ClientResult,
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSimulateTransactionConfig},
+    rpc_request::TokenAccountsFilter,
+    rpc_response::{
+        RpcConfirmedTransactionStatusWithSignature, RpcKeyedAccount, RpcResult,
+        RpcSimulateTransactionResult, RpcVoteAccountStatus,
+    },
+};
+use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    epoch_info::EpochInfo,
+    hash::Hash,
+    message::Message,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{self, Transaction},
+};
+use solana_transaction_status::TransactionStatus;
+
+pub mod mock;
+
+pub const DEVNET_URL: &str = "https://api.devnet.solana.com";
+pub const MAINNET_URL: &str = "https://api.mainnet-beta.solana.com";
+
+// for binaries whose configs don't set one
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Cluster monikers map to the public endpoints; anything else is a URL.
+pub fn resolve_url(url: &str) -> &str {
+    match url {
+        "mainnet" | "mainnet-beta" => MAINNET_URL,
+        "devnet" => DEVNET_URL,
+        "testnet" => "https://api.testnet.solana.com",
+        "localhost" => "http://127.0.0.1:8899",
+        url => url,
+    }
+}
+
+pub fn build_rpc_client(
+    endpoint: &str,
+    commitment: CommitmentConfig,
+    timeout: Duration,
+) -> RpcClient {
+    RpcClient::new_with_timeout_and_commitment(
+        resolve_url(endpoint).to_string(),
+        timeout,
+        commitment,
+    )
+}
+
+// `build_rpc_client` for the pipelines, which take any `SolanaRpc`.
+pub fn connect(
+    endpoint: &str,
+    commitment: CommitmentConfig,
+    timeout: Duration,
+) -> Arc<dyn SolanaRpc> {
+    Arc::new(build_rpc_client(endpoint, commitment, timeout))
+}
+
+// The RPC calls send, balance and blocks make, so they can run against
+// `mock::MockRpc` as well as a cluster. Methods mirror `RpcClient`'s.
+#[async_trait]
+pub trait SolanaRpc: Send + Sync {
+    fn url(&self) -> String;
+
+    fn commitment(&self) -> CommitmentConfig;
+
+    async fn get_latest_blockhash(&self) -> ClientResult<Hash>;
+
+    // with the last block height it's valid for
+    async fn get_latest_blockhash_with_commitment(
+        &self,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<(Hash, u64)>;
+
+    async fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<bool>;
+
+    async fn get_genesis_hash(&self) -> ClientResult<Hash>;
+
+    async fn get_epoch_info(&self) -> ClientResult<EpochInfo>;
+
+    async fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64>;
+
+    async fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account>;
+
+    async fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Option<Account>>;
+
+    async fn get_multiple_accounts_with_config(
+        &self,
+        pubkeys: &[Pubkey],
+        config: RpcAccountInfoConfig,
+    ) -> RpcResult<Vec<Option<Account>>>;
+
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> ClientResult<Vec<Option<Account>>> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64Zstd),
+            commitment: Some(self.commitment()),
+            ..RpcAccountInfoConfig::default()
+        };
+        Ok(self
+            .get_multiple_accounts_with_config(pubkeys, config)
+            .await?
+            .value)
+    }
+
+    async fn get_program_accounts_with_config(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> ClientResult<Vec<(Pubkey, Account)>>;
+
+    async fn get_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+        filter: TokenAccountsFilter,
+    ) -> ClientResult<Vec<RpcKeyedAccount>>;
+
+    async fn get_vote_accounts(&self) -> ClientResult<RpcVoteAccountStatus>;
+
+    async fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64>;
+
+    async fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64>;
+
+    async fn simulate_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult>;
+
+    async fn send_transaction(&self, transaction: &Transaction) -> ClientResult<Signature>;
+
+    async fn send_and_confirm_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> ClientResult<Signature>;
+
+    async fn confirm_transaction(&self, signature: &Signature) -> ClientResult<bool>;
+
+    async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> RpcResult<Vec<Option<TransactionStatus>>>;
+
+    // `None` until the signature reaches the client's commitment
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> ClientResult<Option<transaction::Result<()>>> {
+        let commitment = self.commitment();
+        let status = self
+            .get_signature_statuses(&[*signature])
+            .await?
+            .value
+            .into_iter()
+            .next()
+            .flatten();
+        Ok(status
+            .filter(|status| status.satisfies_commitment(commitment))
+            .map(|status| status.status))
+    }
+
+    async fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>>;
+
+    async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        self.get_signatures_for_address_with_config(
+            address,
+            GetConfirmedSignaturesForAddress2Config::default(),
+        )
+        .await
+    }
+
+    async fn request_airdrop(&self, pubkey: &Pubkey, lamports: u64) -> ClientResult<Signature>;
+}
+
+#[async_trait]
+impl SolanaRpc for RpcClient {
+    fn url(&self) -> String {
+        RpcClient::url(self)
+    }
+
+    fn commitment(&self) -> CommitmentConfig {
+        RpcClient::commitment(self)
+    }
+
+    async fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        RpcClient::get_latest_blockhash(self).await
+    }
+
+    async fn get_latest_blockhash_with_commitment(
+        &self,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<(Hash, u64)> {
+        RpcClient::get_latest_blockhash_with_commitment(self, commitment).await
+    }
+
+    async fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+        commitment: CommitmentConfig,
+    ) -> ClientResult<bool> {
+        RpcClient::is_blockhash_valid(self, blockhash, commitment).await
+    }
+
+    async fn get_genesis_hash(&self) -> ClientResult<Hash> {
+        RpcClient::get_genesis_hash(self).await
+    }
+
+    async fn get_epoch_info(&self) -> ClientResult<EpochInfo> {
+        RpcClient::get_epoch_info(self).await
+    }
+
+    async fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        RpcClient::get_balance(self, pubkey).await
+    }
+
+    async fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        RpcClient::get_account(self, pubkey).await
+    }
+
+    async fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Option<Account>> {
+        RpcClient::get_account_with_commitment(self, pubkey, commitment).await
+    }
+
+    async fn get_multiple_accounts_with_config(
+        &self,
+        pubkeys: &[Pubkey],
+        config: RpcAccountInfoConfig,
+    ) -> RpcResult<Vec<Option<Account>>> {
+        RpcClient::get_multiple_accounts_with_config(self, pubkeys, config).await
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> ClientResult<Vec<Option<Account>>> {
+        RpcClient::get_multiple_accounts(self, pubkeys).await
+    }
+
+    async fn get_program_accounts_with_config(
+        &self,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> ClientResult<Vec<(Pubkey, Account)>> {
+        RpcClient::get_program_accounts_with_config(self, program_id, config).await
+    }
+
+    async fn get_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+        filter: TokenAccountsFilter,
+    ) -> ClientResult<Vec<RpcKeyedAccount>> {
+        RpcClient::get_token_accounts_by_owner(self, owner, filter).await
+    }
+
+    async fn get_vote_accounts(&self) -> ClientResult<RpcVoteAccountStatus> {
+        RpcClient::get_vote_accounts(self).await
+    }
+
+    async fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64> {
+        RpcClient::get_minimum_balance_for_rent_exemption(self, data_len).await
+    }
+
+    async fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64> {
+        RpcClient::get_fee_for_message(self, message).await
+    }
+
+    async fn simulate_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        RpcClient::simulate_transaction_with_config(self, transaction, config).await
+    }
+
+    async fn send_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        RpcClient::send_transaction(self, transaction).await
+    }
+
+    async fn send_and_confirm_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> ClientResult<Signature> {
+        RpcClient::send_and_confirm_transaction(self, transaction).await
+    }
+
+    async fn confirm_transaction(&self, signature: &Signature) -> ClientResult<bool> {
+        RpcClient::confirm_transaction(self, signature).await
+    }
+
+    async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> RpcResult<Vec<Option<TransactionStatus>>> {
+        RpcClient::get_signature_statuses(self, signatures).await
+    }
+
+    async fn get_signature_status(
+        &self,
+        signature: &Signature,
+    ) -> ClientResult<Option<transaction::Result<()>>> {
+        RpcClient::get_signature_status(self, signature).await
+    }
+
+    async fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        config: GetConfirmedSignaturesForAddress2Config,
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        RpcClient::get_signatures_for_address_with_config(self, address, config).await
+    }
+
+    async fn get_signatures_for_address(
+        &self,
+        address: &Pubkey,
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        RpcClient::get_signatures_for_address(self, address).await
+    }
+
+    async fn request_airdrop(&self, pubkey: &Pubkey, lamports: u64) -> ClientResult<Signature> {
+        RpcClient::request_airdrop(self, pubkey, lamports).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monikers_resolve_to_public_endpoints() {
+        assert_eq!(resolve_url("mainnet"), MAINNET_URL);
+        assert_eq!(resolve_url("mainnet-beta"), MAINNET_URL);
+        assert_eq!(resolve_url("devnet"), DEVNET_URL);
+        assert_eq!(resolve_url("localhost"), "http://127.0.0.1:8899");
+        assert_eq!(
+            resolve_url("https://rpc.example.com"),
+            "https://rpc.example.com"
+        );
+    }
+
+    #[test]
+    fn clients_use_the_given_endpoint_and_commitment() {
+        let client = build_rpc_client("devnet", CommitmentConfig::finalized(), DEFAULT_TIMEOUT);
+        assert_eq!(client.url(), DEVNET_URL);
+        assert_eq!(client.commitment(), CommitmentConfig::finalized());
+
+        let rpc = connect(
+            "http://127.0.0.1:8899",
+            CommitmentConfig::processed(),
+            DEFAULT_TIMEOUT,
+        );
+        assert_eq!(rpc.url(), "http://127.0.0.1:8899");
+        assert_eq!(rpc.commitment(), CommitmentConfig::processed());
+    }
+}
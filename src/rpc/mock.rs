@@ -0,0 +1,497 @@
+// An in-memory `SolanaRpc` for exercising the pipelines without a cluster.
+// Accounts, the blockhash and how sent transactions land are scripted up
+// front; `fail` queues errors for a method's next calls.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    sync::{Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSimulateTransactionConfig},
+    rpc_request::{RpcError, RpcResponseErrorData, TokenAccountsFilter},
+    rpc_response::{
+        Response, RpcConfirmedTransactionStatusWithSignature, RpcKeyedAccount, RpcResponseContext,
+        RpcResult, RpcSimulateTransactionResult, RpcVoteAccountStatus,
+    },
+};
+use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    epoch_info::EpochInfo,
+    hash::Hash,
+    message::Message,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Signature,
+    transaction::{Transaction, TransactionError},
+};
+use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
+
+use super::SolanaRpc;
+
+const MOCK_URL: &str = "mock://";
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+// blocks a blockhash stays valid for after the slot it's fetched at
+const BLOCKHASH_VALIDITY: u64 = 150;
+
+// What a scripted failure looks like to the caller; each maps to the
+// `crate::Error` variant of the same name.
+#[derive(Debug, Clone)]
+pub enum Failure {
+    // a timeout
+    Transport,
+    // HTTP 429
+    RateLimited,
+    // the node hasn't reached the requested `min_context_slot`
+    Behind,
+    // preflight or on-chain rejection
+    Transaction(TransactionError),
+}
+
+impl From<Failure> for ClientError {
+    fn from(failure: Failure) -> Self {
+        match failure {
+            Failure::Transport => io::Error::new(io::ErrorKind::TimedOut, "mock timeout").into(),
+            Failure::RateLimited => {
+                ClientErrorKind::Custom("HTTP status client error (429 Too Many Requests)".into())
+                    .into()
+            }
+            Failure::Behind => RpcError::RpcResponseError {
+                code: -32016,
+                message: "Minimum context slot has not been reached".to_string(),
+                data: RpcResponseErrorData::Empty,
+            }
+            .into(),
+            Failure::Transaction(err) => err.into(),
+        }
+    }
+}
+
+// How `send_transaction` treats what it's given.
+#[derive(Debug, Clone, Default)]
+pub enum Landing {
+    // confirmed straight away
+    #[default]
+    Confirm,
+    // accepted, but never gets a status
+    Drop,
+    // accepted, then fails on chain
+    Fail(TransactionError),
+}
+
+struct State {
+    blockhash: Hash,
+    blockhash_valid: bool,
+    genesis_hash: Hash,
+    slot: u64,
+    // context slots the next responses answer from instead of `slot`
+    stale_slots: VecDeque<u64>,
+    accounts: HashMap<Pubkey, Account>,
+    landing: Landing,
+    units_consumed: u64,
+    sent: Vec<Transaction>,
+    statuses: HashMap<Signature, TransactionStatus>,
+    failures: HashMap<&'static str, VecDeque<Failure>>,
+    calls: HashMap<&'static str, usize>,
+}
+
+pub struct MockRpc {
+    commitment: CommitmentConfig,
+    state: Mutex<State>,
+}
+
+impl Default for MockRpc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockRpc {
+    pub fn new() -> Self {
+        Self {
+            commitment: CommitmentConfig::confirmed(),
+            state: Mutex::new(State {
+                blockhash: Hash::new_unique(),
+                blockhash_valid: true,
+                genesis_hash: Hash::new_unique(),
+                slot: 1,
+                stale_slots: VecDeque::new(),
+                accounts: HashMap::new(),
+                landing: Landing::default(),
+                units_consumed: 200_000,
+                sent: vec![],
+                statuses: HashMap::new(),
+                failures: HashMap::new(),
+                calls: HashMap::new(),
+            }),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("mock state lock poisoned")
+    }
+
+    pub fn set_account(&self, pubkey: Pubkey, account: Account) {
+        self.state().accounts.insert(pubkey, account);
+    }
+
+    // A system account holding `lamports`.
+    pub fn set_balance(&self, pubkey: Pubkey, lamports: u64) {
+        self.set_account(
+            pubkey,
+            Account {
+                lamports,
+                ..Account::default()
+            },
+        );
+    }
+
+    pub fn set_slot(&self, slot: u64) {
+        self.state().slot = slot;
+    }
+
+    // The next response answers from `slot`, like a lagging node that
+    // ignores `min_context_slot`. Queued slots are used up in order.
+    pub fn answer_from(&self, slot: u64) {
+        self.state().stale_slots.push_back(slot);
+    }
+
+    pub fn set_genesis_hash(&self, hash: Hash) {
+        self.state().genesis_hash = hash;
+    }
+
+    // Later `is_blockhash_valid` calls report the current blockhash expired.
+    pub fn expire_blockhash(&self) {
+        self.state().blockhash_valid = false;
+    }
+
+    // Moves on to a new, valid blockhash and returns it.
+    pub fn advance_blockhash(&self) -> Hash {
+        let mut state = self.state();
+        state.blockhash = Hash::new_unique();
+        state.blockhash_valid = true;
+        state.blockhash
+    }
+
+    pub fn set_landing(&self, landing: Landing) {
+        self.state().landing = landing;
+    }
+
+    pub fn set_units_consumed(&self, units: u64) {
+        self.state().units_consumed = units;
+    }
+
+    // The next call of `method`, by its `SolanaRpc` name, fails with `failure`.
+    // Queued failures are used up in order.
+    pub fn fail(&self, method: &'static str, failure: Failure) {
+        self.state()
+            .failures
+            .entry(method)
+            .or_default()
+            .push_back(failure);
+    }
+
+    pub fn fail_times(&self, method: &'static str, times: usize, failure: Failure) {
+        for _ in 0..times {
+            self.fail(method, failure.clone());
+        }
+    }
+
+    pub fn calls(&self, method: &str) -> usize {
+        self.state().calls.get(method).copied().unwrap_or_default()
+    }
+
+    pub fn sent(&self) -> Vec<Transaction> {
+        self.state().sent.clone()
+    }
+
+    // Counts the call and pops its next scripted failure, if any.
+    fn enter(&self, method: &'static str) -> Result<(), Failure> {
+        let mut state = self.state();
+        *state.calls.entry(method).or_default() += 1;
+        match state.failures.get_mut(method).and_then(VecDeque::pop_front) {
+            Some(failure) => Err(failure),
+            None => Ok(()),
+        }
+    }
+
+    fn response<T>(&self, value: T) -> Response<T> {
+        let mut state = self.state();
+        let slot = state.stale_slots.pop_front().unwrap_or(state.slot);
+        Response {
+            context: RpcResponseContext::new(slot),
+            value,
+        }
+    }
+
+    fn account(&self, pubkey: &Pubkey) -> Option<Account> {
+        self.state().accounts.get(pubkey).cloned()
+    }
+
+    fn unscripted(method: &str) -> ClientError {
+        ClientErrorKind::Custom(format!("MockRpc doesn't script {method}")).into()
+    }
+
+    fn land(&self, transaction: &Transaction) -> Signature {
+        let signature = transaction.signatures.first().copied().unwrap_or_default();
+        let mut state = self.state();
+        state.sent.push(transaction.clone());
+        let status = match &state.landing {
+            Landing::Confirm => Some(Ok(())),
+            Landing::Drop => None,
+            Landing::Fail(err) => Some(Err(err.clone())),
+        };
+        if let Some(status) = status {
+            let slot = state.slot;
+            state.statuses.insert(
+                signature,
+                TransactionStatus {
+                    slot,
+                    confirmations: None,
+                    err: status.clone().err(),
+                    status,
+                    confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+                },
+            );
+        }
+        signature
+    }
+}
+
+#[async_trait]
+impl SolanaRpc for MockRpc {
+    fn url(&self) -> String {
+        MOCK_URL.to_string()
+    }
+
+    fn commitment(&self) -> CommitmentConfig {
+        self.commitment
+    }
+
+    async fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.enter("get_latest_blockhash")?;
+        Ok(self.state().blockhash)
+    }
+
+    async fn get_latest_blockhash_with_commitment(
+        &self,
+        _: CommitmentConfig,
+    ) -> ClientResult<(Hash, u64)> {
+        self.enter("get_latest_blockhash_with_commitment")?;
+        let state = self.state();
+        Ok((state.blockhash, state.slot + BLOCKHASH_VALIDITY))
+    }
+
+    async fn is_blockhash_valid(
+        &self,
+        blockhash: &Hash,
+        _: CommitmentConfig,
+    ) -> ClientResult<bool> {
+        self.enter("is_blockhash_valid")?;
+        let state = self.state();
+        Ok(state.blockhash_valid && *blockhash == state.blockhash)
+    }
+
+    async fn get_genesis_hash(&self) -> ClientResult<Hash> {
+        self.enter("get_genesis_hash")?;
+        Ok(self.state().genesis_hash)
+    }
+
+    async fn get_epoch_info(&self) -> ClientResult<EpochInfo> {
+        self.enter("get_epoch_info")?;
+        Err(Self::unscripted("get_epoch_info"))
+    }
+
+    async fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        self.enter("get_balance")?;
+        Ok(self.account(pubkey).map_or(0, |account| account.lamports))
+    }
+
+    async fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.enter("get_account")?;
+        self.account(pubkey).ok_or_else(|| {
+            ClientErrorKind::Custom(format!("AccountNotFound: pubkey={pubkey}")).into()
+        })
+    }
+
+    async fn get_account_with_commitment(
+        &self,
+        pubkey: &Pubkey,
+        _: CommitmentConfig,
+    ) -> RpcResult<Option<Account>> {
+        self.enter("get_account_with_commitment")?;
+        Ok(self.response(self.account(pubkey)))
+    }
+
+    async fn get_multiple_accounts_with_config(
+        &self,
+        pubkeys: &[Pubkey],
+        _: RpcAccountInfoConfig,
+    ) -> RpcResult<Vec<Option<Account>>> {
+        self.enter("get_multiple_accounts_with_config")?;
+        let accounts = pubkeys.iter().map(|pubkey| self.account(pubkey)).collect();
+        Ok(self.response(accounts))
+    }
+
+    // Every account the program owns; the config's filters aren't applied.
+    async fn get_program_accounts_with_config(
+        &self,
+        program_id: &Pubkey,
+        _: RpcProgramAccountsConfig,
+    ) -> ClientResult<Vec<(Pubkey, Account)>> {
+        self.enter("get_program_accounts_with_config")?;
+        Ok(self
+            .state()
+            .accounts
+            .iter()
+            .filter(|(_, account)| account.owner == *program_id)
+            .map(|(pubkey, account)| (*pubkey, account.clone()))
+            .collect())
+    }
+
+    async fn get_token_accounts_by_owner(
+        &self,
+        _: &Pubkey,
+        _: TokenAccountsFilter,
+    ) -> ClientResult<Vec<RpcKeyedAccount>> {
+        self.enter("get_token_accounts_by_owner")?;
+        Ok(vec![])
+    }
+
+    async fn get_vote_accounts(&self) -> ClientResult<RpcVoteAccountStatus> {
+        self.enter("get_vote_accounts")?;
+        Ok(RpcVoteAccountStatus {
+            current: vec![],
+            delinquent: vec![],
+        })
+    }
+
+    async fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64> {
+        self.enter("get_minimum_balance_for_rent_exemption")?;
+        Ok(Rent::default().minimum_balance(data_len))
+    }
+
+    async fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64> {
+        self.enter("get_fee_for_message")?;
+        // like the RPC client, which gets no fee for a blockhash the node
+        // doesn't know
+        if message.recent_blockhash != self.state().blockhash {
+            let message = "Invalid blockhash".to_string();
+            return Err(ClientErrorKind::RpcError(RpcError::ForUser(message)).into());
+        }
+        Ok(LAMPORTS_PER_SIGNATURE * message.header.num_required_signatures as u64)
+    }
+
+    async fn simulate_transaction_with_config(
+        &self,
+        _: &Transaction,
+        _: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        self.enter("simulate_transaction_with_config")?;
+        // the result's other fields vary between client versions
+        let result = serde_json::from_value(serde_json::json!({
+            "unitsConsumed": self.state().units_consumed,
+        }))
+        .map_err(|err| ClientErrorKind::Custom(err.to_string()))?;
+        Ok(self.response(result))
+    }
+
+    async fn send_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        self.enter("send_transaction")?;
+        Ok(self.land(transaction))
+    }
+
+    async fn send_and_confirm_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> ClientResult<Signature> {
+        self.enter("send_and_confirm_transaction")?;
+        let signature = self.land(transaction);
+        match self.state().statuses.get(&signature) {
+            Some(TransactionStatus { status: Ok(()), .. }) => Ok(signature),
+            Some(TransactionStatus {
+                status: Err(err), ..
+            }) => Err(err.clone().into()),
+            None => Err(ClientErrorKind::Custom(
+                "unable to confirm transaction; the blockhash may have expired".to_string(),
+            )
+            .into()),
+        }
+    }
+
+    async fn confirm_transaction(&self, signature: &Signature) -> ClientResult<bool> {
+        self.enter("confirm_transaction")?;
+        Ok(self
+            .state()
+            .statuses
+            .get(signature)
+            .is_some_and(|status| status.status.is_ok()))
+    }
+
+    async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> RpcResult<Vec<Option<TransactionStatus>>> {
+        self.enter("get_signature_statuses")?;
+        let statuses = {
+            let state = self.state();
+            signatures
+                .iter()
+                .map(|signature| state.statuses.get(signature).cloned())
+                .collect()
+        };
+        Ok(self.response(statuses))
+    }
+
+    // Landed transactions that mention `address`, newest first.
+    async fn get_signatures_for_address_with_config(
+        &self,
+        address: &Pubkey,
+        _: GetConfirmedSignaturesForAddress2Config,
+    ) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+        self.enter("get_signatures_for_address_with_config")?;
+        let state = self.state();
+        Ok(state
+            .sent
+            .iter()
+            .rev()
+            .filter(|tx| tx.message.account_keys.contains(address))
+            .filter_map(|tx| {
+                let signature = tx.signatures.first()?;
+                let status = state.statuses.get(signature)?;
+                Some(RpcConfirmedTransactionStatusWithSignature {
+                    signature: signature.to_string(),
+                    slot: status.slot,
+                    err: status.err.clone(),
+                    memo: None,
+                    block_time: None,
+                    confirmation_status: status.confirmation_status.clone(),
+                })
+            })
+            .collect())
+    }
+
+    async fn request_airdrop(&self, pubkey: &Pubkey, lamports: u64) -> ClientResult<Signature> {
+        self.enter("request_airdrop")?;
+        let signature = Signature::new_unique();
+        let mut state = self.state();
+        state.accounts.entry(*pubkey).or_default().lamports += lamports;
+        let slot = state.slot;
+        state.statuses.insert(
+            signature,
+            TransactionStatus {
+                slot,
+                confirmations: None,
+                status: Ok(()),
+                err: None,
+                confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+            },
+        );
+        Ok(signature)
+    }
+}
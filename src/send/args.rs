@@ -0,0 +1,59 @@
+use anyhow::{Context, bail};
+
+#[derive(Debug)]
+pub struct BenchSpec {
+    pub n: usize,
+    pub amount: u64,
+}
+
+impl Default for BenchSpec {
+    fn default() -> Self {
+        Self { n: 500, amount: 1 }
+    }
+}
+
+impl BenchSpec {
+    // `n=500 amount=1`, either one optional
+    fn parse(settings: &[String]) -> anyhow::Result<Self> {
+        let mut spec = Self::default();
+        for setting in settings {
+            let (key, value) = setting
+                .split_once('=')
+                .with_context(|| format!("expected key=value, got `{setting}`"))?;
+            match key {
+                "n" => spec.n = value.parse().context("bench n")?,
+                "amount" => spec.amount = value.parse().context("bench amount")?,
+                _ => bail!("unknown bench option `{key}`"),
+            }
+        }
+        Ok(spec)
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct Args {
+    /// Write the pairs that didn't land to this config file, for a rerun
+    #[arg(long, value_name = "PATH", conflicts_with = "bench_settings")]
+    pub failed_out: Option<String>,
+    /// Keep a log of the run in this directory
+    #[arg(long, value_name = "DIR")]
+    pub log_dir: Option<String>,
+    /// Skip recipients missing from the whitelist instead of refusing to run
+    #[arg(long)]
+    pub skip_unlisted: bool,
+    /// Send n self-transfers from the first pair's sender instead
+    #[arg(long = "bench", num_args = 0.., value_name = "n=500 amount=1")]
+    pub bench_settings: Option<Vec<String>>,
+    /// Allow a benchmark against mainnet
+    #[arg(long)]
+    pub i_know_what_im_doing: bool,
+}
+
+impl Args {
+    pub fn bench(&self) -> anyhow::Result<Option<BenchSpec>> {
+        self.bench_settings
+            .as_deref()
+            .map(BenchSpec::parse)
+            .transpose()
+    }
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use solana_sdk::{signature::Keypair, signer::Signer};
+
+use super::{
+    args::BenchSpec,
+    pair::{PairKind, TransferPair},
+    report::PairResult,
+};
+
+pub fn pairs(sender_keypair: &Keypair, spec: &BenchSpec) -> Vec<TransferPair> {
+    // identical amount + blockhash would produce identical signatures
+    (0..spec.n)
+        .map(|i| TransferPair {
+            sender_keypair: sender_keypair.insecure_clone(),
+            recipient: sender_keypair.pubkey(),
+            lamports: spec.amount + i as u64,
+            label: format!("bench-{i}"),
+            kind: PairKind::Transfer {
+                wrap_sol: false,
+                close_to_recipient: false,
+            },
+            extra_signers: vec![],
+            fee_payer: None,
+            reference: None,
+            memo: None,
+            built: None,
+            index: i,
+        })
+        .collect()
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let idx = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[idx]
+}
+
+pub fn print_stats(results: &[PairResult], total_duration: Duration) {
+    let sent: Vec<Duration> = results.iter().filter_map(|result| result.sent).collect();
+    let mut confirmed: Vec<Duration> = results
+        .iter()
+        .filter(|result| result.outcome.is_confirmed())
+        .map(|result| result.duration)
+        .collect();
+    confirmed.sort_unstable();
+
+    let send_window = sent.iter().max().copied().unwrap_or(total_duration);
+    let send_tps = sent.len() as f64 / send_window.as_secs_f64();
+    let confirm_tps = confirmed.len() as f64 / total_duration.as_secs_f64();
+
+    println!(
+        "bench: {} pairs, {} sent, {} confirmed",
+        results.len(),
+        sent.len(),
+        confirmed.len()
+    );
+    println!("send TPS: {send_tps:.2}");
+    println!("confirm TPS: {confirm_tps:.2}");
+    println!(
+        "latency p50: {:?}, p90: {:?}, p99: {:?}, max: {:?}",
+        percentile(&confirmed, 0.5),
+        percentile(&confirmed, 0.9),
+        percentile(&confirmed, 0.99),
+        confirmed.last().copied().unwrap_or_default()
+    );
+}
@@ -0,0 +1,96 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use solana_client::{
+    client_error::{ClientErrorKind, Result as ClientResult},
+    rpc_config::RpcSimulateTransactionConfig,
+};
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction, instruction::Instruction, pubkey::Pubkey,
+    transaction::Transaction,
+};
+use solana_test::{rpc::SolanaRpc, send_config::ComputeUnitLimit};
+use tokio::sync::OnceCell;
+
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Program ids of a transaction's instructions, in order.
+type Shape = Vec<Pubkey>;
+
+#[derive(Debug)]
+pub struct ComputeUnits {
+    limit: Option<ComputeUnitLimit>,
+    margin_percent: u32,
+    cache: Mutex<HashMap<Shape, Arc<OnceCell<u32>>>>,
+}
+
+impl ComputeUnits {
+    pub fn new(limit: Option<ComputeUnitLimit>, margin_percent: u32) -> Self {
+        Self {
+            limit,
+            margin_percent,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn simulate(
+        &self,
+        client: &dyn SolanaRpc,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+    ) -> ClientResult<u32> {
+        let mut simulated = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+            MAX_COMPUTE_UNIT_LIMIT,
+        )];
+        simulated.extend_from_slice(instructions);
+        let tx = Transaction::new_with_payer(&simulated, Some(payer));
+
+        let result = client
+            .simulate_transaction_with_config(
+                &tx,
+                RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    replace_recent_blockhash: true,
+                    ..Default::default()
+                },
+            )
+            .await?
+            .value;
+        if let Some(err) = result.err {
+            return Err(err.into());
+        }
+        let consumed = result.units_consumed.ok_or_else(|| {
+            ClientErrorKind::Custom("simulation did not report units consumed".to_string())
+        })?;
+
+        let limit = consumed + consumed * self.margin_percent as u64 / 100;
+        Ok(limit.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32)
+    }
+
+    /// Prepends a compute unit limit instruction when one is configured,
+    /// returning the limit that was chosen.
+    pub async fn apply(
+        &self,
+        client: &dyn SolanaRpc,
+        payer: &Pubkey,
+        instructions: Vec<Instruction>,
+    ) -> ClientResult<(Vec<Instruction>, Option<u32>)> {
+        let limit = match self.limit {
+            None => return Ok((instructions, None)),
+            Some(ComputeUnitLimit::Fixed(limit)) => limit,
+            Some(ComputeUnitLimit::Auto(_)) => {
+                let shape: Shape = instructions.iter().map(|ix| ix.program_id).collect();
+                let cell = self.cache.lock().unwrap().entry(shape).or_default().clone();
+                *cell
+                    .get_or_try_init(|| self.simulate(client, payer, &instructions))
+                    .await?
+            }
+        };
+
+        let mut out = vec![ComputeBudgetInstruction::set_compute_unit_limit(limit)];
+        out.extend(instructions);
+        Ok((out, Some(limit)))
+    }
+}
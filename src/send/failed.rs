@@ -0,0 +1,167 @@
+use std::{fmt::Write as _, fs, path::Path};
+
+use chrono::{DateTime, Utc};
+use solana_test::config::ConfigFormat;
+
+use super::{
+    Config,
+    report::{Outcome, PairResult},
+};
+
+// `results` must be in the same order as `config.pairs`. The file is written
+// in the format its extension names, so `load_config` reads it back.
+pub fn write(
+    path: &str,
+    mut config: Config,
+    results: &[PairResult],
+    run_started: DateTime<Utc>,
+) -> anyhow::Result<usize> {
+    let mut comments = vec![format!(
+        "failed pairs from run started at {}",
+        run_started.to_rfc3339()
+    )];
+    let mut failed = Vec::new();
+    for (i, (mut pair, result)) in config.pairs.into_iter().zip(results).enumerate() {
+        let reason = match &result.outcome {
+            Outcome::Confirmed(_) | Outcome::AlreadyLanded(_) => None,
+            Outcome::Failed(err) => Some(err.to_string()),
+            Outcome::Skipped(reason) => Some(format!("skipped: {reason}")),
+            Outcome::Aborted => Some("aborted".to_string()),
+        };
+        if let Some(reason) = reason {
+            let reason = reason.replace('\n', " ");
+            comments.push(format!("{} [{}]: {reason}", result.sender, result.label));
+            // keeps the rerun's references apart from the pairs that landed
+            pair.index = Some(pair.index.unwrap_or(i));
+            failed.push(pair);
+        }
+    }
+    config.pairs = failed;
+
+    let count = config.pairs.len();
+    let text = match ConfigFormat::from_path(Path::new(path)) {
+        ConfigFormat::Yaml => header(&comments)? + &serde_yaml::to_string(&config)?,
+        ConfigFormat::Toml => header(&comments)? + &toml::to_string(&config)?,
+        // JSON has no comments, so they go under a key the config doesn't read
+        ConfigFormat::Json => {
+            let mut value = serde_json::to_value(&config)?;
+            value["_comment"] = comments.into();
+            serde_json::to_string_pretty(&value)?
+        }
+    };
+    fs::write(path, text)?;
+    Ok(count)
+}
+
+fn header(comments: &[String]) -> Result<String, std::fmt::Error> {
+    let mut header = String::new();
+    for comment in comments {
+        writeln!(header, "# {comment}")?;
+    }
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use solana_sdk::{pubkey::Pubkey, signature::Signature};
+    use solana_test::{Error, config::load_config};
+
+    use super::*;
+
+    const PAIRS: &str = "
+pairs:
+  - sender_keypair: sender.json
+    recipient: D6U1xDjZK15v25mx7z8Nr23EcoZGZvqs3BpWL9E4zyst
+    lamports: 1000
+    label: landed
+  - sender_keypair: sender.json
+    recipient: D6U1xDjZK15v25mx7z8Nr23EcoZGZvqs3BpWL9E4zyst
+    lamports: 2000
+    label: expired
+compute_unit_margin_percent: 20
+";
+
+    fn result(label: &str, outcome: Outcome) -> PairResult {
+        let sender = Pubkey::new_unique();
+        PairResult {
+            label: label.to_string(),
+            sender,
+            fee_payer: sender,
+            recipient: Pubkey::new_unique(),
+            signers: vec![sender],
+            lamports: 0,
+            created_account: None,
+            nonce_blockhash: None,
+            compute_unit_limit: None,
+            fee: None,
+            submit_delay: None,
+            sent: None,
+            duration: Duration::ZERO,
+            outcome,
+        }
+    }
+
+    // Writes the failed pairs to a file with `extension`, then reads it back.
+    fn round_trip(extension: &str) -> (String, Config) {
+        let config: Config = serde_yaml::from_str(PAIRS).unwrap();
+        let results = [
+            result("landed", Outcome::Confirmed(Signature::default())),
+            result("expired", Outcome::Failed(Error::BlockhashExpired)),
+        ];
+        let path = std::env::temp_dir().join(format!(
+            "failed-round-trip-{}.{extension}",
+            std::process::id()
+        ));
+        let path = path.to_str().unwrap();
+
+        assert_eq!(write(path, config, &results, Utc::now()).unwrap(), 1);
+        let text = fs::read_to_string(path).unwrap();
+        let rerun = load_config(path).unwrap();
+        fs::remove_file(path).unwrap();
+        (text, rerun)
+    }
+
+    fn assert_only_the_failed_pair(rerun: &Config) {
+        assert_eq!(rerun.pairs.len(), 1);
+        assert_eq!(rerun.pairs[0].label.as_deref(), Some("expired"));
+        assert_eq!(rerun.pairs[0].lamports, Some(2000));
+        assert_eq!(rerun.pairs[0].index, Some(1));
+        assert_eq!(rerun.compute_unit_margin_percent, 20);
+    }
+
+    #[test]
+    fn yaml_round_trips_with_the_reasons_as_comments() {
+        let (text, rerun) = round_trip("yaml");
+
+        assert!(text.starts_with("# failed pairs from run started at "));
+        assert!(text.contains("[expired]: blockhash expired before the transaction landed\n"));
+        assert_only_the_failed_pair(&rerun);
+    }
+
+    #[test]
+    fn toml_round_trips_with_the_reasons_as_comments() {
+        let (text, rerun) = round_trip("toml");
+
+        assert!(text.starts_with("# failed pairs from run started at "));
+        assert!(text.contains("[expired]: blockhash expired before the transaction landed\n"));
+        assert_only_the_failed_pair(&rerun);
+    }
+
+    #[test]
+    fn json_round_trips_with_the_reasons_under_a_comment_key() {
+        let (text, rerun) = round_trip("json");
+
+        let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+        let comments = value["_comment"].as_array().unwrap();
+        assert_eq!(comments.len(), 2);
+        assert!(
+            comments[1]
+                .as_str()
+                .unwrap()
+                .ends_with("[expired]: blockhash expired before the transaction landed")
+        );
+        assert_only_the_failed_pair(&rerun);
+    }
+}
@@ -1,125 +1,794 @@
-use std::{env, fs::File, io::BufReader, sync::Arc, time::Instant};
+use std::{
+    collections::{HashSet, VecDeque},
+    process::ExitCode,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
-use anyhow::Result;
-use serde::Deserialize;
-use solana_client::nonblocking::rpc_client::RpcClient;
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use clap::Parser;
 use solana_sdk::{
-    commitment_config::CommitmentConfig,
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    hash::Hash,
+    message::Message,
     pubkey::Pubkey,
-    signature::{Keypair, read_keypair_file},
+    signature::Signature,
     signer::Signer,
-    system_instruction,
-    transaction::Transaction,
+    transaction::{Transaction, TransactionError},
+};
+use solana_test::{
+    Error,
+    blockhash::{self, BlockhashCache},
+    cli::CommonArgs,
+    error, logging,
+    retry::{self, RetryPolicy},
+    rpc::{self, SolanaRpc},
+    send_config::{AbortConfig, Config},
 };
-use tokio::task;
+use tokio::{sync::Semaphore, task, time::sleep};
+use tracing::Instrument;
+
+pub mod args;
+mod bench;
+mod compute;
+mod failed;
+mod memo;
+mod nonce;
+mod pacing;
+mod pair;
+mod reference;
+mod report;
+mod runlog;
+mod size;
+mod stake;
+mod summary;
+mod topup;
+mod whitelist;
+mod wsol;
+
+use compute::ComputeUnits;
+use pacing::Pacing;
+use pair::{PairKind, TransferPair};
+use report::{Outcome, PairResult};
+use runlog::RunLog;
+
+// pairs building or submitting a transaction at once, so an abort stops the
+// rest before they submit; confirming doesn't count
+const MAX_IN_FLIGHT: usize = 32;
 
-#[derive(Debug, Deserialize)]
-struct TransferPairRead {
-    sender_keypair: String,
-    recipient: String,
-    lamports: u64,
+struct SendContext {
+    client: Arc<dyn SolanaRpc>,
+    compute: ComputeUnits,
+    pacing: Pacing,
+    blockhashes: Arc<BlockhashCache>,
+    in_flight: Semaphore,
+    abort: Option<AbortConfig>,
+    // whether each of the last `abort.window` finished pairs failed
+    recent_failures: Mutex<VecDeque<bool>>,
+    aborted: AtomicBool,
 }
 
-#[derive(Debug)]
-struct TransferPair {
-    sender_keypair: Keypair,
-    recipient: Pubkey,
-    lamports: u64,
+impl SendContext {
+    // Counts a finished pair toward `abort_after_failures`, and aborts the
+    // pairs still to submit once more than `count` of the last `window`
+    // failed.
+    fn record(&self, outcome: &Outcome) {
+        let Some(abort) = &self.abort else {
+            return;
+        };
+        let mut recent = self.recent_failures.lock().unwrap();
+        recent.push_back(matches!(outcome, Outcome::Failed(_)));
+        if recent.len() > abort.window {
+            recent.pop_front();
+        }
+        let failures = recent.iter().filter(|failed| **failed).count();
+        if failures > abort.count && !self.aborted.swap(true, Ordering::Relaxed) {
+            tracing::warn!(
+                failures,
+                window = recent.len(),
+                "too many recent failures, aborting remaining pairs"
+            );
+        }
+    }
 }
 
-impl TryFrom<TransferPairRead> for TransferPair {
-    type Error = anyhow::Error;
-
-    fn try_from(value: TransferPairRead) -> anyhow::Result<Self> {
-        let TransferPairRead {
-            sender_keypair,
-            recipient,
-            lamports,
-        } = value;
-        Ok(Self {
-            sender_keypair: read_keypair_file(&sender_keypair)
-                .map_err(|err| anyhow::anyhow!("Can't read keypair file: {err}"))?,
-            recipient: recipient.parse()?,
-            lamports,
+// The inner result is the status of a transaction that landed; only
+// those have paid their fee.
+async fn confirm_signature(
+    client: &dyn SolanaRpc,
+    signature: &Signature,
+    blockhash: &Hash,
+) -> solana_test::Result<std::result::Result<(), TransactionError>> {
+    loop {
+        if let Some(status) = client.get_signature_status(signature).await? {
+            return Ok(status);
+        }
+        if !client
+            .is_blockhash_valid(blockhash, CommitmentConfig::processed())
+            .await?
+        {
+            return Err(Error::BlockhashExpired);
+        }
+        sleep(Duration::from_millis(500)).await;
+    }
+}
+
+async fn send_pair(ctx: Arc<SendContext>, mut pair: TransferPair) -> PairResult {
+    let SendContext {
+        client,
+        compute,
+        pacing,
+        blockhashes,
+        in_flight,
+        aborted,
+        ..
+    } = &*ctx;
+    let client = client.as_ref();
+    let start = Instant::now();
+
+    let mut sent = None;
+    let mut compute_unit_limit = None;
+    let mut submit_delay = None;
+    let mut fee = None;
+    let mut nonce_blockhash = None;
+    let mut landed_before = false;
+    let mut was_aborted = false;
+    let built = pair.built.take();
+    let signature = async {
+        // held until submitted; the semaphore is never closed
+        let permit = in_flight.acquire().await.expect("in-flight semaphore");
+        if aborted.load(Ordering::Relaxed) {
+            was_aborted = true;
+            return Err(Error::Aborted);
+        }
+        let prior = match &pair.reference {
+            Some(reference) => reference::prior_landing(client, reference).await?,
+            None => None,
+        };
+        if let Some(signature) = prior {
+            landed_before = true;
+            return Ok(signature);
+        }
+
+        let mut instructions = match built {
+            Some(instructions) => instructions,
+            None => pair.instructions(client).await?,
+        };
+        pair.add_accounts(&mut instructions);
+        let (instructions, limit) = compute
+            .apply(client, &pair.fee_payer().pubkey(), instructions)
+            .await?;
+        compute_unit_limit = limit;
+
+        let mut blockhash = blockhashes.get();
+        if blockhash.stale {
+            blockhash = blockhashes.force_refresh().await?;
+        }
+        // `size::check` has ruled out transactions over the packet limit
+        let message = Message::new_with_blockhash(
+            &instructions,
+            Some(&pair.fee_payer().pubkey()),
+            &blockhash.hash,
+        );
+        // the node prices a message by its blockhash
+        let estimate = client.get_fee_for_message(&message).await.ok();
+        let mut tx = Transaction::new_unsigned(message);
+        tx.try_sign(&pair.signers(), blockhash.hash)?;
+
+        if pacing.is_enabled() {
+            submit_delay = Some(pacing.wait().await);
+        }
+        // pairs that already submitted keep going so the report reflects what landed
+        if aborted.load(Ordering::Relaxed) {
+            was_aborted = true;
+            return Err(Error::Aborted);
+        }
+        tracing::debug!(
+            sender = %pair.sender_keypair.pubkey(),
+            recipient = %pair.recipient,
+            label = %pair.label,
+            lamports = pair.lamports,
+            "submitting transaction"
+        );
+        // resubmitting the same signed transaction can't land it twice
+        let signature = retry::retry(&RetryPolicy::default(), retry::is_retryable, || {
+            client.send_transaction(&tx)
         })
+        .await
+        .map_err(|err| Error::from_transaction(err, &tx.message))?;
+        drop(permit);
+        sent = Some(start.elapsed());
+        tracing::debug!(%signature, label = %pair.label, "transaction submitted");
+        let status = confirm_signature(client, &signature, &blockhash.hash).await?;
+        fee = estimate;
+        status.map_err(|err| Error::from_transaction(err.into(), &tx.message))?;
+        if let PairKind::CreateNonce { .. } = pair.kind {
+            nonce_blockhash = nonce::stored_blockhash(client, &pair.recipient).await.ok();
+        }
+        Ok::<_, Error>(signature)
+    }
+    .await;
+    match &signature {
+        Ok(signature) => tracing::debug!(%signature, label = %pair.label, "transaction confirmed"),
+        Err(err) => tracing::warn!(
+            error = %err,
+            kind = err.kind(),
+            label = %pair.label,
+            "transaction failed"
+        ),
+    }
+
+    let result = PairResult {
+        signers: pair
+            .signers()
+            .iter()
+            .map(|signer| signer.pubkey())
+            .collect(),
+        created_account: pair.created_account(),
+        label: pair.label,
+        sender: pair.sender_keypair.pubkey(),
+        fee_payer: pair.fee_payer().pubkey(),
+        recipient: pair.recipient,
+        lamports: pair.lamports,
+        nonce_blockhash,
+        compute_unit_limit,
+        fee,
+        submit_delay,
+        sent,
+        duration: start.elapsed(),
+        outcome: match signature {
+            Ok(signature) if landed_before => Outcome::AlreadyLanded(signature),
+            Err(_) if was_aborted => Outcome::Aborted,
+            signature => signature.into(),
+        },
+    };
+    ctx.record(&result.outcome);
+    result
+}
+
+// Sends every pair without a skip reason on its own task, and returns the
+// results in the pairs' order.
+async fn send_all(
+    ctx: &Arc<SendContext>,
+    pairs: Vec<TransferPair>,
+    skip_reasons: Vec<Option<String>>,
+) -> Result<Vec<PairResult>> {
+    let mut handles = Vec::new();
+    for (index, (pair, skip_reason)) in pairs.into_iter().zip(skip_reasons).enumerate() {
+        if let Some(reason) = skip_reason {
+            let result = PairResult::skipped(&pair, reason);
+            handles.push(task::spawn(std::future::ready(result)));
+            continue;
+        }
+        let span = tracing::info_span!("pair", index);
+        handles.push(task::spawn(send_pair(ctx.clone(), pair).instrument(span)));
+    }
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(handle.await?);
+    }
+    Ok(results)
+}
+
+// Runs stopped by `abort_after_failures` end in `Error::Aborted`, so the
+// process exits with its code once everything has been reported.
+fn finish(ctx: &SendContext, results: &[PairResult]) -> Result<()> {
+    if !ctx.aborted.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let count = results
+        .iter()
+        .filter(|result| matches!(result.outcome, Outcome::Aborted))
+        .count();
+    Err(Error::Aborted).with_context(|| format!("too many failures; {count} pairs were not sent"))
+}
+
+fn build_pairs(config: &Config) -> Result<Vec<TransferPair>> {
+    let mut pairs = Vec::with_capacity(config.pairs.len());
+    for (i, read) in config.pairs.iter().enumerate() {
+        let index = read.index.unwrap_or(i);
+        let mut pair = TransferPair::try_from(read.clone())?;
+        pair.index = index;
+        if let Some(run_id) = &config.run_id {
+            pair.reference = Some(reference::derive(run_id, index));
+        }
+        pair.memo = config
+            .memo
+            .as_deref()
+            .map(|template| memo::render(template, &pair.label));
+        pairs.push(pair);
     }
+    Ok(pairs)
 }
 
-#[derive(Debug, Deserialize)]
-struct Config {
-    pairs: Vec<TransferPairRead>,
+/// Sends the transfers listed in the config
+#[derive(Parser)]
+#[command(name = "send", version)]
+struct Cli {
+    #[command(flatten)]
+    common: CommonArgs,
+    #[command(flatten)]
+    args: args::Args,
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let path = env::args().nth(1).expect("Usage: send <config.yaml>");
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    logging::init(&cli.common.log_options());
+    error::exit(run(cli.common, cli.args).await)
+}
+
+pub async fn run(common: CommonArgs, args: args::Args) -> anyhow::Result<()> {
+    let bench_spec = args.bench()?;
+    let run_started = Utc::now();
+
+    common.load_address_book()?;
+    let config: Config = common.load_config()?;
+    let url = common.url(config.rpc_endpoint.as_deref(), rpc::DEVNET_URL);
+
+    let run_log = args
+        .log_dir
+        .as_deref()
+        .map(|dir| RunLog::create(dir, run_started, &config))
+        .transpose()?;
 
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let config: Config = serde_yaml::from_reader(reader)?;
+    if bench_spec.is_some() && url.contains("mainnet") && !args.i_know_what_im_doing {
+        bail!("refusing to run a benchmark against {url}; pass --i-know-what-im-doing to override");
+    }
 
-    let client = RpcClient::new_with_commitment(
-        "https://api.devnet.solana.com".to_string(),
-        CommitmentConfig::confirmed(),
+    let client = rpc::connect(
+        &url,
+        common.commitment(None, CommitmentLevel::Confirmed),
+        rpc::DEFAULT_TIMEOUT,
     );
-    let client = Arc::new(client);
 
-    let recent_blockhash = client.get_latest_blockhash().await?;
+    let pairs = build_pairs(&config)?;
+    let mut pairs = match &bench_spec {
+        Some(spec) => {
+            let Some(first) = pairs.first() else {
+                bail!("bench mode needs at least one pair to take the sender keypair from");
+            };
+            bench::pairs(&first.sender_keypair, spec)
+        }
+        None => pairs,
+    };
 
-    let mut handles = Vec::new();
+    let whitelist = config
+        .whitelist_file
+        .as_deref()
+        .map(whitelist::load)
+        .transpose()?;
+    let blocked: Vec<bool> = pairs
+        .iter()
+        .map(|pair| {
+            pair.is_transfer()
+                && whitelist
+                    .as_ref()
+                    .is_some_and(|whitelist| !whitelist.contains(&pair.recipient))
+        })
+        .collect();
+    let blocked_count = blocked.iter().filter(|blocked| **blocked).count();
+    if blocked_count > 0 && !args.skip_unlisted {
+        let unlisted: Vec<String> = pairs
+            .iter()
+            .zip(&blocked)
+            .filter(|(_, blocked)| **blocked)
+            .map(|(pair, _)| format!("  {} [{}]", pair.recipient, pair.label))
+            .collect();
+        bail!(
+            "{blocked_count} recipients are not in the whitelist (use --skip-unlisted to skip them):\n{}",
+            unlisted.join("\n")
+        );
+    }
+    let mut skip_reasons: Vec<Option<String>> = blocked
+        .iter()
+        .map(|blocked| blocked.then(|| "recipient not in whitelist".to_string()))
+        .collect();
+
+    let seeded: Vec<usize> = (0..pairs.len())
+        .filter(|i| matches!(pairs[*i].kind, PairKind::CreateWithSeed { .. }))
+        .collect();
+    for chunk in seeded.chunks(100) {
+        let addresses: Vec<Pubkey> = chunk.iter().map(|i| pairs[*i].recipient).collect();
+        let accounts = client.get_multiple_accounts(&addresses).await?;
+        for ((i, address), account) in chunk.iter().zip(&addresses).zip(accounts) {
+            if account.is_some() {
+                println!("skipping [{}]: {address} already exists", pairs[*i].label);
+                skip_reasons[*i] = Some(format!("account {address} already exists"));
+            }
+        }
+    }
+
+    size::check(
+        client.as_ref(),
+        &mut pairs,
+        &skip_reasons,
+        config.compute_unit_limit.is_some(),
+    )
+    .await?;
+
+    let vote_accounts: HashSet<_> = pairs
+        .iter()
+        .filter_map(|pair| match &pair.kind {
+            PairKind::Stake { vote_account, .. } => Some(*vote_account),
+            _ => None,
+        })
+        .collect();
+    for vote_account in &vote_accounts {
+        stake::validate_vote_account(client.as_ref(), vote_account).await?;
+    }
+
+    let mut topped_up = vec![];
+    if let Some(topup) = &config.topup {
+        let mut senders = Vec::new();
+        for pair in &pairs {
+            let sender = pair.fee_payer().pubkey();
+            if !senders.contains(&sender) {
+                senders.push(sender);
+            }
+        }
+        topped_up = topup::run(client.as_ref(), topup, senders).await?;
+    }
+
+    let blockhashes = BlockhashCache::spawn(
+        client.clone(),
+        blockhash::DEFAULT_REFRESH_INTERVAL,
+        blockhash::DEFAULT_MAX_AGE,
+    )
+    .await?;
+    let ctx = Arc::new(SendContext {
+        client,
+        compute: ComputeUnits::new(
+            config.compute_unit_limit,
+            config.compute_unit_margin_percent,
+        ),
+        pacing: Pacing::new(config.submit_jitter_ms, config.inter_submit_gap_ms),
+        blockhashes,
+        in_flight: Semaphore::new(MAX_IN_FLIGHT),
+        abort: config.abort_after_failures.clone(),
+        recent_failures: Mutex::new(VecDeque::new()),
+        aborted: AtomicBool::new(false),
+    });
 
     let start = Instant::now();
+    let mut results = send_all(&ctx, pairs, skip_reasons).await?;
+    let total_duration = start.elapsed();
+    let aborted = ctx.aborted.load(Ordering::Relaxed);
 
-    let pairs: Vec<TransferPair> = config
-        .pairs
-        .into_iter()
-        .map(|pair| pair.try_into())
-        .collect::<Result<_, _>>()?;
-
-    for pair in pairs {
-        let client = client.clone();
-        handles.push(task::spawn(async move {
-            let start = Instant::now();
-
-            let tx = Transaction::new_signed_with_payer(
-                &[system_instruction::transfer(
-                    &pair.sender_keypair.pubkey(),
-                    &pair.recipient,
-                    pair.lamports,
-                )],
-                Some(&pair.sender_keypair.pubkey()),
-                &[&pair.sender_keypair],
-                recent_blockhash,
-            );
+    println!("Total time: {:?}", total_duration);
+    if whitelist.is_some() {
+        println!("Blocked by whitelist: {blocked_count}");
+    }
+    if aborted {
+        let count = results
+            .iter()
+            .filter(|result| matches!(result.outcome, Outcome::Aborted))
+            .count();
+        println!(
+            "Aborted pairs: {count} (exit code {})",
+            Error::Aborted.exit_code()
+        );
+    }
+    println!();
 
-            let signature = client.send_and_confirm_transaction(&tx).await;
+    if bench_spec.is_some() {
+        bench::print_stats(&results, total_duration);
+        println!();
+    }
 
-            let duration = start.elapsed();
+    if let Some(run_log) = &run_log {
+        run_log.write_results(&results, &topped_up)?;
+        println!("run log written to {}\n", run_log.dir().display());
+    }
 
-            (pair.sender_keypair.pubkey(), duration, signature)
-        }));
+    if let Some(path) = &args.failed_out {
+        let count = failed::write(path, config, &results, run_started)?;
+        println!("wrote {count} failed pairs to {path}\n");
     }
 
-    let mut results = Vec::new();
-    for handle in handles {
-        results.push(handle.await?);
+    report::print_report(&mut results, common.verbose);
+
+    println!();
+    summary::print(&summary::summarize(&results));
+    topup::print(&topped_up);
+
+    finish(&ctx, &results)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::signature::Keypair;
+    use solana_test::{
+        config::load_config,
+        rpc::mock::{Failure, Landing, MockRpc},
+    };
+
+    use super::*;
+
+    fn config(senders: &[Keypair], recipients: &[Pubkey]) -> Config {
+        let pairs: String = senders
+            .iter()
+            .zip(recipients)
+            .map(|(sender, recipient)| {
+                format!(
+                    "  - sender_keypair: base58:{}\n    recipient: {recipient}\n    lamports: 1000\n",
+                    sender.to_base58_string()
+                )
+            })
+            .collect();
+        serde_yaml::from_str(&format!("run_id: payroll-2026-10\npairs:\n{pairs}")).unwrap()
     }
-    let total_duration = start.elapsed();
 
-    println!("Total time: {:?}\n", total_duration);
+    async fn context(client: Arc<MockRpc>) -> Arc<SendContext> {
+        aborting_context(client, None).await
+    }
 
-    results.sort_unstable_by_key(|(_from, duration, _sig)| std::cmp::Reverse(*duration));
+    // One pair in flight at a time, so each finishes before the next starts.
+    async fn aborting_context(
+        client: Arc<MockRpc>,
+        abort: Option<AbortConfig>,
+    ) -> Arc<SendContext> {
+        let blockhashes = BlockhashCache::spawn(
+            client.clone(),
+            blockhash::DEFAULT_REFRESH_INTERVAL,
+            blockhash::DEFAULT_MAX_AGE,
+        )
+        .await
+        .unwrap();
+        Arc::new(SendContext {
+            client,
+            compute: ComputeUnits::new(None, 10),
+            pacing: Pacing::new(None, None),
+            blockhashes,
+            in_flight: Semaphore::new(1),
+            abort,
+            recent_failures: Mutex::new(VecDeque::new()),
+            aborted: AtomicBool::new(false),
+        })
+    }
 
-    for (from, duration, sig) in results {
-        match sig {
-            Ok(sig) => {
-                println!("took {duration:?} from {from} success: {sig}");
-            }
-            Err(e) => {
-                println!("took {duration:?} from {from} error: {e}");
+    fn recipient_of(tx: &Transaction) -> Pubkey {
+        let transfer = &tx.message.instructions[0];
+        tx.message.account_keys[transfer.accounts[1] as usize]
+    }
+
+    #[tokio::test]
+    async fn rerun_from_failed_file_sends_the_failed_pair() {
+        let client = Arc::new(MockRpc::new());
+        let ctx = context(client.clone()).await;
+        let senders: Vec<Keypair> = (0..3).map(|_| Keypair::new()).collect();
+        let recipients: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let config = config(&senders, &recipients);
+
+        let mut results = Vec::new();
+        for pair in build_pairs(&config).unwrap() {
+            if pair.index == 1 {
+                client.fail(
+                    "send_transaction",
+                    Failure::Transaction(TransactionError::AccountNotFound),
+                );
             }
+            results.push(send_pair(ctx.clone(), pair).await);
+        }
+        assert!(matches!(results[0].outcome, Outcome::Confirmed(_)));
+        assert!(matches!(results[1].outcome, Outcome::Failed(_)));
+        assert!(matches!(results[2].outcome, Outcome::Confirmed(_)));
+        assert_eq!(client.sent().len(), 2);
+
+        let path = std::env::temp_dir().join(format!("failed-{}.yaml", std::process::id()));
+        let path = path.to_str().unwrap();
+        assert_eq!(
+            failed::write(path, config, &results, Utc::now()).unwrap(),
+            1
+        );
+        let rerun: Config = load_config(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let pairs = build_pairs(&rerun).unwrap();
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].index, 1);
+        let result = send_pair(ctx.clone(), pairs.into_iter().next().unwrap()).await;
+        assert!(matches!(result.outcome, Outcome::Confirmed(_)));
+        let sent = client.sent();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(recipient_of(&sent[2]), recipients[1]);
+    }
+
+    #[tokio::test]
+    async fn rerun_of_the_same_config_skips_landed_pairs() {
+        let client = Arc::new(MockRpc::new());
+        let ctx = context(client.clone()).await;
+        let senders: Vec<Keypair> = (0..2).map(|_| Keypair::new()).collect();
+        let recipients: Vec<Pubkey> = (0..2).map(|_| Pubkey::new_unique()).collect();
+        let config = config(&senders, &recipients);
+
+        for pair in build_pairs(&config).unwrap() {
+            send_pair(ctx.clone(), pair).await;
+        }
+        for pair in build_pairs(&config).unwrap() {
+            let result = send_pair(ctx.clone(), pair).await;
+            assert!(matches!(result.outcome, Outcome::AlreadyLanded(_)));
+        }
+        assert_eq!(client.sent().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn memo_template_is_rendered_per_pair() {
+        let client = Arc::new(MockRpc::new());
+        let ctx = context(client.clone()).await;
+        let mut config = config(
+            &[Keypair::new(), Keypair::new()],
+            &[Pubkey::new_unique(), Pubkey::new_unique()],
+        );
+        config.pairs[0].label = Some("alice".to_string());
+        config.memo = Some("payroll {label}".to_string());
+
+        for pair in build_pairs(&config).unwrap() {
+            send_pair(ctx.clone(), pair).await;
+        }
+
+        let memos: Vec<_> = client
+            .sent()
+            .iter()
+            .map(|tx| {
+                let memo = tx.message.instructions.last().unwrap();
+                assert_eq!(
+                    tx.message.account_keys[memo.program_id_index as usize],
+                    memo::PROGRAM_ID
+                );
+                String::from_utf8(memo.data.clone()).unwrap()
+            })
+            .collect();
+        assert_eq!(memos, ["payroll alice", "payroll "]);
+    }
+
+    async fn send_one(client: &Arc<MockRpc>) -> PairResult {
+        let ctx = context(client.clone()).await;
+        let config = config(&[Keypair::new()], &[Pubkey::new_unique()]);
+        let pair = build_pairs(&config).unwrap().into_iter().next().unwrap();
+        send_pair(ctx, pair).await
+    }
+
+    #[tokio::test]
+    async fn confirmed_pairs_record_their_fee() {
+        let client = Arc::new(MockRpc::new());
+
+        let result = send_one(&client).await;
+
+        assert!(matches!(result.outcome, Outcome::Confirmed(_)));
+        assert_eq!(result.fee, Some(5_000));
+        assert_eq!(result.fee_payer, result.sender);
+    }
+
+    #[tokio::test]
+    async fn pairs_that_fail_on_chain_still_record_their_fee() {
+        let client = Arc::new(MockRpc::new());
+        client.set_landing(Landing::Fail(TransactionError::AccountNotFound));
+
+        let result = send_one(&client).await;
+
+        assert!(matches!(result.outcome, Outcome::Failed(_)));
+        assert_eq!(result.fee, Some(5_000));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn transient_send_failures_are_retried() {
+        let client = Arc::new(MockRpc::new());
+        client.fail("send_transaction", Failure::Transport);
+        client.fail("send_transaction", Failure::RateLimited);
+
+        let result = send_one(&client).await;
+
+        assert!(matches!(result.outcome, Outcome::Confirmed(_)));
+        assert_eq!(client.calls("send_transaction"), 3);
+        assert_eq!(client.sent().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn send_gives_up_after_the_last_attempt() {
+        let client = Arc::new(MockRpc::new());
+        let attempts = RetryPolicy::default().max_attempts as usize;
+        client.fail_times("send_transaction", attempts, Failure::Transport);
+
+        let result = send_one(&client).await;
+
+        assert!(matches!(
+            result.outcome,
+            Outcome::Failed(Error::RpcTransport(_))
+        ));
+        assert_eq!(client.calls("send_transaction"), attempts);
+        assert!(client.sent().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rejected_transactions_are_not_retried() {
+        let client = Arc::new(MockRpc::new());
+        client.fail(
+            "send_transaction",
+            Failure::Transaction(TransactionError::InsufficientFundsForFee),
+        );
+
+        let result = send_one(&client).await;
+
+        assert!(matches!(
+            result.outcome,
+            Outcome::Failed(Error::InsufficientFunds(_))
+        ));
+        assert_eq!(client.calls("send_transaction"), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dropped_transaction_fails_once_its_blockhash_expires() {
+        let client = Arc::new(MockRpc::new());
+        client.set_landing(Landing::Drop);
+        client.expire_blockhash();
+
+        let result = send_one(&client).await;
+
+        assert!(matches!(
+            result.outcome,
+            Outcome::Failed(Error::BlockhashExpired)
+        ));
+        assert_eq!(client.sent().len(), 1);
+        // it never landed, so it paid nothing
+        assert_eq!(result.fee, None);
+    }
+
+    #[tokio::test]
+    async fn pairs_after_too_many_failures_are_aborted() {
+        let client = Arc::new(MockRpc::new());
+        client.fail_times(
+            "send_transaction",
+            2,
+            Failure::Transaction(TransactionError::AccountNotFound),
+        );
+        let abort = AbortConfig {
+            count: 1,
+            window: 3,
+        };
+        let ctx = aborting_context(client.clone(), Some(abort)).await;
+        let senders: Vec<Keypair> = (0..5).map(|_| Keypair::new()).collect();
+        let recipients: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        let pairs = build_pairs(&config(&senders, &recipients)).unwrap();
+        let skip_reasons = vec![None; pairs.len()];
+
+        let results = send_all(&ctx, pairs, skip_reasons).await.unwrap();
+
+        assert!(matches!(results[0].outcome, Outcome::Failed(_)));
+        assert!(matches!(results[1].outcome, Outcome::Failed(_)));
+        for result in &results[2..] {
+            assert!(matches!(result.outcome, Outcome::Aborted));
         }
+        assert_eq!(client.calls("send_transaction"), 2);
+        assert_eq!(error::exit(finish(&ctx, &results)), ExitCode::from(3));
     }
 
-    Ok(())
+    #[tokio::test]
+    async fn failures_up_to_the_count_do_not_abort() {
+        let client = Arc::new(MockRpc::new());
+        client.fail(
+            "send_transaction",
+            Failure::Transaction(TransactionError::AccountNotFound),
+        );
+        let abort = AbortConfig {
+            count: 1,
+            window: 3,
+        };
+        let ctx = aborting_context(client.clone(), Some(abort)).await;
+        let senders: Vec<Keypair> = (0..3).map(|_| Keypair::new()).collect();
+        let recipients: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let pairs = build_pairs(&config(&senders, &recipients)).unwrap();
+        let skip_reasons = vec![None; pairs.len()];
+
+        let results = send_all(&ctx, pairs, skip_reasons).await.unwrap();
+
+        assert!(matches!(results[0].outcome, Outcome::Failed(_)));
+        assert!(matches!(results[1].outcome, Outcome::Confirmed(_)));
+        assert!(matches!(results[2].outcome, Outcome::Confirmed(_)));
+        assert!(finish(&ctx, &results).is_ok());
+    }
 }
@@ -0,0 +1,72 @@
+use solana_client::{
+    client_error::{ClientErrorKind, Result as ClientResult},
+    nonce_utils::nonblocking::data_from_account,
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    nonce,
+    pubkey::{Pubkey, PubkeyError},
+    system_instruction, system_program,
+};
+use solana_test::rpc::SolanaRpc;
+
+pub fn nonce_account(sender: &Pubkey, seed: &str) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_with_seed(sender, seed, &system_program::id())
+}
+
+pub async fn create_instructions(
+    client: &dyn SolanaRpc,
+    sender: &Pubkey,
+    nonce_account: &Pubkey,
+    seed: &str,
+    authority: &Pubkey,
+    extra_lamports: u64,
+) -> ClientResult<Vec<Instruction>> {
+    let rent = client
+        .get_minimum_balance_for_rent_exemption(nonce::State::size())
+        .await?;
+    Ok(system_instruction::create_nonce_account_with_seed(
+        sender,
+        nonce_account,
+        sender,
+        seed,
+        authority,
+        rent + extra_lamports,
+    ))
+}
+
+pub async fn withdraw_instruction(
+    client: &dyn SolanaRpc,
+    nonce_account: &Pubkey,
+    authority: &Pubkey,
+    lamports: Option<u64>,
+) -> ClientResult<Instruction> {
+    let lamports = match lamports {
+        Some(lamports) => lamports,
+        None => client.get_balance(nonce_account).await?,
+    };
+    Ok(system_instruction::withdraw_nonce_account(
+        nonce_account,
+        authority,
+        authority,
+        lamports,
+    ))
+}
+
+pub async fn stored_blockhash(
+    client: &dyn SolanaRpc,
+    nonce_account: &Pubkey,
+) -> ClientResult<Hash> {
+    let account = client
+        .get_account_with_commitment(nonce_account, CommitmentConfig::confirmed())
+        .await?
+        .value
+        .ok_or_else(|| {
+            ClientErrorKind::Custom(format!("nonce account {nonce_account} not found"))
+        })?;
+    let data = data_from_account(&account)
+        .map_err(|err| ClientErrorKind::Custom(format!("nonce account: {err}")))?;
+    Ok(data.blockhash())
+}
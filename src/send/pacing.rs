@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use tokio::{sync::Mutex, time::sleep};
+
+#[derive(Debug)]
+pub struct Pacing {
+    jitter_ms: Option<[u64; 2]>,
+    gap: Option<Duration>,
+    last_submit: Mutex<Option<Instant>>,
+}
+
+impl Pacing {
+    pub fn new(jitter_ms: Option<[u64; 2]>, gap_ms: Option<u64>) -> Self {
+        Self {
+            jitter_ms,
+            gap: gap_ms.map(Duration::from_millis),
+            last_submit: Mutex::new(None),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.jitter_ms.is_some() || self.gap.is_some()
+    }
+
+    // Returns the total delay applied before the caller may submit.
+    pub async fn wait(&self) -> Duration {
+        let start = Instant::now();
+
+        if let Some([min, max]) = self.jitter_ms {
+            let jitter = rand::thread_rng().gen_range(min.min(max)..=max.max(min));
+            sleep(Duration::from_millis(jitter)).await;
+        }
+
+        if let Some(gap) = self.gap {
+            let mut last_submit = self.last_submit.lock().await;
+            if let Some(last) = *last_submit {
+                let since = last.elapsed();
+                if since < gap {
+                    sleep(gap - since).await;
+                }
+            }
+            *last_submit = Some(Instant::now());
+        }
+
+        start.elapsed()
+    }
+}
@@ -0,0 +1,465 @@
+use std::collections::HashSet;
+
+use anyhow::{Context, Result, bail};
+use solana_client::client_error::{ClientErrorKind, Result as ClientResult};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction,
+};
+use solana_test::{
+    addressbook, keypair::load_keypair, rpc::SolanaRpc, send_config::TransferPairRead,
+};
+
+use super::{memo, nonce, stake, wsol};
+
+#[derive(Debug)]
+pub enum PairKind {
+    Transfer {
+        wrap_sol: bool,
+        close_to_recipient: bool,
+    },
+    // `recipient` is the derived stake account
+    Stake {
+        vote_account: Pubkey,
+        seed: String,
+    },
+    // `recipient` is the derived account
+    CreateWithSeed {
+        seed: String,
+        space: u64,
+        owner: Pubkey,
+    },
+    // `recipient` is the derived nonce account
+    CreateNonce {
+        seed: String,
+        authority: Pubkey,
+    },
+    // `recipient` is the nonce account, drained back to the sender
+    WithdrawNonce {
+        amount: Option<u64>,
+    },
+    // `recipient` is the nonce account
+    AuthorizeNonce {
+        new_authority: Pubkey,
+    },
+}
+
+#[derive(Debug)]
+pub struct TransferPair {
+    pub sender_keypair: Keypair,
+    pub recipient: Pubkey,
+    pub lamports: u64,
+    pub label: String,
+    pub kind: PairKind,
+    pub extra_signers: Vec<Keypair>,
+    pub fee_payer: Option<Keypair>,
+    pub reference: Option<Pubkey>,
+    // the config's memo template, rendered for this pair
+    pub memo: Option<String>,
+    // `instructions()` as `size::check` built them, so sending doesn't
+    // repeat its RPC calls
+    pub built: Option<Vec<Instruction>>,
+    // position in the run; see `TransferPairRead::index`
+    pub index: usize,
+}
+
+impl TransferPair {
+    pub fn fee_payer(&self) -> &Keypair {
+        self.fee_payer.as_ref().unwrap_or(&self.sender_keypair)
+    }
+
+    pub fn signers(&self) -> Vec<&Keypair> {
+        let mut signers = vec![&self.sender_keypair];
+        signers.extend(&self.extra_signers);
+        signers.extend(
+            self.fee_payer
+                .iter()
+                .filter(|fee_payer| fee_payer.pubkey() != self.sender_keypair.pubkey()),
+        );
+        signers
+    }
+
+    // The reference and the cosigners ride on the first instruction as
+    // readonly accounts; cosigners only need to be signers on the message.
+    pub fn add_accounts(&self, instructions: &mut [Instruction]) {
+        let Some(first) = instructions.first_mut() else {
+            return;
+        };
+        if let Some(reference) = self.reference {
+            first
+                .accounts
+                .push(AccountMeta::new_readonly(reference, false));
+        }
+        first.accounts.extend(
+            self.extra_signers
+                .iter()
+                .map(|signer| AccountMeta::new_readonly(signer.pubkey(), true)),
+        );
+    }
+
+    pub fn is_transfer(&self) -> bool {
+        matches!(self.kind, PairKind::Transfer { .. })
+    }
+
+    pub fn created_account(&self) -> Option<Pubkey> {
+        match self.kind {
+            PairKind::Stake { .. }
+            | PairKind::CreateWithSeed { .. }
+            | PairKind::CreateNonce { .. } => Some(self.recipient),
+            _ => None,
+        }
+    }
+
+    pub async fn instructions(&self, client: &dyn SolanaRpc) -> ClientResult<Vec<Instruction>> {
+        let sender = self.sender_keypair.pubkey();
+        let mut instructions = match &self.kind {
+            PairKind::Transfer {
+                wrap_sol: true,
+                close_to_recipient,
+            } => {
+                let rent = client
+                    .get_minimum_balance_for_rent_exemption(wsol::TOKEN_ACCOUNT_LEN as usize)
+                    .await?;
+                wsol::transfer_instructions(
+                    &sender,
+                    &self.recipient,
+                    self.index,
+                    self.lamports,
+                    rent,
+                    *close_to_recipient,
+                )
+                .map_err(|err| ClientErrorKind::Custom(format!("wSOL instructions: {err}")))?
+            }
+            PairKind::Transfer { .. } => vec![system_instruction::transfer(
+                &sender,
+                &self.recipient,
+                self.lamports,
+            )],
+            PairKind::Stake { vote_account, seed } => {
+                let rent = client
+                    .get_minimum_balance_for_rent_exemption(stake::account_len())
+                    .await?;
+                stake::instructions(
+                    &sender,
+                    &self.recipient,
+                    seed,
+                    vote_account,
+                    self.lamports + rent,
+                )
+            }
+            PairKind::CreateWithSeed { seed, space, owner } => {
+                vec![system_instruction::create_account_with_seed(
+                    &sender,
+                    &self.recipient,
+                    &sender,
+                    seed,
+                    self.lamports,
+                    *space,
+                    owner,
+                )]
+            }
+            PairKind::CreateNonce { seed, authority } => {
+                nonce::create_instructions(
+                    client,
+                    &sender,
+                    &self.recipient,
+                    seed,
+                    authority,
+                    self.lamports,
+                )
+                .await?
+            }
+            PairKind::WithdrawNonce { amount } => {
+                vec![nonce::withdraw_instruction(client, &self.recipient, &sender, *amount).await?]
+            }
+            PairKind::AuthorizeNonce { new_authority } => {
+                vec![system_instruction::authorize_nonce_account(
+                    &self.recipient,
+                    &sender,
+                    new_authority,
+                )]
+            }
+        };
+        if let Some(memo) = &self.memo {
+            instructions.push(memo::instruction(memo));
+        }
+        Ok(instructions)
+    }
+}
+
+impl TryFrom<TransferPairRead> for TransferPair {
+    type Error = anyhow::Error;
+
+    fn try_from(value: TransferPairRead) -> anyhow::Result<Self> {
+        let TransferPairRead {
+            sender_keypair,
+            recipient,
+            lamports,
+            label,
+            mint,
+            close_to_recipient,
+            stake,
+            create_with_seed,
+            create_nonce,
+            withdraw_nonce,
+            authorize_nonce,
+            extra_signers,
+            fee_payer,
+            index,
+        } = value;
+
+        let sender_keypair = load_keypair(&sender_keypair)?;
+        let extra_signers = extra_signers
+            .iter()
+            .map(|path| load_keypair(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut seen = HashSet::from([sender_keypair.pubkey()]);
+        for signer in &extra_signers {
+            if !seen.insert(signer.pubkey()) {
+                bail!(
+                    "extra signer {} duplicates the sender or another extra signer",
+                    signer.pubkey()
+                );
+            }
+        }
+
+        let sections = [
+            recipient.is_some(),
+            stake.is_some(),
+            create_with_seed.is_some(),
+            create_nonce.is_some(),
+            withdraw_nonce.is_some(),
+            authorize_nonce.is_some(),
+        ];
+        if sections.into_iter().filter(|present| *present).count() != 1 {
+            bail!(
+                "a pair needs exactly one of `recipient`, `stake`, `create_with_seed`, \
+                 `create_nonce`, `withdraw_nonce` or `authorize_nonce`"
+            );
+        }
+        if lamports.is_some() && recipient.is_none() {
+            bail!("`lamports` only applies to transfer pairs");
+        }
+        let sender = sender_keypair.pubkey();
+
+        let (recipient, lamports, kind) = if let Some(stake) = stake {
+            let vote_account = addressbook::resolve(&stake.vote_account)?;
+            let seed = stake
+                .seed
+                .unwrap_or_else(|| stake::default_seed(&vote_account));
+            let stake_account = stake::stake_account(&sender, &seed)?;
+            let lamports = (stake.amount_sol * LAMPORTS_PER_SOL as f64).round() as u64;
+            (
+                stake_account,
+                lamports,
+                PairKind::Stake { vote_account, seed },
+            )
+        } else if let Some(create) = create_with_seed {
+            let owner = addressbook::resolve(&create.owner)?;
+            let address = Pubkey::create_with_seed(&sender, &create.seed, &owner)?;
+            (
+                address,
+                create.lamports,
+                PairKind::CreateWithSeed {
+                    seed: create.seed,
+                    space: create.space,
+                    owner,
+                },
+            )
+        } else if let Some(create) = create_nonce {
+            let address = nonce::nonce_account(&sender, &create.seed)?;
+            let authority = match create.authority {
+                Some(authority) => addressbook::resolve(&authority)?,
+                None => sender,
+            };
+            (
+                address,
+                create.lamports,
+                PairKind::CreateNonce {
+                    seed: create.seed,
+                    authority,
+                },
+            )
+        } else if let Some(withdraw) = withdraw_nonce {
+            (
+                addressbook::resolve(&withdraw.nonce_account)?,
+                withdraw.lamports.unwrap_or_default(),
+                PairKind::WithdrawNonce {
+                    amount: withdraw.lamports,
+                },
+            )
+        } else if let Some(authorize) = authorize_nonce {
+            (
+                addressbook::resolve(&authorize.nonce_account)?,
+                0,
+                PairKind::AuthorizeNonce {
+                    new_authority: addressbook::resolve(&authorize.new_authority)?,
+                },
+            )
+        } else {
+            let recipient = recipient.context("transfer pairs need a `recipient`")?;
+            let lamports = lamports.context("transfer pairs need `lamports`")?;
+            let wrap_sol = match mint {
+                Some(ref mint) if addressbook::resolve(mint)? == spl_token::native_mint::id() => {
+                    true
+                }
+                Some(mint) => {
+                    bail!("only the native mint is supported for token pairs, got {mint}")
+                }
+                None => false,
+            };
+            if close_to_recipient && !wrap_sol {
+                bail!("close_to_recipient requires the native mint");
+            }
+            (
+                addressbook::resolve(&recipient)?,
+                lamports,
+                PairKind::Transfer {
+                    wrap_sol,
+                    close_to_recipient,
+                },
+            )
+        };
+
+        Ok(Self {
+            sender_keypair,
+            recipient,
+            lamports,
+            // the recipient's address book name unless the pair has its own
+            label: label
+                .or_else(|| addressbook::label(&recipient).map(str::to_string))
+                .unwrap_or_default(),
+            kind,
+            extra_signers,
+            fee_payer: fee_payer.map(|path| load_keypair(&path)).transpose()?,
+            reference: None,
+            memo: None,
+            built: None,
+            index: index.unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{stake::program as stake_program, system_program};
+    use solana_test::rpc::mock::MockRpc;
+
+    use super::*;
+
+    // the keypair from the seed [7; 32]
+    const SENDER_SECRET: &str = "base58:99eUso3aSbE9tqGSTXzo3TLfKb9RkMTURrHKQ1K7Zh3StnzFNUx8FKCPPPPpR479qsw5zv2WNBKmgiz7WqgAJfM";
+    const SENDER: &str = "GmaDrppBC7P5ARKV8g3djiwP89vz1jLK23V2GBjuAEGB";
+
+    fn pair(section: &str) -> Result<TransferPair> {
+        let read: TransferPairRead =
+            serde_yaml::from_str(&format!("sender_keypair: {SENDER_SECRET}\n{section}")).unwrap();
+        TransferPair::try_from(read)
+    }
+
+    fn address(pair: &TransferPair) -> String {
+        pair.recipient.to_string()
+    }
+
+    // Expected addresses are sha256(base || seed || owner), worked out apart
+    // from the SDK.
+    #[test]
+    fn create_with_seed_derives_from_the_sender_seed_and_owner() {
+        let system = pair(
+            "create_with_seed: {seed: escrow-1, lamports: 1000000, space: 0, owner: '11111111111111111111111111111111'}",
+        )
+        .unwrap();
+        let memo = pair(
+            "create_with_seed: {seed: escrow-1, lamports: 1000000, space: 64, owner: MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr}",
+        )
+        .unwrap();
+
+        assert_eq!(system.sender_keypair.pubkey().to_string(), SENDER);
+        assert_eq!(
+            address(&system),
+            "AB49ucLWqxaZGV3d9goscHxzPF2vRsd5S3NaYtctLqSd"
+        );
+        assert_eq!(
+            address(&memo),
+            "HbbEfEHH83W93YuH1JhpW2TSnF9PtJsQvAwajUF7ae4U"
+        );
+        assert_eq!(memo.created_account(), Some(memo.recipient));
+        assert_eq!(memo.lamports, 1_000_000);
+        let PairKind::CreateWithSeed { seed, space, owner } = &memo.kind else {
+            panic!("expected a create_with_seed pair, got {:?}", memo.kind);
+        };
+        assert_eq!(seed, "escrow-1");
+        assert_eq!(*space, 64);
+        assert_eq!(
+            owner.to_string(),
+            "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr"
+        );
+    }
+
+    #[test]
+    fn nonce_and_stake_accounts_derive_the_same_way() {
+        let nonce = pair("create_nonce: {seed: nonce-0, lamports: 1500000}").unwrap();
+        let stake = pair(
+            "stake: {amount_sol: 1.0, vote_account: Vote111111111111111111111111111111111111111}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            address(&nonce),
+            "F4oPtHUPedyKzTwifFRnNh7X241vdFPEMyVJUtkBMGrJ"
+        );
+        // the default seed is `stake-` and the vote account's first 16 chars
+        assert_eq!(
+            address(&stake),
+            "EwBQP32VJkpVrgNZHfrqHDizxeSgxKsATAw5nPHrS6rC"
+        );
+        assert_eq!(
+            stake.recipient,
+            Pubkey::create_with_seed(
+                &stake.sender_keypair.pubkey(),
+                "stake-Vote111111111111",
+                &stake_program::id()
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn seeds_over_32_bytes_are_rejected() {
+        let seed = "s".repeat(33);
+
+        let result = pair(&format!(
+            "create_with_seed: {{seed: {seed}, lamports: 1, space: 0, owner: '11111111111111111111111111111111'}}"
+        ));
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn create_with_seed_funds_the_derived_address_from_the_sender() {
+        let pair = pair(
+            "create_with_seed: {seed: escrow-1, lamports: 1000000, space: 0, owner: '11111111111111111111111111111111'}",
+        )
+        .unwrap();
+        let sender = pair.sender_keypair.pubkey();
+
+        let instructions = pair.instructions(&MockRpc::new()).await.unwrap();
+
+        assert_eq!(
+            instructions,
+            [system_instruction::create_account_with_seed(
+                &sender,
+                &pair.recipient,
+                &sender,
+                "escrow-1",
+                1_000_000,
+                0,
+                &system_program::id(),
+            )]
+        );
+    }
+}
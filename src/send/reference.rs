@@ -0,0 +1,154 @@
+// Idempotency references: with a `run_id` configured every pair carries a
+// reference pubkey derived from the run id and the pair's index (its position
+// in the config, or in the original run for pairs from a `--failed-out`
+// file), attached as a read-only account to its first instruction. Before
+// sending, the reference's signature history tells us whether an earlier
+// attempt of the same run already landed.
+//
+// Limits: the reference only identifies a pair within an unchanged config, so
+// reordering pairs or reusing a run id for a different payout list breaks the
+// mapping. Detection relies on `getSignaturesForAddress`, which only sees
+// transactions the RPC node has indexed (and none that are still in flight),
+// so a retry racing an unconfirmed earlier attempt can still double-send.
+
+use solana_client::client_error::Result as ClientResult;
+use solana_sdk::{hash::hashv, pubkey::Pubkey, signature::Signature};
+use solana_test::rpc::SolanaRpc;
+
+pub fn derive(run_id: &str, pair_index: usize) -> Pubkey {
+    let hash = hashv(&[
+        b"solana_test-reference",
+        run_id.as_bytes(),
+        &(pair_index as u64).to_le_bytes(),
+    ]);
+    Pubkey::new_from_array(hash.to_bytes())
+}
+
+pub async fn prior_landing(
+    client: &dyn SolanaRpc,
+    reference: &Pubkey,
+) -> ClientResult<Option<Signature>> {
+    let statuses = client.get_signatures_for_address(reference).await?;
+    Ok(statuses
+        .into_iter()
+        .filter(|status| status.err.is_none())
+        .find_map(|status| status.signature.parse().ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use solana_sdk::{
+        commitment_config::CommitmentConfig, hash::Hash, instruction::AccountMeta,
+        native_token::LAMPORTS_PER_SOL, signature::Keypair, signer::Signer, system_instruction,
+        transaction::Transaction, transaction::TransactionError,
+    };
+    use solana_test::rpc::{
+        self,
+        mock::{Landing, MockRpc},
+    };
+
+    use super::*;
+
+    // Sends a transfer carrying `reference`, as `send_pair` would.
+    async fn send_with(client: &MockRpc, reference: Pubkey) -> Signature {
+        let sender = Keypair::new();
+        let mut transfer = system_instruction::transfer(&sender.pubkey(), &Pubkey::new_unique(), 1);
+        transfer
+            .accounts
+            .push(AccountMeta::new_readonly(reference, false));
+        let tx = Transaction::new_signed_with_payer(
+            &[transfer],
+            Some(&sender.pubkey()),
+            &[&sender],
+            Hash::new_unique(),
+        );
+        client.send_transaction(&tx).await.unwrap()
+    }
+
+    #[test]
+    fn derive_is_stable_per_run_and_index() {
+        assert_eq!(derive("payroll-2026-10", 3), derive("payroll-2026-10", 3));
+        assert_ne!(derive("payroll-2026-10", 3), derive("payroll-2026-10", 4));
+        assert_ne!(derive("payroll-2026-10", 3), derive("payroll-2026-11", 3));
+    }
+
+    #[test]
+    fn derive_is_pinned() {
+        // changing the derivation would resend every pair of a rerun
+        assert_eq!(
+            derive("payroll-2026-10", 0).to_string(),
+            "nXsiNGDmREHAgsCV1172hm9eTFP6cTVucUyy7Y6n5Qu"
+        );
+    }
+
+    #[tokio::test]
+    async fn finds_a_landed_transaction() {
+        let client = MockRpc::new();
+        let reference = derive("payroll-2026-10", 0);
+        assert_eq!(prior_landing(&client, &reference).await.unwrap(), None);
+
+        let signature = send_with(&client, reference).await;
+        send_with(&client, derive("payroll-2026-10", 1)).await;
+
+        assert_eq!(
+            prior_landing(&client, &reference).await.unwrap(),
+            Some(signature)
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_failed_and_dropped_transactions() {
+        let client = MockRpc::new();
+        let reference = derive("payroll-2026-10", 0);
+        client.set_landing(Landing::Fail(TransactionError::InsufficientFundsForFee));
+        send_with(&client, reference).await;
+        client.set_landing(Landing::Drop);
+        send_with(&client, reference).await;
+
+        assert_eq!(prior_landing(&client, &reference).await.unwrap(), None);
+    }
+
+    // The mock's signature index is only a stand-in for the node's, so this
+    // checks a real one: start `solana-test-validator` and run it with
+    // `cargo test -- --ignored`.
+    #[tokio::test]
+    #[ignore = "needs solana-test-validator on 127.0.0.1:8899"]
+    async fn finds_a_transaction_landed_on_a_validator() {
+        let client = rpc::build_rpc_client(
+            "localhost",
+            CommitmentConfig::confirmed(),
+            rpc::DEFAULT_TIMEOUT,
+        );
+        let sender = Keypair::new();
+        let airdrop = client
+            .request_airdrop(&sender.pubkey(), LAMPORTS_PER_SOL)
+            .await
+            .unwrap();
+        while !client.confirm_transaction(&airdrop).await.unwrap() {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        // a run id no earlier test run has used
+        let reference = derive(&Pubkey::new_unique().to_string(), 0);
+        assert_eq!(prior_landing(&client, &reference).await.unwrap(), None);
+
+        let mut transfer =
+            system_instruction::transfer(&sender.pubkey(), &Pubkey::new_unique(), 1_000_000);
+        transfer
+            .accounts
+            .push(AccountMeta::new_readonly(reference, false));
+        let tx = Transaction::new_signed_with_payer(
+            &[transfer],
+            Some(&sender.pubkey()),
+            &[&sender],
+            client.get_latest_blockhash().await.unwrap(),
+        );
+        let signature = client.send_and_confirm_transaction(&tx).await.unwrap();
+
+        assert_eq!(
+            prior_landing(&client, &reference).await.unwrap(),
+            Some(signature)
+        );
+    }
+}
@@ -0,0 +1,386 @@
+use std::time::Duration;
+
+use serde::Serialize;
+use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature, signer::Signer};
+use solana_test::{Error, addressbook};
+
+use super::{pair::TransferPair, summary, topup::TopUp};
+
+#[derive(Debug)]
+pub enum Outcome {
+    Confirmed(Signature),
+    // landed during an earlier attempt of the same run
+    AlreadyLanded(Signature),
+    Failed(Error),
+    Skipped(String),
+    Aborted,
+}
+
+impl Outcome {
+    pub fn is_confirmed(&self) -> bool {
+        matches!(self, Self::Confirmed(_) | Self::AlreadyLanded(_))
+    }
+
+    pub fn status(&self) -> &'static str {
+        match self {
+            Self::Confirmed(_) => "confirmed",
+            Self::AlreadyLanded(_) => "already_landed",
+            Self::Failed(_) => "failed",
+            Self::Skipped(_) => "skipped",
+            Self::Aborted => "aborted",
+        }
+    }
+}
+
+impl From<Result<Signature, Error>> for Outcome {
+    fn from(value: Result<Signature, Error>) -> Self {
+        match value {
+            Ok(signature) => Self::Confirmed(signature),
+            Err(err) => Self::Failed(err),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PairResult {
+    pub label: String,
+    pub sender: Pubkey,
+    // who pays `fee`: the pair's fee payer if it has one, else the sender
+    pub fee_payer: Pubkey,
+    pub recipient: Pubkey,
+    pub signers: Vec<Pubkey>,
+    pub lamports: u64,
+    pub created_account: Option<Pubkey>,
+    pub nonce_blockhash: Option<Hash>,
+    pub compute_unit_limit: Option<u32>,
+    // set once the transaction lands, whether it succeeds or fails
+    pub fee: Option<u64>,
+    pub submit_delay: Option<Duration>,
+    pub sent: Option<Duration>,
+    pub duration: Duration,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Record {
+    pub label: String,
+    pub sender: String,
+    pub recipient: String,
+    pub lamports: u64,
+    pub status: &'static str,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+    // `Error::kind` of a failure
+    pub error_kind: Option<&'static str>,
+    pub duration_ms: u128,
+    pub compute_unit_limit: Option<u32>,
+    pub fee: Option<u64>,
+    pub fee_payer: String,
+    pub created_account: Option<String>,
+    pub nonce_blockhash: Option<String>,
+}
+
+impl PairResult {
+    pub fn record(&self) -> Record {
+        let (signature, error, error_kind) = match &self.outcome {
+            Outcome::Confirmed(sig) | Outcome::AlreadyLanded(sig) => {
+                (Some(sig.to_string()), None, None)
+            }
+            Outcome::Failed(err) => (None, Some(err.to_string()), Some(err.kind())),
+            Outcome::Skipped(reason) => (None, Some(reason.clone()), None),
+            Outcome::Aborted => (None, None, None),
+        };
+        Record {
+            label: self.label.clone(),
+            sender: self.sender.to_string(),
+            recipient: self.recipient.to_string(),
+            lamports: self.lamports,
+            status: self.outcome.status(),
+            signature,
+            error,
+            error_kind,
+            duration_ms: self.duration.as_millis(),
+            compute_unit_limit: self.compute_unit_limit,
+            fee: self.fee,
+            fee_payer: self.fee_payer.to_string(),
+            created_account: self.created_account.map(|account| account.to_string()),
+            nonce_blockhash: self.nonce_blockhash.map(|hash| hash.to_string()),
+        }
+    }
+
+    pub fn skipped(pair: &TransferPair, reason: String) -> Self {
+        Self {
+            label: pair.label.clone(),
+            sender: pair.sender_keypair.pubkey(),
+            fee_payer: pair.fee_payer().pubkey(),
+            recipient: pair.recipient,
+            signers: pair
+                .signers()
+                .iter()
+                .map(|signer| signer.pubkey())
+                .collect(),
+            lamports: pair.lamports,
+            created_account: None,
+            nonce_blockhash: None,
+            compute_unit_limit: None,
+            fee: None,
+            submit_delay: None,
+            sent: None,
+            duration: Duration::ZERO,
+            outcome: Outcome::Skipped(reason),
+        }
+    }
+
+    // The result's line, after a line of signers when it has cosigners.
+    pub fn render(&self, verbose: bool) -> String {
+        let Self {
+            label,
+            sender,
+            duration,
+            compute_unit_limit,
+            signers,
+            submit_delay,
+            created_account,
+            nonce_blockhash,
+            ..
+        } = self;
+        let sender = addressbook::display(sender);
+        let mut cu = match compute_unit_limit {
+            Some(limit) => format!(" (cu limit {limit})"),
+            None => String::new(),
+        };
+        if let Some(delay) = submit_delay.filter(|_| verbose) {
+            cu += &format!(" (submit delayed {delay:?})");
+        }
+        if let Some(account) = created_account {
+            cu += &format!(" (created {})", addressbook::display(account));
+        }
+        if let Some(blockhash) = nonce_blockhash {
+            cu += &format!(" (nonce blockhash {blockhash})");
+        }
+        let mut out = String::new();
+        if signers.len() > 1 {
+            let signers: Vec<String> = signers.iter().map(|signer| signer.to_string()).collect();
+            out += &format!("signers of {sender} [{label}]: {}\n", signers.join(", "));
+        }
+        out += &match &self.outcome {
+            Outcome::Confirmed(sig) => {
+                format!("took {duration:?} from {sender} [{label}]{cu} success: {sig}")
+            }
+            Outcome::AlreadyLanded(sig) => {
+                format!("from {sender} [{label}] already landed earlier: {sig}")
+            }
+            Outcome::Failed(e) => {
+                format!("took {duration:?} from {sender} [{label}]{cu} error: {e}")
+            }
+            Outcome::Skipped(reason) => format!("skipped from {sender} [{label}]: {reason}"),
+            Outcome::Aborted => format!("aborted from {sender} [{label}]"),
+        };
+        out
+    }
+}
+
+// Slowest first.
+pub fn render_report(results: &mut [PairResult], verbose: bool) -> String {
+    results.sort_unstable_by_key(|result| std::cmp::Reverse(result.duration));
+
+    results
+        .iter()
+        .map(|result| result.render(verbose) + "\n")
+        .collect()
+}
+
+pub fn print_report(results: &mut [PairResult], verbose: bool) {
+    print!("{}", render_report(results, verbose));
+}
+
+// `topped_up` lists the senders airdropped before the run.
+pub fn to_json(results: &[PairResult], topped_up: &[TopUp]) -> serde_json::Result<String> {
+    let records: Vec<Record> = results.iter().map(PairResult::record).collect();
+    serde_json::to_string_pretty(&serde_json::json!({
+        "results": records,
+        "summary": summary::summarize(results),
+        "topped_up": topped_up,
+    }))
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn to_csv(results: &[PairResult]) -> String {
+    let mut out = String::from(
+        "label,sender,recipient,lamports,status,signature,error,error_kind,duration_ms,compute_unit_limit,fee,fee_payer,created_account,nonce_blockhash\n",
+    );
+    for result in results {
+        let record = result.record();
+        let row = [
+            csv_field(&record.label),
+            record.sender,
+            record.recipient,
+            record.lamports.to_string(),
+            record.status.to_string(),
+            record.signature.unwrap_or_default(),
+            csv_field(&record.error.unwrap_or_default()),
+            record.error_kind.unwrap_or_default().to_string(),
+            record.duration_ms.to_string(),
+            record
+                .compute_unit_limit
+                .map(|limit| limit.to_string())
+                .unwrap_or_default(),
+            record.fee.map(|fee| fee.to_string()).unwrap_or_default(),
+            record.fee_payer,
+            record.created_account.unwrap_or_default(),
+            record.nonce_blockhash.unwrap_or_default(),
+        ];
+        out += &row.join(",");
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SENDER: &str = "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi";
+    const RECIPIENT: &str = "8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR";
+    const COSIGNER: &str = "CktRuQ2mttgRGkXJtyksdKHjUdc2C4TgDzyB98oEzy8";
+
+    fn result(label: &str, duration_ms: u64, outcome: Outcome) -> PairResult {
+        let sender = Pubkey::new_from_array([1; 32]);
+        PairResult {
+            label: label.to_string(),
+            sender,
+            fee_payer: sender,
+            recipient: Pubkey::new_from_array([2; 32]),
+            signers: vec![sender],
+            lamports: 1_000,
+            created_account: None,
+            nonce_blockhash: None,
+            compute_unit_limit: None,
+            fee: None,
+            submit_delay: None,
+            sent: None,
+            duration: Duration::from_millis(duration_ms),
+            outcome,
+        }
+    }
+
+    fn confirmed(label: &str, duration_ms: u64) -> PairResult {
+        PairResult {
+            compute_unit_limit: Some(450),
+            fee: Some(5_000),
+            ..result(label, duration_ms, Outcome::Confirmed(Signature::default()))
+        }
+    }
+
+    fn failed(label: &str, duration_ms: u64) -> PairResult {
+        result(label, duration_ms, Outcome::Failed(Error::BlockhashExpired))
+    }
+
+    #[test]
+    fn the_report_lists_the_slowest_first() {
+        let mut results = [
+            confirmed("alice", 20),
+            failed("", 900),
+            result(
+                "carol",
+                0,
+                Outcome::Skipped("recipient not in the whitelist".to_string()),
+            ),
+        ];
+
+        let report = render_report(&mut results, false);
+
+        let sig = Signature::default();
+        assert_eq!(
+            report,
+            format!(
+                "took 900ms from {SENDER} [] error: blockhash expired before the transaction landed\n\
+                 took 20ms from {SENDER} [alice] (cu limit 450) success: {sig}\n\
+                 skipped from {SENDER} [carol]: recipient not in the whitelist\n"
+            )
+        );
+    }
+
+    #[test]
+    fn cosigners_and_delays_add_to_a_line() {
+        let cosigner = Pubkey::new_from_array([3; 32]);
+        let mut result = confirmed("alice", 20);
+        result.signers.push(cosigner);
+        result.submit_delay = Some(Duration::from_millis(150));
+        result.created_account = Some(cosigner);
+
+        let sig = Signature::default();
+        assert_eq!(
+            result.render(true),
+            format!(
+                "signers of {SENDER} [alice]: {SENDER}, {COSIGNER}\n\
+                 took 20ms from {SENDER} [alice] (cu limit 450) (submit delayed 150ms) (created {COSIGNER}) success: {sig}"
+            )
+        );
+        assert!(!result.render(false).contains("submit delayed"));
+    }
+
+    #[test]
+    fn json_carries_each_record_and_the_summary() {
+        let results = [confirmed("alice", 20), failed("", 900)];
+        let topped_up = [TopUp {
+            address: SENDER.to_string(),
+            lamports: 2_000_000_000,
+        }];
+
+        let json = to_json(&results, &topped_up).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let confirmed = &json["results"][0];
+        assert_eq!(confirmed["label"], "alice");
+        assert_eq!(confirmed["sender"], SENDER);
+        assert_eq!(confirmed["recipient"], RECIPIENT);
+        assert_eq!(confirmed["lamports"], 1_000);
+        assert_eq!(confirmed["status"], "confirmed");
+        assert_eq!(confirmed["signature"], Signature::default().to_string());
+        assert_eq!(confirmed["error"], serde_json::Value::Null);
+        assert_eq!(confirmed["duration_ms"], 20);
+        assert_eq!(confirmed["compute_unit_limit"], 450);
+        assert_eq!(confirmed["fee"], 5_000);
+        assert_eq!(confirmed["fee_payer"], SENDER);
+        let failed = &json["results"][1];
+        assert_eq!(failed["label"], "");
+        assert_eq!(failed["status"], "failed");
+        assert_eq!(failed["signature"], serde_json::Value::Null);
+        assert_eq!(failed["error_kind"], "blockhash_expired");
+        assert_eq!(json["summary"]["total"]["pairs"], 2);
+        assert_eq!(
+            json["topped_up"],
+            serde_json::json!([{"address": SENDER, "lamports": 2_000_000_000}])
+        );
+    }
+
+    #[test]
+    fn csv_quotes_labels_and_errors_and_leaves_missing_values_empty() {
+        let results = [
+            confirmed("Smith, Jane", 20),
+            result("", 5, Outcome::Skipped("said \"no\"".to_string())),
+        ];
+
+        let csv = to_csv(&results);
+
+        let sig = Signature::default();
+        assert_eq!(
+            csv.lines().collect::<Vec<_>>(),
+            vec![
+                "label,sender,recipient,lamports,status,signature,error,error_kind,duration_ms,compute_unit_limit,fee,fee_payer,created_account,nonce_blockhash".to_string(),
+                format!(
+                    "\"Smith, Jane\",{SENDER},{RECIPIENT},1000,confirmed,{sig},,,20,450,5000,{SENDER},,"
+                ),
+                format!(",{SENDER},{RECIPIENT},1000,skipped,,\"said \"\"no\"\"\",,5,,,{SENDER},,"),
+            ]
+        );
+    }
+}
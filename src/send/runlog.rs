@@ -0,0 +1,123 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use solana_test::logging;
+
+use super::{Config, report, report::PairResult, topup::TopUp};
+
+const REDACTED: &str = "<redacted>";
+
+pub struct RunLog {
+    dir: PathBuf,
+}
+
+pub fn redact(config: &Config) -> Config {
+    let mut config = config.clone();
+    for pair in &mut config.pairs {
+        pair.sender_keypair = REDACTED.to_string();
+        for signer in &mut pair.extra_signers {
+            *signer = REDACTED.to_string();
+        }
+        if let Some(fee_payer) = &mut pair.fee_payer {
+            *fee_payer = REDACTED.to_string();
+        }
+    }
+    config
+}
+
+impl RunLog {
+    pub fn create(root: &str, started: DateTime<Utc>, config: &Config) -> anyhow::Result<Self> {
+        let dir = Path::new(root).join(started.format("%Y%m%dT%H%M%SZ").to_string());
+        fs::create_dir_all(&dir)?;
+
+        fs::write(
+            dir.join("config.yaml"),
+            serde_yaml::to_string(&redact(config))?,
+        )?;
+
+        // `--log-file`, when given, has the logs instead
+        if !logging::log_to_file(&dir.join("send.log"))? {
+            tracing::info!(dir = %dir.display(), "run logs go to --log-file, not send.log");
+        }
+
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn write_results(&self, results: &[PairResult], topped_up: &[TopUp]) -> anyhow::Result<()> {
+        fs::write(
+            self.dir.join("results.json"),
+            report::to_json(results, topped_up)?,
+        )?;
+        fs::write(self.dir.join("results.csv"), report::to_csv(results))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str =
+        "4NMwxzmYj2uvHuq8xoqhY8RXg63KSVJM1DXkpbmkUY7YQWuoyQgFnnzn6yo3CMnqZasnNPNuAT2TLwQsCaKkUddp";
+
+    // Every keypair field of a pair, with `spec` in each.
+    fn config(spec: &str) -> Config {
+        serde_yaml::from_str(&format!(
+            r#"
+pairs:
+  - sender_keypair: "{spec}"
+    recipient: D6U1xDjZK15v25mx7z8Nr23EcoZGZvqs3BpWL9E4zyst
+    lamports: 7
+    extra_signers: ["{spec}", "{spec}"]
+    fee_payer: "{spec}"
+"#
+        ))
+        .unwrap()
+    }
+
+    fn assert_redacted(spec: &str) {
+        let config = redact(&config(spec));
+        let pair = &config.pairs[0];
+        assert_eq!(pair.sender_keypair, REDACTED);
+        assert_eq!(pair.extra_signers, [REDACTED, REDACTED]);
+        assert_eq!(pair.fee_payer.as_deref(), Some(REDACTED));
+        assert_eq!(
+            pair.recipient.as_deref(),
+            Some("D6U1xDjZK15v25mx7z8Nr23EcoZGZvqs3BpWL9E4zyst")
+        );
+        assert!(!serde_yaml::to_string(&config).unwrap().contains(spec));
+    }
+
+    #[test]
+    fn redacts_keypair_paths() {
+        assert_redacted("/home/alice/.config/solana/id.json");
+    }
+
+    #[test]
+    fn redacts_env_specs() {
+        assert_redacted("env:SENDER_SECRET");
+    }
+
+    #[test]
+    fn redacts_base58_secrets() {
+        assert_redacted(&format!("base58:{SECRET}"));
+    }
+
+    #[test]
+    fn run_log_config_has_no_secrets() {
+        let root = std::env::temp_dir().join(format!("runlog-test-{}", std::process::id()));
+        let spec = format!("base58:{SECRET}");
+        let run_log = RunLog::create(root.to_str().unwrap(), Utc::now(), &config(&spec)).unwrap();
+        let written = fs::read_to_string(run_log.dir().join("config.yaml")).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        assert!(written.contains(REDACTED));
+        assert!(!written.contains(SECRET));
+    }
+}
@@ -0,0 +1,214 @@
+use anyhow::Context;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction, instruction::Instruction, message::Message,
+    packet::PACKET_DATA_SIZE, signer::Signer,
+};
+use solana_test::{Error, rpc::SolanaRpc};
+
+use super::pair::TransferPair;
+
+const SIGNATURE_LEN: usize = 64;
+
+fn short_vec_len(len: usize) -> usize {
+    match len {
+        0..0x80 => 1,
+        0x80..0x4000 => 2,
+        _ => 3,
+    }
+}
+
+// Size of the signed transaction on the wire, computed from the unsigned message.
+pub fn packet_size(message: &Message) -> usize {
+    let signatures = message.header.num_required_signatures as usize;
+    short_vec_len(signatures) + signatures * SIGNATURE_LEN + message.serialize().len()
+}
+
+// Returns the number of bytes over the packet limit, if any.
+pub fn overage(message: &Message) -> Option<usize> {
+    packet_size(message)
+        .checked_sub(PACKET_DATA_SIZE)
+        .filter(|over| *over > 0)
+}
+
+// The message `send_pair` will sign for `pair`, with a stand-in for the
+// compute unit limit instruction when one is configured; the limit's value
+// doesn't change the size.
+fn message(
+    pair: &TransferPair,
+    mut instructions: Vec<Instruction>,
+    compute_unit_limit: bool,
+) -> Message {
+    pair.add_accounts(&mut instructions);
+    if compute_unit_limit {
+        instructions.insert(0, ComputeBudgetInstruction::set_compute_unit_limit(0));
+    }
+    Message::new(&instructions, Some(&pair.fee_payer().pubkey()))
+}
+
+// Builds each pair's instructions and fails on the first pair that can't be
+// built or whose transaction wouldn't fit in a packet, before anything is
+// sent. The instructions are kept on the pair for `send_pair`.
+pub async fn check(
+    client: &dyn SolanaRpc,
+    pairs: &mut [TransferPair],
+    skip_reasons: &[Option<String>],
+    compute_unit_limit: bool,
+) -> anyhow::Result<()> {
+    for (pair, skip_reason) in pairs.iter_mut().zip(skip_reasons) {
+        if skip_reason.is_some() {
+            continue;
+        }
+        let instructions = pair
+            .instructions(client)
+            .await
+            .with_context(|| format!("building pair {} [{}]", pair.index, pair.label))?;
+        let message = message(pair, instructions.clone(), compute_unit_limit);
+        if let Some(over) = overage(&message) {
+            return Err(Error::TransactionTooLarge {
+                size: packet_size(&message),
+                over,
+            })
+            .with_context(|| {
+                format!(
+                    "pair {} [{}] has {} extra signers and {} instructions",
+                    pair.index,
+                    pair.label,
+                    pair.extra_signers.len(),
+                    message.instructions.len()
+                )
+            });
+        }
+        pair.built = Some(instructions);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{pubkey::Pubkey, signature::Keypair, system_instruction};
+    use solana_test::rpc::mock::{Failure, MockRpc};
+
+    use super::super::{pair::PairKind, wsol};
+    use super::*;
+
+    fn pair(wrap_sol: bool, extra_signers: usize) -> TransferPair {
+        TransferPair {
+            sender_keypair: Keypair::new(),
+            recipient: Pubkey::new_unique(),
+            lamports: 1_000_000,
+            label: "alice".to_string(),
+            kind: PairKind::Transfer {
+                wrap_sol,
+                close_to_recipient: false,
+            },
+            extra_signers: (0..extra_signers).map(|_| Keypair::new()).collect(),
+            fee_payer: None,
+            reference: None,
+            memo: None,
+            built: None,
+            index: 2,
+        }
+    }
+
+    #[test]
+    fn plain_transfer() {
+        let sender = Pubkey::new_unique();
+        let ix = system_instruction::transfer(&sender, &Pubkey::new_unique(), 1);
+        let message = Message::new(&[ix], Some(&sender));
+        assert_eq!(packet_size(&message), 215);
+        assert_eq!(overage(&message), None);
+    }
+
+    #[test]
+    fn wsol_pair() {
+        let sender = Pubkey::new_unique();
+        let instructions =
+            wsol::transfer_instructions(&sender, &Pubkey::new_unique(), 0, 1, 2_039_280, false)
+                .unwrap();
+        let message = Message::new(&instructions, Some(&sender));
+        assert_eq!(packet_size(&message), 556);
+        assert_eq!(overage(&message), None);
+    }
+
+    // A transfer plus an instruction whose data pads the packet to `size`.
+    fn padded(size: usize) -> Message {
+        let sender = Pubkey::new_unique();
+        let transfer = system_instruction::transfer(&sender, &Pubkey::new_unique(), 1);
+        let padding =
+            |len| Instruction::new_with_bytes(Pubkey::new_unique(), &vec![0; len], vec![]);
+        let base = packet_size(&Message::new(
+            &[transfer.clone(), padding(0)],
+            Some(&sender),
+        ));
+        // data over 127 bytes takes a second length byte
+        let len = size - base - 1;
+        let message = Message::new(&[transfer, padding(len)], Some(&sender));
+        assert_eq!(packet_size(&message), size);
+        message
+    }
+
+    #[test]
+    fn at_the_limit() {
+        assert_eq!(overage(&padded(PACKET_DATA_SIZE)), None);
+        assert_eq!(overage(&padded(PACKET_DATA_SIZE + 1)), Some(1));
+    }
+
+    #[tokio::test]
+    async fn check_names_the_oversized_pair() {
+        let client = MockRpc::new();
+        let mut pairs = [pair(false, 0), pair(true, 20)];
+        let err = check(&client, &mut pairs, &[None, None], false)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "pair 2 [alice] has 20 extra signers and 6 instructions"
+        );
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::TransactionTooLarge { .. })
+        ));
+
+        // skipped pairs aren't sent, so they don't count
+        check(
+            &client,
+            &mut pairs,
+            &[None, Some("unlisted".to_string())],
+            false,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn check_keeps_the_instructions_for_sending() {
+        let client = MockRpc::new();
+        let mut pairs = [pair(false, 0), pair(true, 0)];
+
+        check(&client, &mut pairs, &[None, None], false)
+            .await
+            .unwrap();
+
+        assert_eq!(client.calls("get_minimum_balance_for_rent_exemption"), 1);
+        for pair in &pairs {
+            assert_eq!(
+                pair.built.as_ref(),
+                Some(&pair.instructions(&client).await.unwrap())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn check_reports_pairs_it_cannot_build() {
+        let client = MockRpc::new();
+        client.fail("get_minimum_balance_for_rent_exemption", Failure::Transport);
+        let mut pairs = [pair(true, 0)];
+
+        let err = check(&client, &mut pairs, &[None], false)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "building pair 2 [alice]");
+        assert!(pairs[0].built.is_none());
+    }
+}
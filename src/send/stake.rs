@@ -0,0 +1,61 @@
+use anyhow::{Context, bail};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::{Pubkey, PubkeyError},
+    stake::{
+        self,
+        instruction::create_account_with_seed_and_delegate_stake,
+        state::{Authorized, Lockup, StakeStateV2},
+    },
+};
+use solana_test::rpc::SolanaRpc;
+
+pub fn account_len() -> usize {
+    StakeStateV2::size_of()
+}
+
+pub fn default_seed(vote_account: &Pubkey) -> String {
+    let vote_account = vote_account.to_string();
+    format!("stake-{}", &vote_account[..16])
+}
+
+pub fn stake_account(sender: &Pubkey, seed: &str) -> Result<Pubkey, PubkeyError> {
+    Pubkey::create_with_seed(sender, seed, &stake::program::id())
+}
+
+pub async fn validate_vote_account(
+    client: &dyn SolanaRpc,
+    vote_account: &Pubkey,
+) -> anyhow::Result<()> {
+    let account = client
+        .get_account(vote_account)
+        .await
+        .with_context(|| format!("vote account {vote_account} not found"))?;
+    if account.owner != solana_sdk::vote::program::id() {
+        bail!(
+            "{vote_account} is not a vote account (owned by {})",
+            account.owner
+        );
+    }
+    Ok(())
+}
+
+// `lamports` must already include the rent-exempt reserve.
+pub fn instructions(
+    sender: &Pubkey,
+    stake_account: &Pubkey,
+    seed: &str,
+    vote_account: &Pubkey,
+    lamports: u64,
+) -> Vec<Instruction> {
+    create_account_with_seed_and_delegate_stake(
+        sender,
+        stake_account,
+        sender,
+        seed,
+        vote_account,
+        &Authorized::auto(sender),
+        &Lockup::default(),
+        lamports,
+    )
+}
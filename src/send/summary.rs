@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+use solana_test::addressbook;
+
+use super::report::{Outcome, PairResult};
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Totals {
+    pub pairs: usize,
+    pub lamports_attempted: u64,
+    pub lamports_confirmed: u64,
+    pub fees: u64,
+    pub failures: usize,
+}
+
+impl Totals {
+    fn add(&mut self, result: &PairResult) {
+        self.pairs += 1;
+        match &result.outcome {
+            Outcome::Confirmed(_) => {
+                self.lamports_attempted += result.lamports;
+                self.lamports_confirmed += result.lamports;
+            }
+            Outcome::AlreadyLanded(_) => {
+                self.lamports_confirmed += result.lamports;
+            }
+            Outcome::Failed(_) => {
+                self.lamports_attempted += result.lamports;
+                self.failures += 1;
+            }
+            Outcome::Skipped(_) | Outcome::Aborted => {}
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    pub senders: BTreeMap<String, Totals>,
+    pub total: Totals,
+}
+
+pub fn summarize(results: &[PairResult]) -> Summary {
+    let mut summary = Summary::default();
+    for result in results {
+        summary
+            .senders
+            .entry(result.sender.to_string())
+            .or_default()
+            .add(result);
+        summary.total.add(result);
+        // set for every transaction that landed, failed ones included, and
+        // paid by the fee payer rather than the sender
+        if let Some(fee) = result.fee {
+            summary
+                .senders
+                .entry(result.fee_payer.to_string())
+                .or_default()
+                .fees += fee;
+            summary.total.fees += fee;
+        }
+    }
+    summary
+}
+
+pub fn print(summary: &Summary) {
+    println!(
+        "{:<44} {:>6} {:>16} {:>16} {:>12} {:>8}",
+        "sender", "pairs", "attempted", "confirmed", "fees", "failed"
+    );
+    let rows = summary
+        .senders
+        .iter()
+        // keys stay bare pubkeys for the JSON report
+        .map(|(sender, totals)| {
+            let name = match sender.parse::<Pubkey>() {
+                Ok(pubkey) => addressbook::display(&pubkey),
+                Err(_) => sender.clone(),
+            };
+            (name, totals)
+        })
+        .chain([("total".to_string(), &summary.total)]);
+    for (name, totals) in rows {
+        println!(
+            "{name:<44} {:>6} {:>16} {:>16} {:>12} {:>8}",
+            totals.pairs,
+            totals.lamports_attempted,
+            totals.lamports_confirmed,
+            totals.fees,
+            totals.failures
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use solana_sdk::signature::Signature;
+    use solana_test::Error;
+
+    use super::*;
+
+    fn result(sender: Pubkey, lamports: u64, fee: Option<u64>, outcome: Outcome) -> PairResult {
+        PairResult {
+            label: "pair".to_string(),
+            sender,
+            fee_payer: sender,
+            recipient: Pubkey::new_unique(),
+            signers: vec![sender],
+            lamports,
+            created_account: None,
+            nonce_blockhash: None,
+            compute_unit_limit: None,
+            fee,
+            submit_delay: None,
+            sent: None,
+            duration: Duration::ZERO,
+            outcome,
+        }
+    }
+
+    #[test]
+    fn totals_per_sender_and_overall() {
+        let alice = Pubkey::new_unique();
+        let bob = Pubkey::new_unique();
+        let results = [
+            result(
+                alice,
+                100,
+                Some(5_000),
+                Outcome::Confirmed(Signature::default()),
+            ),
+            result(alice, 200, None, Outcome::Failed(Error::BlockhashExpired)),
+            result(
+                bob,
+                300,
+                Some(5_000),
+                Outcome::Confirmed(Signature::default()),
+            ),
+        ];
+
+        let summary = summarize(&results);
+
+        let alice = &summary.senders[&alice.to_string()];
+        assert_eq!(alice.pairs, 2);
+        assert_eq!(alice.lamports_attempted, 300);
+        assert_eq!(alice.lamports_confirmed, 100);
+        assert_eq!(alice.fees, 5_000);
+        assert_eq!(alice.failures, 1);
+        let bob = &summary.senders[&bob.to_string()];
+        assert_eq!(
+            (bob.pairs, bob.lamports_confirmed, bob.failures),
+            (1, 300, 0)
+        );
+        let total = &summary.total;
+        assert_eq!(total.pairs, 3);
+        assert_eq!(total.lamports_attempted, 600);
+        assert_eq!(total.lamports_confirmed, 400);
+        assert_eq!(total.fees, 10_000);
+        assert_eq!(total.failures, 1);
+    }
+
+    #[test]
+    fn earlier_landings_count_as_confirmed_but_not_attempted() {
+        let sender = Pubkey::new_unique();
+        let results = [result(
+            sender,
+            100,
+            Some(5_000),
+            Outcome::AlreadyLanded(Signature::default()),
+        )];
+
+        let total = summarize(&results).total;
+
+        assert_eq!(total.pairs, 1);
+        assert_eq!(total.lamports_attempted, 0);
+        assert_eq!(total.lamports_confirmed, 100);
+        assert_eq!(total.fees, 0);
+    }
+
+    #[test]
+    fn skipped_and_aborted_pairs_only_count_as_pairs() {
+        let sender = Pubkey::new_unique();
+        let results = [
+            result(
+                sender,
+                100,
+                None,
+                Outcome::Skipped("not whitelisted".to_string()),
+            ),
+            result(sender, 100, None, Outcome::Aborted),
+        ];
+
+        let total = summarize(&results).total;
+
+        assert_eq!(total.pairs, 2);
+        assert_eq!(total.lamports_attempted, 0);
+        assert_eq!(total.failures, 0);
+    }
+
+    #[test]
+    fn failed_transactions_that_landed_count_their_fee() {
+        let sender = Pubkey::new_unique();
+        let results = [
+            result(
+                sender,
+                100,
+                Some(5_000),
+                Outcome::Failed(Error::BlockhashExpired),
+            ),
+            // never landed, so no fee
+            result(sender, 100, None, Outcome::Failed(Error::BlockhashExpired)),
+        ];
+
+        let summary = summarize(&results);
+
+        assert_eq!(summary.senders[&sender.to_string()].fees, 5_000);
+        assert_eq!(summary.total.fees, 5_000);
+        assert_eq!(summary.total.failures, 2);
+    }
+
+    #[test]
+    fn fees_go_to_the_fee_payer() {
+        let sender = Pubkey::new_unique();
+        let fee_payer = Pubkey::new_unique();
+        let mut sponsored = result(
+            sender,
+            100,
+            Some(10_000),
+            Outcome::Confirmed(Signature::default()),
+        );
+        sponsored.fee_payer = fee_payer;
+
+        let summary = summarize(&[sponsored]);
+
+        let sender = &summary.senders[&sender.to_string()];
+        assert_eq!((sender.pairs, sender.lamports_confirmed), (1, 100));
+        assert_eq!(sender.fees, 0);
+        let fee_payer = &summary.senders[&fee_payer.to_string()];
+        assert_eq!((fee_payer.pairs, fee_payer.fees), (0, 10_000));
+        assert_eq!(summary.total.fees, 10_000);
+    }
+
+    #[test]
+    fn no_results_sum_to_zero() {
+        let summary = summarize(&[]);
+        assert!(summary.senders.is_empty());
+        assert_eq!(summary.total.pairs, 0);
+    }
+}
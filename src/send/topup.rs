@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use anyhow::{Context, bail};
+use serde::Serialize;
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signature::Signature};
+use solana_test::{
+    retry::{RetryPolicy, retry},
+    rpc::SolanaRpc,
+    send_config::TopupConfig,
+};
+use tokio::time::sleep;
+
+const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+const AIRDROP_ATTEMPTS: u32 = 5;
+const CONFIRM_POLLS: u32 = 30;
+
+// A sender that was airdropped `lamports` before the run.
+#[derive(Debug, Serialize)]
+pub struct TopUp {
+    pub address: String,
+    pub lamports: u64,
+}
+
+fn sol_to_lamports(sol: f64) -> u64 {
+    (sol * LAMPORTS_PER_SOL as f64).round() as u64
+}
+
+async fn wait_for_airdrop(client: &dyn SolanaRpc, signature: &Signature) -> anyhow::Result<()> {
+    for _ in 0..CONFIRM_POLLS {
+        if client.confirm_transaction(signature).await? {
+            return Ok(());
+        }
+        sleep(Duration::from_secs(1)).await;
+    }
+    bail!("airdrop {signature} was not confirmed in time")
+}
+
+async fn airdrop(client: &dyn SolanaRpc, address: &Pubkey, lamports: u64) -> anyhow::Result<()> {
+    let policy = RetryPolicy {
+        max_attempts: AIRDROP_ATTEMPTS,
+        base_delay: Duration::from_secs(2),
+        ..RetryPolicy::default()
+    };
+    // faucets fail in too many ways to tell which are worth retrying
+    retry(
+        &policy,
+        |_: &anyhow::Error| true,
+        || async {
+            let signature = client.request_airdrop(address, lamports).await?;
+            wait_for_airdrop(client, &signature).await
+        },
+    )
+    .await
+    .with_context(|| format!("airdrop to {address} failed {AIRDROP_ATTEMPTS} times"))
+}
+
+pub async fn run(
+    client: &dyn SolanaRpc,
+    config: &TopupConfig,
+    senders: impl IntoIterator<Item = Pubkey>,
+) -> anyhow::Result<Vec<TopUp>> {
+    let genesis_hash = client
+        .get_genesis_hash()
+        .await
+        .context("Can't fetch genesis hash")?;
+    if genesis_hash.to_string() == MAINNET_GENESIS_HASH {
+        bail!("topup refuses to run against mainnet");
+    }
+
+    let min_balance = sol_to_lamports(config.min_balance_sol);
+    let airdrop_lamports = sol_to_lamports(config.airdrop_sol);
+    let mut topped_up = vec![];
+    for sender in senders {
+        let balance = client.get_balance(&sender).await?;
+        if balance >= min_balance {
+            continue;
+        }
+        airdrop(client, &sender, airdrop_lamports).await?;
+        tracing::info!(
+            %sender,
+            sol = config.airdrop_sol,
+            balance_sol = balance as f64 / LAMPORTS_PER_SOL as f64,
+            "topped up sender"
+        );
+        topped_up.push(TopUp {
+            address: sender.to_string(),
+            lamports: airdrop_lamports,
+        });
+    }
+    Ok(topped_up)
+}
+
+pub fn print(topped_up: &[TopUp]) {
+    if topped_up.is_empty() {
+        return;
+    }
+    println!("\nTopped up:");
+    for top_up in topped_up {
+        println!("  {}: +{} lamports", top_up.address, top_up.lamports);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::hash::Hash;
+    use solana_test::rpc::mock::MockRpc;
+
+    use super::*;
+
+    const CONFIG: TopupConfig = TopupConfig {
+        min_balance_sol: 1.0,
+        airdrop_sol: 2.0,
+    };
+
+    #[tokio::test]
+    async fn only_senders_below_the_minimum_are_topped_up() {
+        let client = MockRpc::new();
+        let (short, funded) = (Pubkey::new_unique(), Pubkey::new_unique());
+        client.set_balance(short, LAMPORTS_PER_SOL / 2);
+        client.set_balance(funded, LAMPORTS_PER_SOL);
+
+        let topped_up = run(&client, &CONFIG, [short, funded]).await.unwrap();
+
+        assert_eq!(topped_up.len(), 1);
+        assert_eq!(topped_up[0].address, short.to_string());
+        assert_eq!(topped_up[0].lamports, 2 * LAMPORTS_PER_SOL);
+        assert_eq!(
+            client.get_balance(&short).await.unwrap(),
+            5 * LAMPORTS_PER_SOL / 2
+        );
+        assert_eq!(client.calls("request_airdrop"), 1);
+    }
+
+    #[tokio::test]
+    async fn refuses_to_run_against_mainnet() {
+        let client = MockRpc::new();
+        client.set_genesis_hash(MAINNET_GENESIS_HASH.parse::<Hash>().unwrap());
+
+        let result = run(&client, &CONFIG, [Pubkey::new_unique()]).await;
+
+        assert!(result.is_err());
+        assert_eq!(client.calls("request_airdrop"), 0);
+    }
+}
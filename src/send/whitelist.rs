@@ -0,0 +1,31 @@
+use std::{collections::HashSet, fs};
+
+use anyhow::{Context, bail};
+use solana_sdk::pubkey::Pubkey;
+
+pub fn load(path: &str) -> anyhow::Result<HashSet<Pubkey>> {
+    let contents = fs::read_to_string(path).with_context(|| format!("Can't read {path}"))?;
+
+    let mut whitelist = HashSet::new();
+    let mut errors = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        match line.parse::<Pubkey>() {
+            Ok(pubkey) => {
+                whitelist.insert(pubkey);
+            }
+            Err(err) => errors.push(format!("  line {}: `{line}`: {err}", i + 1)),
+        }
+    }
+
+    if !errors.is_empty() {
+        bail!(
+            "invalid entries in whitelist {path}:\n{}",
+            errors.join("\n")
+        );
+    }
+    Ok(whitelist)
+}
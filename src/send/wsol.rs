@@ -0,0 +1,210 @@
+use solana_sdk::{
+    instruction::Instruction, program_error::ProgramError, pubkey::Pubkey, system_instruction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account_idempotent,
+};
+use spl_token::{instruction as token_instruction, native_mint};
+
+pub const TOKEN_ACCOUNT_LEN: u64 = 165;
+
+// `pair_index` keeps pairs between the same sender and recipient apart. Fits
+// the 32 byte seed limit for any index below 10^10.
+pub fn temporary_account_seed(recipient: &Pubkey, pair_index: usize) -> String {
+    let recipient = recipient.to_string();
+    format!("wsol-{}-{pair_index}", &recipient[..16])
+}
+
+pub fn temporary_account(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    pair_index: usize,
+) -> Result<Pubkey, ProgramError> {
+    Pubkey::create_with_seed(
+        sender,
+        &temporary_account_seed(recipient, pair_index),
+        &spl_token::id(),
+    )
+    .map_err(|_| ProgramError::InvalidSeeds)
+}
+
+// Wraps `lamports` into a temporary token account derived from the sender,
+// moves the wrapped amount to the recipient and closes the temporary account.
+// With `close_to_recipient` the recipient gets native SOL instead: the
+// temporary account is closed straight into their wallet. Its rent deposit
+// then comes out of `lamports`, so the recipient gets exactly `lamports`,
+// which has to cover the rent.
+pub fn transfer_instructions(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    pair_index: usize,
+    lamports: u64,
+    rent: u64,
+    close_to_recipient: bool,
+) -> Result<Vec<Instruction>, ProgramError> {
+    let token_program = spl_token::id();
+    let mint = native_mint::id();
+    let seed = temporary_account_seed(recipient, pair_index);
+    let temporary = temporary_account(sender, recipient, pair_index)?;
+    let funding = if close_to_recipient {
+        if lamports < rent {
+            return Err(ProgramError::InsufficientFunds);
+        }
+        lamports
+    } else {
+        rent + lamports
+    };
+
+    let mut instructions = vec![
+        system_instruction::create_account_with_seed(
+            sender,
+            &temporary,
+            sender,
+            &seed,
+            funding,
+            TOKEN_ACCOUNT_LEN,
+            &token_program,
+        ),
+        token_instruction::initialize_account3(&token_program, &temporary, &mint, sender)?,
+        token_instruction::sync_native(&token_program, &temporary)?,
+    ];
+
+    if close_to_recipient {
+        instructions.push(token_instruction::close_account(
+            &token_program,
+            &temporary,
+            recipient,
+            sender,
+            &[],
+        )?);
+        return Ok(instructions);
+    }
+
+    let recipient_account = get_associated_token_address(recipient, &mint);
+    instructions.extend([
+        create_associated_token_account_idempotent(sender, recipient, &mint, &token_program),
+        token_instruction::transfer_checked(
+            &token_program,
+            &temporary,
+            &mint,
+            &recipient_account,
+            sender,
+            &[],
+            lamports,
+            native_mint::DECIMALS,
+        )?,
+        token_instruction::close_account(&token_program, &temporary, sender, sender, &[])?,
+    ]);
+    Ok(instructions)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::system_program;
+    use spl_token::instruction::TokenInstruction;
+
+    use super::*;
+
+    const RENT: u64 = 2_039_280;
+
+    fn token_instruction(instruction: &Instruction) -> TokenInstruction<'_> {
+        assert_eq!(instruction.program_id, spl_token::id());
+        TokenInstruction::unpack(&instruction.data).unwrap()
+    }
+
+    #[test]
+    fn wraps_transfers_and_closes_back_to_sender() {
+        let sender = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let temporary = temporary_account(&sender, &recipient, 0).unwrap();
+        let instructions =
+            transfer_instructions(&sender, &recipient, 0, 1_000, RENT, false).unwrap();
+
+        assert_eq!(instructions.len(), 6);
+        assert_eq!(instructions[0].program_id, system_program::id());
+        assert_eq!(instructions[0].accounts[1].pubkey, temporary);
+        assert!(matches!(
+            token_instruction(&instructions[1]),
+            TokenInstruction::InitializeAccount3 { owner } if owner == sender
+        ));
+        assert!(matches!(
+            token_instruction(&instructions[2]),
+            TokenInstruction::SyncNative
+        ));
+        assert_eq!(
+            instructions[3].program_id,
+            spl_associated_token_account::id()
+        );
+        assert!(matches!(
+            token_instruction(&instructions[4]),
+            TokenInstruction::TransferChecked {
+                amount: 1_000,
+                decimals: native_mint::DECIMALS
+            }
+        ));
+        assert!(matches!(
+            token_instruction(&instructions[5]),
+            TokenInstruction::CloseAccount
+        ));
+        // closed back into the sender, which gets the rent deposit back
+        assert_eq!(instructions[5].accounts[0].pubkey, temporary);
+        assert_eq!(instructions[5].accounts[1].pubkey, sender);
+    }
+
+    #[test]
+    fn close_to_recipient_takes_the_rent_out_of_the_amount() {
+        let sender = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        let lamports = RENT + 1_000;
+        let instructions =
+            transfer_instructions(&sender, &recipient, 0, lamports, RENT, true).unwrap();
+
+        assert_eq!(instructions.len(), 4);
+        assert_eq!(instructions[0].program_id, system_program::id());
+        assert!(matches!(
+            token_instruction(&instructions[1]),
+            TokenInstruction::InitializeAccount3 { .. }
+        ));
+        assert!(matches!(
+            token_instruction(&instructions[2]),
+            TokenInstruction::SyncNative
+        ));
+        assert!(matches!(
+            token_instruction(&instructions[3]),
+            TokenInstruction::CloseAccount
+        ));
+        assert_eq!(instructions[3].accounts[1].pubkey, recipient);
+
+        let expected = system_instruction::create_account_with_seed(
+            &sender,
+            &temporary_account(&sender, &recipient, 0).unwrap(),
+            &sender,
+            &temporary_account_seed(&recipient, 0),
+            lamports,
+            TOKEN_ACCOUNT_LEN,
+            &spl_token::id(),
+        );
+        assert_eq!(instructions[0], expected);
+    }
+
+    #[test]
+    fn close_to_recipient_needs_the_rent() {
+        let sender = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        assert_eq!(
+            transfer_instructions(&sender, &recipient, 0, RENT - 1, RENT, true),
+            Err(ProgramError::InsufficientFunds)
+        );
+    }
+
+    #[test]
+    fn pairs_between_the_same_accounts_get_their_own_temporary_account() {
+        let sender = Pubkey::new_unique();
+        let recipient = Pubkey::new_unique();
+        assert_ne!(
+            temporary_account(&sender, &recipient, 0).unwrap(),
+            temporary_account(&sender, &recipient, 1).unwrap()
+        );
+        assert!(temporary_account_seed(&recipient, 9_999_999_999).len() <= 32);
+    }
+}
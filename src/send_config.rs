@@ -0,0 +1,142 @@
+// The send config schema, read by the send binary and by the balance
+// binary's `--check-send`.
+
+use serde::{Deserialize, Serialize};
+
+fn default_compute_unit_margin_percent() -> u32 {
+    10
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Config {
+    pub pairs: Vec<TransferPairRead>,
+    // devnet when unset; cluster monikers like `mainnet` work too
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rpc_endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compute_unit_limit: Option<ComputeUnitLimit>,
+    #[serde(default = "default_compute_unit_margin_percent")]
+    pub compute_unit_margin_percent: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub whitelist_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topup: Option<TopupConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submit_jitter_ms: Option<[u64; 2]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inter_submit_gap_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub abort_after_failures: Option<AbortConfig>,
+    // Memo attached to every pair; `{label}` becomes the pair's label
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+// Stops submitting once more than `count` of the last `window` finished pairs
+// failed; pairs already submitted still finish.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AbortConfig {
+    pub count: usize,
+    pub window: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TransferPairRead {
+    pub sender_keypair: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recipient: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lamports: Option<u64>,
+    // Shown in every output and the memo; defaults to the recipient's
+    // address book name, and to empty when the book doesn't know it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mint: Option<String>,
+    // Unwrap into the recipient's wallet instead of their wSOL account. The
+    // temporary token account's rent is taken out of `lamports`, which must
+    // be at least the rent-exempt minimum (about 0.002 SOL).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub close_to_recipient: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stake: Option<StakeRead>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub create_with_seed: Option<CreateWithSeedRead>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub create_nonce: Option<CreateNonceRead>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdraw_nonce: Option<WithdrawNonceRead>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorize_nonce: Option<AuthorizeNonceRead>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_signers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_payer: Option<String>,
+    // The pair's position in the run it was first sent in, which its
+    // reference and wSOL account derive from. Written to `--failed-out`
+    // files so reruns don't take another pair's place; defaults to the
+    // position in this config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CreateWithSeedRead {
+    pub seed: String,
+    pub lamports: u64,
+    pub space: u64,
+    pub owner: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StakeRead {
+    pub amount_sol: f64,
+    pub vote_account: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CreateNonceRead {
+    pub seed: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authority: Option<String>,
+    // on top of the rent-exempt minimum
+    #[serde(default)]
+    pub lamports: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WithdrawNonceRead {
+    pub nonce_account: String,
+    // drains the whole account when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lamports: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AuthorizeNonceRead {
+    pub nonce_account: String,
+    pub new_authority: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Auto {
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ComputeUnitLimit {
+    Fixed(u32),
+    Auto(Auto),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TopupConfig {
+    pub min_balance_sol: f64,
+    pub airdrop_sol: f64,
+}
@@ -0,0 +1,89 @@
+// the tools share some files, which then get compiled once per tool
+#![allow(clippy::duplicate_mod)]
+
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use solana_test::{cli::CommonArgs, error, logging};
+
+// Each tool's sources, compiled in whole; their own `main`s, which back the
+// standalone binaries, go unused here.
+#[allow(dead_code)]
+#[path = "../balance/main.rs"]
+mod balance;
+#[allow(dead_code)]
+#[path = "../blocks/main.rs"]
+mod blocks;
+#[allow(dead_code)]
+#[path = "../send/main.rs"]
+mod send;
+#[allow(dead_code)]
+#[path = "../vault/main.rs"]
+mod vault;
+
+/// Transfers, balance checks, block-triggered sends and deposit vaults
+#[derive(Parser)]
+#[command(name = "solana-tools", version)]
+struct Cli {
+    #[command(flatten)]
+    common: CommonArgs,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Sends the transfers listed in the config
+    Send(send::args::Args),
+    /// Checks SOL and token balances
+    Balance(Box<balance::args::Args>),
+    /// Sends a transfer on every tenth block seen over geyser
+    Blocks,
+    /// Manages the signer's vault in the deposit program
+    Vault(vault::Args),
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let Cli { common, command } = Cli::parse();
+    logging::init(&common.log_options());
+    let result = match command {
+        Command::Send(args) => send::run(common, args).await,
+        Command::Balance(args) => balance::run(common, *args).await,
+        Command::Blocks => blocks::run(common).await,
+        Command::Vault(args) => vault::run(common, args).await,
+    };
+    error::exit(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn cli_is_well_formed() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn subcommands_take_the_common_flags() {
+        let cli = Cli::try_parse_from(["solana-tools", "blocks", "--url", "devnet", "-v"]).unwrap();
+        assert!(matches!(cli.command, Command::Blocks));
+        assert_eq!(cli.common.url.as_deref(), Some("devnet"));
+        assert!(cli.common.verbose);
+    }
+
+    #[test]
+    fn typos_suggest_the_subcommand() {
+        let error = Cli::try_parse_from(["solana-tools", "sned"])
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(
+            error.contains("similar subcommand exists: 'send'"),
+            "{error}"
+        );
+    }
+}
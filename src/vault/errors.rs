@@ -0,0 +1,177 @@
+// Turns the deposit program's custom error codes back into the names and
+// messages of its `#[error_code]` enum.
+
+use solana_client::client_error::ClientError;
+use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
+
+// `VAULT_ERRORS`: (variant, message) for each VaultError, generated by
+// build.rs from programs/deposit.
+include!(concat!(env!("OUT_DIR"), "/vault_errors.rs"));
+
+// Anchor numbers a program's own errors from here.
+const ERROR_CODE_OFFSET: u32 = 6000;
+
+// The framework errors the vault instructions are likely to hit.
+const ANCHOR_ERRORS: &[(u32, &str, &str)] = &[
+    (
+        101,
+        "InstructionFallbackNotFound",
+        "Fallback functions are not supported",
+    ),
+    (
+        102,
+        "InstructionDidNotDeserialize",
+        "The program could not deserialize the given instruction",
+    ),
+    (2000, "ConstraintMut", "A mut constraint was violated"),
+    (
+        2001,
+        "ConstraintHasOne",
+        "A has one constraint was violated",
+    ),
+    (2002, "ConstraintSigner", "A signer constraint was violated"),
+    (2003, "ConstraintRaw", "A raw constraint was violated"),
+    (2006, "ConstraintSeeds", "A seeds constraint was violated"),
+    (
+        2012,
+        "ConstraintAddress",
+        "An address constraint was violated",
+    ),
+    (
+        3001,
+        "AccountDiscriminatorNotFound",
+        "No discriminator was found on the account",
+    ),
+    (
+        3002,
+        "AccountDiscriminatorMismatch",
+        "Account discriminator did not match what was expected",
+    ),
+    (
+        3003,
+        "AccountDidNotDeserialize",
+        "Failed to deserialize the account",
+    ),
+    (
+        3005,
+        "AccountNotEnoughKeys",
+        "Not enough account keys given to the instruction",
+    ),
+    (
+        3007,
+        "AccountOwnedByWrongProgram",
+        "The given account is owned by a different program than expected",
+    ),
+    (
+        3012,
+        "AccountNotInitialized",
+        "The program expected this account to be already initialized",
+    ),
+];
+
+pub fn describe(code: u32) -> Option<String> {
+    let own = code
+        .checked_sub(ERROR_CODE_OFFSET)
+        .and_then(|index| VAULT_ERRORS.get(index as usize))
+        .map(|(name, message)| format!("VaultError::{name}: {message}"));
+    own.or_else(|| {
+        ANCHOR_ERRORS
+            .iter()
+            .find(|(anchor_code, ..)| *anchor_code == code)
+            .map(|(_, name, message)| format!("anchor::{name}: {message}"))
+    })
+}
+
+// `None` for failures that didn't come from the program, such as a network
+// error.
+pub fn explain(err: &ClientError) -> Option<String> {
+    describe_transaction_error(&err.get_transaction_error()?)
+}
+
+pub fn describe_transaction_error(err: &TransactionError) -> Option<String> {
+    match err {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => describe(*code),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use deposit::VaultError;
+    use solana_client::client_error::ClientErrorKind;
+
+    use super::*;
+
+    fn from_program(error: VaultError) -> String {
+        format!("VaultError::{error:?}: {error}")
+    }
+
+    #[test]
+    fn vault_errors_keep_their_anchor_codes() {
+        for error in [
+            VaultError::InsufficientFunds,
+            VaultError::WouldBreakRentExemption,
+            VaultError::ZeroAmount,
+            VaultError::StillLocked,
+            VaultError::WrongPayoutAddress,
+            VaultError::InvalidBatchVault,
+        ] {
+            assert_eq!(describe(u32::from(error)), Some(from_program(error)));
+        }
+        // the last variant is the last entry, so none were skipped
+        let last = u32::from(VaultError::InvalidBatchVault) - ERROR_CODE_OFFSET;
+        assert_eq!(VAULT_ERRORS.len(), last as usize + 1);
+    }
+
+    #[test]
+    fn anchor_framework_errors_are_described() {
+        assert_eq!(
+            describe(2006).as_deref(),
+            Some("anchor::ConstraintSeeds: A seeds constraint was violated")
+        );
+        assert_eq!(
+            describe(3012).as_deref(),
+            Some(
+                "anchor::AccountNotInitialized: The program expected this account to be already initialized"
+            )
+        );
+        assert_eq!(describe(42), None);
+        assert_eq!(
+            describe(ERROR_CODE_OFFSET + VAULT_ERRORS.len() as u32),
+            None
+        );
+    }
+
+    #[test]
+    fn instruction_errors_decode_to_the_vault_error() {
+        let err = TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(u32::from(VaultError::InsufficientFunds)),
+        );
+        assert_eq!(
+            describe_transaction_error(&err).as_deref(),
+            Some("VaultError::InsufficientFunds: Insufficient funds in the vault.")
+        );
+
+        let err = TransactionError::InstructionError(0, InstructionError::InvalidArgument);
+        assert_eq!(describe_transaction_error(&err), None);
+        assert_eq!(
+            describe_transaction_error(&TransactionError::BlockhashNotFound),
+            None
+        );
+    }
+
+    #[test]
+    fn client_errors_are_explained_through_their_transaction_error() {
+        let err: ClientError =
+            ClientErrorKind::TransactionError(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(u32::from(VaultError::StillLocked)),
+            ))
+            .into();
+        assert_eq!(explain(&err), Some(from_program(VaultError::StillLocked)));
+
+        let err: ClientError = ClientErrorKind::Custom("connection refused".into()).into();
+        assert_eq!(explain(&err), None);
+    }
+}
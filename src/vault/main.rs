@@ -0,0 +1,388 @@
+use std::process::ExitCode;
+
+use anyhow::{Context, bail};
+use chrono::DateTime;
+use clap::Parser;
+use serde::Deserialize;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig},
+};
+use solana_sdk::{
+    commitment_config::CommitmentLevel,
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use solana_test::{
+    addressbook, cli::CommonArgs, error, events, keypair::load_keypair, logging, rpc, vault_stats,
+};
+
+mod errors;
+#[cfg(test)]
+mod native;
+mod program;
+
+use program::ProgramConfig;
+
+#[derive(Debug, Deserialize)]
+struct Config {
+    // devnet when unset
+    rpc_endpoint: Option<String>,
+    keypair: String,
+    program_id: String,
+}
+
+#[derive(clap::Args)]
+pub struct Args {
+    /// Simulate the transaction and report what it would do, without sending it
+    #[arg(long, global = true)]
+    simulate: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Create the signer's vault
+    Init {
+        /// Only pay out through a request and a delay
+        #[arg(long)]
+        delayed: bool,
+    },
+    /// Deposit SOL into the signer's vault
+    Deposit {
+        #[arg(value_name = "SOL", value_parser = lamports)]
+        lamports: u64,
+    },
+    /// Withdraw SOL from the signer's vault
+    Withdraw {
+        #[arg(value_name = "SOL", value_parser = lamports)]
+        lamports: u64,
+    },
+    /// Withdraw everything that can be withdrawn
+    WithdrawAll,
+    /// Withdraw everything and close the vault
+    Close,
+    /// Show a vault, the signer's by default
+    Show {
+        /// Pubkey or address book `@name`
+        creator: Option<String>,
+    },
+}
+
+fn lamports(sol: &str) -> anyhow::Result<u64> {
+    let sol: f64 = sol.parse().context("amount in SOL")?;
+    if !sol.is_finite() || sol <= 0.0 {
+        bail!("amount must be a positive number of SOL");
+    }
+    Ok((sol * LAMPORTS_PER_SOL as f64).round() as u64)
+}
+
+async fn program_config(
+    client: &RpcClient,
+    program_id: &Pubkey,
+) -> anyhow::Result<Option<ProgramConfig>> {
+    let address = program::config_address(program_id);
+    let account = client
+        .get_account_with_commitment(&address, client.commitment())
+        .await?
+        .value;
+    account
+        .map(|account| program::decode_config(&account.data))
+        .transpose()
+}
+
+async fn send(client: &RpcClient, payer: &Keypair, instruction: Instruction) -> anyhow::Result<()> {
+    let blockhash = client.get_latest_blockhash().await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[payer],
+        blockhash,
+    );
+    let signature = client
+        .send_and_confirm_transaction(&tx)
+        .await
+        .map_err(|err| {
+            let message = errors::explain(&err);
+            let err = anyhow::Error::from(err);
+            match message {
+                Some(message) => err.context(message),
+                None => err,
+            }
+        })?;
+    println!("confirmed {signature}");
+    Ok(())
+}
+
+// Runs the exact transaction `send` would, without signature checks, and
+// reports what it would do.
+async fn simulate(
+    client: &RpcClient,
+    payer: &Keypair,
+    instruction: Instruction,
+) -> anyhow::Result<()> {
+    let program_id = instruction.program_id;
+    let writable: Vec<Pubkey> = instruction
+        .accounts
+        .iter()
+        .filter(|meta| meta.is_writable)
+        .map(|meta| meta.pubkey)
+        .collect();
+    let blockhash = client.get_latest_blockhash().await?;
+    let tx = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[payer],
+        blockhash,
+    );
+    let before = client.get_multiple_accounts(&writable).await?;
+    let result = client
+        .simulate_transaction_with_config(
+            &tx,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                commitment: Some(client.commitment()),
+                accounts: Some(RpcSimulateTransactionAccountsConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    addresses: writable.iter().map(Pubkey::to_string).collect(),
+                }),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await?
+        .value;
+
+    println!("logs:");
+    for line in annotate_logs(&result.logs.unwrap_or_default(), &program_id) {
+        println!("  {line}");
+    }
+    if let Some(units) = result.units_consumed {
+        println!("compute units: {units}");
+    }
+    if let Some(err) = result.err {
+        match errors::describe_transaction_error(&err) {
+            Some(message) => bail!("simulation failed: {message} ({err})"),
+            None => bail!("simulation failed: {err}"),
+        }
+    }
+
+    println!("lamport changes:");
+    let after = result.accounts.unwrap_or_default();
+    for ((address, before), after) in writable.iter().zip(before).zip(after) {
+        let before = before.map_or(0, |account| account.lamports);
+        let after = after.map_or(0, |account| account.lamports);
+        println!("  {}", lamport_change(address, before, after));
+    }
+    Ok(())
+}
+
+// The program's log lines, each decoded event indented under its data line.
+fn annotate_logs(logs: &[String], program_id: &Pubkey) -> Vec<String> {
+    let mut decoded = events::from_logs(logs, program_id).into_iter().peekable();
+    let mut out = vec![];
+    for (i, line) in logs.iter().enumerate() {
+        out.push(line.clone());
+        while let Some((_, event)) = decoded.next_if(|(index, _)| *index == i) {
+            let mut line = format!(
+                "  => {:?} of {} lamports by {} (fee {}), vault {}",
+                event.kind, event.amount, event.user, event.fee, event.vault
+            );
+            if let Some(balance) = event.new_balance {
+                line += &format!(" now holds {balance}");
+            }
+            out.push(line);
+        }
+    }
+    out
+}
+
+fn lamport_change(address: &Pubkey, before: u64, after: u64) -> String {
+    format!(
+        "{}: {before} -> {after} ({:+})",
+        addressbook::display(address),
+        i128::from(after) - i128::from(before)
+    )
+}
+
+fn time_string(ts: i64) -> String {
+    match DateTime::from_timestamp(ts, 0) {
+        Some(time) if ts > 0 => time.to_rfc3339(),
+        _ => "never".to_string(),
+    }
+}
+
+async fn show(client: &RpcClient, program_id: &Pubkey, creator: &Pubkey) -> anyhow::Result<()> {
+    let address = program::vault_address(program_id, creator);
+    let Some(account) = client
+        .get_account_with_commitment(&address, client.commitment())
+        .await?
+        .value
+    else {
+        println!("vault {address}: none");
+        return Ok(());
+    };
+    let vault = program::decode_vault(&account.data)?;
+    let rent = client
+        .get_minimum_balance_for_rent_exemption(account.data.len())
+        .await?;
+    // what the program lets the owner take out: lamports above rent, capped
+    // at the deposits not yet withdrawn
+    let withdrawable = account
+        .lamports
+        .saturating_sub(rent)
+        .min(vault.remaining_deposits());
+    println!("vault:        {address}");
+    println!("version:      {}", vault.version);
+    println!("owner:        {}", addressbook::display(&vault.owner));
+    if vault.creator != vault.owner {
+        println!("creator:      {}", addressbook::display(&vault.creator));
+    }
+    println!("lamports:     {}", account.lamports);
+    println!("deposited:    {}", vault.total_deposited);
+    println!("withdrawn:    {}", vault.total_withdrawn);
+    println!("withdrawable: {withdrawable}");
+    if let Some(stats) = vault_stats::decode_stats(&account.data) {
+        println!(
+            "deposits:     {} (last {})",
+            stats.deposit_count,
+            time_string(stats.last_deposit_ts)
+        );
+        println!(
+            "withdrawals:  {} (last {})",
+            stats.withdraw_count,
+            time_string(stats.last_withdraw_ts)
+        );
+    }
+    Ok(())
+}
+
+/// Manages the signer's vault in the deposit program
+#[derive(Parser)]
+#[command(name = "vault", version)]
+struct Cli {
+    #[command(flatten)]
+    common: CommonArgs,
+    #[command(flatten)]
+    args: Args,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    logging::init(&cli.common.log_options());
+    error::exit(run(cli.common, cli.args).await)
+}
+
+pub async fn run(common: CommonArgs, args: Args) -> anyhow::Result<()> {
+    common.load_address_book()?;
+    let config: Config = common.load_config()?;
+    let program_id = addressbook::resolve(&config.program_id).context("program_id")?;
+    let user = load_keypair(&config.keypair)?;
+    let client = rpc::build_rpc_client(
+        &common.url(config.rpc_endpoint.as_deref(), rpc::DEVNET_URL),
+        common.commitment(None, CommitmentLevel::Confirmed),
+        rpc::DEFAULT_TIMEOUT,
+    );
+
+    let command = args.command;
+    let instruction = match command {
+        Command::Show { creator } => {
+            let creator = match creator {
+                Some(creator) => addressbook::resolve(&creator).context("creator")?,
+                None => user.pubkey(),
+            };
+            return show(&client, &program_id, &creator).await;
+        }
+        Command::Init { delayed } => program::initialize(&program_id, &user.pubkey(), delayed),
+        Command::Deposit { lamports } => program::deposit(&program_id, &user.pubkey(), lamports),
+        Command::Withdraw { .. } | Command::WithdrawAll | Command::Close => {
+            let treasury = program_config(&client, &program_id)
+                .await?
+                .filter(|config| config.fee_bps > 0)
+                .map(|config| config.treasury);
+            match command {
+                Command::Withdraw { lamports } => {
+                    program::withdraw(&program_id, &user.pubkey(), treasury, lamports)
+                }
+                Command::WithdrawAll => {
+                    program::withdraw_all(&program_id, &user.pubkey(), treasury)
+                }
+                _ => program::withdraw_and_close(&program_id, &user.pubkey(), treasury),
+            }
+        }
+    };
+    if args.simulate {
+        return simulate(&client, &user, instruction).await;
+    }
+    send(&client, &user, instruction).await?;
+    show(&client, &program_id, &user.pubkey()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use deposit::Vault;
+
+    use super::{native::Program, *};
+
+    const VAULT: &str = "8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR";
+
+    #[tokio::test]
+    async fn a_simulated_deposit_decodes_the_programs_event() {
+        let mut harness = Program::start().await;
+        let user = harness.payer().pubkey();
+        let vault = program::vault_address(&deposit::ID, &user);
+        let rent = harness.rent(Vault::SPACE).await;
+
+        let logs = harness
+            .simulate(program::deposit(&deposit::ID, &user, LAMPORTS_PER_SOL / 4))
+            .await;
+        let annotated = annotate_logs(&logs, &deposit::ID);
+
+        let data = logs
+            .iter()
+            .position(|line| line.starts_with("Program data: "))
+            .unwrap_or_else(|| panic!("no event logged in {logs:?}"));
+        assert_eq!(annotated[..=data], logs[..=data]);
+        assert_eq!(
+            annotated[data + 1],
+            format!(
+                "  => Deposit of {} lamports by {user} (fee 0), vault {vault} now holds {}",
+                LAMPORTS_PER_SOL / 4,
+                rent + LAMPORTS_PER_SOL / 4
+            )
+        );
+        assert_eq!(annotated[data + 2..], logs[data + 1..]);
+    }
+
+    #[tokio::test]
+    async fn events_logged_by_other_programs_are_not_decoded() {
+        let mut harness = Program::start().await;
+        let user = harness.payer().pubkey();
+
+        let logs = harness
+            .simulate(program::deposit(&deposit::ID, &user, LAMPORTS_PER_SOL))
+            .await;
+
+        assert!(logs.iter().any(|line| line.starts_with("Program data: ")));
+        assert_eq!(annotate_logs(&logs, &Pubkey::new_unique()), logs);
+    }
+
+    #[test]
+    fn lamport_changes_are_signed() {
+        let vault = Pubkey::new_from_array([2; 32]);
+
+        assert_eq!(
+            lamport_change(&vault, 1_447_680, 251_447_680),
+            format!("{VAULT}: 1447680 -> 251447680 (+250000000)")
+        );
+        assert_eq!(
+            lamport_change(&vault, 251_447_680, 0),
+            format!("{VAULT}: 251447680 -> 0 (-251447680)")
+        );
+    }
+}
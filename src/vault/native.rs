@@ -0,0 +1,156 @@
+// Runs the deposit program natively under solana-program-test, the way
+// programs/deposit's own tests do, so the client can be checked against what
+// the program really logs.
+
+use std::sync::{Once, OnceLock};
+
+use solana_program_test::{ProgramTest, ProgramTestContext, processor};
+use solana_sdk::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    program_stubs::{self, SyscallStubs},
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    transaction::Transaction,
+};
+
+// Natively, `sol_log_data` only prints to stdout. This routes it through
+// program-test's `sol_log` instead, which can only log it as
+// "Program log: data: <base64>"; `Program::simulate` turns those back into
+// the "Program data: <base64>" lines the runtime logs.
+struct LogData;
+
+static NATIVE_STUBS: OnceLock<Box<dyn SyscallStubs>> = OnceLock::new();
+
+impl LogData {
+    fn install() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            let native = program_stubs::set_syscall_stubs(Box::new(LogData));
+            let _ = NATIVE_STUBS.set(native);
+        });
+    }
+
+    fn native() -> &'static dyn SyscallStubs {
+        NATIVE_STUBS.wait().as_ref()
+    }
+}
+
+impl SyscallStubs for LogData {
+    fn sol_log(&self, message: &str) {
+        Self::native().sol_log(message)
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        use base64::{Engine, engine::general_purpose::STANDARD};
+
+        let fields: Vec<String> = fields.iter().map(|field| STANDARD.encode(field)).collect();
+        Self::native().sol_log(&format!("data: {}", fields.join(" ")))
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        Self::native().sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::native().sol_get_clock_sysvar(var_addr)
+    }
+
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::native().sol_get_epoch_schedule_sysvar(var_addr)
+    }
+
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::native().sol_get_epoch_rewards_sysvar(var_addr)
+    }
+
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::native().sol_get_fees_sysvar(var_addr)
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        Self::native().sol_get_rent_sysvar(var_addr)
+    }
+
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        Self::native().sol_get_last_restart_slot(var_addr)
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        Self::native().sol_get_return_data()
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        Self::native().sol_set_return_data(data)
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        Self::native().sol_get_stack_height()
+    }
+}
+
+// `entry` wants the account slice to live as long as the accounts in it.
+fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(accounts.to_vec().into_boxed_slice());
+    deposit::entry(program_id, accounts, data)
+}
+
+pub struct Program {
+    ctx: ProgramTestContext,
+}
+
+impl Program {
+    pub async fn start() -> Self {
+        let mut program = ProgramTest::new("deposit", deposit::ID, processor!(process));
+        program.prefer_bpf(false);
+        let ctx = program.start_with_context().await;
+        // program-test has installed its stubs by now
+        LogData::install();
+        Self { ctx }
+    }
+
+    // the funded wallet that signs and pays for everything
+    pub fn payer(&self) -> &Keypair {
+        &self.ctx.payer
+    }
+
+    pub async fn rent(&mut self, space: usize) -> u64 {
+        let rent = self.ctx.banks_client.get_rent().await.unwrap();
+        rent.minimum_balance(space)
+    }
+
+    // The logs of `instruction` simulated without being committed, with
+    // event data as the runtime logs it.
+    pub async fn simulate(&mut self, instruction: Instruction) -> Vec<String> {
+        let tx = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&self.ctx.payer.pubkey()),
+            &[&self.ctx.payer],
+            self.ctx.last_blockhash,
+        );
+        let simulated = self
+            .ctx
+            .banks_client
+            .simulate_transaction(tx)
+            .await
+            .unwrap();
+        simulated.result.unwrap().unwrap();
+        simulated
+            .simulation_details
+            .unwrap()
+            .logs
+            .into_iter()
+            .map(|line| match line.strip_prefix("Program log: data: ") {
+                Some(data) => format!("Program data: {data}"),
+                None => line,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,209 @@
+// Instructions and accounts of the deposit program (programs/deposit),
+// built by hand: Anchor prefixes instruction data with the first 8 bytes of
+// sha256("global:<name>") and account data with those of
+// sha256("account:<Name>"), and borsh-encodes the rest.
+
+use solana_sdk::{
+    hash::hash,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+
+fn discriminator(preimage: &str) -> [u8; 8] {
+    let mut out = [0; 8];
+    out.copy_from_slice(&hash(preimage.as_bytes()).to_bytes()[..8]);
+    out
+}
+
+fn data(name: &str, args: &[u8]) -> Vec<u8> {
+    let mut data = discriminator(&format!("global:{name}")).to_vec();
+    data.extend_from_slice(args);
+    data
+}
+
+pub fn vault_address(program_id: &Pubkey, creator: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault", creator.as_ref()], program_id).0
+}
+
+pub fn config_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], program_id).0
+}
+
+// The accounts `#[event_cpi]` appends when the program is built with
+// `cpi-events`. Without it they're extra accounts the program ignores, so
+// they're always passed.
+fn event_accounts(program_id: &Pubkey) -> [AccountMeta; 2] {
+    let authority = Pubkey::find_program_address(&[b"__event_authority"], program_id).0;
+    [
+        AccountMeta::new_readonly(authority, false),
+        AccountMeta::new_readonly(*program_id, false),
+    ]
+}
+
+// Anchor reads an optional account passed as the program id as `None`.
+fn optional(program_id: &Pubkey, account: Option<Pubkey>, writable: bool) -> AccountMeta {
+    match account {
+        Some(account) if writable => AccountMeta::new(account, false),
+        Some(account) => AccountMeta::new_readonly(account, false),
+        None => AccountMeta::new_readonly(*program_id, false),
+    }
+}
+
+pub fn initialize(program_id: &Pubkey, user: &Pubkey, delayed: bool) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &data("initialize", &[u8::from(delayed)]),
+        vec![
+            AccountMeta::new(vault_address(program_id, user), false),
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+// Uses deposit_init_if_needed, so the first deposit also creates the vault.
+pub fn deposit(program_id: &Pubkey, user: &Pubkey, lamports: u64) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &data("deposit_init_if_needed", &lamports.to_le_bytes()),
+        [
+            AccountMeta::new(vault_address(program_id, user), false),
+            // required whether or not the config exists
+            AccountMeta::new_readonly(config_address(program_id), false),
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ]
+        .into_iter()
+        .chain(event_accounts(program_id))
+        .collect(),
+    )
+}
+
+fn withdraw_accounts(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    treasury: Option<Pubkey>,
+) -> Vec<AccountMeta> {
+    [
+        AccountMeta::new(vault_address(program_id, user), false),
+        // required whether or not the config exists
+        AccountMeta::new_readonly(config_address(program_id), false),
+        optional(program_id, treasury, true),
+        // payout address; the CLI doesn't set one, so it's always left out
+        optional(program_id, None, true),
+        AccountMeta::new(*user, true),
+    ]
+    .into_iter()
+    .chain(event_accounts(program_id))
+    .collect()
+}
+
+// `treasury` is needed only while the config charges a withdrawal fee.
+pub fn withdraw(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    treasury: Option<Pubkey>,
+    lamports: u64,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &data("withdraw", &lamports.to_le_bytes()),
+        withdraw_accounts(program_id, user, treasury),
+    )
+}
+
+pub fn withdraw_all(program_id: &Pubkey, user: &Pubkey, treasury: Option<Pubkey>) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &data("withdraw_all", &[]),
+        withdraw_accounts(program_id, user, treasury),
+    )
+}
+
+// Closing charges the withdrawal fee on the remaining deposits, so it takes
+// the config and `treasury` like `withdraw`.
+pub fn withdraw_and_close(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    treasury: Option<Pubkey>,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &data("withdraw_and_close", &[]),
+        vec![
+            AccountMeta::new(vault_address(program_id, user), false),
+            AccountMeta::new_readonly(config_address(program_id), false),
+            optional(program_id, treasury, true),
+            AccountMeta::new(*user, true),
+        ],
+    )
+}
+
+// The fixed-size head of a vault: discriminator, version, owner, the two
+// totals, the bump and the creator. Everything after `pending_owner` moves
+// with that option's length, and `show` doesn't need it.
+const OWNER_START: usize = 8 + 1;
+const TOTALS_START: usize = OWNER_START + 32;
+const CREATOR_START: usize = TOTALS_START + 8 + 8 + 1;
+const HEAD_LEN: usize = CREATOR_START + 32;
+
+pub struct Vault {
+    pub version: u8,
+    pub owner: Pubkey,
+    pub total_deposited: u64,
+    pub total_withdrawn: u64,
+    pub creator: Pubkey,
+}
+
+impl Vault {
+    pub fn remaining_deposits(&self) -> u64 {
+        self.total_deposited.saturating_sub(self.total_withdrawn)
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().expect("8 bytes"))
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::try_from(&data[offset..offset + 32]).expect("32 bytes")
+}
+
+pub fn decode_vault(data: &[u8]) -> anyhow::Result<Vault> {
+    if data.len() < 8 || data[..8] != discriminator("account:Vault") {
+        anyhow::bail!("not a vault account");
+    }
+    if data.len() == 8 + 32 {
+        anyhow::bail!("vault is on the legacy layout; run migrate_vault first");
+    }
+    if data.len() < HEAD_LEN {
+        anyhow::bail!("vault account is {} bytes, too short", data.len());
+    }
+    Ok(Vault {
+        version: data[8],
+        owner: read_pubkey(data, OWNER_START),
+        total_deposited: read_u64(data, TOTALS_START),
+        total_withdrawn: read_u64(data, TOTALS_START + 8),
+        creator: read_pubkey(data, CREATOR_START),
+    })
+}
+
+// discriminator, admin, paused, cap, bump, fee_bps, treasury
+const FEE_START: usize = 8 + 32 + 1 + 8 + 1;
+const CONFIG_LEN: usize = FEE_START + 2 + 32;
+
+pub struct ProgramConfig {
+    pub fee_bps: u16,
+    pub treasury: Pubkey,
+}
+
+pub fn decode_config(data: &[u8]) -> anyhow::Result<ProgramConfig> {
+    if data.len() < CONFIG_LEN || data[..8] != discriminator("account:Config") {
+        anyhow::bail!("not a program config account");
+    }
+    Ok(ProgramConfig {
+        fee_bps: u16::from_le_bytes([data[FEE_START], data[FEE_START + 1]]),
+        treasury: read_pubkey(data, FEE_START + 2),
+    })
+}
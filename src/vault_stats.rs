@@ -0,0 +1,72 @@
+// Deposit and withdrawal statistics at the end of a deposit program vault
+// (programs/deposit). They follow the optional and variable-length
+// fields, so the whole borsh layout is walked to reach them. Read by the
+// vault and balance binaries.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct VaultStats {
+    pub deposit_count: u32,
+    pub withdraw_count: u32,
+    // unix timestamps, 0 before the first one
+    pub last_deposit_ts: i64,
+    pub last_withdraw_ts: i64,
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Option<&[u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn skip_option(&mut self, len: usize) -> Option<()> {
+        match self.take(1)?[0] {
+            0 => Some(()),
+            1 => self.take(len).map(|_| ()),
+            _ => None,
+        }
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn i64(&mut self) -> Option<i64> {
+        Some(i64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+}
+
+// discriminator, version, owner, totals, bump, creator
+const HEAD_LEN: usize = 8 + 1 + 32 + 8 + 8 + 1 + 32;
+const RECENT_DEPOSITS: usize = 4;
+
+// `None` for vaults on a layout from before the statistics, or that aren't
+// vaults at all; the caller checks the discriminator.
+pub fn decode_stats(data: &[u8]) -> Option<VaultStats> {
+    let mut reader = Reader(data.get(HEAD_LEN..)?);
+    // pending owner
+    reader.skip_option(32)?;
+    // lock, delayed flag, pending withdrawal and its time, shared balance
+    reader.take(8 + 1 + 8 + 8 + 8)?;
+    let withdrawers = reader.u32()? as usize;
+    reader.take(withdrawers.checked_mul(32)?)?;
+    // beneficiary
+    reader.skip_option(32)?;
+    // inactivity period, last activity, epoch limit and counters
+    reader.take(8 + 8 + 8 + 8 + 8)?;
+    // recent deposits and the ring index
+    reader.take((16 + 8 + 8) * RECENT_DEPOSITS + 1)?;
+    Some(VaultStats {
+        deposit_count: reader.u32()?,
+        withdraw_count: reader.u32()?,
+        last_deposit_ts: reader.i64()?,
+        last_withdraw_ts: reader.i64()?,
+    })
+}